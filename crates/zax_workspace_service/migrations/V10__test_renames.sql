@@ -0,0 +1,12 @@
+-- V10: Record test files renamed between consecutive runs
+-- Each row maps a failure's stable_id in the previous run to its stable_id in
+-- run_id, so deltas treat the failure as continued rather than fixed + new.
+
+CREATE TABLE test_renames (
+    id INTEGER PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(run_id),
+    old_stable_id TEXT NOT NULL,
+    new_stable_id TEXT NOT NULL
+);
+
+CREATE INDEX idx_test_renames_run ON test_renames(run_id);
@@ -0,0 +1,5 @@
+-- V11: Store finding severity so warnings can be kept alongside errors
+-- Values follow ESLint: 1 = warning, 2 = error.
+-- Existing rows were ingested errors-only and default to 2.
+
+ALTER TABLE findings ADD COLUMN severity INTEGER NOT NULL DEFAULT 2;
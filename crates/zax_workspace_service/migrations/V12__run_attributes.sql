@@ -0,0 +1,11 @@
+-- V12: Custom key-value attributes attached to runs at ingest
+-- (coverage %, bundle size, ...), stored as text for trends across runs.
+
+CREATE TABLE run_attributes (
+    id INTEGER PRIMARY KEY,
+    run_id TEXT NOT NULL REFERENCES runs(run_id),
+    key TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX idx_run_attributes_run_key ON run_attributes(run_id, key);
@@ -0,0 +1,5 @@
+-- V13: Record the package scope each run was ingested with, so runs that
+-- covered different packages are not compared as one sequence.
+-- Existing runs get '' (the whole workspace).
+
+ALTER TABLE runs ADD COLUMN package_scope TEXT NOT NULL DEFAULT '';
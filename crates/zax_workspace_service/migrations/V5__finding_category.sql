@@ -0,0 +1,5 @@
+-- V5: Add ESLint rule category to findings for triage grouping
+-- Values: 'problem', 'suggestion', 'layout', or 'unknown'.
+-- Existing rows predate categorization and default to 'unknown'.
+
+ALTER TABLE findings ADD COLUMN category TEXT NOT NULL DEFAULT 'unknown';
@@ -0,0 +1,5 @@
+-- V6: Track whether ESLint offered an automatic fix for a finding
+-- Set from the presence of the message's `fix` object (0 = no fix available).
+-- Existing rows predate the flag and default to not fixable.
+
+ALTER TABLE findings ADD COLUMN fixable INTEGER NOT NULL DEFAULT 0;
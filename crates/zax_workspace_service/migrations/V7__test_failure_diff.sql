@@ -0,0 +1,6 @@
+-- V7: Store structured assertion diffs for test failures
+-- Holds the assertion's expected/actual values when the reporter captured
+-- them separately from the message. NULL = not captured; use `message`.
+
+ALTER TABLE test_failures ADD COLUMN expected TEXT;
+ALTER TABLE test_failures ADD COLUMN received TEXT;
@@ -0,0 +1,6 @@
+-- V8: Distinguish file-level errors from assertion failures
+-- Values: 'assertion' or 'file_error' (import errors, top-level throws).
+-- Existing file-level errors are recognized by their synthesized test id.
+
+ALTER TABLE test_failures ADD COLUMN kind TEXT NOT NULL DEFAULT 'assertion';
+UPDATE test_failures SET kind = 'file_error' WHERE test_id LIKE '%::file-error';
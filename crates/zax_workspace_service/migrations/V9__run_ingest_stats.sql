@@ -0,0 +1,7 @@
+-- V9: Record ingest timing and size per run for CI cadence analysis
+-- Timestamps are Unix milliseconds; NULL = run predates ingest stats.
+-- artifact_bytes is the total size of the run's artifacts as read from disk.
+
+ALTER TABLE runs ADD COLUMN ingest_started_at INTEGER;
+ALTER TABLE runs ADD COLUMN ingest_completed_at INTEGER;
+ALTER TABLE runs ADD COLUMN artifact_bytes INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,98 @@
+//! Circuit breaker for affected selection.
+//!
+//! Counts consecutive internal errors (lock timeouts, a poisoned graph) and,
+//! past a threshold, stops attempting selection for a cooldown so callers get
+//! full runs instead of a selection from a graph that keeps failing.
+
+use std::time::{Duration, Instant};
+
+/// Default number of consecutive errors that opens the breaker.
+pub const DEFAULT_BREAKER_THRESHOLD: u32 = 3;
+/// Default time the breaker stays open before selection is retried.
+pub const DEFAULT_BREAKER_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// Tracks consecutive selection errors.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    pub threshold: u32,
+    pub cooldown: Duration,
+    consecutive_errors: u32,
+    opened_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            consecutive_errors: 0,
+            opened_at: None,
+        }
+    }
+
+    /// Whether selection should be skipped. An open breaker closes once the
+    /// cooldown has elapsed, giving the next query a fresh attempt.
+    pub fn is_open(&mut self) -> bool {
+        match self.opened_at {
+            Some(opened) if opened.elapsed() >= self.cooldown => {
+                self.reset();
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Record an internal error. Returns true when this error opened the breaker.
+    pub fn record_error(&mut self) -> bool {
+        self.consecutive_errors = self.consecutive_errors.saturating_add(1);
+        if self.opened_at.is_none() && self.consecutive_errors >= self.threshold {
+            self.opened_at = Some(Instant::now());
+            return true;
+        }
+        false
+    }
+
+    /// Record a successful selection, clearing the error count.
+    pub fn record_success(&mut self) {
+        self.reset();
+    }
+
+    fn reset(&mut self) {
+        self.consecutive_errors = 0;
+        self.opened_at = None;
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(DEFAULT_BREAKER_THRESHOLD, DEFAULT_BREAKER_COOLDOWN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_threshold_consecutive_errors() {
+        let mut breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        assert!(!breaker.record_error());
+        breaker.record_success();
+        assert!(!breaker.record_error());
+        assert!(!breaker.record_error());
+        assert!(!breaker.is_open());
+        assert!(breaker.record_error());
+        assert!(breaker.is_open());
+        // Already open: further errors do not re-trip
+        assert!(!breaker.record_error());
+    }
+
+    #[test]
+    fn closes_after_cooldown() {
+        let mut breaker = CircuitBreaker::new(1, Duration::ZERO);
+        assert!(breaker.record_error());
+        assert!(!breaker.is_open());
+        assert!(breaker.record_error());
+    }
+}
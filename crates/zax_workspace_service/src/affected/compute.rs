@@ -70,7 +70,7 @@ mod tests {
         graph.add_file(a.clone());
         graph.add_file(b.clone());
         // a imports b
-        graph.update_edges(&a, &[b.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&b));
 
         let mut dirty = HashSet::new();
         dirty.insert(b.clone());
@@ -94,9 +94,9 @@ mod tests {
         graph.add_file(d.clone());
 
         // a → b → c → d
-        graph.update_edges(&a, &[b.clone()]);
-        graph.update_edges(&b, &[c.clone()]);
-        graph.update_edges(&c, &[d.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&b));
+        graph.update_edges(&b, std::slice::from_ref(&c));
+        graph.update_edges(&c, std::slice::from_ref(&d));
 
         let mut dirty = HashSet::new();
         dirty.insert(d.clone());
@@ -121,9 +121,9 @@ mod tests {
         graph.add_file(c.clone());
 
         // a → b → c → a (cycle)
-        graph.update_edges(&a, &[b.clone()]);
-        graph.update_edges(&b, &[c.clone()]);
-        graph.update_edges(&c, &[a.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&b));
+        graph.update_edges(&b, std::slice::from_ref(&c));
+        graph.update_edges(&c, std::slice::from_ref(&a));
 
         let mut dirty = HashSet::new();
         dirty.insert(a.clone());
@@ -151,8 +151,8 @@ mod tests {
         // a → b → d
         // a → c → d
         graph.update_edges(&a, &[b.clone(), c.clone()]);
-        graph.update_edges(&b, &[d.clone()]);
-        graph.update_edges(&c, &[d.clone()]);
+        graph.update_edges(&b, std::slice::from_ref(&d));
+        graph.update_edges(&c, std::slice::from_ref(&d));
 
         let mut dirty = HashSet::new();
         dirty.insert(d.clone());
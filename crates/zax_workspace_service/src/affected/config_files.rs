@@ -0,0 +1,187 @@
+//! Config files whose changes force full runs.
+//!
+//! Lockfiles and the built-in tool configs always count. A workspace can add
+//! globs for its own tools (e.g. `turbo.json`, `jest.config.*`) in `zax.toml`:
+//!
+//! ```toml
+//! [affected]
+//! config_files = ["turbo.json", "jest.config.*", "config/*.json"]
+//! ```
+//!
+//! Globs without a `/` match file names anywhere in the workspace; globs with
+//! a `/` match workspace-relative paths.
+//!
+//! The same `[affected]` table also holds extra test file extensions, read by
+//! `load_test_patterns`.
+
+use super::discovery::TestFilePatterns;
+use super::watcher::is_config_file;
+use crate::logging::log_line;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Workspace config file read from the workspace root.
+pub const WORKSPACE_CONFIG_FILE: &str = "zax.toml";
+
+#[derive(Debug, Default, Deserialize)]
+struct WorkspaceToml {
+    #[serde(default)]
+    affected: AffectedToml,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AffectedToml {
+    #[serde(default)]
+    config_files: Vec<String>,
+    /// Extra test file extensions, see `TestFilePatterns`.
+    #[serde(default)]
+    test_extensions: Vec<String>,
+}
+
+/// Built-in config files merged with a workspace's configured globs.
+#[derive(Debug, Clone)]
+pub struct ConfigFiles {
+    workspace_root: PathBuf,
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl ConfigFiles {
+    /// Build a matcher from the built-ins plus `patterns`.
+    ///
+    /// # Errors
+    /// Returns an error if a pattern is not a valid glob.
+    pub fn new(workspace_root: PathBuf, patterns: &[String]) -> Result<Self, globset::Error> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let glob = Glob::new(pattern)?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        Ok(Self {
+            workspace_root,
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    /// Load configured globs from `zax.toml` in the workspace root.
+    ///
+    /// A missing file means built-ins only; an unreadable or invalid file is
+    /// logged and also falls back to the built-ins.
+    pub fn load(workspace_root: &Path) -> Self {
+        let patterns = match read_affected_toml(&workspace_root.join(WORKSPACE_CONFIG_FILE)) {
+            Ok(affected) => affected.config_files,
+            Err(e) => {
+                log_line!("[affected] WARN: ignoring {WORKSPACE_CONFIG_FILE}: {e}");
+                Vec::new()
+            }
+        };
+        // Watcher events carry canonical paths
+        let root = workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| workspace_root.to_path_buf());
+        Self::new(root.clone(), &patterns).unwrap_or_else(|e| {
+            log_line!("[affected] WARN: invalid config_files glob in {WORKSPACE_CONFIG_FILE}: {e}");
+            Self::builtin(root)
+        })
+    }
+
+    /// Only the built-in config files.
+    pub fn builtin(workspace_root: PathBuf) -> Self {
+        Self {
+            workspace_root,
+            names: GlobSet::empty(),
+            paths: GlobSet::empty(),
+        }
+    }
+
+    /// Check if a change to `path` should trigger a full run.
+    pub fn matches(&self, path: &Path) -> bool {
+        if is_config_file(path) {
+            return true;
+        }
+        if path
+            .file_name()
+            .is_some_and(|name| self.names.is_match(name))
+        {
+            return true;
+        }
+        let relative = path.strip_prefix(&self.workspace_root).unwrap_or(path);
+        self.paths.is_match(relative)
+    }
+}
+
+/// Load test file patterns: the built-ins plus `test_extensions` from
+/// `zax.toml`. An unreadable or invalid file means built-ins only; it is
+/// already reported by `ConfigFiles::load`.
+pub fn load_test_patterns(workspace_root: &Path) -> TestFilePatterns {
+    let extensions = read_affected_toml(&workspace_root.join(WORKSPACE_CONFIG_FILE))
+        .map(|affected| affected.test_extensions)
+        .unwrap_or_default();
+    TestFilePatterns::default().with_extensions(&extensions)
+}
+
+fn read_affected_toml(path: &Path) -> Result<AffectedToml, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(AffectedToml::default()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let config: WorkspaceToml = toml::from_str(&content).map_err(|e| e.to_string())?;
+    Ok(config.affected)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn configured_globs_extend_builtins() {
+        let root = PathBuf::from("/ws");
+        let patterns = ["turbo.json".to_string(), "config/*.json".to_string()];
+        let files = ConfigFiles::new(root.clone(), &patterns).unwrap();
+        assert!(files.matches(&root.join("package.json")));
+        assert!(files.matches(&root.join("apps/web/turbo.json")));
+        assert!(files.matches(&root.join("config/app.json")));
+        assert!(!files.matches(&root.join("apps/config/app.json")));
+        assert!(!files.matches(&root.join("src/main.ts")));
+    }
+
+    #[test]
+    fn load_reads_zax_toml_and_falls_back_on_errors() {
+        let dir = tempdir().unwrap();
+        let turbo = dir.path().join("turbo.json");
+        assert!(!ConfigFiles::load(dir.path()).matches(&turbo));
+
+        let config = dir.path().join(WORKSPACE_CONFIG_FILE);
+        fs::write(&config, "[affected]\nconfig_files = [\"turbo.json\"]\n").unwrap();
+        assert!(ConfigFiles::load(dir.path()).matches(&turbo));
+
+        fs::write(&config, "[affected\n").unwrap();
+        let files = ConfigFiles::load(dir.path());
+        assert!(!files.matches(&turbo));
+        assert!(files.matches(&dir.path().join("package.json")));
+    }
+
+    #[test]
+    fn load_test_patterns_adds_configured_extensions() {
+        let dir = tempdir().unwrap();
+        let test = Path::new("src/a.test.cts");
+        assert!(!load_test_patterns(dir.path()).matches(test));
+
+        let config = dir.path().join(WORKSPACE_CONFIG_FILE);
+        fs::write(&config, "[affected]\ntest_extensions = [\"cts\"]\n").unwrap();
+        let patterns = load_test_patterns(dir.path());
+        assert!(patterns.matches(test));
+        assert!(patterns.matches(Path::new("src/a.spec.tsx")));
+    }
+}
@@ -0,0 +1,157 @@
+//! Affected computation from an explicit change list.
+//!
+//! CI passes a git diff instead of relying on live watcher state. Changes are
+//! evaluated against the shared dependency graph without applying them, so
+//! neither the graph nor the persistent dirty set is mutated.
+//!
+//! Clients that know the changed line ranges can narrow further: dependents
+//! that import none of the changed exports are skipped (see `symbols`).
+
+use super::compute::compute_affected;
+use super::graph::DepGraph;
+use super::parser::ParseLimits;
+use super::resolver::PathResolver;
+use super::symbols::{changed_exports, symbol_imports, LineRange};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Kind of change reported for a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A single changed file with an absolute path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: PathBuf,
+    pub kind: ChangeKind,
+}
+
+/// Compute files affected by a change list.
+///
+/// Every changed file is a seed itself, so the imports it gained or lost
+/// cannot change which files reach it through reverse edges: the graph is
+/// walked as is, without re-parsing, and changed files it lacks (e.g. added
+/// ones) are included directly. Deleted files are seeds until their
+/// dependents are collected, then are dropped since they no longer exist.
+pub fn affected_from_changes(graph: &DepGraph, changes: &[FileChange]) -> HashSet<PathBuf> {
+    let dirty: HashSet<PathBuf> = changes.iter().map(|c| c.path.clone()).collect();
+    let mut affected = compute_affected(&dirty, graph);
+    for change in changes {
+        if change.kind == ChangeKind::Deleted {
+            affected.remove(&change.path);
+        } else {
+            affected.insert(change.path.clone());
+        }
+    }
+    affected
+}
+
+/// A modified file with its changed lines. No ranges means the whole file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeChange {
+    pub path: PathBuf,
+    pub ranges: Vec<LineRange>,
+}
+
+/// Compute files affected by line-range changes.
+///
+/// When a file's ranges touch only exported declarations, only its direct
+/// dependents importing a changed export (and whatever depends on those) are
+/// affected; otherwise every dependent is, as with a whole-file change.
+pub fn affected_from_ranges(
+    graph: &DepGraph,
+    resolver: &PathResolver,
+    changes: &[RangeChange],
+    limits: ParseLimits,
+) -> HashSet<PathBuf> {
+    let mut changed = HashSet::new();
+    let mut seeds = HashSet::new();
+    for change in changes {
+        let exports = (!change.ranges.is_empty())
+            .then(|| changed_exports(&change.path, &change.ranges, limits))
+            .flatten();
+        let Some(exports) = exports else {
+            seeds.insert(change.path.clone());
+            continue;
+        };
+        if graph.contains(&change.path) {
+            changed.insert(change.path.clone());
+        }
+        let dependents = graph.get_dependents(&change.path).into_iter();
+        let target = &change.path;
+        seeds.extend(dependents.filter(|d| imports_any(d, target, &exports, resolver, limits)));
+    }
+    let mut affected = compute_affected(&seeds, graph);
+    affected.extend(changed);
+    affected
+}
+
+/// Whether `dependent` imports any of `exports` from `target`. Imports that
+/// bind no specific names, or an edge no import accounts for, count as using
+/// every export.
+#[allow(clippy::too_many_arguments)]
+fn imports_any(
+    dependent: &Path,
+    target: &Path,
+    exports: &HashSet<String>,
+    resolver: &PathResolver,
+    limits: ParseLimits,
+) -> bool {
+    let Some(imports) = symbol_imports(dependent, limits) else {
+        return true;
+    };
+    let mut from_target = imports
+        .iter()
+        .filter(|i| resolver.resolve(dependent, &i.specifier).as_deref() == Some(target))
+        .peekable();
+    if from_target.peek().is_none() {
+        return true;
+    }
+    from_target.any(|i| {
+        i.names
+            .as_ref()
+            .is_none_or(|names| names.iter().any(|n| exports.contains(n)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_files_select_dependents_without_touching_graph() {
+        let a = PathBuf::from("/ws/a.ts");
+        let b = PathBuf::from("/ws/b.ts");
+        let c = PathBuf::from("/ws/c.ts");
+        let gone = PathBuf::from("/ws/gone.ts");
+        let mut graph = DepGraph::new();
+        for path in [&b, &c, &gone] {
+            graph.add_file(path.clone());
+        }
+        graph.update_edges(&c, &[b.clone(), gone.clone()]);
+        let changes = [
+            FileChange {
+                path: a.clone(),
+                kind: ChangeKind::Added,
+            },
+            FileChange {
+                path: b.clone(),
+                kind: ChangeKind::Modified,
+            },
+            FileChange {
+                path: gone.clone(),
+                kind: ChangeKind::Deleted,
+            },
+        ];
+
+        let affected = affected_from_changes(&graph, &changes);
+        assert_eq!(affected, HashSet::from([a.clone(), b, c]));
+        // Shared graph is untouched
+        assert!(!graph.contains(&a));
+        assert!(graph.contains(&gone));
+    }
+}
@@ -1,72 +1,183 @@
 //! Test file discovery.
 //!
-//! Maps source files to their corresponding test files by convention.
+//! Maps source files to their corresponding test files by convention, plus
+//! any explicit associations from `zax.tests.json`.
+//!
+//! Test files are recognized by `TestFilePatterns`. A workspace can add
+//! extensions to the built-in ones in `zax.toml`:
+//!
+//! ```toml
+//! [affected]
+//! test_extensions = ["cts", "vue"]
+//! ```
 
-use std::collections::HashSet;
+use super::test_mappings::TestMappings;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Infixes that mark a test file (`foo.test.ts`, `foo.spec.ts`).
+const TEST_INFIXES: &[&str] = &["test", "spec"];
+/// Extensions recognized on test files unless configured otherwise.
+const TEST_FILE_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mts", "mjs"];
+/// Extensions probed when mapping a source file to its tests by convention.
+const PROBE_EXTENSIONS: &[&str] = &["ts", "tsx"];
+
+/// Test file naming rules: `{stem}.{infix}.{ext}` for every infix and
+/// configured extension, plus anything under a `__tests__` directory.
+#[derive(Debug, Clone)]
+pub struct TestFilePatterns {
+    extensions: Vec<String>,
+}
 
-/// Test file patterns.
-const TEST_EXTENSIONS: &[&str] = &[".test.ts", ".test.tsx", ".spec.ts", ".spec.tsx"];
+impl TestFilePatterns {
+    /// Create patterns for the given extensions (without leading dot).
+    pub fn new(extensions: &[&str]) -> Self {
+        Self {
+            extensions: extensions.iter().map(|e| (*e).to_string()).collect(),
+        }
+    }
 
-/// Discover test files for affected source files.
-///
-/// For each affected file:
-/// - If it's already a test file, include it directly
-/// - Otherwise, try to find matching test files by convention
-pub fn discover_tests(
-    affected: &HashSet<PathBuf>,
-    workspace_root: &Path,
-) -> Vec<PathBuf> {
-    let mut tests = HashSet::new();
-
-    for path in affected {
-        if is_test_file(path) {
-            tests.insert(path.clone());
-        } else if let Some(test_files) = find_test_files(path, workspace_root) {
-            for test in test_files {
-                tests.insert(test);
+    /// Also recognize `extensions` (with or without leading dot), e.g. from
+    /// `test_extensions` in `zax.toml`.
+    #[must_use]
+    pub fn with_extensions(mut self, extensions: &[String]) -> Self {
+        for ext in extensions.iter().map(|e| e.trim_start_matches('.')) {
+            if !ext.is_empty() && !self.extensions.iter().any(|known| known == ext) {
+                self.extensions.push(ext.to_string());
             }
         }
+        self
+    }
+
+    /// All test suffixes, infix-major (`.test.ts`, `.test.tsx`, `.spec.ts`, ...).
+    pub fn suffixes(&self) -> Vec<String> {
+        TEST_INFIXES
+            .iter()
+            .flat_map(|infix| {
+                self.extensions
+                    .iter()
+                    .map(move |ext| format!(".{infix}.{ext}"))
+            })
+            .collect()
+    }
+
+    /// Check if a path is a test file.
+    pub fn matches(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        self.has_test_suffix(name) || path.components().any(|c| c.as_os_str() == "__tests__")
     }
 
-    tests.into_iter().collect()
+    fn has_test_suffix(&self, name: &str) -> bool {
+        self.extensions.iter().any(|ext| {
+            name.strip_suffix(ext.as_str())
+                .and_then(|rest| rest.strip_suffix('.'))
+                .is_some_and(|rest| {
+                    TEST_INFIXES
+                        .iter()
+                        .any(|infix| rest.strip_suffix(infix).is_some_and(|r| r.ends_with('.')))
+                })
+        })
+    }
 }
 
-/// Check if a path is a test file.
-pub fn is_test_file(path: &Path) -> bool {
-    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
-        return false;
-    };
+impl Default for TestFilePatterns {
+    fn default() -> Self {
+        Self::new(TEST_FILE_EXTENSIONS)
+    }
+}
 
-    // Check common test patterns
-    for ext in TEST_EXTENSIONS {
-        if name.ends_with(ext) {
-            return true;
-        }
+/// Suffixes probed next to a source file when looking for its tests.
+fn probe_suffixes() -> &'static [String] {
+    static SUFFIXES: OnceLock<Vec<String>> = OnceLock::new();
+    SUFFIXES.get_or_init(|| TestFilePatterns::new(PROBE_EXTENSIONS).suffixes())
+}
+
+/// Cached source→test-file mapping.
+///
+/// Avoids re-probing the filesystem for every affected source file on each
+/// query. Entries are invalidated when a test file with a matching stem is
+/// created or deleted.
+#[derive(Debug, Default)]
+pub struct DiscoveryCache {
+    entries: HashMap<PathBuf, Vec<PathBuf>>,
+    mappings: TestMappings,
+    patterns: TestFilePatterns,
+}
+
+impl DiscoveryCache {
+    /// Create an empty discovery cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also select tests explicitly mapped to a source file.
+    #[must_use]
+    pub fn with_mappings(mut self, mappings: TestMappings) -> Self {
+        self.mappings = mappings;
+        self
     }
 
-    // Check .js variants
-    if name.ends_with(".test.js")
-        || name.ends_with(".test.jsx")
-        || name.ends_with(".spec.js")
-        || name.ends_with(".spec.jsx")
-        || name.ends_with(".test.mts")
-        || name.ends_with(".test.mjs")
-        || name.ends_with(".spec.mts")
-        || name.ends_with(".spec.mjs")
-    {
-        return true;
+    /// Recognize test files by `patterns` instead of the built-in ones.
+    #[must_use]
+    pub fn with_patterns(mut self, patterns: TestFilePatterns) -> Self {
+        self.patterns = patterns;
+        self
     }
 
-    // Check __tests__ directory
-    if path
-        .components()
-        .any(|c| c.as_os_str() == "__tests__")
-    {
-        return true;
+    /// Discover test files for affected source files.
+    ///
+    /// For each affected file:
+    /// - If it's already a test file, include it directly
+    /// - Otherwise, look up (or find and cache) matching test files by
+    ///   convention, followed by any explicitly mapped tests
+    pub fn discover_tests(
+        &mut self,
+        affected: &HashSet<PathBuf>,
+        workspace_root: &Path,
+    ) -> Vec<PathBuf> {
+        let mut tests = HashSet::new();
+
+        for path in affected {
+            if self.patterns.matches(path) {
+                tests.insert(path.clone());
+            } else {
+                let mappings = &mut self.mappings;
+                let found = self.entries.entry(path.clone()).or_insert_with(|| {
+                    let mut found = find_test_files(path, workspace_root).unwrap_or_default();
+                    found.extend(mappings.tests_for(path));
+                    found
+                });
+                tests.extend(found.iter().cloned());
+            }
+        }
+
+        tests.into_iter().collect()
+    }
+
+    /// Invalidate cached entries that a changed test file may map to.
+    pub fn invalidate_test_file(&mut self, test_path: &Path) {
+        // Mapped tests may belong to any source
+        if self.mappings.invalidate_test_file(test_path) {
+            self.entries.clear();
+            return;
+        }
+        let Some(stem) = test_source_stem(test_path) else {
+            return;
+        };
+        self.entries
+            .retain(|source, _| source.file_stem().and_then(|s| s.to_str()) != Some(stem));
     }
+}
 
-    false
+/// Source stem a test file maps to (e.g. `foo.test.ts` -> `foo`).
+fn test_source_stem(test_path: &Path) -> Option<&str> {
+    let name = test_path.file_name()?.to_str()?;
+    probe_suffixes()
+        .iter()
+        .find_map(|ext| name.strip_suffix(ext.as_str()))
 }
 
 /// Find test files for a source file by convention.
@@ -90,7 +201,7 @@ fn find_test_files(source: &Path, workspace_root: &Path) -> Option<Vec<PathBuf>>
 }
 
 fn find_colocated_tests(parent: &Path, stem: &str, candidates: &mut Vec<PathBuf>) {
-    for ext in TEST_EXTENSIONS {
+    for ext in probe_suffixes() {
         let test_path = parent.join(format!("{stem}{ext}"));
         if test_path.exists() {
             candidates.push(test_path);
@@ -101,7 +212,7 @@ fn find_colocated_tests(parent: &Path, stem: &str, candidates: &mut Vec<PathBuf>
 fn find_tests_dir_tests(parent: &Path, stem: &str, candidates: &mut Vec<PathBuf>) {
     let tests_dir = parent.join("__tests__");
     if tests_dir.exists() {
-        for ext in TEST_EXTENSIONS {
+        for ext in probe_suffixes() {
             let test_path = tests_dir.join(format!("{stem}{ext}"));
             if test_path.exists() {
                 candidates.push(test_path);
@@ -111,8 +222,12 @@ fn find_tests_dir_tests(parent: &Path, stem: &str, candidates: &mut Vec<PathBuf>
 }
 
 fn find_sibling_test_dir(source: &Path, workspace_root: &Path, stem: &str, out: &mut Vec<PathBuf>) {
-    let Some(relative) = source.strip_prefix(workspace_root).ok() else { return };
-    let Some(rel_parent) = relative.parent() else { return };
+    let Some(relative) = source.strip_prefix(workspace_root).ok() else {
+        return;
+    };
+    let Some(rel_parent) = relative.parent() else {
+        return;
+    };
     let components: Vec<_> = rel_parent.components().collect();
     if components.is_empty() {
         return;
@@ -127,7 +242,7 @@ fn find_sibling_test_dir(source: &Path, workspace_root: &Path, stem: &str, out:
     if !test_dir.exists() && !test_dir.parent().is_some_and(Path::exists) {
         return;
     }
-    for ext in TEST_EXTENSIONS {
+    for ext in probe_suffixes() {
         let test_path = test_dir.join(format!("{stem}{ext}"));
         if test_path.exists() {
             out.push(test_path);
@@ -141,6 +256,14 @@ mod tests {
     use std::fs;
     use tempfile::tempdir;
 
+    fn discover_tests(affected: &HashSet<PathBuf>, workspace_root: &Path) -> Vec<PathBuf> {
+        DiscoveryCache::new().discover_tests(affected, workspace_root)
+    }
+
+    fn is_test_file(path: &Path) -> bool {
+        TestFilePatterns::default().matches(path)
+    }
+
     #[test]
     fn is_test_file_detects_patterns() {
         assert!(is_test_file(Path::new("foo.test.ts")));
@@ -157,6 +280,86 @@ mod tests {
         assert!(!is_test_file(Path::new("foo.tsx")));
     }
 
+    /// The matcher as it was before being derived from infixes × extensions.
+    fn legacy_is_test_file(path: &Path) -> bool {
+        const LEGACY: &[&str] = &[
+            ".test.ts",
+            ".test.tsx",
+            ".spec.ts",
+            ".spec.tsx",
+            ".test.js",
+            ".test.jsx",
+            ".spec.js",
+            ".spec.jsx",
+            ".test.mts",
+            ".test.mjs",
+            ".spec.mts",
+            ".spec.mjs",
+        ];
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        LEGACY.iter().any(|s| name.ends_with(s))
+            || path.components().any(|c| c.as_os_str() == "__tests__")
+    }
+
+    #[test]
+    fn derived_matcher_equals_legacy_matrix() {
+        let stems = ["foo", "", "test", "spec", "foo.test", "a.spec.b"];
+        let infixes = ["test", "spec", "tests", "Test", "", "xtest"];
+        let exts = [
+            "ts", "tsx", "js", "jsx", "mts", "mjs", "cts", "cjs", "json", "",
+        ];
+        for dir in ["src", "src/__tests__"] {
+            for stem in stems {
+                for infix in infixes {
+                    for ext in exts {
+                        let path = PathBuf::from(format!("{dir}/{stem}.{infix}.{ext}"));
+                        assert_eq!(
+                            is_test_file(&path),
+                            legacy_is_test_file(&path),
+                            "{}",
+                            path.display()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn suffixes_cover_infix_extension_product() {
+        let patterns = TestFilePatterns::new(&["ts", "cts"]);
+        assert_eq!(
+            patterns.suffixes(),
+            vec![".test.ts", ".test.cts", ".spec.ts", ".spec.cts"]
+        );
+        assert!(patterns.matches(Path::new("foo.spec.cts")));
+        assert!(!patterns.matches(Path::new("foo.test.js")));
+        assert_eq!(
+            probe_suffixes(),
+            [".test.ts", ".test.tsx", ".spec.ts", ".spec.tsx"]
+        );
+    }
+
+    #[test]
+    fn configured_extensions_extend_builtins() {
+        let extra = [".cts".to_string(), "ts".to_string(), String::new()];
+        let patterns = TestFilePatterns::default().with_extensions(&extra);
+        assert!(patterns.matches(Path::new("src/foo.test.cts")));
+        assert!(patterns.matches(Path::new("src/foo.spec.ts")));
+        assert_eq!(
+            patterns.suffixes().len(),
+            2 * (TEST_FILE_EXTENSIONS.len() + 1)
+        );
+
+        let dir = tempdir().unwrap();
+        let test_file = dir.path().join("src/foo.test.cts");
+        let affected = HashSet::from([test_file.clone()]);
+        let mut cache = DiscoveryCache::new().with_patterns(patterns);
+        assert_eq!(cache.discover_tests(&affected, dir.path()), vec![test_file]);
+    }
+
     #[test]
     fn discover_includes_test_files_directly() {
         let dir = tempdir().unwrap();
@@ -245,4 +448,42 @@ mod tests {
         let tests = discover_tests(&affected, dir.path());
         assert!(tests.is_empty());
     }
+
+    #[test]
+    fn cache_invalidated_by_new_colocated_test() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        let source = src.join("foo.ts");
+        fs::write(&source, "").unwrap();
+
+        let mut affected = HashSet::new();
+        affected.insert(source);
+
+        let mut cache = DiscoveryCache::new();
+        assert!(cache.discover_tests(&affected, dir.path()).is_empty());
+        assert_eq!(cache.entries.len(), 1);
+
+        // Cached lookup does not see the new test until invalidated
+        let test = src.join("foo.test.ts");
+        fs::write(&test, "").unwrap();
+        assert!(cache.discover_tests(&affected, dir.path()).is_empty());
+
+        cache.invalidate_test_file(&test);
+        assert_eq!(cache.entries.len(), 0);
+        assert_eq!(cache.discover_tests(&affected, dir.path()), vec![test]);
+    }
+
+    #[test]
+    fn invalidate_ignores_unrelated_stems() {
+        let dir = tempdir().unwrap();
+        let mut affected = HashSet::new();
+        affected.insert(dir.path().join("foo.ts"));
+
+        let mut cache = DiscoveryCache::new();
+        cache.discover_tests(&affected, dir.path());
+        cache.invalidate_test_file(&dir.path().join("bar.test.ts"));
+        cache.invalidate_test_file(&dir.path().join("foo.ts"));
+        assert_eq!(cache.entries.len(), 1);
+    }
 }
@@ -1,17 +1,66 @@
 //! Dependency graph using petgraph.
 //!
 //! Stores file dependencies as a directed graph where edge A→B means "A imports B".
-#![allow(clippy::print_stderr)]
+//!
+//! Optionally, test files are kept out of the graph as leaves: nothing imports
+//! them, so their imports are held in a side table instead of nodes and edges,
+//! leaving the node limit to the modules under test.
+//!
+//! A built graph can be saved to disk and loaded on the next start, so only
+//! files modified since need re-parsing (see `save` and `load`).
 
+use super::discovery::TestFilePatterns;
+use crate::logging::log_line;
+use petgraph::algo::tarjan_scc;
 use petgraph::stable_graph::{NodeIndex, StableDiGraph};
 use petgraph::visit::EdgeRef;
 use petgraph::Direction;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Error, ErrorKind};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Default maximum number of nodes before triggering full run.
+pub const DEFAULT_MAX_GRAPH_NODES: usize = 10_000;
+/// Default number of direct dependents above which a module is reported as a hub.
+pub const DEFAULT_FAN_IN_THRESHOLD: usize = 50;
+
+/// A module with more direct dependents than the fan-in threshold.
+///
+/// Hubs are an architectural risk and a source of over-selection, since any
+/// change to them selects most of the workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HubModule {
+    pub path: PathBuf,
+    pub dependents: usize,
+}
 
-/// Maximum number of nodes before triggering full run.
-const MAX_GRAPH_NODES: usize = 10_000;
+/// First line of a saved graph; bump the version when `SavedGraph` changes
+/// so older files are discarded instead of misread.
+const SAVED_GRAPH_HEADER: &str = "zax-graph 1";
+
+/// Body of a saved graph.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedGraph {
+    /// Settings the edges were resolved with; a mismatch discards the file.
+    key: String,
+    /// Unix seconds when the saved build started.
+    built_at: u64,
+    files: Vec<SavedFile>,
+}
+
+/// A file with the files it imports.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedFile {
+    path: PathBuf,
+    imports: Vec<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    truncated_from: Option<usize>,
+}
 
 /// A node in the dependency graph.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -20,11 +69,34 @@ pub enum GraphNode {
     Module(PathBuf),
 }
 
+/// A test file kept outside the graph, with the modules it imports.
+#[derive(Debug, Clone)]
+struct TestLeaf {
+    path: PathBuf,
+    imports: Vec<NodeIndex>,
+}
+
 /// Dependency graph storing file import relationships.
+#[derive(Clone)]
 pub struct DepGraph {
     graph: StableDiGraph<GraphNode, ()>,
     path_to_idx: HashMap<PathBuf, NodeIndex>,
     overflow: bool,
+    /// Node count at which further files overflow the graph.
+    max_nodes: usize,
+    /// Key lookups by lowercased path, for case-insensitive filesystems.
+    case_insensitive: bool,
+    /// Files whose imports were truncated, with their full import count.
+    truncated_imports: HashMap<PathBuf, (PathBuf, usize)>,
+    /// Keep test files as leaves instead of nodes.
+    test_leaves: bool,
+    /// What counts as a test file for `test_leaves`.
+    test_patterns: TestFilePatterns,
+    leaves: HashMap<PathBuf, TestLeaf>,
+    /// Leaf keys by imported node, for dependent lookups.
+    leaf_importers: HashMap<NodeIndex, HashSet<PathBuf>>,
+    /// Incremented on every change to files, edges or truncation records.
+    generation: u64,
 }
 
 impl Default for DepGraph {
@@ -40,36 +112,102 @@ impl DepGraph {
             graph: StableDiGraph::new(),
             path_to_idx: HashMap::new(),
             overflow: false,
+            max_nodes: DEFAULT_MAX_GRAPH_NODES,
+            case_insensitive: false,
+            truncated_imports: HashMap::new(),
+            test_leaves: false,
+            test_patterns: TestFilePatterns::default(),
+            leaves: HashMap::new(),
+            leaf_importers: HashMap::new(),
+            generation: 0,
         }
     }
 
-    /// Add a file to the graph. Returns the node index.
-    /// If the graph exceeds `MAX_GRAPH_NODES`, sets overflow flag and returns None.
-    pub fn add_file(&mut self, path: PathBuf) -> Option<NodeIndex> {
-        if let Some(&idx) = self.path_to_idx.get(&path) {
-            return Some(idx);
+    /// Set the node limit. Applies to files added from now on.
+    pub fn set_max_nodes(&mut self, max_nodes: usize) {
+        self.max_nodes = max_nodes;
+    }
+
+    /// Treat paths differing only in case as one file. Call before files are
+    /// added; nodes keep the spelling they were first added with.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+    }
+
+    /// Keep test files out of the graph: they are never import targets, and
+    /// don't count towards the node limit. Call before files are added.
+    pub fn set_test_leaves(&mut self, test_leaves: bool) {
+        self.test_leaves = test_leaves;
+    }
+
+    /// Recognize test leaves by `patterns`. Call before files are added.
+    pub fn set_test_patterns(&mut self, patterns: TestFilePatterns) {
+        self.test_patterns = patterns;
+    }
+
+    fn is_leaf(&self, path: &Path) -> bool {
+        self.test_leaves && self.test_patterns.matches(path)
+    }
+
+    /// The `path_to_idx` key for a path.
+    fn key<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+        if self.case_insensitive {
+            Cow::Owned(PathBuf::from(path.to_string_lossy().to_lowercase()))
+        } else {
+            Cow::Borrowed(path)
+        }
+    }
+
+    fn index_of(&self, path: &Path) -> Option<NodeIndex> {
+        self.path_to_idx.get(self.key(path).as_ref()).copied()
+    }
+
+    /// Add a file to the graph. Returns false if the graph exceeds its node
+    /// limit, setting the overflow flag.
+    pub fn add_file(&mut self, path: PathBuf) -> bool {
+        if self.is_leaf(&path) {
+            let key = self.key(&path).into_owned();
+            if let Entry::Vacant(entry) = self.leaves.entry(key) {
+                entry.insert(TestLeaf {
+                    path,
+                    imports: Vec::new(),
+                });
+                self.generation += 1;
+            }
+            return true;
+        }
+        if self.index_of(&path).is_some() {
+            return true;
         }
 
-        if self.graph.node_count() >= MAX_GRAPH_NODES {
+        if self.graph.node_count() >= self.max_nodes {
             if !self.overflow {
-                eprintln!(
+                log_line!(
                     "[affected] WARN: graph exceeded {} nodes, triggering full run",
-                    MAX_GRAPH_NODES
+                    self.max_nodes
                 );
                 self.overflow = true;
             }
-            return None;
+            return false;
         }
 
-        let idx = self.graph.add_node(GraphNode::Module(path.clone()));
-        self.path_to_idx.insert(path, idx);
-        Some(idx)
+        let key = self.key(&path).into_owned();
+        let idx = self.graph.add_node(GraphNode::Module(path));
+        self.path_to_idx.insert(key, idx);
+        self.generation += 1;
+        true
     }
 
     /// Update outgoing edges for a file atomically.
-    /// Removes all existing outgoing edges and adds new ones.
+    /// Removes all existing outgoing edges and adds new ones, at most one per
+    /// target even when a module is imported more than once.
     pub fn update_edges(&mut self, from: &Path, imports: &[PathBuf]) {
-        let Some(&from_idx) = self.path_to_idx.get(from) else {
+        self.generation += 1;
+        if self.is_leaf(from) {
+            self.update_leaf_imports(from, imports);
+            return;
+        }
+        let Some(from_idx) = self.index_of(from) else {
             return;
         };
 
@@ -86,15 +224,47 @@ impl DepGraph {
 
         // Add new edges
         for import in imports {
-            if let Some(&to_idx) = self.path_to_idx.get(import) {
-                self.graph.add_edge(from_idx, to_idx, ());
+            if let Some(to_idx) = self.index_of(import) {
+                self.graph.update_edge(from_idx, to_idx, ());
+            }
+        }
+    }
+
+    /// Replace a leaf's imports, keeping `leaf_importers` in step. Imports
+    /// that are not graph nodes (including other tests) are dropped.
+    fn update_leaf_imports(&mut self, from: &Path, imports: &[PathBuf]) {
+        let key = self.key(from).into_owned();
+        let mut targets: Vec<NodeIndex> = imports.iter().filter_map(|p| self.index_of(p)).collect();
+        targets.sort_unstable();
+        targets.dedup();
+        let Some(leaf) = self.leaves.get_mut(&key) else {
+            return;
+        };
+        for idx in std::mem::replace(&mut leaf.imports, targets.clone()) {
+            if let Some(importers) = self.leaf_importers.get_mut(&idx) {
+                importers.remove(&key);
             }
         }
+        for idx in targets {
+            self.leaf_importers
+                .entry(idx)
+                .or_default()
+                .insert(key.clone());
+        }
+    }
+
+    /// Paths of the leaves importing a node.
+    fn leaf_dependents(&self, idx: NodeIndex) -> impl Iterator<Item = PathBuf> + '_ {
+        self.leaf_importers
+            .get(&idx)
+            .into_iter()
+            .flatten()
+            .filter_map(|key| Some(self.leaves.get(key)?.path.clone()))
     }
 
     /// Get all files that directly depend on (import) the given file.
     pub fn get_dependents(&self, path: &Path) -> Vec<PathBuf> {
-        let Some(&idx) = self.path_to_idx.get(path) else {
+        let Some(idx) = self.index_of(path) else {
             return Vec::new();
         };
 
@@ -108,16 +278,223 @@ impl DepGraph {
                     None
                 }
             })
+            .chain(self.leaf_dependents(idx))
             .collect()
     }
 
+    /// Get all files that the given file directly depends on (imports).
+    pub fn get_dependencies(&self, path: &Path) -> Vec<PathBuf> {
+        if let Some(leaf) = self.leaves.get(self.key(path).as_ref()) {
+            return leaf
+                .imports
+                .iter()
+                .filter_map(|&idx| self.path_of(idx))
+                .collect();
+        }
+        let Some(idx) = self.index_of(path) else {
+            return Vec::new();
+        };
+
+        self.graph
+            .edges_directed(idx, Direction::Outgoing)
+            .filter_map(|e| {
+                let target = e.target();
+                if let Some(GraphNode::Module(p)) = self.graph.node_weight(target) {
+                    Some(p.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Find one shortest import chain from `from` to `to`, following edges
+    /// in import direction (`from` imports ... imports `to`).
+    ///
+    /// Returns the files along the chain including both ends, or an empty
+    /// vector if either file is unknown or `to` is not reachable.
+    pub fn dependency_path(&self, from: &Path, to: &Path) -> Vec<PathBuf> {
+        let Some(goal) = self.index_of(to) else {
+            return Vec::new();
+        };
+        if let Some(leaf) = self.leaves.get(self.key(from).as_ref()) {
+            // A leaf has no node: search from its imports, then prepend it
+            let mut chain = self.shortest_chain(&leaf.imports, goal);
+            if !chain.is_empty() {
+                chain.insert(0, leaf.path.clone());
+            }
+            return chain;
+        }
+        match self.index_of(from) {
+            Some(start) => self.shortest_chain(&[start], goal),
+            None => Vec::new(),
+        }
+    }
+
+    /// Breadth-first search from `starts` to `goal` along import edges.
+    fn shortest_chain(&self, starts: &[NodeIndex], goal: NodeIndex) -> Vec<PathBuf> {
+        let mut parent: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue: VecDeque<NodeIndex> = starts.iter().copied().collect();
+        let mut seen: HashSet<NodeIndex> = starts.iter().copied().collect();
+        while let Some(idx) = queue.pop_front() {
+            if idx == goal {
+                return self.trace_path(&parent, goal);
+            }
+            for next in self.graph.neighbors_directed(idx, Direction::Outgoing) {
+                if seen.insert(next) {
+                    parent.insert(next, idx);
+                    queue.push_back(next);
+                }
+            }
+        }
+        Vec::new()
+    }
+
+    /// Walk BFS parents back from `goal` to the start node.
+    fn trace_path(&self, parent: &HashMap<NodeIndex, NodeIndex>, goal: NodeIndex) -> Vec<PathBuf> {
+        let mut chain = vec![goal];
+        let mut current = goal;
+        while let Some(&prev) = parent.get(&current) {
+            chain.push(prev);
+            current = prev;
+        }
+        chain
+            .into_iter()
+            .rev()
+            .filter_map(|idx| self.path_of(idx))
+            .collect()
+    }
+
+    fn path_of(&self, idx: NodeIndex) -> Option<PathBuf> {
+        let GraphNode::Module(path) = self.graph.node_weight(idx)?;
+        Some(path.clone())
+    }
+
     /// Remove a file and all its connected edges.
     pub fn remove_file(&mut self, path: &Path) {
-        if let Some(idx) = self.path_to_idx.remove(path) {
+        self.generation += 1;
+        let key = self.key(path).into_owned();
+        self.truncated_imports.remove(&key);
+        if self.leaves.contains_key(&key) {
+            self.update_leaf_imports(path, &[]);
+            self.leaves.remove(&key);
+        }
+        if let Some(idx) = self.path_to_idx.remove(&key) {
+            // Indices are reused, so leaves must not keep pointing at this one
+            for leaf_key in self.leaf_importers.remove(&idx).unwrap_or_default() {
+                if let Some(leaf) = self.leaves.get_mut(&leaf_key) {
+                    leaf.imports.retain(|&i| i != idx);
+                }
+            }
             self.graph.remove_node(idx);
         }
     }
 
+    /// Record that a file's imports were truncated from `count` (`None` once
+    /// a re-parse keeps them all), so its edges are known to be incomplete.
+    pub fn set_truncated_imports(&mut self, path: &Path, count: Option<usize>) {
+        let key = self.key(path).into_owned();
+        let previous = match count {
+            Some(count) => self
+                .truncated_imports
+                .insert(key, (path.to_path_buf(), count)),
+            None => self.truncated_imports.remove(&key),
+        };
+        if previous.map(|(_, count)| count) != count {
+            self.generation += 1;
+        }
+    }
+
+    /// Counter bumped by every mutation. Starts at 0 for a new graph, so it
+    /// only orders states of one graph instance.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Files with truncated imports and their full import counts, by path.
+    pub fn truncated_imports(&self) -> Vec<(PathBuf, usize)> {
+        let mut files: Vec<_> = self.truncated_imports.values().cloned().collect();
+        files.sort();
+        files
+    }
+
+    /// All files in the graph, test leaves included.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let modules = self
+            .graph
+            .node_indices()
+            .filter_map(|idx| self.path_of(idx));
+        modules
+            .chain(self.leaves.values().map(|leaf| leaf.path.clone()))
+            .collect()
+    }
+
+    /// Write every file and its imports to `path`, replacing it atomically.
+    ///
+    /// `key` identifies the settings the edges were resolved with and
+    /// `built_at` when the build started; `load` checks both.
+    pub fn save(&self, path: &Path, key: &str, built_at: SystemTime) -> std::io::Result<()> {
+        let files = self
+            .files()
+            .into_iter()
+            .map(|file| SavedFile {
+                imports: self.get_dependencies(&file),
+                truncated_from: self
+                    .truncated_imports
+                    .get(self.key(&file).as_ref())
+                    .map(|t| t.1),
+                path: file,
+            })
+            .collect();
+        let built_at = built_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let saved = SavedGraph {
+            key: key.to_string(),
+            built_at,
+            files,
+        };
+        let body = serde_json::to_string(&saved).map_err(Error::other)?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, format!("{SAVED_GRAPH_HEADER}\n{body}"))?;
+        std::fs::rename(&tmp, path)
+    }
+
+    /// Add the files and edges saved at `path` by `save`, returning when the
+    /// saved build started. Files modified after that need re-parsing.
+    ///
+    /// # Errors
+    /// Fails with `InvalidData`, leaving the graph untouched, if the file has
+    /// another format version or `key`, or does not parse.
+    pub fn load(&mut self, path: &Path, key: &str) -> std::io::Result<SystemTime> {
+        let content = std::fs::read_to_string(path)?;
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, msg);
+        let Some((header, body)) = content.split_once('\n') else {
+            return Err(invalid("missing header".to_string()));
+        };
+        if header != SAVED_GRAPH_HEADER {
+            return Err(invalid(format!("unsupported format {header:?}")));
+        }
+        let saved: SavedGraph = serde_json::from_str(body).map_err(|e| invalid(e.to_string()))?;
+        if saved.key != key {
+            return Err(invalid("saved with different settings".to_string()));
+        }
+        for file in &saved.files {
+            self.add_file(file.path.clone());
+        }
+        for file in &saved.files {
+            let imports: Vec<PathBuf> = file
+                .imports
+                .iter()
+                .filter(|p| self.add_file((*p).clone()))
+                .cloned()
+                .collect();
+            self.update_edges(&file.path, &imports);
+            self.set_truncated_imports(&file.path, file.truncated_from);
+        }
+        Ok(UNIX_EPOCH + Duration::from_secs(saved.built_at))
+    }
+
     /// Check if graph has overflowed.
     pub fn is_overflow(&self) -> bool {
         self.overflow
@@ -128,6 +505,16 @@ impl DepGraph {
         self.graph.node_count()
     }
 
+    /// True if no file has been added.
+    pub fn is_empty(&self) -> bool {
+        self.graph.node_count() == 0 && self.leaves.is_empty()
+    }
+
+    /// Get the number of test files kept outside the graph as leaves.
+    pub fn leaf_count(&self) -> usize {
+        self.leaves.len()
+    }
+
     /// Get current edge count.
     pub fn edge_count(&self) -> usize {
         self.graph.edge_count()
@@ -135,9 +522,59 @@ impl DepGraph {
 
     /// Check if graph contains a file.
     pub fn contains(&self, path: &Path) -> bool {
-        self.path_to_idx.contains_key(path)
+        self.index_of(path).is_some() || self.leaves.contains_key(self.key(path).as_ref())
     }
 
+    /// Get modules with more than `threshold` distinct direct dependents,
+    /// most-imported first.
+    pub fn hub_modules(&self, threshold: usize) -> Vec<HubModule> {
+        let mut hubs: Vec<HubModule> = self
+            .graph
+            .node_indices()
+            .filter_map(|idx| {
+                let dependents = self
+                    .graph
+                    .edges_directed(idx, Direction::Incoming)
+                    .map(|e| e.source())
+                    .collect::<HashSet<_>>()
+                    .len()
+                    + self.leaf_importers.get(&idx).map_or(0, HashSet::len);
+                if dependents <= threshold {
+                    return None;
+                }
+                let GraphNode::Module(path) = self.graph.node_weight(idx)?;
+                Some(HubModule {
+                    path: path.clone(),
+                    dependents,
+                })
+            })
+            .collect();
+        hubs.sort_by(|a, b| {
+            b.dependents
+                .cmp(&a.dependents)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        hubs
+    }
+
+    /// Get import cycles: the strongly connected components of more than one
+    /// module, each as sorted paths. Linear in nodes and edges.
+    pub fn find_cycles(&self) -> Vec<Vec<PathBuf>> {
+        let mut cycles: Vec<Vec<PathBuf>> = tarjan_scc(&self.graph)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .map(|component| {
+                let mut paths: Vec<PathBuf> = component
+                    .into_iter()
+                    .filter_map(|idx| self.path_of(idx))
+                    .collect();
+                paths.sort();
+                paths
+            })
+            .collect();
+        cycles.sort();
+        cycles
+    }
 }
 
 /// Thread-safe wrapper around `DepGraph`.
@@ -149,6 +586,7 @@ pub fn new_shared_graph() -> SharedDepGraph {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
 
@@ -156,8 +594,7 @@ mod tests {
     fn add_file_creates_node() {
         let mut graph = DepGraph::new();
         let path = PathBuf::from("/src/foo.ts");
-        let idx = graph.add_file(path.clone());
-        assert!(idx.is_some());
+        assert!(graph.add_file(path.clone()));
         assert!(graph.contains(&path));
         assert_eq!(graph.node_count(), 1);
     }
@@ -172,6 +609,28 @@ mod tests {
         assert_eq!(graph.node_count(), 1);
     }
 
+    #[test]
+    fn case_variants_share_a_node_when_case_insensitive() {
+        let mut graph = DepGraph::new();
+        graph.set_case_insensitive(true);
+        let upper = PathBuf::from("/src/Foo.ts");
+        let lower = PathBuf::from("/src/foo.ts");
+        let a = PathBuf::from("/src/a.ts");
+        graph.add_file(upper.clone());
+        graph.add_file(a.clone());
+        assert_eq!(graph.add_file(lower.clone()), graph.add_file(upper.clone()));
+        assert_eq!(graph.node_count(), 2);
+
+        graph.update_edges(&a, std::slice::from_ref(&lower));
+        assert_eq!(graph.get_dependents(&upper), vec![a.clone()]);
+        assert_eq!(graph.get_dependencies(&a), vec![upper.clone()]);
+
+        let mut sensitive = DepGraph::new();
+        sensitive.add_file(upper);
+        sensitive.add_file(lower);
+        assert_eq!(sensitive.node_count(), 2);
+    }
+
     #[test]
     fn update_edges_creates_edges() {
         let mut graph = DepGraph::new();
@@ -191,6 +650,28 @@ mod tests {
         assert_eq!(graph.get_dependents(&c), vec![a.clone()]);
     }
 
+    #[test]
+    fn dependency_path_follows_import_chain() {
+        let mut graph = DepGraph::new();
+        let a = PathBuf::from("/src/a.ts");
+        let b = PathBuf::from("/src/b.ts");
+        let c = PathBuf::from("/src/c.ts");
+        for p in [&a, &b, &c] {
+            graph.add_file(p.clone());
+        }
+
+        // a imports b, b imports c
+        graph.update_edges(&a, std::slice::from_ref(&b));
+        graph.update_edges(&b, std::slice::from_ref(&c));
+
+        assert_eq!(
+            graph.dependency_path(&a, &c),
+            vec![a.clone(), b.clone(), c.clone()]
+        );
+        assert!(graph.dependency_path(&c, &a).is_empty());
+        assert_eq!(graph.dependency_path(&a, &a), vec![a.clone()]);
+    }
+
     #[test]
     fn update_edges_removes_old_edges() {
         let mut graph = DepGraph::new();
@@ -203,17 +684,150 @@ mod tests {
         graph.add_file(c.clone());
 
         // Initially a imports b
-        graph.update_edges(&a, &[b.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&b));
         assert_eq!(graph.edge_count(), 1);
         assert_eq!(graph.get_dependents(&b), vec![a.clone()]);
 
         // Now a imports only c
-        graph.update_edges(&a, &[c.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&c));
         assert_eq!(graph.edge_count(), 1);
         assert!(graph.get_dependents(&b).is_empty());
         assert_eq!(graph.get_dependents(&c), vec![a.clone()]);
     }
 
+    #[test]
+    fn repeated_import_yields_one_edge() {
+        let mut graph = DepGraph::new();
+        let a = PathBuf::from("/src/a.ts");
+        let x = PathBuf::from("/src/x.ts");
+        graph.add_file(a.clone());
+        graph.add_file(x.clone());
+
+        // import { a } from './x'; import type { B } from './x';
+        graph.update_edges(&a, &[x.clone(), x.clone()]);
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.get_dependents(&x), vec![a]);
+    }
+
+    #[test]
+    fn test_leaves_are_not_import_targets() {
+        let mut graph = DepGraph::new();
+        graph.set_test_leaves(true);
+        let a = PathBuf::from("/src/a.ts");
+        let test = PathBuf::from("/src/a.test.ts");
+        let helper = PathBuf::from("/src/helper.test.ts");
+        for path in [&a, &test, &helper] {
+            assert!(graph.add_file(path.clone()));
+        }
+
+        graph.update_edges(&test, &[a.clone(), helper.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&test));
+        assert_eq!(
+            (graph.node_count(), graph.leaf_count(), graph.edge_count()),
+            (1, 2, 0)
+        );
+        assert!(graph.contains(&test));
+        assert!(graph.get_dependents(&test).is_empty());
+        assert_eq!(graph.get_dependents(&a), vec![test.clone()]);
+        assert_eq!(graph.get_dependencies(&test), vec![a.clone()]);
+        assert_eq!(
+            graph.dependency_path(&test, &a),
+            vec![test.clone(), a.clone()]
+        );
+
+        graph.remove_file(&a);
+        assert!(graph.get_dependencies(&test).is_empty());
+    }
+
+    #[test]
+    fn get_dependencies_returns_imports() {
+        let mut graph = DepGraph::new();
+        let a = PathBuf::from("/src/a.ts");
+        let b = PathBuf::from("/src/b.ts");
+
+        graph.add_file(a.clone());
+        graph.add_file(b.clone());
+        graph.update_edges(&a, std::slice::from_ref(&b));
+
+        assert_eq!(graph.get_dependencies(&a), vec![b.clone()]);
+        assert!(graph.get_dependencies(&b).is_empty());
+    }
+
+    #[test]
+    fn saved_graph_loads_with_matching_format_and_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("graph.bin");
+        let mut graph = DepGraph::new();
+        graph.set_test_leaves(true);
+        let a = PathBuf::from("/src/a.ts");
+        let b = PathBuf::from("/src/b.ts");
+        let test = PathBuf::from("/src/a.test.ts");
+        for path in [&a, &b, &test] {
+            graph.add_file(path.clone());
+        }
+        graph.update_edges(&a, std::slice::from_ref(&b));
+        graph.update_edges(&test, std::slice::from_ref(&a));
+        graph.set_truncated_imports(&a, Some(900));
+        let built_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        graph.save(&cache, "key", built_at).unwrap();
+
+        let mut loaded = DepGraph::new();
+        loaded.set_test_leaves(true);
+        assert_eq!(loaded.load(&cache, "key").unwrap(), built_at);
+        assert_eq!(loaded.get_dependencies(&a), vec![b.clone()]);
+        assert_eq!(loaded.get_dependents(&a), vec![test]);
+        assert_eq!(loaded.truncated_imports(), vec![(a, 900)]);
+        assert_eq!(loaded.leaf_count(), 1);
+
+        let err = DepGraph::new().load(&cache, "other").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let body = std::fs::read_to_string(&cache).unwrap();
+        std::fs::write(&cache, body.replacen(SAVED_GRAPH_HEADER, "zax-graph 0", 1)).unwrap();
+        let mut stale = DepGraph::new();
+        assert_eq!(
+            stale.load(&cache, "key").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        assert!(stale.is_empty());
+    }
+
+    #[test]
+    fn mutations_bump_generation() {
+        let mut graph = DepGraph::new();
+        let a = PathBuf::from("/src/a.ts");
+        let b = PathBuf::from("/src/b.ts");
+        graph.add_file(a.clone());
+        graph.add_file(b.clone());
+        let added = graph.generation();
+        assert_eq!(added, 2);
+        graph.add_file(a.clone());
+        assert_eq!(graph.generation(), added);
+
+        graph.update_edges(&a, std::slice::from_ref(&b));
+        graph.set_truncated_imports(&a, Some(600));
+        graph.set_truncated_imports(&a, Some(600));
+        let edged = graph.generation();
+        assert_eq!(edged, added + 2);
+        graph.remove_file(&b);
+        assert!(graph.generation() > edged);
+    }
+
+    #[test]
+    fn find_cycles_reports_components_larger_than_one() {
+        let mut graph = DepGraph::new();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|n| PathBuf::from(format!("/src/{n}.ts")));
+        for path in [&a, &b, &c, &d] {
+            graph.add_file(path.clone());
+        }
+        graph.update_edges(&a, std::slice::from_ref(&b));
+        graph.update_edges(&b, &[c.clone(), a.clone()]);
+        graph.update_edges(&d, std::slice::from_ref(&d));
+
+        assert_eq!(graph.find_cycles(), vec![vec![a.clone(), b.clone()]]);
+        graph.update_edges(&b, &[c]);
+        assert!(graph.find_cycles().is_empty());
+    }
+
     #[test]
     fn remove_file_removes_node_and_edges() {
         let mut graph = DepGraph::new();
@@ -222,7 +836,7 @@ mod tests {
 
         graph.add_file(a.clone());
         graph.add_file(b.clone());
-        graph.update_edges(&a, &[b.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&b));
 
         assert_eq!(graph.node_count(), 2);
         assert_eq!(graph.edge_count(), 1);
@@ -248,9 +862,9 @@ mod tests {
         graph.add_file(util.clone());
 
         // a, b, c all import util
-        graph.update_edges(&a, &[util.clone()]);
-        graph.update_edges(&b, &[util.clone()]);
-        graph.update_edges(&c, &[util.clone()]);
+        graph.update_edges(&a, std::slice::from_ref(&util));
+        graph.update_edges(&b, std::slice::from_ref(&util));
+        graph.update_edges(&c, std::slice::from_ref(&util));
 
         let dependents = graph.get_dependents(&util);
         assert_eq!(dependents.len(), 3);
@@ -263,17 +877,17 @@ mod tests {
     fn overflow_at_max_nodes() {
         let mut graph = DepGraph::new();
 
-        for i in 0..MAX_GRAPH_NODES {
+        for i in 0..DEFAULT_MAX_GRAPH_NODES {
             let path = PathBuf::from(format!("/src/file{i}.ts"));
-            assert!(graph.add_file(path).is_some());
+            assert!(graph.add_file(path));
         }
 
         assert!(!graph.is_overflow());
-        assert_eq!(graph.node_count(), MAX_GRAPH_NODES);
+        assert_eq!(graph.node_count(), DEFAULT_MAX_GRAPH_NODES);
 
         // One more should trigger overflow
         let extra = PathBuf::from("/src/extra.ts");
-        assert!(graph.add_file(extra).is_none());
+        assert!(!graph.add_file(extra));
         assert!(graph.is_overflow());
     }
 
@@ -291,4 +905,28 @@ mod tests {
             assert_eq!(g.node_count(), 1);
         }
     }
+
+    #[test]
+    fn hub_modules_reports_fan_in_above_threshold() {
+        let mut graph = DepGraph::new();
+        let hub = PathBuf::from("/ws/hub.ts");
+        let leaf = PathBuf::from("/ws/leaf.ts");
+        graph.add_file(hub.clone());
+        graph.add_file(leaf.clone());
+        for i in 0..3 {
+            let importer = PathBuf::from(format!("/ws/m{i}.ts"));
+            graph.add_file(importer.clone());
+            graph.update_edges(&importer, &[hub.clone(), hub.clone()]);
+        }
+        graph.update_edges(&PathBuf::from("/ws/m0.ts"), &[hub.clone(), leaf]);
+
+        assert_eq!(
+            graph.hub_modules(2),
+            vec![HubModule {
+                path: hub,
+                dependents: 3
+            }]
+        );
+        assert!(graph.hub_modules(3).is_empty());
+    }
 }
@@ -3,16 +3,22 @@
 //! Provides file watching, import parsing, dependency graph, and affected computation
 //! to enable running only tests affected by changed files.
 
+pub mod breaker;
 pub mod compute;
+pub mod config_files;
+pub mod diff;
 pub mod discovery;
 pub mod graph;
 pub mod parser;
 pub mod resolver;
 pub mod state;
+pub mod symbols;
+pub mod test_mappings;
 pub mod watcher;
 
 // Re-export key types used by main.rs
-pub use graph::SharedDepGraph;
-pub use parser::parse_imports;
-pub use resolver::PathResolver;
-pub use state::AffectedState;
+pub use graph::{SharedDepGraph, DEFAULT_FAN_IN_THRESHOLD, DEFAULT_MAX_GRAPH_NODES};
+pub use parser::{parse_file, ParseLimits};
+pub use resolver::{PathResolver, SharedNegativeCache, DEFAULT_ROOT_ALIAS};
+pub use state::{AffectedState, DEFAULT_FULL_RUN_RATIO, DEFAULT_LOCK_TIMEOUT};
+pub use watcher::{DEFAULT_DEBOUNCE_MS, DEFAULT_MAX_DIRTY_FILES};
@@ -1,16 +1,38 @@
 //! TypeScript/JavaScript import parser using tree-sitter.
 //!
-//! Extracts static import statements from TS/JS files for dependency graph construction.
-#![allow(clippy::print_stderr)]
+//! Extracts import statements from TS/JS files for dependency graph construction.
 
+use crate::logging::log_line;
 use std::path::Path;
 use tree_sitter::{Parser, Query, QueryCursor, StreamingIterator};
 
-/// Maximum number of imports to extract per file.
-const MAX_IMPORTS_PER_FILE: usize = 500;
+/// Default maximum number of imports to extract per file.
+pub const DEFAULT_MAX_IMPORTS_PER_FILE: usize = 500;
 /// Maximum path length for logging.
 const MAX_PATH_LOG_LENGTH: usize = 256;
 
+/// Size thresholds above which a file is treated as generated or minified
+/// and skipped without invoking tree-sitter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// Maximum length of any single line in bytes.
+    pub max_line_length: usize,
+    /// Maximum total file size in bytes.
+    pub max_file_size: usize,
+    /// Imports beyond this many per file are dropped (and reported).
+    pub max_imports: usize,
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        Self {
+            max_line_length: 10_000,
+            max_file_size: 1024 * 1024,
+            max_imports: DEFAULT_MAX_IMPORTS_PER_FILE,
+        }
+    }
+}
+
 /// Kind of import statement.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ImportKind {
@@ -22,12 +44,18 @@ pub enum ImportKind {
     Namespace,
     /// `export { x } from './path'`
     ReExportNamed,
+    /// `export { default } from './path'`, `export { default as X } from './path'`
+    ReExportDefault,
     /// `export * from './path'`
     ReExportAll,
     /// `require('./path')`
     Require,
     /// `import type { x } from './path'`
     TypeOnly,
+    /// `vi.mock('./path')`, `jest.mock('./path')`, `require.resolve('./path')`
+    Mock,
+    /// `import('./path')`, including `import('./path').then(...)` and `await import('./path')`
+    Dynamic,
 }
 
 /// A parsed import statement.
@@ -39,60 +67,131 @@ pub struct ImportStatement {
     pub kind: ImportKind,
 }
 
+/// Imports of one file, noting whether any were dropped.
+#[derive(Debug, Default)]
+pub struct ParsedImports {
+    pub imports: Vec<ImportStatement>,
+    /// Imports found before truncating to `max_imports`; `None` if none
+    /// were dropped.
+    pub truncated_from: Option<usize>,
+}
+
 /// Parse imports from a TypeScript/JavaScript file.
 ///
-/// Returns empty Vec on parse errors (logged as warnings).
-/// Truncates to first 500 imports if exceeded (logged as warning).
-pub fn parse_imports(path: &Path) -> Vec<ImportStatement> {
+/// Returns no imports on parse errors (logged as warnings).
+/// Returns no imports for files exceeding `limits` (logged as info).
+/// Truncates to the first `limits.max_imports` imports if exceeded (logged
+/// as warning), reporting it so callers can record files whose edges are
+/// incomplete.
+pub fn parse_file(path: &Path, limits: ParseLimits) -> ParsedImports {
+    read_source(path, limits)
+        .map(|content| parse_source(&content, path, limits.max_imports))
+        .unwrap_or_default()
+}
+
+/// Read a file for parsing. Returns `None` for unreadable files (logged as
+/// warnings) and files exceeding `limits` (logged as info).
+pub(super) fn read_source(path: &Path, limits: ParseLimits) -> Option<String> {
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.len() > limits.max_file_size as u64 {
+            log_info_skip(path, &format!("{} bytes", metadata.len()));
+            return None;
+        }
+    }
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
         Err(e) => {
             log_warn_parse_error(path, &format!("read error: {e}"));
-            return Vec::new();
+            return None;
         }
     };
-    parse_imports_from_str(&content, path)
-}
-
-/// Parse imports from source string (for testing).
-pub fn parse_imports_from_str(content: &str, path: &Path) -> Vec<ImportStatement> {
-    let mut parser = Parser::new();
-    let language = get_language_for_path(path);
-    if parser.set_language(&language).is_err() {
-        log_warn_parse_error(path, "failed to set language");
-        return Vec::new();
+    let longest = content.lines().map(str::len).max().unwrap_or(0);
+    if longest > limits.max_line_length {
+        log_info_skip(path, &format!("line of {longest} bytes"));
+        return None;
     }
+    Some(content)
+}
 
-    let Some(tree) = parser.parse(content, None) else {
-        log_warn_parse_error(path, "parse returned None");
-        return Vec::new();
+/// Parse imports from source string, keeping at most `max_imports`.
+fn parse_source(content: &str, path: &Path, max_imports: usize) -> ParsedImports {
+    let (tree, language) = match parse_with_fallback(content, path) {
+        Ok(parsed) => parsed,
+        Err(reason) => {
+            log_warn_parse_error(path, reason);
+            return ParsedImports::default();
+        }
     };
 
-    let root = tree.root_node();
-    if root.has_error() {
-        log_warn_parse_error(path, "syntax errors in file");
-        return Vec::new();
+    let mut imports = extract_imports(content, &tree.root_node(), &language);
+
+    let mut truncated_from = None;
+    if imports.len() > max_imports {
+        log_warn_import_limit(path, imports.len(), max_imports);
+        truncated_from = Some(imports.len());
+        imports.truncate(max_imports);
+    }
+
+    ParsedImports {
+        imports,
+        truncated_from,
     }
+}
 
-    let mut imports = extract_imports(content, &root);
+/// Parse `content` with the grammar for `path`, rejecting syntax errors.
+///
+/// A `.ts` file that fails to parse is retried with the TSX grammar, since
+/// misnamed or loader-handled files sometimes contain JSX.
+pub(super) fn parse_with_fallback(
+    content: &str,
+    path: &Path,
+) -> Result<(tree_sitter::Tree, tree_sitter::Language), &'static str> {
+    let language = get_language_for_path(path);
+    match parse_tree(content, &language) {
+        Ok(tree) => Ok((tree, language)),
+        Err(reason) if !is_tsx(path) => {
+            let tsx: tree_sitter::Language = tree_sitter_typescript::LANGUAGE_TSX.into();
+            parse_tree(content, &tsx)
+                .map(|tree| (tree, tsx))
+                .map_err(|_| reason)
+        }
+        Err(reason) => Err(reason),
+    }
+}
 
-    if imports.len() > MAX_IMPORTS_PER_FILE {
-        log_warn_import_limit(path, imports.len());
-        imports.truncate(MAX_IMPORTS_PER_FILE);
+/// Parse `content` with `language`, rejecting trees with syntax errors.
+fn parse_tree(
+    content: &str,
+    language: &tree_sitter::Language,
+) -> Result<tree_sitter::Tree, &'static str> {
+    let mut parser = Parser::new();
+    if parser.set_language(language).is_err() {
+        return Err("failed to set language");
+    }
+    let tree = parser.parse(content, None).ok_or("parse returned None")?;
+    if tree.root_node().has_error() {
+        return Err("syntax errors in file");
     }
+    Ok(tree)
+}
 
-    imports
+fn is_tsx(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("tsx")
 }
 
 fn get_language_for_path(path: &Path) -> tree_sitter::Language {
-    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
-    match ext {
-        "tsx" => tree_sitter_typescript::LANGUAGE_TSX.into(),
-        _ => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+    if is_tsx(path) {
+        tree_sitter_typescript::LANGUAGE_TSX.into()
+    } else {
+        tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into()
     }
 }
 
-fn extract_imports(content: &str, root: &tree_sitter::Node) -> Vec<ImportStatement> {
+pub(super) fn extract_imports(
+    content: &str,
+    root: &tree_sitter::Node,
+    language: &tree_sitter::Language,
+) -> Vec<ImportStatement> {
     let mut imports = Vec::new();
 
     // Query for import and export statements
@@ -102,9 +201,25 @@ fn extract_imports(content: &str, root: &tree_sitter::Node) -> Vec<ImportStateme
         (call_expression
             function: (identifier) @func (#eq? @func "require")
             arguments: (arguments (string) @source))
+        (call_expression
+            function: (member_expression) @callee
+                (#match? @callee "^(vi|jest)\\.(mock|doMock)$|^require\\.resolve$")
+            arguments: (arguments . (string) @source))
+        (call_expression
+            function: (import)
+            arguments: (arguments . (string) @source))
     "#;
 
-    let Ok(query) = Query::new(&get_language_for_path(Path::new("x.ts")), query_str) else {
+    let query = match Query::new(language, query_str) {
+        Ok(query) => query,
+        Err(e) => {
+            log_line!("[affected] ERROR: invalid import query: {e}");
+            return imports;
+        }
+    };
+    // Every pattern must capture `source`; without it nothing would match
+    let Some(source_index) = query.capture_index_for_name("source") else {
+        log_line!("[affected] ERROR: import query has no @source capture");
         return imports;
     };
 
@@ -112,12 +227,9 @@ fn extract_imports(content: &str, root: &tree_sitter::Node) -> Vec<ImportStateme
     let mut matches = cursor.matches(&query, *root, content.as_bytes());
 
     while let Some(m) = matches.next() {
-        for capture in m.captures {
-            let node = capture.node;
-            if capture.index == query.capture_index_for_name("source").unwrap_or(999) {
-                if let Some(import) = extract_import_from_node(content, &node, root) {
-                    imports.push(import);
-                }
+        for capture in m.captures.iter().filter(|c| c.index == source_index) {
+            if let Some(import) = extract_import_from_node(content, &capture.node, root) {
+                imports.push(import);
             }
         }
     }
@@ -156,7 +268,7 @@ fn determine_import_kind(
     match parent.kind() {
         "import_statement" => classify_import_statement(content, parent),
         "export_statement" => classify_export_statement(content, parent),
-        "arguments" => ImportKind::Require,
+        "arguments" => classify_call_expression(parent),
         _ => ImportKind::Named,
     }
 }
@@ -190,20 +302,59 @@ fn classify_export_statement(content: &str, node: &tree_sitter::Node) -> ImportK
         return ImportKind::ReExportAll;
     }
 
+    // export { default } from './path', export { default as X } from './path'
+    if reexports_default(content, node) {
+        return ImportKind::ReExportDefault;
+    }
+
     // export { x } from './path'
     ImportKind::ReExportNamed
 }
 
-fn log_warn_parse_error(path: &Path, reason: &str) {
+/// Check whether an export clause re-exports the source module's `default`.
+/// `export { x as default }` re-exports a named binding and does not count.
+fn reexports_default(content: &str, node: &tree_sitter::Node) -> bool {
+    let mut cursor = node.walk();
+    let Some(clause) = node
+        .children(&mut cursor)
+        .find(|c| c.kind() == "export_clause")
+    else {
+        return false;
+    };
+    let mut clause_cursor = clause.walk();
+    let found = clause
+        .named_children(&mut clause_cursor)
+        .filter_map(|spec| spec.child_by_field_name("name"))
+        .any(|name| name.utf8_text(content.as_bytes()) == Ok("default"));
+    found
+}
+
+fn classify_call_expression(arguments: &tree_sitter::Node) -> ImportKind {
+    // require('./x') has an identifier callee; vi.mock/jest.mock/require.resolve
+    // have a member expression callee; import('./x') has an `import` callee
+    let callee = arguments
+        .parent()
+        .and_then(|call| call.child_by_field_name("function"));
+    match callee.map(|n| n.kind()) {
+        Some("member_expression") => ImportKind::Mock,
+        Some("import") => ImportKind::Dynamic,
+        _ => ImportKind::Require,
+    }
+}
+
+pub(super) fn log_warn_parse_error(path: &Path, reason: &str) {
+    let display = truncate_path(path);
+    log_line!("[affected] WARN: parse error in {display}: {reason}");
+}
+
+fn log_info_skip(path: &Path, reason: &str) {
     let display = truncate_path(path);
-    eprintln!("[affected] WARN: parse error in {display}: {reason}");
+    log_line!("[affected] INFO: skipping likely generated file {display}: {reason}");
 }
 
-fn log_warn_import_limit(path: &Path, count: usize) {
+fn log_warn_import_limit(path: &Path, count: usize, max_imports: usize) {
     let display = truncate_path(path);
-    eprintln!(
-        "[affected] WARN: {display} has {count} imports, truncating to {MAX_IMPORTS_PER_FILE}"
-    );
+    log_line!("[affected] WARN: {display} has {count} imports, truncating to {max_imports}");
 }
 
 fn truncate_path(path: &Path) -> String {
@@ -216,12 +367,44 @@ fn truncate_path(path: &Path) -> String {
 }
 
 #[cfg(test)]
+#[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
     use std::path::PathBuf;
 
     fn parse(content: &str) -> Vec<ImportStatement> {
-        parse_imports_from_str(content, Path::new("test.ts"))
+        parse_source(content, Path::new("test.ts"), DEFAULT_MAX_IMPORTS_PER_FILE).imports
+    }
+
+    #[test]
+    fn skips_minified_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.js");
+        let body = "var a=1;".repeat(2_000);
+        std::fs::write(&path, format!("import './x';{body}")).unwrap();
+        assert!(parse_file(&path, ParseLimits::default()).imports.is_empty());
+    }
+
+    #[test]
+    fn skips_oversized_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.ts");
+        std::fs::write(&path, "import './x';\nconst a = 1;\n").unwrap();
+        let limits = ParseLimits {
+            max_file_size: 10,
+            ..ParseLimits::default()
+        };
+        assert!(parse_file(&path, limits).imports.is_empty());
+    }
+
+    #[test]
+    fn parses_normal_multiline_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.ts");
+        std::fs::write(&path, "import { x } from './x';\nexport const a = x;\n").unwrap();
+        let imports = parse_file(&path, ParseLimits::default()).imports;
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].specifier, "./x");
     }
 
     #[test]
@@ -256,6 +439,24 @@ mod tests {
         assert_eq!(imports[0].kind, ImportKind::ReExportNamed);
     }
 
+    #[test]
+    fn extracts_reexport_default() {
+        for content in [
+            "export { default } from './bar';",
+            "export { default as Bar } from './bar';",
+        ] {
+            let imports = parse(content);
+            assert_eq!(imports.len(), 1, "{content}");
+            assert_eq!(imports[0].specifier, "./bar");
+            assert_eq!(imports[0].kind, ImportKind::ReExportDefault, "{content}");
+        }
+        // Renaming a named export to default still re-exports a named binding
+        assert_eq!(
+            parse("export { foo as default } from './bar';")[0].kind,
+            ImportKind::ReExportNamed
+        );
+    }
+
     #[test]
     fn extracts_reexport_all() {
         let imports = parse("export * from './bar';");
@@ -280,6 +481,87 @@ mod tests {
         assert_eq!(imports[0].kind, ImportKind::TypeOnly);
     }
 
+    #[test]
+    fn extracts_vi_mock() {
+        let imports = parse("vi.mock('./x', () => ({ x: 1 }));");
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].specifier, "./x");
+        assert_eq!(imports[0].kind, ImportKind::Mock);
+    }
+
+    #[test]
+    fn extracts_jest_mock() {
+        let imports = parse("jest.mock('./x');");
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].kind, ImportKind::Mock);
+    }
+
+    #[test]
+    fn extracts_require_resolve() {
+        let imports = parse("const p = require.resolve('./y');");
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].specifier, "./y");
+        assert_eq!(imports[0].kind, ImportKind::Mock);
+    }
+
+    #[test]
+    fn extracts_dynamic_import_with_then() {
+        let imports = parse("import('./x').then(m => m.foo);");
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].specifier, "./x");
+        assert_eq!(imports[0].kind, ImportKind::Dynamic);
+    }
+
+    #[test]
+    fn extracts_awaited_dynamic_import() {
+        let imports = parse("async function f() { const m = await import('./x'); }");
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].specifier, "./x");
+        assert_eq!(imports[0].kind, ImportKind::Dynamic);
+    }
+
+    #[test]
+    fn ignores_other_member_calls() {
+        assert!(parse("console.log('./x'); foo.mock('./y');").is_empty());
+    }
+
+    #[test]
+    fn extracts_every_supported_form_exactly_once() {
+        let content = r#"
+            import { a } from './named';
+            import b from './default';
+            import * as c from './namespace';
+            import type { D } from './type';
+            export { e } from './reexport';
+            export { default as E } from './reexport-default';
+            export * from './reexport-all';
+            const f = require('./require');
+            vi.mock('./vi-mock');
+            jest.mock('./jest-mock');
+            const g = require.resolve('./resolve');
+            import('./dynamic').then(m => m.h);
+        "#;
+        let imports = parse(content);
+        let kinds: Vec<ImportKind> = imports.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ImportKind::Named,
+                ImportKind::Default,
+                ImportKind::Namespace,
+                ImportKind::TypeOnly,
+                ImportKind::ReExportNamed,
+                ImportKind::ReExportDefault,
+                ImportKind::ReExportAll,
+                ImportKind::Require,
+                ImportKind::Mock,
+                ImportKind::Mock,
+                ImportKind::Mock,
+                ImportKind::Dynamic,
+            ]
+        );
+    }
+
     #[test]
     fn extracts_multiple_imports() {
         let content = r#"
@@ -301,6 +583,16 @@ mod tests {
         assert!(imports.is_empty());
     }
 
+    #[test]
+    fn recovers_jsx_in_ts_file_via_tsx_fallback() {
+        let content =
+            "import { Button } from './button';\nexport const App = () => <Button label=\"x\" />;";
+        let imports =
+            parse_source(content, Path::new("app.ts"), DEFAULT_MAX_IMPORTS_PER_FILE).imports;
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].specifier, "./button");
+    }
+
     #[test]
     fn truncates_at_500_imports() {
         let mut content = String::new();
@@ -308,7 +600,29 @@ mod tests {
             content.push_str(&format!("import {{ x{i} }} from './m{i}';\n"));
         }
         let imports = parse(&content);
-        assert_eq!(imports.len(), MAX_IMPORTS_PER_FILE);
+        assert_eq!(imports.len(), DEFAULT_MAX_IMPORTS_PER_FILE);
+    }
+
+    #[test]
+    fn larger_import_limit_keeps_more_imports() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client.ts");
+        let content: String = (0..600)
+            .map(|i| format!("export {{ x{i} }} from './m{i}';\n"))
+            .collect();
+        std::fs::write(&path, content).unwrap();
+
+        let default = parse_file(&path, ParseLimits::default());
+        assert_eq!(default.imports.len(), DEFAULT_MAX_IMPORTS_PER_FILE);
+        assert_eq!(default.truncated_from, Some(600));
+
+        let limits = ParseLimits {
+            max_imports: 1000,
+            ..ParseLimits::default()
+        };
+        let raised = parse_file(&path, limits);
+        assert_eq!(raised.imports.len(), 600);
+        assert_eq!(raised.truncated_from, None);
     }
 
     #[test]
@@ -2,57 +2,219 @@
 //!
 //! Resolves import specifiers to absolute paths, handling tsconfig paths,
 //! package.json exports, and various module resolution strategies.
-#![allow(clippy::print_stderr)]
+//!
+//! Supported tsconfig `paths` forms, with or without `baseUrl`:
+//! - wildcard aliases: `"@lib/*": ["src/lib/*"]`
+//! - exact aliases: `"@config": ["src/config.ts"]`, matching only the bare
+//!   specifier (`@config/x` does not match)
+//! - targets with or without an extension
+//!
+//! An optional root alias prefix (`~/` by default) is rewritten to the
+//! workspace root for configs that use it without declaring it in `paths`.
+//!
+//! Without a tsconfig (plain-JS workspaces) relative and package imports
+//! still resolve; only `paths` aliases are unavailable.
+//!
+//! Bare specifiers that fail to resolve (usually external packages) can be
+//! remembered in a shared LRU, so re-parsing a file on every save does not
+//! retry and re-warn them. Owners clear it when config files change, and when
+//! a source file is created since a tsconfig alias or workspace package
+//! specifier may resolve to it.
 
-use oxc_resolver::{ResolveOptions, Resolver, TsconfigDiscovery, TsconfigOptions, TsconfigReferences};
+use crate::logging::log_line;
+use crate::normalize::path::PathMode;
+use oxc_resolver::{
+    ResolveOptions, Resolver, TsconfigDiscovery, TsconfigOptions, TsconfigReferences,
+};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
 
 /// Maximum path length for logging.
 const MAX_PATH_LOG_LENGTH: usize = 256;
+/// Root alias prefix used when the alias is enabled without a value.
+pub const DEFAULT_ROOT_ALIAS: &str = "~/";
+/// Default number of unresolvable `(directory, specifier)` pairs remembered.
+pub const DEFAULT_NEGATIVE_CACHE_SIZE: usize = 4096;
+
+/// Resolvers are rebuilt per graph update, so the missing-tsconfig notice is
+/// logged once per process.
+static MISSING_TSCONFIG_LOGGED: Once = Once::new();
+
+/// Least-recently-used set of bare specifiers that failed to resolve from a
+/// directory.
+#[derive(Debug)]
+pub struct NegativeCache {
+    capacity: usize,
+    entries: HashMap<(PathBuf, String), u64>,
+    /// Entries keyed by last use, oldest first.
+    by_use: BTreeMap<u64, (PathBuf, String)>,
+    tick: u64,
+    /// Negative results recorded, each logged once.
+    recorded: u64,
+}
+
+/// Negative cache shared by the resolvers of one workspace.
+pub type SharedNegativeCache = Arc<Mutex<NegativeCache>>;
+
+impl NegativeCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            by_use: BTreeMap::new(),
+            tick: 0,
+            recorded: 0,
+        }
+    }
+
+    /// Create an empty shared cache.
+    pub fn shared(capacity: usize) -> SharedNegativeCache {
+        Arc::new(Mutex::new(Self::new(capacity)))
+    }
+
+    /// Check for a remembered failure, marking it recently used.
+    fn contains(&mut self, key: &(PathBuf, String)) -> bool {
+        let Some(last_use) = self.entries.get(key).copied() else {
+            return false;
+        };
+        self.by_use.remove(&last_use);
+        self.touch(key.clone());
+        true
+    }
+
+    /// Remember a failure, evicting the least recently used entry when full.
+    fn insert(&mut self, key: (PathBuf, String)) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.by_use.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.recorded += 1;
+        self.touch(key);
+    }
+
+    fn touch(&mut self, key: (PathBuf, String)) {
+        self.tick += 1;
+        self.entries.insert(key.clone(), self.tick);
+        self.by_use.insert(self.tick, key);
+    }
+
+    /// Forget all failures, e.g. after a tsconfig or lockfile change.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.by_use.clear();
+    }
+
+    /// Number of negative results recorded (and warned about) so far.
+    pub fn recorded(&self) -> u64 {
+        self.recorded
+    }
+}
 
 /// Path resolver for TypeScript/JavaScript imports.
 pub struct PathResolver {
     resolver: Resolver,
     workspace_root: PathBuf,
+    path_mode: PathMode,
+    /// Specifier prefix meaning "workspace root" (disabled when `None`).
+    root_alias: Option<String>,
+    negatives: Option<SharedNegativeCache>,
 }
 
 impl PathResolver {
     /// Create a new resolver for the given workspace root.
-    pub fn new(workspace_root: PathBuf) -> Self {
+    pub fn new(workspace_root: PathBuf, path_mode: PathMode) -> Self {
         let tsconfig_path = workspace_root.join("tsconfig.json");
-        Self::with_tsconfig(workspace_root, tsconfig_path)
+        Self::with_tsconfig(workspace_root, tsconfig_path, path_mode)
     }
 
     /// Create a resolver with a custom tsconfig path.
-    pub fn with_tsconfig(workspace_root: PathBuf, tsconfig_path: PathBuf) -> Self {
-        let options = build_resolve_options(tsconfig_path);
+    /// A missing tsconfig disables `paths` aliases instead of failing.
+    pub fn with_tsconfig(
+        workspace_root: PathBuf,
+        tsconfig_path: PathBuf,
+        path_mode: PathMode,
+    ) -> Self {
+        let tsconfig = if tsconfig_path.is_file() {
+            Some(tsconfig_path)
+        } else {
+            log_info_missing_tsconfig(&tsconfig_path);
+            None
+        };
+        let options = build_resolve_options(tsconfig, path_mode);
         Self {
             resolver: Resolver::new(options),
             workspace_root,
+            path_mode,
+            root_alias: None,
+            negatives: None,
         }
     }
 
+    /// Rewrite specifiers starting with `prefix` (e.g. `~/`) to paths under
+    /// the workspace root before resolving.
+    #[must_use]
+    pub fn with_root_alias(mut self, prefix: Option<String>) -> Self {
+        self.root_alias = prefix.filter(|p| !p.is_empty());
+        self
+    }
+
+    /// Skip (and stop warning about) bare specifiers remembered as
+    /// unresolvable in `cache`.
+    #[must_use]
+    pub fn with_negative_cache(mut self, cache: SharedNegativeCache) -> Self {
+        self.negatives = Some(cache);
+        self
+    }
+
     /// Resolve an import specifier to an absolute path.
     ///
     /// Returns None if:
     /// - Resolution fails (logged as warning)
     /// - Resolved path is outside workspace (logged as warning)
+    /// - A bare specifier is cached as unresolvable from this directory
     pub fn resolve(&self, from: &Path, specifier: &str) -> Option<PathBuf> {
         let from_dir = from.parent()?;
+        let rewritten = self.rewrite_root_alias(specifier);
+        let target = rewritten.as_deref().unwrap_or(specifier);
+
+        // Relative files may appear later, so only bare specifiers are cached
+        let cache = self
+            .negatives
+            .as_ref()
+            .filter(|_| is_bare_specifier(target));
+        let key = (from_dir.to_path_buf(), target.to_string());
+        if cache.is_some_and(|c| c.lock().is_ok_and(|mut c| c.contains(&key))) {
+            return None;
+        }
+        let resolved = self.resolve_uncached(from, specifier, target);
+        if resolved.is_none() {
+            if let Some(Ok(mut cache)) = cache.map(|c| c.lock()) {
+                cache.insert(key);
+            }
+        }
+        resolved
+    }
 
-        let Ok(resolution) = self.resolver.resolve(from_dir, specifier) else {
+    fn resolve_uncached(&self, from: &Path, specifier: &str, target: &str) -> Option<PathBuf> {
+        let from_dir = from.parent()?;
+        let Ok(resolution) = self.resolver.resolve(from_dir, target) else {
             log_warn_unresolvable(from, specifier);
             return None;
         };
         let resolved = resolution.into_path_buf();
 
         // Canonicalize and check workspace boundary
-        let Ok(canonical) = resolved.canonicalize() else {
+        let Ok(canonical) = self.path_mode.resolve(&resolved) else {
             log_warn_unresolvable(from, specifier);
             return None;
         };
 
-        let Ok(workspace_canonical) = self.workspace_root.canonicalize() else {
+        let Ok(workspace_canonical) = self.path_mode.resolve(&self.workspace_root) else {
             return None;
         };
 
@@ -63,9 +225,25 @@ impl PathResolver {
 
         Some(canonical)
     }
+
+    fn rewrite_root_alias(&self, specifier: &str) -> Option<String> {
+        let rest = specifier.strip_prefix(self.root_alias.as_deref()?)?;
+        Some(
+            self.workspace_root
+                .join(rest)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+/// Package-style specifiers (`react`, `@scope/pkg`, tsconfig aliases), as
+/// opposed to relative or absolute paths.
+fn is_bare_specifier(specifier: &str) -> bool {
+    !specifier.starts_with('.') && !Path::new(specifier).is_absolute()
 }
 
-fn build_resolve_options(tsconfig_path: PathBuf) -> ResolveOptions {
+fn build_resolve_options(tsconfig_path: Option<PathBuf>, path_mode: PathMode) -> ResolveOptions {
     ResolveOptions {
         extensions: vec![
             ".ts".into(),
@@ -77,6 +255,7 @@ fn build_resolve_options(tsconfig_path: PathBuf) -> ResolveOptions {
             ".cts".into(),
             ".cjs".into(),
         ],
+        extension_alias: esm_extension_aliases(),
         main_files: vec!["index".into()],
         condition_names: vec![
             "import".into(),
@@ -84,14 +263,31 @@ fn build_resolve_options(tsconfig_path: PathBuf) -> ResolveOptions {
             "node".into(),
             "default".into(),
         ],
-        tsconfig: Some(TsconfigDiscovery::Manual(TsconfigOptions {
-            config_file: tsconfig_path,
-            references: TsconfigReferences::Disabled,
-        })),
+        tsconfig: tsconfig_path.map(|config_file| {
+            TsconfigDiscovery::Manual(TsconfigOptions {
+                config_file,
+                references: TsconfigReferences::Disabled,
+            })
+        }),
+        symlinks: path_mode == PathMode::Canonical,
         ..Default::default()
     }
 }
 
+/// ESM imports name the emitted file (`./x.js`), so try the TypeScript
+/// source first. `oxc_resolver` does not rewrite extensions on its own.
+fn esm_extension_aliases() -> Vec<(String, Vec<String>)> {
+    [
+        (".js", &[".ts", ".tsx", ".js"][..]),
+        (".jsx", &[".tsx", ".jsx"]),
+        (".mjs", &[".mts", ".mjs"]),
+        (".cjs", &[".cts", ".cjs"]),
+    ]
+    .into_iter()
+    .map(|(ext, aliases)| (ext.into(), aliases.iter().map(|a| (*a).into()).collect()))
+    .collect()
+}
+
 fn truncate_path(path: &Path) -> String {
     let s = path.display().to_string();
     if s.len() > MAX_PATH_LOG_LENGTH {
@@ -109,8 +305,17 @@ fn truncate_str(s: &str) -> &str {
     }
 }
 
+fn log_info_missing_tsconfig(tsconfig_path: &Path) {
+    MISSING_TSCONFIG_LOGGED.call_once(|| {
+        log_line!(
+            "[affected] INFO: no tsconfig at {}, tsconfig path aliases unavailable",
+            truncate_path(tsconfig_path)
+        );
+    });
+}
+
 fn log_warn_unresolvable(from: &Path, specifier: &str) {
-    eprintln!(
+    log_line!(
         "[affected] WARN: cannot resolve '{}' from {}",
         truncate_str(specifier),
         truncate_path(from)
@@ -118,7 +323,7 @@ fn log_warn_unresolvable(from: &Path, specifier: &str) {
 }
 
 fn log_warn_outside_workspace(from: &Path, specifier: &str, resolved: &Path) {
-    eprintln!(
+    log_line!(
         "[affected] WARN: '{}' from {} resolves outside workspace to {}",
         truncate_str(specifier),
         truncate_path(from),
@@ -134,27 +339,7 @@ mod tests {
 
     fn setup_workspace_no_tsconfig() -> (tempfile::TempDir, PathResolver) {
         let dir = tempdir().unwrap();
-        // Create resolver without tsconfig (uses production-like options)
-        let options = ResolveOptions {
-            extensions: vec![
-                ".ts".into(),
-                ".tsx".into(),
-                ".js".into(),
-                ".jsx".into(),
-            ],
-            main_files: vec!["index".into()],
-            condition_names: vec![
-                "import".into(),
-                "require".into(),
-                "node".into(),
-                "default".into(),
-            ],
-            ..Default::default()
-        };
-        let resolver = PathResolver {
-            resolver: Resolver::new(options),
-            workspace_root: dir.path().to_path_buf(),
-        };
+        let resolver = PathResolver::new(dir.path().to_path_buf(), PathMode::Canonical);
         (dir, resolver)
     }
 
@@ -170,6 +355,25 @@ mod tests {
         assert!(result.unwrap().ends_with("foo.ts"));
     }
 
+    #[test]
+    fn resolves_relative_imports_without_tsconfig() {
+        let (dir, resolver) = setup_workspace_no_tsconfig();
+        assert!(!dir.path().join("tsconfig.json").exists());
+        let lib = dir.path().join("lib");
+        fs::create_dir_all(&lib).unwrap();
+        fs::write(lib.join("util.js"), "module.exports = {};").unwrap();
+        fs::write(lib.join("index.js"), "").unwrap();
+        let from = dir.path().join("main.js");
+
+        let util = resolver.resolve(&from, "./lib/util").unwrap();
+        assert_eq!(util, lib.join("util.js").canonicalize().unwrap());
+        assert!(resolver
+            .resolve(&from, "./lib")
+            .unwrap()
+            .ends_with("lib/index.js"));
+        assert!(resolver.resolve(&from, "@alias/util").is_none());
+    }
+
     #[test]
     fn resolves_with_extension() {
         let (dir, resolver) = setup_workspace_no_tsconfig();
@@ -204,6 +408,40 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn unresolvable_bare_specifier_is_recorded_once() {
+        let (dir, resolver) = setup_workspace_no_tsconfig();
+        let cache = NegativeCache::shared(DEFAULT_NEGATIVE_CACHE_SIZE);
+        let resolver = resolver.with_negative_cache(Arc::clone(&cache));
+        let main = dir.path().join("main.ts");
+        fs::write(&main, "").unwrap();
+
+        assert!(resolver.resolve(&main, "left-pad").is_none());
+        assert!(resolver.resolve(&main, "left-pad").is_none());
+        assert_eq!(cache.lock().unwrap().recorded(), 1);
+
+        // Relative specifiers are never cached
+        resolver.resolve(&main, "./missing");
+        assert_eq!(cache.lock().unwrap().recorded(), 1);
+
+        cache.lock().unwrap().clear();
+        assert!(resolver.resolve(&main, "left-pad").is_none());
+        assert_eq!(cache.lock().unwrap().recorded(), 2);
+    }
+
+    #[test]
+    fn negative_cache_evicts_least_recently_used() {
+        let mut cache = NegativeCache::new(2);
+        let key = |s: &str| (PathBuf::from("/ws"), s.to_string());
+        cache.insert(key("a"));
+        cache.insert(key("b"));
+        assert!(cache.contains(&key("a")));
+        cache.insert(key("c"));
+        assert!(cache.contains(&key("a")));
+        assert!(!cache.contains(&key("b")));
+        assert!(cache.contains(&key("c")));
+    }
+
     #[test]
     fn rejects_path_outside_workspace() {
         let (dir, resolver) = setup_workspace_no_tsconfig();
@@ -216,6 +454,102 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn esm_js_specifier_resolves_to_ts_source() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(src.join("x.ts"), "export const x = 1;").unwrap();
+        fs::write(src.join("view.tsx"), "export const v = 1;").unwrap();
+        fs::write(src.join("m.mts"), "export const m = 1;").unwrap();
+        fs::write(src.join("c.cts"), "export const c = 1;").unwrap();
+        let resolver = PathResolver::new(dir.path().to_path_buf(), PathMode::Canonical);
+        let from = src.join("main.ts");
+
+        let resolve = |spec| resolver.resolve(&from, spec).unwrap();
+        assert!(resolve("./x.js").ends_with("x.ts"));
+        assert!(resolve("./view.js").ends_with("view.tsx"));
+        assert!(resolve("./m.mjs").ends_with("m.mts"));
+        assert!(resolve("./c.cjs").ends_with("c.cts"));
+    }
+
+    #[test]
+    fn js_specifier_falls_back_to_real_js_file() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("legacy.js"), "module.exports = 1;").unwrap();
+        let resolver = PathResolver::new(dir.path().to_path_buf(), PathMode::Canonical);
+
+        let result = resolver.resolve(&dir.path().join("main.ts"), "./legacy.js");
+        assert!(result.unwrap().ends_with("legacy.js"));
+    }
+
+    fn setup_paths_workspace(compiler_options: &str) -> (tempfile::TempDir, PathResolver) {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("lib")).unwrap();
+        fs::write(src.join("config.ts"), "export default {};").unwrap();
+        fs::write(src.join("lib/util.ts"), "export const u = 1;").unwrap();
+        fs::write(
+            dir.path().join("tsconfig.json"),
+            format!(r#"{{"compilerOptions":{compiler_options}}}"#),
+        )
+        .unwrap();
+        let resolver = PathResolver::new(dir.path().to_path_buf(), PathMode::Canonical);
+        (dir, resolver)
+    }
+
+    #[test]
+    fn resolves_exact_paths_alias() {
+        let (dir, resolver) = setup_paths_workspace(
+            r#"{"paths":{"@config":["src/config.ts"],"@lib/*":["src/lib/*"]}}"#,
+        );
+        let from = dir.path().join("src/main.ts");
+
+        let config = resolver.resolve(&from, "@config").unwrap();
+        assert_eq!(
+            config,
+            dir.path().join("src/config.ts").canonicalize().unwrap()
+        );
+        assert!(resolver
+            .resolve(&from, "@lib/util")
+            .unwrap()
+            .ends_with("src/lib/util.ts"));
+    }
+
+    #[test]
+    fn resolves_exact_paths_alias_with_base_url() {
+        let (dir, resolver) =
+            setup_paths_workspace(r#"{"baseUrl":"src","paths":{"@config":["config"]}}"#);
+        let from = dir.path().join("src/main.ts");
+
+        let config = resolver.resolve(&from, "@config").unwrap();
+        assert!(config.ends_with("src/config.ts"));
+        // An exact alias does not match deeper specifiers
+        assert!(resolver.resolve(&from, "@config/extra").is_none());
+    }
+
+    #[test]
+    fn root_alias_resolves_from_workspace_root() {
+        let dir = tempdir().unwrap();
+        let lib = dir.path().join("lib");
+        let nested = dir.path().join("src/deep");
+        fs::create_dir_all(&lib).unwrap();
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(lib.join("x.ts"), "export const x = 1;").unwrap();
+        let from = nested.join("main.ts");
+
+        let plain = PathResolver::new(dir.path().to_path_buf(), PathMode::Canonical);
+        assert!(plain.resolve(&from, "~/lib/x").is_none());
+
+        let aliased = PathResolver::new(dir.path().to_path_buf(), PathMode::Canonical)
+            .with_root_alias(Some(DEFAULT_ROOT_ALIAS.into()));
+        let resolved = aliased.resolve(&from, "~/lib/x").unwrap();
+        assert_eq!(resolved, lib.join("x.ts").canonicalize().unwrap());
+    }
+
     #[test]
     fn truncate_path_short() {
         let path = PathBuf::from("/short.ts");
@@ -2,39 +2,184 @@
 //!
 //! Combines dirty tracker, dependency graph, and affected computation
 //! into a unified state for the RPC handler.
-#![allow(clippy::print_stderr)]
 
+use super::breaker::CircuitBreaker;
 use super::compute::compute_affected;
-use super::discovery::discover_tests;
-use super::graph::{new_shared_graph, SharedDepGraph};
-use super::parser::parse_imports;
-use super::resolver::PathResolver;
-use super::watcher::{is_config_file, start_watcher, DirtyTracker, WatcherConfig};
+use super::config_files::{load_test_patterns, ConfigFiles};
+use super::diff::{
+    affected_from_changes, affected_from_ranges, ChangeKind, FileChange, RangeChange,
+};
+use super::discovery::{DiscoveryCache, TestFilePatterns};
+use super::graph::{new_shared_graph, DepGraph, SharedDepGraph, DEFAULT_FAN_IN_THRESHOLD};
+use super::parser::{parse_file, ParseLimits};
+use super::resolver::{
+    NegativeCache, PathResolver, SharedNegativeCache, DEFAULT_NEGATIVE_CACHE_SIZE,
+};
+use super::test_mappings::TestMappings;
+use super::watcher::{
+    start_watcher, DirtyTracker, DrainSnapshot, WatcherConfig, DEFAULT_DEBOUNCE_MS,
+    DEFAULT_MAX_DIRTY_FILES,
+};
+use crate::logging::{self, log_line};
+use crate::normalize::path::PathMode;
 use ignore::WalkBuilder;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLockReadGuard, TryLockError, TryLockResult};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+
+/// Default fraction of tests above which a selection becomes a full run.
+pub const DEFAULT_FULL_RUN_RATIO: f64 = 0.7;
+/// Default time a query waits for the graph lock before returning a full run.
+pub const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Delay between attempts to take the graph lock.
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Why a full run was returned instead of a selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullRunReason {
+    ForceFull,
+    GraphBuilding,
+    ConfigChanged,
+    DirtyOverflow,
+    GraphOverflow,
+    /// Selected tests exceed the configured fraction of all tests.
+    AffectedRatio,
+    /// The file watcher failed, so the dirty set cannot be trusted.
+    WatcherUnavailable,
+    /// The graph lock stayed held by a writer past the query deadline.
+    Timeout,
+    /// Repeated internal errors opened the circuit breaker.
+    CircuitOpen,
+    /// The client's cursor was issued before the dirty tracker was reset, or
+    /// is older than the changes its bounded history still remembers.
+    CursorExpired,
+    /// The graph finished building without a single file, so no change can
+    /// reach a test through it (e.g. a wrong workspace root).
+    EmptyGraph,
+    /// Source files were affected but no test file in scope matches the
+    /// recognized test naming, so an empty selection would run nothing.
+    DiscoveryMiss,
+}
+
+impl FullRunReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ForceFull => "force_full",
+            Self::GraphBuilding => "graph_building",
+            Self::ConfigChanged => "config_changed",
+            Self::DirtyOverflow => "dirty_overflow",
+            Self::GraphOverflow => "graph_overflow",
+            Self::AffectedRatio => "affected_ratio",
+            Self::WatcherUnavailable => "watcher_unavailable",
+            Self::Timeout => "timeout",
+            Self::CircuitOpen => "circuit_open",
+            Self::CursorExpired => "cursor_expired",
+            Self::EmptyGraph => "empty_graph",
+            Self::DiscoveryMiss => "discovery_miss",
+        }
+    }
+}
 
 /// Result of affected test computation.
 #[derive(Debug, Clone)]
 pub struct AffectedResult {
     pub test_files: Vec<String>,
     pub dirty_files: Vec<String>,
+    /// Affected non-test modules: changed files and everything that
+    /// transitively imports them, sorted. Empty for full runs.
+    pub source_files: Vec<String>,
     pub is_full_run: bool,
+    pub full_run_reason: Option<FullRunReason>,
+    /// Change-history position this result covers changes up to; empty for
+    /// diff-based selections.
+    pub cursor: String,
+    /// `DepGraph::generation` the selection was made against; 0 for
+    /// diff-based selections and while the graph is locked for writing.
+    pub graph_generation: u64,
 }
 
 impl AffectedResult {
     /// Create an empty result (no tests affected).
     fn empty() -> Self {
-        Self { test_files: Vec::new(), dirty_files: Vec::new(), is_full_run: false }
+        Self::selected(Vec::new(), Vec::new())
     }
 
     /// Create a full run result with no tests discovered yet.
-    fn full_run_empty() -> Self {
-        Self { test_files: Vec::new(), dirty_files: Vec::new(), is_full_run: true }
+    fn full_run_empty(dirty_files: Vec<String>) -> Self {
+        Self::full_run(Vec::new(), dirty_files, FullRunReason::GraphBuilding)
+    }
+
+    fn selected(mut test_files: Vec<String>, mut dirty_files: Vec<String>) -> Self {
+        test_files.sort();
+        dirty_files.sort();
+        Self {
+            test_files,
+            dirty_files,
+            source_files: Vec::new(),
+            is_full_run: false,
+            full_run_reason: None,
+            cursor: String::new(),
+            graph_generation: 0,
+        }
+    }
+
+    fn full_run(
+        mut test_files: Vec<String>,
+        mut dirty_files: Vec<String>,
+        reason: FullRunReason,
+    ) -> Self {
+        test_files.sort();
+        dirty_files.sort();
+        Self {
+            test_files,
+            dirty_files,
+            source_files: Vec::new(),
+            is_full_run: true,
+            full_run_reason: Some(reason),
+            cursor: String::new(),
+            graph_generation: 0,
+        }
+    }
+
+    /// BLAKE3 hex digest of the sorted `test_files`, letting polling clients
+    /// skip re-processing an unchanged selection.
+    pub fn result_hash(&self) -> String {
+        let mut files: Vec<&str> = self.test_files.iter().map(String::as_str).collect();
+        files.sort_unstable();
+        let mut hasher = blake3::Hasher::new();
+        for file in files {
+            // NUL cannot appear in paths, so the encoding is unambiguous
+            hasher.update(file.as_bytes());
+            hasher.update(b"\0");
+        }
+        hasher.finalize().to_hex().to_string()
     }
+
+    /// Split `dirty_files` into sorted `(source_files, test_files)`, so clients
+    /// can run directly edited tests even while the graph is building.
+    pub fn partition_dirty(&self, patterns: &TestFilePatterns) -> (Vec<String>, Vec<String>) {
+        let (mut tests, mut sources): (Vec<String>, Vec<String>) = self
+            .dirty_files
+            .iter()
+            .cloned()
+            .partition(|f| patterns.matches(Path::new(f)));
+        sources.sort();
+        tests.sort();
+        (sources, tests)
+    }
+}
+
+/// Size and health of the dependency graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphStats {
+    pub nodes: usize,
+    pub edges: usize,
+    /// Import cycles, i.e. groups of modules that import each other.
+    pub cycles: usize,
 }
 
 /// Shared state for affected test selection.
@@ -43,211 +188,939 @@ pub struct AffectedState {
     pub graph: SharedDepGraph,
     pub graph_ready: Arc<AtomicBool>,
     pub workspace_root: PathBuf,
+    pub path_mode: PathMode,
+    /// Selections above this fraction of all tests become full runs.
+    pub full_run_ratio: f64,
+    /// Thresholds for skipping generated or minified files.
+    pub parse_limits: ParseLimits,
+    /// Modules with more direct dependents than this are reported as hubs.
+    pub fan_in_threshold: usize,
+    /// Specifier prefix resolved from the workspace root (e.g. `~/`).
+    pub root_alias: Option<String>,
+    /// How long a selection waits for the graph lock before a full run.
+    pub lock_timeout: Duration,
+    /// Changed files above this many bytes are not tracked (`None` = no limit).
+    pub watch_max_file_size: Option<u64>,
+    /// Poll interval of the fallback polling watcher.
+    pub watch_debounce: Duration,
+    /// Files whose changes force full runs, loaded from `zax.toml`.
+    pub config_files: ConfigFiles,
+    /// What counts as a test file, with extensions from `zax.toml`.
+    pub test_patterns: TestFilePatterns,
+    /// Bare specifiers known to be unresolvable, cleared on config changes
+    /// and when a source file is created.
+    pub negative_cache: SharedNegativeCache,
+    discovery_cache: DiscoveryCache,
+    /// Total test count per package scope, cleared when test files change.
+    test_counts: HashMap<String, usize>,
     event_rx: Option<mpsc::Receiver<PathBuf>>,
+    /// Received events not yet applied, kept when the graph lock timed out.
+    pending_events: VecDeque<PathBuf>,
+    /// Set once the watcher fails to start or stops; selections become full runs.
+    watcher_unavailable: bool,
+    /// Opens after repeated selection errors; selections become full runs.
+    pub breaker: CircuitBreaker,
+    /// Import cycle count at a graph generation.
+    cycle_count: Option<(u64, usize)>,
+    /// Background graph build, aborted when the state is dropped (e.g. evicted).
+    pub build_task: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl AffectedState {
     /// Create a new affected state for the workspace.
     pub fn new(workspace_root: PathBuf) -> Self {
-        let tracker = DirtyTracker::new(workspace_root.clone());
+        let tracker = DirtyTracker::new(workspace_root.clone(), DEFAULT_MAX_DIRTY_FILES);
         let graph = new_shared_graph();
         let graph_ready = Arc::new(AtomicBool::new(false));
+        let config_files = ConfigFiles::load(&workspace_root);
+        let test_patterns = load_test_patterns(&workspace_root);
+        if let Ok(mut graph) = graph.write() {
+            graph.set_test_patterns(test_patterns.clone());
+        }
+        let discovery_cache = DiscoveryCache::new()
+            .with_mappings(TestMappings::load(&workspace_root))
+            .with_patterns(test_patterns.clone());
 
         Self {
             tracker,
             graph,
             graph_ready,
             workspace_root,
+            path_mode: PathMode::Canonical,
+            full_run_ratio: DEFAULT_FULL_RUN_RATIO,
+            parse_limits: ParseLimits::default(),
+            fan_in_threshold: DEFAULT_FAN_IN_THRESHOLD,
+            root_alias: None,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            watch_max_file_size: None,
+            watch_debounce: Duration::from_millis(DEFAULT_DEBOUNCE_MS),
+            config_files,
+            test_patterns,
+            negative_cache: NegativeCache::shared(DEFAULT_NEGATIVE_CACHE_SIZE),
+            discovery_cache,
+            test_counts: HashMap::new(),
             event_rx: None,
+            pending_events: VecDeque::new(),
+            watcher_unavailable: false,
+            breaker: CircuitBreaker::default(),
+            cycle_count: None,
+            build_task: None,
         }
     }
 
     /// Start the file watcher background task.
-    /// Returns an error if the watcher fails to start.
+    /// Returns an error if the watcher fails to start, after which selections
+    /// conservatively return full runs.
     pub fn start_watcher(&mut self) -> Result<(), String> {
-        let config = WatcherConfig::new(self.workspace_root.clone());
-        let rx = start_watcher(config).map_err(|e| format!("watcher start failed: {e}"))?;
-        self.event_rx = Some(rx);
-        Ok(())
+        let mut config = WatcherConfig::new(self.workspace_root.clone());
+        config.path_mode = self.path_mode;
+        config.max_file_size = self.watch_max_file_size;
+        config.debounce = self.watch_debounce;
+        config.config_files = self.config_files.clone();
+        match start_watcher(config) {
+            Ok(rx) => {
+                self.event_rx = Some(rx);
+                Ok(())
+            }
+            Err(e) => {
+                self.mark_watcher_unavailable();
+                Err(format!("watcher start failed: {e}"))
+            }
+        }
     }
 
-    /// Process pending file events from the watcher.
-    pub fn process_events(&mut self) {
-        // Collect paths first to avoid borrowing issues
-        let paths: Vec<PathBuf> = if let Some(ref mut rx) = self.event_rx {
-            let mut collected = Vec::new();
-            while let Ok(path) = rx.try_recv() {
-                collected.push(path);
-            }
-            collected
-        } else {
+    /// Replace the dirty tracker with one overflowing above `max_files`.
+    /// Call before the watcher is started.
+    pub fn set_max_dirty_files(&mut self, max_files: usize) {
+        self.tracker = DirtyTracker::new(self.workspace_root.clone(), max_files);
+    }
+
+    /// Set this workspace's graph node limit, above which selections become
+    /// full runs. Call before the graph is built.
+    pub fn set_max_graph_nodes(&self, max_nodes: usize) {
+        if let Ok(mut graph) = self.graph.write() {
+            graph.set_max_nodes(max_nodes);
+        }
+    }
+
+    /// Treat paths differing only in case as one graph node, for
+    /// case-insensitive filesystems. Call before the graph is built.
+    pub fn set_case_insensitive_paths(&self, case_insensitive: bool) {
+        if let Ok(mut graph) = self.graph.write() {
+            graph.set_case_insensitive(case_insensitive);
+        }
+    }
+
+    /// Keep test files out of the graph as leaves, so only source modules
+    /// count towards the node limit. Call before the graph is built.
+    pub fn set_test_leaves(&self, test_leaves: bool) {
+        if let Ok(mut graph) = self.graph.write() {
+            graph.set_test_leaves(test_leaves);
+        }
+    }
+
+    /// Record that file changes are no longer being observed.
+    pub fn mark_watcher_unavailable(&mut self) {
+        if !self.watcher_unavailable {
+            log_line!("[affected] ERROR: file watcher unavailable, selections will be full runs");
+        }
+        self.watcher_unavailable = true;
+        self.event_rx = None;
+    }
+
+    /// Move paths from the watcher channel to the pending events.
+    ///
+    /// A disconnected channel means the watcher thread exited (e.g. on the
+    /// inotify watch limit), so the watcher is marked unavailable.
+    fn receive_events(&mut self) {
+        let Some(rx) = self.event_rx.as_mut() else {
             return;
         };
+        loop {
+            match rx.try_recv() {
+                Ok(path) => self.pending_events.push_back(path),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.mark_watcher_unavailable();
+                    break;
+                }
+            }
+        }
+    }
 
-        for path in paths {
-            // Check if config file changed
-            if is_config_file(&path) && self.tracker.check_config_change(&path) {
-                eprintln!(
-                    "[affected] INFO: config file changed: {}",
-                    path.display()
-                );
-                self.tracker.set_config_changed();
+    /// Process pending file events from the watcher.
+    ///
+    /// Graph locks are waited for at most `lock_timeout` in total. Past it the
+    /// unapplied events stay queued for the next call and `Timeout` is returned.
+    pub fn process_events(&mut self) -> Result<(), FullRunReason> {
+        let deadline = Instant::now() + self.lock_timeout;
+        self.receive_events();
+        while let Some(event_path) = self.pending_events.pop_front() {
+            // Applying an event twice is harmless, so a timed-out one is retried whole
+            let applied = self
+                .expand_removed_dir(&event_path, deadline)
+                .and_then(|paths| {
+                    paths
+                        .iter()
+                        .try_for_each(|p| self.process_event(p, deadline))
+                });
+            match applied {
+                Ok(()) => {}
+                Err(FullRunReason::Timeout) => {
+                    self.pending_events.push_front(event_path);
+                    return Err(FullRunReason::Timeout);
+                }
+                Err(reason) => return Err(reason),
             }
+        }
+        Ok(())
+    }
 
-            // Add to dirty set
-            self.tracker.add_dirty(path.clone());
+    /// Paths an event stands for: a removed directory, which the watcher may
+    /// report without its files, expands to the graph files that were in it.
+    fn expand_removed_dir(
+        &self,
+        path: &Path,
+        deadline: Instant,
+    ) -> Result<Vec<PathBuf>, FullRunReason> {
+        if path.exists() || is_ts_js_file(path) || !self.graph_ready.load(Ordering::SeqCst) {
+            return Ok(vec![path.to_path_buf()]);
+        }
+        let contained: Vec<PathBuf> = lock_until(|| self.graph.try_read(), deadline)?
+            .files()
+            .into_iter()
+            .filter(|f| f.starts_with(path))
+            .collect();
+        if contained.is_empty() {
+            Ok(vec![path.to_path_buf()])
+        } else {
+            Ok(contained)
+        }
+    }
 
-            // Update graph if ready
-            if self.graph_ready.load(Ordering::SeqCst) {
-                self.update_graph_for_file(&path);
+    fn process_event(&mut self, path: &Path, deadline: Instant) -> Result<(), FullRunReason> {
+        // Check if config file changed
+        if self.config_files.matches(path) && self.tracker.check_config_change(path) {
+            log_line!("[affected] INFO: config file changed: {}", path.display());
+            self.tracker.set_config_changed();
+            if let Ok(mut negatives) = self.negative_cache.lock() {
+                negatives.clear();
             }
         }
+
+        // Test file created or deleted: cached source mappings may be stale
+        if self.test_patterns.matches(path) {
+            self.discovery_cache.invalidate_test_file(path);
+            self.test_counts.clear();
+        }
+
+        // Add to dirty set
+        self.tracker.add_dirty(path.to_path_buf());
+
+        // Update graph if ready
+        if self.graph_ready.load(Ordering::SeqCst) {
+            self.update_graph_for_file(path, deadline)?;
+        }
+        Ok(())
     }
 
-    /// Update the graph when a file changes.
-    fn update_graph_for_file(&self, path: &Path) {
-        if !is_ts_js_file(path) {
-            return;
+    fn resolver(&self) -> PathResolver {
+        PathResolver::new(self.workspace_root.clone(), self.path_mode)
+            .with_root_alias(self.root_alias.clone())
+            .with_negative_cache(Arc::clone(&self.negative_cache))
+    }
+
+    /// Update the graph when a file changes, waiting for its lock until `deadline`.
+    fn update_graph_for_file(&self, path: &Path, deadline: Instant) -> Result<(), FullRunReason> {
+        if !is_ts_js_file(path) || path.is_dir() {
+            return Ok(());
         }
 
-        let Ok(path) = path.canonicalize() else {
-            return;
-        };
+        // A deleted file cannot be resolved; the watcher resolved it while
+        // it could, so its path is used as is
+        let path = self
+            .path_mode
+            .resolve(path)
+            .unwrap_or_else(|_| path.to_path_buf());
 
         // Check if file still exists (delete case)
         if !path.exists() {
-            if let Ok(mut graph) = self.graph.write() {
-                graph.remove_file(&path);
+            let mut graph = lock_until(|| self.graph.try_write(), deadline)?;
+            // Importers stay affected once the deleted file's node is gone
+            for dependent in graph.get_dependents(&path) {
+                self.tracker.add_dirty(dependent);
             }
-            return;
+            graph.remove_file(&path);
+            return Ok(());
         }
 
         // Parse and update edges
-        let resolver = PathResolver::new(self.workspace_root.clone());
-        let imports = parse_imports(&path);
+        let resolver = self.resolver();
+        let parsed = parse_file(&path, self.parse_limits);
 
         // Add file if new
-        if let Ok(mut graph) = self.graph.write() {
-            graph.add_file(path.clone());
+        let mut graph = lock_until(|| self.graph.try_write(), deadline)?;
+        let created = !graph.contains(&path);
+        graph.add_file(path.clone());
+        graph.set_truncated_imports(&path, parsed.truncated_from);
+        drop(graph);
+
+        // A bare specifier such as a tsconfig alias may name the new file
+        if created {
+            if let Ok(mut negatives) = self.negative_cache.lock() {
+                negatives.clear();
+            }
         }
 
         // Resolve imports
         let mut resolved = Vec::new();
-        for import in imports {
+        for import in parsed.imports {
             if let Some(resolved_path) = resolver.resolve(&path, &import.specifier) {
-                if let Ok(mut graph) = self.graph.write() {
-                    if graph.add_file(resolved_path.clone()).is_some() {
-                        resolved.push(resolved_path);
-                    }
+                let mut graph = lock_until(|| self.graph.try_write(), deadline)?;
+                if graph.add_file(resolved_path.clone()) {
+                    resolved.push(resolved_path);
                 }
             }
         }
 
         // Update edges
-        if let Ok(mut graph) = self.graph.write() {
-            graph.update_edges(&path, &resolved);
-        }
+        lock_until(|| self.graph.try_write(), deadline)?.update_edges(&path, &resolved);
+        Ok(())
     }
 
-    /// Get affected tests based on current dirty set.
+    /// Get affected tests based on current dirty set, draining it.
     /// If `package_scope` is non-empty, filters tests to those within the package.
     pub fn get_affected_tests(&mut self, force_full: bool, package_scope: &str) -> AffectedResult {
-        let request_id = generate_request_id();
-        log_request_start(&request_id, force_full, package_scope);
-        self.process_events();
+        logging::in_request(|| self.select_affected(force_full, package_scope, |t| Some(t.drain())))
+    }
+
+    /// Preview affected tests from a snapshot of the dirty set without draining it,
+    /// so a later `get_affected_tests` call still sees the same changes.
+    pub fn peek_affected_tests(&mut self, force_full: bool, package_scope: &str) -> AffectedResult {
+        logging::in_request(|| {
+            self.select_affected(force_full, package_scope, |t| Some(t.snapshot()))
+        })
+    }
+
+    /// Select tests for only the files changed since `cursor` (from a previous
+    /// result), leaving the dirty set for draining clients. A cursor this
+    /// state did not issue yields a full run.
+    pub fn get_affected_tests_since(
+        &mut self,
+        force_full: bool,
+        package_scope: &str,
+        cursor: &str,
+    ) -> AffectedResult {
+        logging::in_request(|| self.select_affected(force_full, package_scope, |t| t.since(cursor)))
+    }
+
+    fn select_affected(
+        &mut self,
+        force_full: bool,
+        package_scope: &str,
+        read_dirty: impl FnOnce(&DirtyTracker) -> Option<DrainSnapshot>,
+    ) -> AffectedResult {
+        log_request_start(force_full, package_scope);
+        let processed = self.process_events();
+        // Never wait here: a held write lock is handled by the selection timeout
+        let graph_generation = self
+            .graph
+            .try_read()
+            .map(|g| g.generation())
+            .unwrap_or_default();
+
+        if let Some(result) = self.unselectable(force_full, package_scope) {
+            return AffectedResult {
+                cursor: self.tracker.cursor(),
+                graph_generation,
+                ..result
+            };
+        }
+
+        // The dirty set is left unread, so the queued events still drive the next selection
+        if let Err(reason) = processed {
+            self.record_selection_error(reason);
+            let result = self.handle_full_run_with_dirty(reason, package_scope, &[]);
+            return AffectedResult {
+                cursor: self.tracker.cursor(),
+                graph_generation,
+                ..result
+            };
+        }
+
+        // Events landing after this read stay queued for the next selection
+        let Some(snapshot) = read_dirty(&self.tracker) else {
+            log_info("unknown cursor, returning is_full_run=true");
+            let reason = FullRunReason::CursorExpired;
+            let result = self.handle_full_run_with_dirty(reason, package_scope, &[]);
+            return AffectedResult {
+                cursor: self.tracker.cursor(),
+                graph_generation,
+                ..result
+            };
+        };
+        let result = self.select_from_dirty(package_scope, &snapshot);
+        AffectedResult {
+            cursor: snapshot.cursor,
+            graph_generation,
+            ..result
+        }
+    }
 
+    /// Full run returned before the dirty set is read, if selection is impossible.
+    fn unselectable(&mut self, force_full: bool, package_scope: &str) -> Option<AffectedResult> {
         if force_full {
-            return self.handle_full_run(&request_id, package_scope, Vec::new());
+            return Some(self.handle_full_run(package_scope, Vec::new()));
+        }
+
+        if self.watcher_unavailable {
+            let reason = FullRunReason::WatcherUnavailable;
+            return Some(self.handle_full_run_with_dirty(reason, package_scope, &[]));
         }
 
         if !self.graph_ready.load(Ordering::SeqCst) {
-            log_info(&request_id, "graph still building, returning is_full_run=true");
-            return AffectedResult::full_run_empty();
+            log_info("graph still building, returning is_full_run=true");
+            // Snapshot so the changes still drive selection once the graph is ready
+            let dirty = self.tracker.snapshot().files;
+            let dirty_files = to_relative_strings(&dirty, &self.workspace_root);
+            return Some(AffectedResult::full_run_empty(dirty_files));
         }
+        None
+    }
 
-        let (dirty, overflow, config_changed) = self.tracker.drain();
-        let dirty_files = to_relative_strings(&dirty, &self.workspace_root);
+    /// Select tests for a dirty set read from the tracker.
+    fn select_from_dirty(
+        &mut self,
+        package_scope: &str,
+        snapshot: &DrainSnapshot,
+    ) -> AffectedResult {
+        let dirty = &snapshot.files;
+        let dirty_files = to_relative_strings(dirty, &self.workspace_root);
+        if self.breaker.is_open() {
+            let reason = FullRunReason::CircuitOpen;
+            return self.handle_full_run_with_dirty(reason, package_scope, &dirty_files);
+        }
+        let shared = Arc::clone(&self.graph);
+        let graph = match read_graph_with_timeout(&shared, self.lock_timeout) {
+            Ok(graph) => graph,
+            Err(reason) => {
+                self.record_selection_error(reason);
+                return self.handle_full_run_with_dirty(reason, package_scope, &dirty_files);
+            }
+        };
+        self.breaker.record_success();
 
         if let Some(result) = self.check_full_run_conditions(
-            &request_id, package_scope, &dirty_files, overflow, config_changed
+            package_scope,
+            &dirty_files,
+            snapshot.overflow,
+            snapshot.config_changed,
+            graph.is_overflow(),
         ) {
             return result;
         }
 
         if dirty.is_empty() {
-            log_info(&request_id, "dirty set empty, no tests affected");
+            log_info("dirty set empty, no tests affected");
             return AffectedResult::empty();
         }
+        if graph.is_empty() {
+            drop(graph);
+            let reason = FullRunReason::EmptyGraph;
+            return self.handle_full_run_with_dirty(reason, package_scope, &dirty_files);
+        }
+
+        let mut affected = compute_affected(dirty, &graph);
+        drop(graph);
+        // Edited tests always run, even without a graph node to reach them
+        let edited_tests = dirty
+            .iter()
+            .filter(|p| self.test_patterns.matches(p) && p.is_file());
+        affected.extend(edited_tests.cloned());
+        self.compute_affected_result(package_scope, &affected, dirty_files)
+    }
+
+    /// Count an internal selection error, logging when it opens the breaker.
+    fn record_selection_error(&mut self, reason: FullRunReason) {
+        if self.breaker.record_error() {
+            log_line!(
+                "[affected] ERROR: {} consecutive selection errors (last: {}), circuit open: \
+                 selections will be full runs for {}s",
+                self.breaker.threshold,
+                reason.as_str(),
+                self.breaker.cooldown.as_secs()
+            );
+        }
+    }
+
+    /// Get test files that have no in-workspace dependency edges.
+    ///
+    /// Orphan tests import only external packages (or nothing), so no source
+    /// change can select them through the graph; they only run on full runs.
+    /// Returns `None` while the graph is still building.
+    pub fn get_orphan_tests(&mut self, package_scope: &str) -> Option<Vec<String>> {
+        let _ = self.process_events();
+
+        if !self.graph_ready.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let test_files = self.discover_all_tests_scoped(package_scope);
+        let graph = self.graph.read().ok()?;
+        let mut orphans: Vec<String> = test_files
+            .into_iter()
+            .filter(|rel| {
+                self.path_mode
+                    .resolve(&self.workspace_root.join(rel))
+                    .map_or(true, |p| graph.get_dependencies(&p).is_empty())
+            })
+            .collect();
+        orphans.sort();
+        Some(orphans)
+    }
+
+    /// Get modules whose direct dependents exceed `fan_in_threshold`, as
+    /// workspace-relative paths with dependent counts, most-imported first.
+    /// Returns `None` while the graph is still building.
+    pub fn get_hub_modules(&mut self) -> Option<Vec<(String, usize)>> {
+        let _ = self.process_events();
+
+        if !self.graph_ready.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let graph = self.graph.read().ok()?;
+        let root = self
+            .path_mode
+            .resolve(&self.workspace_root)
+            .unwrap_or_else(|_| self.workspace_root.clone());
+        Some(
+            graph
+                .hub_modules(self.fan_in_threshold)
+                .into_iter()
+                .filter_map(|hub| Some((path_to_relative(&hub.path, &root)?, hub.dependents)))
+                .collect(),
+        )
+    }
+
+    /// Get the graph's node, edge and import cycle counts. Returns `None`
+    /// while the graph is still building or its lock stays held past
+    /// `lock_timeout`. Cycles are recounted only after the graph changes.
+    pub fn get_graph_stats(&mut self) -> Option<GraphStats> {
+        self.process_events().ok()?;
+
+        if !self.graph_ready.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let shared = Arc::clone(&self.graph);
+        let graph = read_graph_with_timeout(&shared, self.lock_timeout).ok()?;
+        let cycles = match self.cycle_count {
+            Some((generation, count)) if generation == graph.generation() => count,
+            _ => {
+                let count = graph.find_cycles().len();
+                self.cycle_count = Some((graph.generation(), count));
+                count
+            }
+        };
+        Some(GraphStats {
+            nodes: graph.node_count(),
+            edges: graph.edge_count(),
+            cycles,
+        })
+    }
+
+    /// Get files whose imports were truncated at the per-file limit, as
+    /// `(workspace-relative path, full import count)`, so callers know their
+    /// edges are incomplete. Returns `None` while the graph is still building.
+    pub fn get_truncated_imports(&mut self) -> Option<Vec<(String, usize)>> {
+        let _ = self.process_events();
+
+        if !self.graph_ready.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let graph = self.graph.read().ok()?;
+        let root = self
+            .path_mode
+            .resolve(&self.workspace_root)
+            .unwrap_or_else(|_| self.workspace_root.clone());
+        Some(
+            graph
+                .truncated_imports()
+                .into_iter()
+                .filter_map(|(path, count)| Some((path_to_relative(&path, &root)?, count)))
+                .collect(),
+        )
+    }
+
+    /// Get one shortest import chain from `from` to `to` (workspace-relative),
+    /// as workspace-relative paths including both ends. Empty if `to` is not
+    /// reachable from `from`; `None` while the graph is still building.
+    pub fn get_dependency_path(&mut self, from: &str, to: &str) -> Option<Vec<String>> {
+        let _ = self.process_events();
+
+        if !self.graph_ready.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let resolve = |rel: &str| self.path_mode.resolve(&self.workspace_root.join(rel)).ok();
+        let root = self
+            .path_mode
+            .resolve(&self.workspace_root)
+            .unwrap_or_else(|_| self.workspace_root.clone());
+        let graph = self.graph.read().ok()?;
+        let path = match (resolve(from), resolve(to)) {
+            (Some(from), Some(to)) => graph.dependency_path(&from, &to),
+            _ => Vec::new(),
+        };
+        Some(to_relative_strings_vec(&path, &root))
+    }
+
+    /// Get affected tests for an explicit change list (e.g. from a git diff).
+    ///
+    /// Paths may be workspace-relative or absolute. Works on a transient copy
+    /// of the graph and never drains or mutates the persistent dirty set.
+    pub fn get_affected_from_diff(
+        &mut self,
+        changes: &[FileChange],
+        package_scope: &str,
+    ) -> AffectedResult {
+        logging::in_request(|| self.select_from_diff(changes, package_scope))
+    }
+
+    fn select_from_diff(&mut self, changes: &[FileChange], package_scope: &str) -> AffectedResult {
+        let pkg = if package_scope.is_empty() {
+            "<none>"
+        } else {
+            package_scope
+        };
+        log_info(&format!(
+            "AffectedFromDiff changes={}, package={pkg}",
+            changes.len()
+        ));
 
-        self.compute_affected_result(&request_id, package_scope, &dirty, dirty_files)
+        if !self.graph_ready.load(Ordering::SeqCst) {
+            log_info("graph still building, returning is_full_run=true");
+            return AffectedResult::full_run_empty(Vec::new());
+        }
+
+        let changes: Vec<FileChange> = changes
+            .iter()
+            .filter_map(|c| self.resolve_change(c))
+            .collect();
+        let dirty: HashSet<PathBuf> = changes.iter().map(|c| c.path.clone()).collect();
+        let dirty_files = to_relative_strings(&dirty, &self.workspace_root);
+
+        if changes.iter().any(|c| self.config_files.matches(&c.path)) {
+            return self.handle_full_run_with_dirty(
+                FullRunReason::ConfigChanged,
+                package_scope,
+                &dirty_files,
+            );
+        }
+        self.select_from_reported(package_scope, dirty_files, |graph| {
+            affected_from_changes(graph, &changes)
+        })
+    }
+
+    /// Select tests for changes reported by the client rather than the watcher.
+    ///
+    /// Like `select_from_dirty`, an open breaker, a graph lock held past
+    /// `lock_timeout` or an overflowed graph yield a full run.
+    fn select_from_reported(
+        &mut self,
+        package_scope: &str,
+        dirty_files: Vec<String>,
+        affected_in: impl FnOnce(&DepGraph) -> HashSet<PathBuf>,
+    ) -> AffectedResult {
+        if self.breaker.is_open() {
+            let reason = FullRunReason::CircuitOpen;
+            return self.handle_full_run_with_dirty(reason, package_scope, &dirty_files);
+        }
+        let shared = Arc::clone(&self.graph);
+        let graph = match read_graph_with_timeout(&shared, self.lock_timeout) {
+            Ok(graph) => graph,
+            Err(reason) => {
+                self.record_selection_error(reason);
+                return self.handle_full_run_with_dirty(reason, package_scope, &dirty_files);
+            }
+        };
+        self.breaker.record_success();
+        if graph.is_overflow() {
+            drop(graph);
+            let reason = FullRunReason::GraphOverflow;
+            return self.handle_full_run_with_dirty(reason, package_scope, &dirty_files);
+        }
+        let affected = affected_in(&graph);
+        drop(graph);
+        self.compute_affected_result(package_scope, &affected, dirty_files)
+    }
+
+    /// Get affected tests for changed line ranges of modified files.
+    ///
+    /// Files whose ranges touch only exported declarations affect just the
+    /// dependents importing a changed export; see `diff::affected_from_ranges`.
+    /// Like `get_affected_from_diff`, never drains the persistent dirty set.
+    pub fn get_affected_from_ranges(
+        &mut self,
+        changes: &[RangeChange],
+        package_scope: &str,
+    ) -> AffectedResult {
+        logging::in_request(|| self.select_from_ranges(changes, package_scope))
+    }
+
+    fn select_from_ranges(
+        &mut self,
+        changes: &[RangeChange],
+        package_scope: &str,
+    ) -> AffectedResult {
+        let pkg = if package_scope.is_empty() {
+            "<none>"
+        } else {
+            package_scope
+        };
+        log_info(&format!(
+            "AffectedFromRanges files={}, package={pkg}",
+            changes.len()
+        ));
+
+        if !self.graph_ready.load(Ordering::SeqCst) {
+            log_info("graph still building, returning is_full_run=true");
+            return AffectedResult::full_run_empty(Vec::new());
+        }
+
+        let changes = self.resolve_range_changes(changes);
+        let dirty: HashSet<PathBuf> = changes.iter().map(|c| c.path.clone()).collect();
+        let dirty_files = to_relative_strings(&dirty, &self.workspace_root);
+
+        if dirty.iter().any(|p| self.config_files.matches(p)) {
+            return self.handle_full_run_with_dirty(
+                FullRunReason::ConfigChanged,
+                package_scope,
+                &dirty_files,
+            );
+        }
+        let resolver = self.resolver();
+        let limits = self.parse_limits;
+        self.select_from_reported(package_scope, dirty_files, |graph| {
+            affected_from_ranges(graph, &resolver, &changes, limits)
+        })
+    }
+
+    /// Resolves the paths of modified-file range changes, dropping any outside the workspace.
+    fn resolve_range_changes(&self, changes: &[RangeChange]) -> Vec<RangeChange> {
+        changes
+            .iter()
+            .filter_map(|c| {
+                let modified = FileChange {
+                    path: c.path.clone(),
+                    kind: ChangeKind::Modified,
+                };
+                let path = self.resolve_change(&modified)?.path;
+                Some(RangeChange {
+                    path,
+                    ranges: c.ranges.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Join workspace-relative paths against the canonical workspace root.
+    pub fn to_absolute_paths(&self, files: Vec<String>) -> Vec<String> {
+        let root = self
+            .path_mode
+            .resolve(&self.workspace_root)
+            .unwrap_or_else(|_| self.workspace_root.clone());
+        files
+            .into_iter()
+            .map(|f| root.join(f).to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Convert all paths in a result to absolute paths.
+    pub fn absolutize(&self, result: AffectedResult) -> AffectedResult {
+        AffectedResult {
+            test_files: self.to_absolute_paths(result.test_files),
+            dirty_files: self.to_absolute_paths(result.dirty_files),
+            source_files: self.to_absolute_paths(result.source_files),
+            ..result
+        }
+    }
+
+    /// Resolve a change path against the workspace root.
+    /// Deleted files no longer exist, so their parent directory is resolved instead.
+    fn resolve_change(&self, change: &FileChange) -> Option<FileChange> {
+        let joined = self.workspace_root.join(&change.path);
+        let path = match self.path_mode.resolve(&joined) {
+            Ok(p) => p,
+            Err(_) if change.kind == ChangeKind::Deleted => self
+                .path_mode
+                .resolve(joined.parent()?)
+                .ok()?
+                .join(joined.file_name()?),
+            Err(_) => return None,
+        };
+        Some(FileChange {
+            path,
+            kind: change.kind,
+        })
     }
 
     /// Handle conditions that require a full test run.
     #[allow(clippy::too_many_arguments)]
     fn check_full_run_conditions(
         &self,
-        request_id: &str,
         package_scope: &str,
         dirty_files: &[String],
         overflow: bool,
         config_changed: bool,
+        graph_overflow: bool,
     ) -> Option<AffectedResult> {
-        if config_changed {
-            return Some(self.handle_full_run_with_dirty(request_id, "config changed", package_scope, dirty_files));
-        }
-        if overflow {
-            return Some(self.handle_full_run_with_dirty(request_id, "dirty overflow", package_scope, dirty_files));
-        }
-        if self.is_graph_overflow() {
-            return Some(self.handle_full_run_with_dirty(request_id, "graph overflow", package_scope, dirty_files));
-        }
-        None
-    }
-
-    /// Check if the dependency graph has overflowed.
-    fn is_graph_overflow(&self) -> bool {
-        self.graph.read().map(|g| g.is_overflow()).unwrap_or(true)
+        let reason = if config_changed {
+            FullRunReason::ConfigChanged
+        } else if overflow {
+            FullRunReason::DirtyOverflow
+        } else if graph_overflow {
+            FullRunReason::GraphOverflow
+        } else {
+            return None;
+        };
+        Some(self.handle_full_run_with_dirty(reason, package_scope, dirty_files))
     }
 
     /// Handle a full run request, returning all tests in scope.
-    fn handle_full_run(&self, request_id: &str, package_scope: &str, dirty_files: Vec<String>) -> AffectedResult {
+    fn handle_full_run(&self, package_scope: &str, dirty_files: Vec<String>) -> AffectedResult {
         let test_files = self.discover_all_tests_scoped(package_scope);
-        log_info(request_id, &format!("force_full=true, returning {} tests", test_files.len()));
-        AffectedResult { test_files, dirty_files, is_full_run: true }
+        log_info(&format!(
+            "force_full=true, returning {} tests",
+            test_files.len()
+        ));
+        AffectedResult::full_run(test_files, dirty_files, FullRunReason::ForceFull)
     }
 
     /// Handle a full run with dirty files already computed.
-    #[allow(clippy::too_many_arguments)]
-    fn handle_full_run_with_dirty(&self, request_id: &str, reason: &str, package_scope: &str, dirty_files: &[String]) -> AffectedResult {
+    fn handle_full_run_with_dirty(
+        &self,
+        reason: FullRunReason,
+        package_scope: &str,
+        dirty_files: &[String],
+    ) -> AffectedResult {
         let test_files = self.discover_all_tests_scoped(package_scope);
-        log_info(request_id, &format!("{}, returning {} tests", reason, test_files.len()));
-        AffectedResult { test_files, dirty_files: dirty_files.to_vec(), is_full_run: true }
+        log_info(&format!(
+            "{}, returning {} tests",
+            reason.as_str(),
+            test_files.len()
+        ));
+        AffectedResult::full_run(test_files, dirty_files.to_vec(), reason)
     }
 
-    /// Compute affected tests from dirty set.
-    #[allow(clippy::too_many_arguments)]
+    /// Select tests for an affected set computed from the dirty files.
     fn compute_affected_result(
-        &self,
-        request_id: &str,
+        &mut self,
         package_scope: &str,
-        dirty: &HashSet<PathBuf>,
+        affected: &HashSet<PathBuf>,
         dirty_files: Vec<String>,
     ) -> AffectedResult {
-        let affected = self.graph.read()
-            .map(|g| compute_affected(dirty, &g))
-            .unwrap_or_default();
+        let test_files = self.select_tests(affected, package_scope);
 
-        let test_paths = discover_tests(&affected, &self.workspace_root);
-        let test_files = filter_by_package_scope(
-            to_relative_strings_vec(&test_paths, &self.workspace_root),
+        log_info(&format!(
+            "dirty={}, affected={}, tests={}",
+            dirty_files.len(),
+            affected.len(),
+            test_files.len()
+        ));
+
+        if test_files.is_empty() && self.is_discovery_miss(affected, package_scope) {
+            return self.handle_full_run_with_dirty(
+                FullRunReason::DiscoveryMiss,
+                package_scope,
+                &dirty_files,
+            );
+        }
+        let result = self.finish_selection(package_scope, test_files, dirty_files);
+        self.with_source_files(result, affected, package_scope)
+    }
+
+    /// Attach the affected non-test modules to a selection.
+    fn with_source_files(
+        &self,
+        mut result: AffectedResult,
+        affected: &HashSet<PathBuf>,
+        package_scope: &str,
+    ) -> AffectedResult {
+        if result.is_full_run {
+            return result;
+        }
+        let sources: HashSet<PathBuf> = affected
+            .iter()
+            .filter(|p| !self.test_patterns.matches(p))
+            .cloned()
+            .collect();
+        result.source_files = filter_by_package_scope(
+            to_relative_strings(&sources, &self.workspace_root),
             package_scope,
         );
+        result.source_files.sort();
+        result
+    }
 
-        log_info(request_id, &format!(
-            "dirty={}, affected={}, tests={}", dirty.len(), affected.len(), test_files.len()
-        ));
+    /// True when affected sources are in scope but the scope has no test file
+    /// at all, i.e. the project's test layout is not recognized. A workspace
+    /// with tests that just don't reach the change keeps its empty selection.
+    fn is_discovery_miss(&mut self, affected: &HashSet<PathBuf>, package_scope: &str) -> bool {
+        let sources: HashSet<PathBuf> = affected
+            .iter()
+            .filter(|p| !self.test_patterns.matches(p))
+            .cloned()
+            .collect();
+        let sources = to_relative_strings(&sources, &self.workspace_root);
+        let in_scope = filter_by_package_scope(sources, package_scope);
+        !in_scope.is_empty() && self.total_test_count(package_scope) == 0
+    }
 
-        AffectedResult { test_files, dirty_files, is_full_run: false }
+    /// Discover test files for an affected set, filtered by package scope.
+    fn select_tests(&mut self, affected: &HashSet<PathBuf>, package_scope: &str) -> Vec<String> {
+        let test_paths = self
+            .discovery_cache
+            .discover_tests(affected, &self.workspace_root);
+        filter_by_package_scope(
+            to_relative_strings_vec(&test_paths, &self.workspace_root),
+            package_scope,
+        )
+    }
+
+    /// Return the selection, or a full run if it covers too many tests.
+    ///
+    /// Running most tests individually is slower than running everything.
+    fn finish_selection(
+        &mut self,
+        package_scope: &str,
+        test_files: Vec<String>,
+        dirty_files: Vec<String>,
+    ) -> AffectedResult {
+        if test_files.is_empty() {
+            return AffectedResult::selected(test_files, dirty_files);
+        }
+        let total = self.total_test_count(package_scope);
+        if total > 0 && test_files.len() as f64 / total as f64 > self.full_run_ratio {
+            return self.handle_full_run_with_dirty(
+                FullRunReason::AffectedRatio,
+                package_scope,
+                &dirty_files,
+            );
+        }
+        AffectedResult::selected(test_files, dirty_files)
+    }
+
+    /// Count all test files in scope, cached until a test file changes.
+    fn total_test_count(&mut self, package_scope: &str) -> usize {
+        if let Some(&count) = self.test_counts.get(package_scope) {
+            return count;
+        }
+        let count = self.discover_all_tests_scoped(package_scope).len();
+        self.test_counts.insert(package_scope.to_string(), count);
+        count
     }
 
     /// Discover all test files, filtered by package scope.
@@ -260,7 +1133,7 @@ impl AffectedState {
 
         for entry in walker.flatten() {
             let path = entry.path();
-            if super::discovery::is_test_file(path) {
+            if self.test_patterns.matches(path) {
                 if let Some(rel) = path_to_relative(path, &self.workspace_root) {
                     if matches_package_scope(&rel, package_scope) {
                         tests.push(rel);
@@ -272,6 +1145,14 @@ impl AffectedState {
     }
 }
 
+impl Drop for AffectedState {
+    fn drop(&mut self) {
+        if let Some(task) = self.build_task.take() {
+            task.abort();
+        }
+    }
+}
+
 fn is_ts_js_file(path: &Path) -> bool {
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
     matches!(
@@ -280,22 +1161,45 @@ fn is_ts_js_file(path: &Path) -> bool {
     )
 }
 
-fn log_request_start(request_id: &str, force_full: bool, package_scope: &str) {
-    let pkg = if package_scope.is_empty() { "<none>" } else { package_scope };
-    eprintln!("[affected:{request_id}] INFO: GetAffectedTests force_full={force_full}, package={pkg}");
+/// Take a read lock on the graph, giving up after `timeout` so a long rebuild
+/// holding the write lock cannot block a query indefinitely.
+///
+/// A poisoned lock is treated like an overflowed graph.
+fn read_graph_with_timeout(
+    graph: &SharedDepGraph,
+    timeout: Duration,
+) -> Result<RwLockReadGuard<'_, DepGraph>, FullRunReason> {
+    lock_until(|| graph.try_read(), Instant::now() + timeout)
+}
+
+/// Retry `try_lock` until it succeeds or `deadline` passes.
+fn lock_until<G>(
+    try_lock: impl Fn() -> TryLockResult<G>,
+    deadline: Instant,
+) -> Result<G, FullRunReason> {
+    loop {
+        match try_lock() {
+            Ok(guard) => return Ok(guard),
+            Err(TryLockError::Poisoned(_)) => return Err(FullRunReason::GraphOverflow),
+            Err(TryLockError::WouldBlock) if Instant::now() >= deadline => {
+                return Err(FullRunReason::Timeout);
+            }
+            Err(TryLockError::WouldBlock) => std::thread::sleep(LOCK_RETRY_INTERVAL),
+        }
+    }
 }
 
-fn log_info(request_id: &str, msg: &str) {
-    eprintln!("[affected:{request_id}] INFO: {msg}");
+fn log_request_start(force_full: bool, package_scope: &str) {
+    let pkg = if package_scope.is_empty() {
+        "<none>"
+    } else {
+        package_scope
+    };
+    log_line!("[affected] INFO: GetAffectedTests force_full={force_full}, package={pkg}");
 }
 
-fn generate_request_id() -> String {
-    use std::time::{SystemTime, UNIX_EPOCH};
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_nanos();
-    format!("{:x}", nanos % 0xFFFFFF)
+fn log_info(msg: &str) {
+    log_line!("[affected] INFO: {msg}");
 }
 
 fn path_to_relative(path: &Path, workspace_root: &Path) -> Option<String> {
@@ -402,6 +1306,28 @@ mod tests {
         assert!(result.is_full_run);
     }
 
+    #[test]
+    fn dirty_files_partitioned_while_graph_building() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        let root = dir.path().canonicalize().unwrap();
+        state.tracker.add_dirty(root.join("a.test.ts"));
+        state.tracker.add_dirty(root.join("a.ts"));
+
+        let result = state.get_affected_tests(false, "");
+        assert_eq!(result.full_run_reason, Some(FullRunReason::GraphBuilding));
+        let (sources, tests) = result.partition_dirty(&state.test_patterns);
+        assert_eq!(sources, vec!["a.ts"]);
+        assert_eq!(tests, vec!["a.test.ts"]);
+
+        // Not drained: the edits still select tests once the graph is ready
+        build_graph_for_test(&state);
+        assert_eq!(
+            state.get_affected_tests(false, "").test_files,
+            vec!["a.test.ts"]
+        );
+    }
+
     #[test]
     fn affected_result_empty_dirty() {
         let dir = tempdir().unwrap();
@@ -414,14 +1340,6 @@ mod tests {
         assert!(result.dirty_files.is_empty());
     }
 
-    #[test]
-    fn generate_request_id_is_hex() {
-        let id = generate_request_id();
-        assert!(!id.is_empty());
-        // Should be valid hex
-        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
-    }
-
     #[test]
     fn matches_package_scope_empty_matches_all() {
         assert!(matches_package_scope("packages/auth/test.ts", ""));
@@ -435,14 +1353,23 @@ mod tests {
 
     #[test]
     fn matches_package_scope_prefix_with_slash() {
-        assert!(matches_package_scope("packages/auth/test.ts", "packages/auth"));
-        assert!(matches_package_scope("packages/auth/src/test.ts", "packages/auth"));
+        assert!(matches_package_scope(
+            "packages/auth/test.ts",
+            "packages/auth"
+        ));
+        assert!(matches_package_scope(
+            "packages/auth/src/test.ts",
+            "packages/auth"
+        ));
     }
 
     #[test]
     fn matches_package_scope_no_partial_prefix() {
         // "packages/auth-admin" should NOT match "packages/auth"
-        assert!(!matches_package_scope("packages/auth-admin/test.ts", "packages/auth"));
+        assert!(!matches_package_scope(
+            "packages/auth-admin/test.ts",
+            "packages/auth"
+        ));
     }
 
     #[test]
@@ -484,4 +1411,887 @@ mod tests {
         let all_result = state.get_affected_tests(true, "");
         assert_eq!(all_result.test_files.len(), 2);
     }
+
+    fn build_graph_for_test(state: &AffectedState) {
+        let walker = WalkBuilder::new(&state.workspace_root).build();
+        for entry in walker.flatten() {
+            let deadline = Instant::now() + DEFAULT_LOCK_TIMEOUT;
+            state.update_graph_for_file(entry.path(), deadline).unwrap();
+        }
+        state.graph_ready.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn orphan_tests_reports_tests_without_workspace_imports() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        let test = dir.path().join("test");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(&test).unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(src.join("x.ts"), "export const x = 1;").unwrap();
+        fs::write(
+            test.join("x.test.ts"),
+            "import { x } from '../src/x';\ntest('x', () => {});",
+        )
+        .unwrap();
+        fs::write(
+            test.join("orphan.test.ts"),
+            "import { test } from 'vitest';\ntest('o', () => {});",
+        )
+        .unwrap();
+
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        let orphans = state.get_orphan_tests("").unwrap();
+        assert_eq!(orphans, vec!["test/orphan.test.ts"]);
+    }
+
+    #[test]
+    fn orphan_tests_unavailable_while_graph_building() {
+        let dir = tempdir().unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        assert!(state.get_orphan_tests("").is_none());
+    }
+
+    #[test]
+    fn new_test_file_event_invalidates_discovery_cache() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(&src).unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        let source = src.join("foo.ts");
+        fs::write(&source, "export const foo = 1;").unwrap();
+
+        let (tx, rx) = mpsc::channel(16);
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        state.event_rx = Some(rx);
+        build_graph_for_test(&state);
+
+        tx.try_send(source.clone()).unwrap();
+        assert!(state.get_affected_tests(false, "").test_files.is_empty());
+
+        let test = src.join("foo.test.ts");
+        fs::write(&test, "import { foo } from './foo';").unwrap();
+        tx.try_send(test).unwrap();
+        tx.try_send(source).unwrap();
+        let result = state.get_affected_tests(false, "");
+        assert_eq!(result.test_files, vec!["src/foo.test.ts"]);
+    }
+
+    #[test]
+    fn directory_events_are_not_parsed_but_removals_drop_their_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::write(root.join("tsconfig.json"), "{}").unwrap();
+        fs::create_dir_all(root.join("src/old")).unwrap();
+        fs::create_dir_all(root.join("src/vendor.js")).unwrap();
+        fs::write(root.join("src/old/a.ts"), "export const a = 1;").unwrap();
+        fs::write(root.join("src/a.test.ts"), "import { a } from './old/a';").unwrap();
+        let (tx, rx) = mpsc::channel(16);
+        let mut state = AffectedState::new(root.clone());
+        state.event_rx = Some(rx);
+        build_graph_for_test(&state);
+
+        let id = logging::in_request(|| {
+            tx.try_send(root.join("src/vendor.js")).unwrap();
+            state.process_events().unwrap();
+            logging::current_request_id().unwrap()
+        });
+        let prefix = format!("[req:{id}] ");
+        let lines = logging::buffer().recent(0);
+        assert!(!lines
+            .iter()
+            .any(|l| l.starts_with(&prefix) && l.contains("parse error")));
+
+        fs::remove_dir_all(root.join("src/old")).unwrap();
+        tx.try_send(root.join("src/old")).unwrap();
+        let result = state.get_affected_tests(false, "");
+        assert_eq!(result.test_files, vec!["src/a.test.ts"]);
+        assert!(!state
+            .graph
+            .read()
+            .unwrap()
+            .contains(&root.join("src/old/a.ts")));
+    }
+
+    #[test]
+    fn explicit_test_mapping_selects_unrelated_test() {
+        let dir = ratio_workspace();
+        fs::create_dir_all(dir.path().join("src/api")).unwrap();
+        fs::create_dir_all(dir.path().join("e2e")).unwrap();
+        fs::write(
+            dir.path().join("src/api/users.ts"),
+            "export const users = [];",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("e2e/signup.test.ts"),
+            "test('signup', () => {});",
+        )
+        .unwrap();
+        let changes = [FileChange {
+            path: PathBuf::from("src/api/users.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        assert!(state
+            .get_affected_from_diff(&changes, "")
+            .test_files
+            .is_empty());
+
+        let mapping = r#"{"mappings":[{"source":"src/api/**","tests":"e2e/*.test.ts"}]}"#;
+        fs::write(dir.path().join("zax.tests.json"), mapping).unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let result = state.get_affected_from_diff(&changes, "");
+        assert!(!result.is_full_run);
+        assert_eq!(result.test_files, vec!["e2e/signup.test.ts"]);
+    }
+
+    #[test]
+    fn mock_and_resolve_calls_form_edges() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("x.ts"), "export const x = 1;").unwrap();
+        fs::write(dir.path().join("y.ts"), "export const y = 1;").unwrap();
+        fs::write(
+            dir.path().join("a.test.ts"),
+            "vi.mock('./x');\nconst p = require.resolve('./y');",
+        )
+        .unwrap();
+
+        let state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        let root = dir.path().canonicalize().unwrap();
+        let graph = state.graph.read().unwrap();
+        let mut deps = graph.get_dependencies(&root.join("a.test.ts"));
+        deps.sort();
+        assert_eq!(deps, vec![root.join("x.ts"), root.join("y.ts")]);
+    }
+
+    #[test]
+    fn diff_deleted_file_selects_former_dependents() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("b.ts"), "export const b = 1;").unwrap();
+        fs::write(dir.path().join("a.ts"), "import { b } from './b';").unwrap();
+        fs::write(dir.path().join("a.test.ts"), "import './a';").unwrap();
+        fs::write(dir.path().join("other.test.ts"), "test('o', () => {});").unwrap();
+
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        state.tracker.add_dirty(dir.path().join("other.test.ts"));
+        fs::remove_file(dir.path().join("b.ts")).unwrap();
+
+        let changes = [FileChange {
+            path: PathBuf::from("b.ts"),
+            kind: ChangeKind::Deleted,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        assert!(!result.is_full_run);
+        assert_eq!(result.test_files, vec!["a.test.ts"]);
+        assert_eq!(result.dirty_files, vec!["b.ts"]);
+
+        // Persistent dirty set and shared graph are untouched
+        assert_eq!(state.tracker.drain().files.len(), 1);
+        let root = dir.path().canonicalize().unwrap();
+        assert!(state.graph.read().unwrap().contains(&root.join("b.ts")));
+    }
+
+    #[test]
+    fn range_change_to_unused_export_selects_fewer_tests() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(
+            dir.path().join("a.ts"),
+            "export const used = 1;\nexport const unused = 2;\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("uses.test.ts"),
+            "import { used } from './a';",
+        )
+        .unwrap();
+        fs::write(dir.path().join("other.test.ts"), "test('o', () => {});").unwrap();
+
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let change = |ranges: Vec<(usize, usize)>| {
+            [RangeChange {
+                path: PathBuf::from("a.ts"),
+                ranges,
+            }]
+        };
+
+        let whole_file = state.get_affected_from_ranges(&change(Vec::new()), "");
+        assert_eq!(whole_file.test_files, vec!["uses.test.ts"]);
+        let used = state.get_affected_from_ranges(&change(vec![(1, 1)]), "");
+        assert_eq!(used.test_files, vec!["uses.test.ts"]);
+        let unused = state.get_affected_from_ranges(&change(vec![(2, 2)]), "");
+        assert!(!unused.is_full_run);
+        assert!(unused.test_files.is_empty());
+        assert_eq!(unused.dirty_files, vec!["a.ts"]);
+    }
+
+    #[test]
+    fn diff_config_change_forces_full_run() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        state.graph_ready.store(true, Ordering::SeqCst);
+
+        let changes = [FileChange {
+            path: PathBuf::from("package.json"),
+            kind: ChangeKind::Modified,
+        }];
+        assert!(state.get_affected_from_diff(&changes, "").is_full_run);
+    }
+
+    fn poison_graph_lock(state: &AffectedState) {
+        let graph = Arc::clone(&state.graph);
+        let poisoner = std::thread::spawn(move || {
+            let _guard = graph.write().unwrap();
+            std::panic::resume_unwind(Box::new("poison the graph lock"));
+        });
+        assert!(poisoner.join().is_err());
+    }
+
+    #[test]
+    fn diff_with_poisoned_graph_lock_is_full_run() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        poison_graph_lock(&state);
+
+        let changes = [FileChange {
+            path: PathBuf::from("a.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        assert_eq!(result.full_run_reason, Some(FullRunReason::GraphOverflow));
+        assert_eq!(result.test_files.len(), 4);
+        assert_eq!(result.dirty_files, vec!["a.ts"]);
+    }
+
+    #[test]
+    fn ranges_with_poisoned_graph_lock_is_full_run() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        poison_graph_lock(&state);
+
+        let changes = [RangeChange {
+            path: PathBuf::from("a.ts"),
+            ranges: vec![(1, 1)],
+        }];
+        let result = state.get_affected_from_ranges(&changes, "");
+        assert_eq!(result.full_run_reason, Some(FullRunReason::GraphOverflow));
+        assert_eq!(result.test_files.len(), 4);
+        assert_eq!(result.dirty_files, vec!["a.ts"]);
+    }
+
+    #[test]
+    fn configured_config_file_change_forces_full_run() {
+        let dir = ratio_workspace();
+        fs::write(dir.path().join("turbo.json"), r#"{"pipeline":{}}"#).unwrap();
+        let changes = [FileChange {
+            path: PathBuf::from("turbo.json"),
+            kind: ChangeKind::Modified,
+        }];
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        assert!(!state.get_affected_from_diff(&changes, "").is_full_run);
+
+        fs::write(
+            dir.path().join("zax.toml"),
+            "[affected]\nconfig_files = [\"turbo.json\"]\n",
+        )
+        .unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let result = state.get_affected_from_diff(&changes, "");
+        assert_eq!(result.full_run_reason, Some(FullRunReason::ConfigChanged));
+        assert_eq!(result.test_files.len(), 4);
+    }
+
+    #[test]
+    fn configured_test_extensions_are_discovered() {
+        let dir = ratio_workspace();
+        fs::write(dir.path().join("e.test.cts"), "test('e', () => {});").unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        assert_eq!(state.get_affected_tests(true, "").test_files.len(), 4);
+
+        fs::write(
+            dir.path().join("zax.toml"),
+            "[affected]\ntest_extensions = [\"cts\"]\n",
+        )
+        .unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        let result = state.get_affected_tests(true, "");
+        assert_eq!(result.test_files.len(), 5);
+        assert!(result.test_files.contains(&"e.test.cts".to_string()));
+
+        let root = dir.path().canonicalize().unwrap();
+        state.tracker.add_dirty(root.join("e.test.cts"));
+        let result = state.get_affected_tests(false, "");
+        let (_, tests) = result.partition_dirty(&state.test_patterns);
+        assert_eq!(tests, vec!["e.test.cts"]);
+    }
+
+    #[test]
+    fn repeated_selection_errors_open_circuit_breaker() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+        // Poison the graph lock so every selection fails internally
+        let graph = Arc::clone(&state.graph);
+        let _ = std::thread::spawn(move || {
+            let _guard = graph.write().unwrap();
+            std::panic::resume_unwind(Box::new("simulated graph failure"));
+        })
+        .join();
+
+        for _ in 0..state.breaker.threshold {
+            state.tracker.add_dirty(root.join("a.ts"));
+            let result = state.get_affected_tests(false, "");
+            assert_eq!(result.full_run_reason, Some(FullRunReason::GraphOverflow));
+        }
+        state.tracker.add_dirty(root.join("a.ts"));
+        let result = state.get_affected_tests(false, "");
+        assert!(result.is_full_run);
+        assert_eq!(result.full_run_reason, Some(FullRunReason::CircuitOpen));
+        assert_eq!(result.test_files.len(), 4);
+        assert_eq!(result.dirty_files, vec!["a.ts"]);
+        assert!(logging::buffer()
+            .recent(0)
+            .iter()
+            .any(|l| l.contains("circuit open")));
+    }
+
+    fn ratio_workspace() -> tempfile::TempDir {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("shared.ts"), "export const s = 1;").unwrap();
+        fs::write(dir.path().join("a.ts"), "export const a = 1;").unwrap();
+        fs::write(
+            dir.path().join("a.test.ts"),
+            "import './a';\nimport './shared';",
+        )
+        .unwrap();
+        fs::write(dir.path().join("b.test.ts"), "import './shared';").unwrap();
+        fs::write(dir.path().join("c.test.ts"), "import './shared';").unwrap();
+        fs::write(dir.path().join("d.test.ts"), "test('d', () => {});").unwrap();
+        dir
+    }
+
+    #[test]
+    fn large_affected_ratio_forces_full_run() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        // 3 of 4 tests exceeds the default 0.7 ratio
+        let changes = [FileChange {
+            path: PathBuf::from("shared.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        assert!(result.is_full_run);
+        assert_eq!(result.full_run_reason, Some(FullRunReason::AffectedRatio));
+        assert_eq!(result.test_files.len(), 4);
+    }
+
+    #[test]
+    fn unrecognized_test_layout_falls_back_to_full_run() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("a.ts"), "export const a = 1;").unwrap();
+        fs::create_dir(dir.path().join("checks")).unwrap();
+        fs::write(dir.path().join("checks/a_check.ts"), "import '../a';").unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        let changes = [FileChange {
+            path: PathBuf::from("a.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        assert!(result.is_full_run);
+        assert_eq!(result.full_run_reason, Some(FullRunReason::DiscoveryMiss));
+        assert_eq!(result.dirty_files, vec!["a.ts"]);
+    }
+
+    #[test]
+    fn small_affected_ratio_keeps_selection() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        let changes = [FileChange {
+            path: PathBuf::from("a.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        assert!(!result.is_full_run);
+        assert_eq!(result.full_run_reason, None);
+        assert_eq!(result.test_files, vec!["a.test.ts"]);
+    }
+
+    #[test]
+    fn source_files_list_transitively_affected_modules() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("base.ts"), "export const b = 1;").unwrap();
+        fs::write(dir.path().join("mid.ts"), "import './base';").unwrap();
+        fs::write(dir.path().join("top.ts"), "import './mid';").unwrap();
+        fs::write(dir.path().join("other.ts"), "export const o = 1;").unwrap();
+        fs::write(dir.path().join("top.test.ts"), "import './top';").unwrap();
+        fs::write(dir.path().join("other.test.ts"), "import './other';").unwrap();
+        fs::write(dir.path().join("x.test.ts"), "test('x', () => {});").unwrap();
+        fs::write(dir.path().join("y.test.ts"), "test('y', () => {});").unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        let changes = [FileChange {
+            path: PathBuf::from("base.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        assert!(!result.is_full_run);
+        assert_eq!(result.source_files, vec!["base.ts", "mid.ts", "top.ts"]);
+        assert_eq!(result.test_files, vec!["top.test.ts"]);
+    }
+
+    #[test]
+    fn absolute_paths_exist_on_disk() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        let changes = [FileChange {
+            path: PathBuf::from("a.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        let result = state.absolutize(result);
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(
+            result.test_files,
+            vec![root.join("a.test.ts").to_string_lossy()]
+        );
+        assert!(result
+            .dirty_files
+            .iter()
+            .chain(&result.test_files)
+            .all(|f| {
+                let path = Path::new(f);
+                path.is_absolute() && path.exists()
+            }));
+    }
+
+    #[test]
+    fn peek_does_not_drain_dirty_set() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+        state.tracker.add_dirty(root.join("a.ts"));
+
+        let first = state.peek_affected_tests(false, "");
+        let second = state.peek_affected_tests(false, "");
+        assert_eq!(first.test_files, vec!["a.test.ts"]);
+        assert_eq!(second.test_files, first.test_files);
+
+        let drained = state.get_affected_tests(false, "");
+        assert_eq!(drained.test_files, first.test_files);
+        assert!(state.get_affected_tests(false, "").test_files.is_empty());
+    }
+
+    #[test]
+    fn dependency_path_uses_relative_paths() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+
+        assert_eq!(
+            state.get_dependency_path("a.test.ts", "a.ts").unwrap(),
+            vec!["a.test.ts", "a.ts"]
+        );
+        assert!(state
+            .get_dependency_path("a.ts", "a.test.ts")
+            .unwrap()
+            .is_empty());
+        assert!(state
+            .get_dependency_path("missing.ts", "a.ts")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn graph_node_limits_are_per_workspace() {
+        let small_dir = ratio_workspace();
+        let small = AffectedState::new(small_dir.path().to_path_buf());
+        small.set_max_graph_nodes(3);
+        let large_dir = ratio_workspace();
+        let large = AffectedState::new(large_dir.path().to_path_buf());
+        large.set_max_graph_nodes(10);
+
+        build_graph_for_test(&small);
+        build_graph_for_test(&large);
+
+        // Both workspaces hold six modules
+        let small_graph = small.graph.read().unwrap();
+        assert!(small_graph.is_overflow());
+        assert_eq!(small_graph.node_count(), 3);
+        let large_graph = large.graph.read().unwrap();
+        assert!(!large_graph.is_overflow());
+        assert_eq!(large_graph.node_count(), 6);
+    }
+
+    #[test]
+    fn empty_ready_graph_with_dirty_files_forces_full_run() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        // Ready, but the build added nothing
+        state.graph_ready.store(true, Ordering::SeqCst);
+        state
+            .tracker
+            .add_dirty(dir.path().canonicalize().unwrap().join("a.ts"));
+
+        let result = state.get_affected_tests(false, "");
+        assert!(result.is_full_run);
+        assert_eq!(result.full_run_reason, Some(FullRunReason::EmptyGraph));
+        assert_eq!(result.test_files.len(), 4);
+    }
+
+    #[test]
+    fn test_leaves_leave_node_limit_to_sources() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        state.set_max_graph_nodes(3);
+        state.set_test_leaves(true);
+        build_graph_for_test(&state);
+
+        // Six modules, four of them tests
+        {
+            let graph = state.graph.read().unwrap();
+            assert!(!graph.is_overflow());
+            assert_eq!((graph.node_count(), graph.leaf_count()), (2, 4));
+        }
+        let changes = [FileChange {
+            path: PathBuf::from("a.ts"),
+            kind: ChangeKind::Modified,
+        }];
+        let result = state.get_affected_from_diff(&changes, "");
+        assert!(!result.is_full_run);
+        assert_eq!(result.test_files, vec!["a.test.ts"]);
+    }
+
+    #[test]
+    fn graph_stats_count_import_cycles() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        fs::write(dir.path().join("a.ts"), "import './b';").unwrap();
+        fs::write(dir.path().join("b.ts"), "import './a';").unwrap();
+        fs::write(dir.path().join("c.ts"), "import './a';").unwrap();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        assert_eq!(state.get_graph_stats(), None);
+        build_graph_for_test(&state);
+
+        let stats = state.get_graph_stats().unwrap();
+        assert_eq!(
+            stats,
+            GraphStats {
+                nodes: 3,
+                edges: 3,
+                cycles: 1
+            }
+        );
+    }
+
+    #[test]
+    fn hub_modules_reported_above_fan_in_threshold() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        state.fan_in_threshold = 2;
+        build_graph_for_test(&state);
+
+        // shared.ts is imported by three tests; a.ts by one
+        assert_eq!(
+            state.get_hub_modules().unwrap(),
+            vec![("shared.ts".to_string(), 3)]
+        );
+        state.fan_in_threshold = 3;
+        assert!(state.get_hub_modules().unwrap().is_empty());
+    }
+
+    #[test]
+    fn held_graph_lock_times_out_to_full_run() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        state.lock_timeout = Duration::from_millis(50);
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+
+        // Simulate a long rebuild holding the write lock
+        let graph = Arc::clone(&state.graph);
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let writer = std::thread::spawn(move || {
+            let _guard = graph.write().unwrap();
+            locked_tx.send(()).unwrap();
+            let _ = release_rx.recv();
+        });
+        locked_rx.recv().unwrap();
+
+        state.tracker.add_dirty(root.join("a.ts"));
+        let started = Instant::now();
+        let result = state.get_affected_tests(false, "");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.full_run_reason, Some(FullRunReason::Timeout));
+        assert_eq!(result.test_files.len(), 4);
+        assert_eq!(result.dirty_files, vec!["a.ts"]);
+
+        release_tx.send(()).unwrap();
+        writer.join().unwrap();
+        state.tracker.add_dirty(root.join("a.ts"));
+        assert_eq!(
+            state.get_affected_tests(false, "").test_files,
+            vec!["a.test.ts"]
+        );
+    }
+
+    #[test]
+    fn pending_event_with_held_graph_lock_times_out_and_stays_queued() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        state.lock_timeout = Duration::from_millis(50);
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+        let (tx, rx) = mpsc::channel(16);
+        state.event_rx = Some(rx);
+
+        let graph = Arc::clone(&state.graph);
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let (release_tx, release_rx) = std::sync::mpsc::channel::<()>();
+        let writer = std::thread::spawn(move || {
+            let _guard = graph.write().unwrap();
+            locked_tx.send(()).unwrap();
+            let _ = release_rx.recv();
+        });
+        locked_rx.recv().unwrap();
+
+        tx.try_send(root.join("a.ts")).unwrap();
+        let started = Instant::now();
+        let result = state.get_affected_tests(false, "");
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(result.full_run_reason, Some(FullRunReason::Timeout));
+        assert_eq!(state.pending_events, vec![root.join("a.ts")]);
+
+        release_tx.send(()).unwrap();
+        writer.join().unwrap();
+        assert_eq!(
+            state.get_affected_tests(false, "").test_files,
+            vec!["a.test.ts"]
+        );
+        assert!(state.pending_events.is_empty());
+    }
+
+    #[test]
+    fn alias_target_created_after_failed_resolve_is_linked() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let tsconfig = r#"{"compilerOptions": {"paths": {"@lib/*": ["lib/*"]}}}"#;
+        fs::write(root.join("tsconfig.json"), tsconfig).unwrap();
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::write(root.join("b.test.ts"), "import { n } from '@lib/new';").unwrap();
+        let mut state = AffectedState::new(root.clone());
+        let (tx, rx) = mpsc::channel(16);
+        state.event_rx = Some(rx);
+        build_graph_for_test(&state);
+        assert_eq!(state.negative_cache.lock().unwrap().recorded(), 1);
+
+        // The importer is re-parsed after the alias target appears
+        fs::write(root.join("lib/new.ts"), "export const n = 1;").unwrap();
+        tx.try_send(root.join("lib/new.ts")).unwrap();
+        tx.try_send(root.join("b.test.ts")).unwrap();
+        state.process_events().unwrap();
+        assert_eq!(
+            state
+                .graph
+                .read()
+                .unwrap()
+                .get_dependents(&root.join("lib/new.ts")),
+            vec![root.join("b.test.ts")]
+        );
+    }
+
+    #[test]
+    fn change_during_selection_is_dirty_on_next_query() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+        let (tx, rx) = mpsc::channel(16);
+        state.event_rx = Some(rx);
+
+        // Hold the graph lock so the selection blocks after draining, then
+        // deliver a watcher event before releasing it
+        let graph = Arc::clone(&state.graph);
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let late_change = root.join("b.test.ts");
+        let writer = std::thread::spawn(move || {
+            let _guard = graph.write().unwrap();
+            locked_tx.send(()).unwrap();
+            std::thread::sleep(Duration::from_millis(200));
+            tx.blocking_send(late_change).unwrap();
+            tx
+        });
+        locked_rx.recv().unwrap();
+
+        state.tracker.add_dirty(root.join("a.ts"));
+        let first = state.get_affected_tests(false, "");
+        let _tx = writer.join().unwrap();
+        assert_eq!(first.test_files, vec!["a.test.ts"]);
+        let second = state.get_affected_tests(false, "");
+        assert_eq!(second.test_files, vec!["b.test.ts"]);
+        assert_eq!(second.dirty_files, vec!["b.test.ts"]);
+    }
+
+    #[test]
+    fn cursor_calls_return_only_files_dirtied_since_the_previous_call() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+
+        let start = state.peek_affected_tests(false, "").cursor;
+        state.tracker.add_dirty(root.join("a.ts"));
+        let first = state.get_affected_tests_since(false, "", &start);
+        assert_eq!(first.test_files, vec!["a.test.ts"]);
+        state.tracker.add_dirty(root.join("b.test.ts"));
+        let second = state.get_affected_tests_since(false, "", &first.cursor);
+        assert_eq!(second.dirty_files, vec!["b.test.ts"]);
+        assert_eq!(second.test_files, vec!["b.test.ts"]);
+
+        let expired = state.get_affected_tests_since(false, "", "0-0");
+        assert_eq!(expired.full_run_reason, Some(FullRunReason::CursorExpired));
+    }
+
+    #[test]
+    fn result_hash_tracks_the_selected_set() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+
+        state.tracker.add_dirty(root.join("a.ts"));
+        let first = state.get_affected_tests(false, "");
+        state.tracker.add_dirty(root.join("a.ts"));
+        let again = state.get_affected_tests(false, "");
+        assert_eq!(first.result_hash(), again.result_hash());
+
+        let reordered = AffectedResult {
+            test_files: vec!["b.test.ts".into(), "a.test.ts".into()],
+            ..first.clone()
+        };
+        let sorted = AffectedResult {
+            test_files: vec!["a.test.ts".into(), "b.test.ts".into()],
+            ..first.clone()
+        };
+        assert_eq!(reordered.result_hash(), sorted.result_hash());
+        assert_ne!(first.result_hash(), sorted.result_hash());
+        assert_ne!(first.result_hash(), AffectedResult::empty().result_hash());
+    }
+
+    #[test]
+    fn results_report_the_graph_generation() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+
+        let first = state.get_affected_tests(false, "").graph_generation;
+        assert!(first > 0);
+        assert_eq!(state.peek_affected_tests(false, "").graph_generation, first);
+        state.graph.write().unwrap().add_file(root.join("new.ts"));
+        assert!(state.get_affected_tests(false, "").graph_generation > first);
+    }
+
+    #[test]
+    fn dirty_standalone_test_is_selected_without_graph_node() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        let root = dir.path().canonicalize().unwrap();
+        // Created after the build and never parsed into the graph
+        let standalone = root.join("standalone.test.ts");
+        fs::write(&standalone, "test('s', () => {});").unwrap();
+        assert!(!state.graph.read().unwrap().contains(&standalone));
+
+        state.tracker.add_dirty(standalone);
+        state.tracker.add_dirty(root.join("removed.test.ts"));
+        let result = state.get_affected_tests(false, "");
+        assert!(!result.is_full_run);
+        assert_eq!(result.test_files, vec!["standalone.test.ts"]);
+    }
+
+    #[test]
+    fn request_logs_share_one_id_across_modules() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        // Make the parser skip the changed file so it logs from its own module
+        state.parse_limits.max_file_size = 1;
+        let changes = [RangeChange {
+            path: PathBuf::from("a.ts"),
+            ranges: vec![(1, 1)],
+        }];
+
+        let id = logging::in_request(|| {
+            state.get_affected_from_ranges(&changes, "");
+            logging::current_request_id().unwrap()
+        });
+
+        let prefix = format!("[req:{id}] ");
+        let lines: Vec<String> = logging::buffer()
+            .recent(0)
+            .into_iter()
+            .filter(|l| l.starts_with(&prefix))
+            .collect();
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("AffectedFromRanges files=1")));
+        assert!(lines
+            .iter()
+            .any(|l| l.contains("skipping likely generated file")));
+    }
+
+    #[test]
+    fn failed_watcher_forces_full_runs() {
+        let dir = ratio_workspace();
+        let mut state = AffectedState::new(dir.path().to_path_buf());
+        build_graph_for_test(&state);
+        // Simulate the watcher thread exiting: its sender is dropped
+        let (tx, rx) = mpsc::channel(1);
+        drop(tx);
+        state.event_rx = Some(rx);
+
+        for result in [
+            state.peek_affected_tests(false, ""),
+            state.get_affected_tests(false, ""),
+        ] {
+            assert!(result.is_full_run);
+            assert_eq!(
+                result.full_run_reason,
+                Some(FullRunReason::WatcherUnavailable)
+            );
+            assert_eq!(result.test_files.len(), 4);
+        }
+    }
 }
@@ -0,0 +1,458 @@
+//! Export-level narrowing of line-range changes.
+//!
+//! A change confined to some exports of a module only affects dependents that
+//! import those exports. This maps changed lines to the export names they
+//! touch and lists the names each import binds. Whatever cannot be attributed
+//! to specific exports yields `None`, and callers treat the whole module as
+//! changed.
+
+use super::parser::{
+    extract_imports, log_warn_parse_error, parse_with_fallback, read_source, ImportKind,
+    ParseLimits,
+};
+use std::collections::HashSet;
+use std::path::Path;
+use tree_sitter::Node;
+
+/// Changed lines, 1-based and inclusive.
+pub type LineRange = (usize, usize);
+
+/// Export names one import takes from its target module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolImport {
+    pub specifier: String,
+    /// Imported names (`default` for default imports); `None` when the whole
+    /// module is used: namespace and side-effect imports, `export *`,
+    /// `require`, dynamic imports and mocks.
+    pub names: Option<Vec<String>>,
+}
+
+/// What a top-level statement contributes to the module's exports.
+#[derive(Debug, Default)]
+struct Statement {
+    /// Bindings it declares.
+    locals: Vec<String>,
+    /// Export names it provides.
+    exports: Vec<String>,
+    /// `(local, exported)` pairs of an `export { a as b }` clause.
+    aliases: Vec<(String, String)>,
+    /// Identifiers it references.
+    refs: HashSet<String>,
+    /// Overlaps a changed range.
+    changed: bool,
+    /// Runs on import without declaring known bindings, so a change reaching
+    /// it cannot be narrowed.
+    effectful: bool,
+}
+
+/// Export names of `path` affected by changes to `ranges`.
+///
+/// Includes exports whose declarations overlap a range and, transitively,
+/// exports referencing a changed top-level binding. Returns `None` when the
+/// file does not parse or a range touches anything but declarations and
+/// comments (imports, top-level calls, local export lists).
+pub fn changed_exports(
+    path: &Path,
+    ranges: &[LineRange],
+    limits: ParseLimits,
+) -> Option<HashSet<String>> {
+    let content = read_source(path, limits)?;
+    let (tree, _) = parse_with_fallback(&content, path)
+        .map_err(|reason| log_warn_parse_error(path, reason))
+        .ok()?;
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let statements = root
+        .named_children(&mut cursor)
+        .map(|node| classify(&content, &node, ranges))
+        .collect::<Option<Vec<_>>>()?;
+    propagate(&statements)
+}
+
+/// Imports of `path` with the names each binds, or `None` if it does not
+/// parse.
+pub fn symbol_imports(path: &Path, limits: ParseLimits) -> Option<Vec<SymbolImport>> {
+    let content = read_source(path, limits)?;
+    let (tree, language) = parse_with_fallback(&content, path)
+        .map_err(|reason| log_warn_parse_error(path, reason))
+        .ok()?;
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let mut imports: Vec<SymbolImport> = root
+        .named_children(&mut cursor)
+        .filter(|node| matches!(node.kind(), "import_statement" | "export_statement"))
+        .filter_map(|node| {
+            let specifier = text(&content, &node.child_by_field_name("source")?);
+            Some(SymbolImport {
+                specifier,
+                names: bound_names(&content, &node),
+            })
+        })
+        .collect();
+    // Calls bind no names and may sit anywhere in the file
+    imports.extend(
+        extract_imports(&content, &root, &language)
+            .into_iter()
+            .filter(|i| {
+                matches!(
+                    i.kind,
+                    ImportKind::Require | ImportKind::Mock | ImportKind::Dynamic
+                )
+            })
+            .map(|i| SymbolImport {
+                specifier: i.specifier,
+                names: None,
+            }),
+    );
+    Some(imports)
+}
+
+/// Describe a top-level statement, or `None` if it overlaps a range and the
+/// change cannot be attributed to exports.
+fn classify(content: &str, node: &Node, ranges: &[LineRange]) -> Option<Statement> {
+    let changed = overlaps(node, ranges);
+    let statement = match node.kind() {
+        "comment" | "empty_statement" => Statement::default(),
+        "import_statement" if changed => return None,
+        "import_statement" => Statement::default(),
+        "export_statement" => classify_export(content, node, changed)?,
+        _ if is_declaration(node.kind()) => match declared_names(content, node) {
+            Some(locals) => Statement {
+                locals,
+                refs: identifiers(content, node),
+                ..Default::default()
+            },
+            None => effectful(content, node),
+        },
+        _ => effectful(content, node),
+    };
+    if changed && statement.effectful {
+        return None;
+    }
+    Some(Statement {
+        changed,
+        ..statement
+    })
+}
+
+/// Re-exports declare nothing locally; only their names can change.
+fn classify_reexport(content: &str, node: &Node, changed: bool) -> Option<Statement> {
+    let exports = if changed {
+        reexported_names(content, node)?
+    } else {
+        Vec::new()
+    };
+    Some(Statement {
+        exports,
+        ..Default::default()
+    })
+}
+
+fn classify_export(content: &str, node: &Node, changed: bool) -> Option<Statement> {
+    let is_default = has_token(node, "default");
+    if node.child_by_field_name("source").is_some() {
+        return classify_reexport(content, node, changed);
+    }
+    if let Some(decl) = node.child_by_field_name("declaration") {
+        let locals = declared_names(content, &decl);
+        let exports = match (&locals, is_default) {
+            (_, true) => vec!["default".to_string()],
+            (Some(locals), false) => locals.clone(),
+            (None, false) => return Some(effectful(content, node)),
+        };
+        let locals = locals.unwrap_or_default();
+        return Some(Statement {
+            locals,
+            exports,
+            refs: identifiers(content, &decl),
+            ..Default::default()
+        });
+    }
+    if let Some(value) = node.child_by_field_name("value") {
+        let exports = vec!["default".to_string()];
+        return Some(Statement {
+            exports,
+            refs: identifiers(content, &value),
+            ..Default::default()
+        });
+    }
+    match clause_of(node, "export_clause") {
+        // A changed `export { a as b }` may rebind any of its names
+        Some(_) if changed => None,
+        Some(clause) => Some(Statement {
+            aliases: export_aliases(content, &clause),
+            ..Default::default()
+        }),
+        None => Some(effectful(content, node)),
+    }
+}
+
+/// Close the changed set over references between top-level statements.
+fn propagate(statements: &[Statement]) -> Option<HashSet<String>> {
+    let mut changed: Vec<bool> = statements.iter().map(|s| s.changed).collect();
+    let mut locals: HashSet<&str> = HashSet::new();
+    for statement in statements.iter().filter(|s| s.changed) {
+        locals.extend(statement.locals.iter().map(String::as_str));
+    }
+    let mut grew = true;
+    while grew {
+        grew = false;
+        for (statement, changed) in statements.iter().zip(changed.iter_mut()) {
+            if *changed || !statement.refs.iter().any(|r| locals.contains(r.as_str())) {
+                continue;
+            }
+            if statement.effectful {
+                return None;
+            }
+            *changed = true;
+            grew = true;
+            locals.extend(statement.locals.iter().map(String::as_str));
+        }
+    }
+    let mut exports: HashSet<String> = statements
+        .iter()
+        .zip(&changed)
+        .filter(|(_, &changed)| changed)
+        .flat_map(|(s, _)| s.exports.iter().cloned())
+        .collect();
+    for (local, exported) in statements.iter().flat_map(|s| &s.aliases) {
+        if locals.contains(local.as_str()) {
+            exports.insert(exported.clone());
+        }
+    }
+    Some(exports)
+}
+
+fn effectful(content: &str, node: &Node) -> Statement {
+    Statement {
+        refs: identifiers(content, node),
+        effectful: true,
+        ..Default::default()
+    }
+}
+
+fn overlaps(node: &Node, ranges: &[LineRange]) -> bool {
+    let start = node.start_position().row + 1;
+    let end = node.end_position().row + 1;
+    ranges.iter().any(|&(from, to)| from <= end && start <= to)
+}
+
+fn is_declaration(kind: &str) -> bool {
+    matches!(
+        kind,
+        "function_declaration"
+            | "generator_function_declaration"
+            | "function_signature"
+            | "class_declaration"
+            | "abstract_class_declaration"
+            | "lexical_declaration"
+            | "variable_declaration"
+            | "interface_declaration"
+            | "type_alias_declaration"
+            | "enum_declaration"
+            | "internal_module"
+            | "module"
+    )
+}
+
+/// Bindings declared by a declaration; `None` for destructuring patterns and
+/// anonymous declarations.
+fn declared_names(content: &str, node: &Node) -> Option<Vec<String>> {
+    if !matches!(node.kind(), "lexical_declaration" | "variable_declaration") {
+        return node
+            .child_by_field_name("name")
+            .map(|name| vec![text(content, &name)]);
+    }
+    let mut cursor = node.walk();
+    let names = node
+        .named_children(&mut cursor)
+        .filter(|c| c.kind() == "variable_declarator")
+        .map(|d| {
+            d.child_by_field_name("name")
+                .filter(|n| n.kind() == "identifier")
+        })
+        .map(|name| name.map(|n| text(content, &n)))
+        .collect();
+    names
+}
+
+/// Names a re-export provides; `None` for `export * from`.
+fn reexported_names(content: &str, node: &Node) -> Option<Vec<String>> {
+    if let Some(clause) = clause_of(node, "export_clause") {
+        return Some(
+            export_aliases(content, &clause)
+                .into_iter()
+                .map(|(_, exported)| exported)
+                .collect(),
+        );
+    }
+    let namespace = clause_of(node, "namespace_export")?;
+    let mut cursor = namespace.walk();
+    let name = namespace
+        .named_children(&mut cursor)
+        .next()
+        .map(|n| text(content, &n));
+    name.map(|name| vec![name])
+}
+
+/// Names an import statement or re-export takes from its source.
+fn bound_names(content: &str, node: &Node) -> Option<Vec<String>> {
+    if node.kind() == "export_statement" {
+        let clause = clause_of(node, "export_clause")?;
+        return Some(
+            export_aliases(content, &clause)
+                .into_iter()
+                .map(|(local, _)| local)
+                .collect(),
+        );
+    }
+    let clause = clause_of(node, "import_clause")?;
+    let mut names = Vec::new();
+    let mut cursor = clause.walk();
+    for child in clause.named_children(&mut cursor) {
+        match child.kind() {
+            "identifier" => names.push("default".to_string()),
+            "named_imports" => names.extend(specifier_names(content, &child, "import_specifier")),
+            _ => return None,
+        }
+    }
+    Some(names)
+}
+
+/// `(name, alias or name)` of each specifier in an export clause.
+fn export_aliases(content: &str, clause: &Node) -> Vec<(String, String)> {
+    let mut cursor = clause.walk();
+    let aliases = clause
+        .named_children(&mut cursor)
+        .filter(|c| c.kind() == "export_specifier")
+        .filter_map(|spec| {
+            let name = text(content, &spec.child_by_field_name("name")?);
+            let alias = spec
+                .child_by_field_name("alias")
+                .map_or_else(|| name.clone(), |a| text(content, &a));
+            Some((name, alias))
+        })
+        .collect();
+    aliases
+}
+
+fn specifier_names(content: &str, node: &Node, kind: &str) -> Vec<String> {
+    let mut cursor = node.walk();
+    let names = node
+        .named_children(&mut cursor)
+        .filter(|c| c.kind() == kind)
+        .filter_map(|spec| spec.child_by_field_name("name"))
+        .map(|name| text(content, &name))
+        .collect();
+    names
+}
+
+fn clause_of<'tree>(node: &Node<'tree>, kind: &str) -> Option<Node<'tree>> {
+    let mut cursor = node.walk();
+    let clause = node.named_children(&mut cursor).find(|c| c.kind() == kind);
+    clause
+}
+
+fn has_token(node: &Node, token: &str) -> bool {
+    let mut cursor = node.walk();
+    let found = node
+        .children(&mut cursor)
+        .any(|c| !c.is_named() && c.kind() == token);
+    found
+}
+
+/// Identifiers referenced anywhere under `node`.
+fn identifiers(content: &str, node: &Node) -> HashSet<String> {
+    let mut found = HashSet::new();
+    let mut stack = vec![*node];
+    while let Some(current) = stack.pop() {
+        if matches!(
+            current.kind(),
+            "identifier" | "type_identifier" | "shorthand_property_identifier"
+        ) {
+            found.insert(text(content, &current));
+        }
+        let mut cursor = current.walk();
+        stack.extend(current.named_children(&mut cursor));
+    }
+    found
+}
+
+/// Node text with string-literal quotes removed (`import { "a-b" as x }`).
+fn text(content: &str, node: &Node) -> String {
+    let raw = node.utf8_text(content.as_bytes()).unwrap_or("");
+    raw.trim_matches(|c| c == '"' || c == '\'' || c == '`')
+        .to_string()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    const MODULE: &str = "\
+import { dep } from './dep';
+export const a = 1;
+export function b() {
+  return helper();
+}
+function helper() {
+  return dep;
+}
+export default class Widget {}
+export { helper as alias };
+";
+
+    fn exports_for(ranges: &[LineRange]) -> Option<Vec<String>> {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("mod.ts");
+        fs::write(&path, MODULE).unwrap();
+        let mut names: Vec<String> = changed_exports(&path, ranges, ParseLimits::default())?
+            .into_iter()
+            .collect();
+        names.sort();
+        Some(names)
+    }
+
+    #[test]
+    fn changed_exports_follow_local_references() {
+        assert_eq!(exports_for(&[(2, 2)]).unwrap(), vec!["a"]);
+        assert_eq!(exports_for(&[(4, 4)]).unwrap(), vec!["b"]);
+        // helper is used by b and re-exported as alias
+        assert_eq!(exports_for(&[(7, 7)]).unwrap(), vec!["alias", "b"]);
+        assert_eq!(exports_for(&[(9, 9)]).unwrap(), vec!["default"]);
+        // Imports and local export lists cannot be narrowed
+        assert!(exports_for(&[(1, 1)]).is_none());
+        assert!(exports_for(&[(10, 10)]).is_none());
+    }
+
+    #[test]
+    fn symbol_imports_list_bound_names() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("user.ts");
+        let source = "\
+import Widget, { a as x, b } from './mod';
+import * as all from './all';
+import './side-effect';
+export { c } from './re';
+const lazy = require('./lazy');
+";
+        fs::write(&path, source).unwrap();
+        let imports = symbol_imports(&path, ParseLimits::default()).unwrap();
+        let names = |specifier: &str| {
+            imports
+                .iter()
+                .find(|i| i.specifier == specifier)
+                .unwrap()
+                .names
+                .clone()
+        };
+        assert_eq!(names("./mod").unwrap(), vec!["default", "a", "b"]);
+        assert_eq!(names("./re").unwrap(), vec!["c"]);
+        assert!(names("./all").is_none());
+        assert!(names("./side-effect").is_none());
+        assert!(names("./lazy").is_none());
+    }
+}
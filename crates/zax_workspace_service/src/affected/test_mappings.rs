@@ -0,0 +1,199 @@
+//! Explicit source→test associations.
+//!
+//! Convention-based discovery misses tests that live far from their source
+//! and do not import it (e.g. integration suites). A workspace can pin those
+//! in `zax.tests.json` at its root:
+//!
+//! ```json
+//! { "mappings": [{ "source": "src/api/**", "tests": "tests/integration/api/*.test.ts" }] }
+//! ```
+//!
+//! Both globs match workspace-relative paths; `*` does not cross `/`.
+
+use crate::logging::log_line;
+use globset::{GlobBuilder, GlobMatcher};
+use ignore::WalkBuilder;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Mapping file read from the workspace root.
+pub const TEST_MAPPINGS_FILE: &str = "zax.tests.json";
+
+#[derive(Debug, Default, Deserialize)]
+struct MappingsJson {
+    #[serde(default)]
+    mappings: Vec<MappingJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MappingJson {
+    source: String,
+    tests: String,
+}
+
+/// One `source` glob → `tests` glob association.
+#[derive(Debug)]
+struct Mapping {
+    source: GlobMatcher,
+    tests: GlobMatcher,
+    /// Test files matching `tests`, listed on first use.
+    expanded: Option<Vec<PathBuf>>,
+}
+
+/// Explicit mappings of one workspace.
+#[derive(Debug, Default)]
+pub struct TestMappings {
+    workspace_root: PathBuf,
+    mappings: Vec<Mapping>,
+}
+
+impl TestMappings {
+    /// Build mappings from `(source, tests)` glob pairs.
+    ///
+    /// # Errors
+    /// Returns an error if a pattern is not a valid glob.
+    pub fn new(workspace_root: PathBuf, pairs: &[(&str, &str)]) -> Result<Self, globset::Error> {
+        let mappings = pairs
+            .iter()
+            .map(|(source, tests)| {
+                Ok(Mapping {
+                    source: matcher(source)?,
+                    tests: matcher(tests)?,
+                    expanded: None,
+                })
+            })
+            .collect::<Result<_, globset::Error>>()?;
+        Ok(Self {
+            workspace_root,
+            mappings,
+        })
+    }
+
+    /// Load mappings from `zax.tests.json` in the workspace root.
+    ///
+    /// A missing file means no mappings; an unreadable or invalid file is
+    /// logged and also yields none.
+    pub fn load(workspace_root: &Path) -> Self {
+        // Affected paths are canonical
+        let root = workspace_root
+            .canonicalize()
+            .unwrap_or_else(|_| workspace_root.to_path_buf());
+        let loaded = read_mappings(&root.join(TEST_MAPPINGS_FILE)).and_then(|json| {
+            let pairs: Vec<(&str, &str)> = json
+                .mappings
+                .iter()
+                .map(|m| (m.source.as_str(), m.tests.as_str()))
+                .collect();
+            Self::new(root.clone(), &pairs).map_err(|e| e.to_string())
+        });
+        loaded.unwrap_or_else(|e| {
+            log_line!("[affected] WARN: ignoring {TEST_MAPPINGS_FILE}: {e}");
+            Self {
+                workspace_root: root,
+                mappings: Vec::new(),
+            }
+        })
+    }
+
+    /// Test files explicitly associated with `source`.
+    pub fn tests_for(&mut self, source: &Path) -> Vec<PathBuf> {
+        let Ok(relative) = source.strip_prefix(&self.workspace_root) else {
+            return Vec::new();
+        };
+        let mut tests = Vec::new();
+        for mapping in &mut self.mappings {
+            if mapping.source.is_match(relative) {
+                let root = &self.workspace_root;
+                let expanded = mapping
+                    .expanded
+                    .get_or_insert_with(|| expand(root, &mapping.tests));
+                tests.extend(expanded.iter().cloned());
+            }
+        }
+        tests
+    }
+
+    /// Forget listed tests of mappings whose `tests` glob matches a created
+    /// or deleted test file. Returns true if any mapping was affected.
+    pub fn invalidate_test_file(&mut self, test_path: &Path) -> bool {
+        let Ok(relative) = test_path.strip_prefix(&self.workspace_root) else {
+            return false;
+        };
+        let mut invalidated = false;
+        for mapping in self
+            .mappings
+            .iter_mut()
+            .filter(|m| m.tests.is_match(relative))
+        {
+            mapping.expanded = None;
+            invalidated = true;
+        }
+        invalidated
+    }
+}
+
+fn matcher(pattern: &str) -> Result<GlobMatcher, globset::Error> {
+    Ok(GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher())
+}
+
+fn read_mappings(path: &Path) -> Result<MappingsJson, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MappingsJson::default()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// List files under the workspace matching a `tests` glob.
+fn expand(workspace_root: &Path, tests: &GlobMatcher) -> Vec<PathBuf> {
+    WalkBuilder::new(workspace_root)
+        .hidden(false)
+        .git_ignore(true)
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter(|entry| {
+            entry
+                .path()
+                .strip_prefix(workspace_root)
+                .is_ok_and(|relative| tests.is_match(relative))
+        })
+        .map(ignore::DirEntry::into_path)
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn load_reads_mappings_and_ignores_invalid_files() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("src/api")).unwrap();
+        fs::create_dir_all(root.join("e2e")).unwrap();
+        fs::write(root.join("e2e/api.test.ts"), "").unwrap();
+        let source = root.join("src/api/users.ts");
+        assert!(TestMappings::load(&root).tests_for(&source).is_empty());
+
+        let json = r#"{"mappings":[{"source":"src/api/*","tests":"e2e/*.test.ts"}]}"#;
+        fs::write(root.join(TEST_MAPPINGS_FILE), json).unwrap();
+        let mut mappings = TestMappings::load(&root);
+        assert_eq!(
+            mappings.tests_for(&source),
+            vec![root.join("e2e/api.test.ts")]
+        );
+        assert!(mappings
+            .tests_for(&root.join("src/api/nested/x.ts"))
+            .is_empty());
+
+        fs::write(root.join(TEST_MAPPINGS_FILE), "{").unwrap();
+        assert!(TestMappings::load(&root).tests_for(&source).is_empty());
+    }
+}
@@ -1,100 +1,277 @@
 //! File watcher and dirty tracker using notify-rs.
 //!
 //! Monitors the workspace for file changes and maintains a set of dirty files.
-#![allow(clippy::print_stderr)]
 #![allow(clippy::unwrap_used)]
 
+use super::config_files::ConfigFiles;
+use crate::logging::log_line;
+use crate::normalize::path::PathMode;
 use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::Mutex;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 
-/// Maximum dirty set size before triggering overflow.
-const MAX_DIRTY_FILES: usize = 500;
-/// Debounce interval in milliseconds.
-const DEBOUNCE_MS: u64 = 100;
+/// Default dirty set size before triggering overflow.
+pub const DEFAULT_MAX_DIRTY_FILES: usize = 500;
+/// Config file hashes remembered; the least recently checked are evicted.
+const MAX_CONFIG_HASHES: usize = 256;
+/// Changed paths remembered for cursor clients; older cursors expire.
+const MAX_CHANGE_HISTORY: usize = 4096;
+/// Default debounce interval in milliseconds.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 100;
+/// How long `start_watcher` waits for the workspace watch to be registered.
+const WATCHER_READY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Errno inotify reports when `max_user_watches` is exhausted.
+const ENOSPC: i32 = 28;
+/// Keeps epochs of trackers created within one clock tick distinct.
+static EPOCH_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Dirty state read in one lock window, so a change is either in a drained
+/// snapshot or left for the next one, never lost in between.
+#[derive(Debug, Clone, Default)]
+pub struct DrainSnapshot {
+    pub files: HashSet<PathBuf>,
+    pub overflow: bool,
+    pub config_changed: bool,
+    /// Cursor marking the point this snapshot was read; pass it to `since`
+    /// to get only the changes that follow.
+    pub cursor: String,
+}
+
+/// When each path last changed, numbered by a per-tracker sequence, so
+/// cursor clients see changes since their last call without draining.
+///
+/// Only the `capacity` most recently changed paths are kept. A cursor older
+/// than the last change forgotten is expired, since it would miss that change.
+#[derive(Debug)]
+struct ChangeHistory {
+    /// Distinguishes cursors of a previous tracker (evicted state, restart).
+    epoch: u64,
+    sequence: u64,
+    capacity: usize,
+    changed_at: HashMap<PathBuf, u64>,
+    /// Paths keyed by their last change, oldest first.
+    by_sequence: BTreeMap<u64, PathBuf>,
+    /// Sequence of the newest change evicted from the history.
+    evicted_through: u64,
+    config_changed_at: u64,
+}
+
+impl ChangeHistory {
+    fn new(capacity: usize) -> Self {
+        let epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)
+            .wrapping_add(EPOCH_SEQUENCE.fetch_add(1, Ordering::Relaxed));
+        Self {
+            epoch,
+            sequence: 0,
+            capacity,
+            changed_at: HashMap::new(),
+            by_sequence: BTreeMap::new(),
+            evicted_through: 0,
+            config_changed_at: 0,
+        }
+    }
+
+    fn record(&mut self, path: PathBuf) {
+        self.sequence += 1;
+        if let Some(previous) = self.changed_at.insert(path.clone(), self.sequence) {
+            self.by_sequence.remove(&previous);
+        }
+        self.by_sequence.insert(self.sequence, path);
+        while self.by_sequence.len() > self.capacity {
+            let Some((sequence, oldest)) = self.by_sequence.pop_first() else {
+                break;
+            };
+            self.changed_at.remove(&oldest);
+            self.evicted_through = sequence;
+        }
+    }
+
+    /// Paths changed after `sequence`.
+    fn changed_after(&self, sequence: u64) -> HashSet<PathBuf> {
+        self.by_sequence
+            .range(sequence + 1..)
+            .map(|(_, path)| path.clone())
+            .collect()
+    }
+
+    fn record_config_change(&mut self) {
+        self.sequence += 1;
+        self.config_changed_at = self.sequence;
+    }
+
+    fn cursor(&self) -> String {
+        format!("{:x}-{}", self.epoch, self.sequence)
+    }
+
+    /// Sequence a cursor issued by this history stands for, if the changes
+    /// after it are all still remembered.
+    fn parse_cursor(&self, cursor: &str) -> Option<u64> {
+        let (epoch, sequence) = cursor.split_once('-')?;
+        let sequence = sequence.parse().ok()?;
+        let current = u64::from_str_radix(epoch, 16).ok() == Some(self.epoch);
+        let remembered = (self.evicted_through..=self.sequence).contains(&sequence);
+        (current && remembered).then_some(sequence)
+    }
+}
+
+struct TrackerState {
+    dirty: DrainSnapshot,
+    history: ChangeHistory,
+}
+
+/// Least-recently-checked content hashes of config files.
+///
+/// An evicted file is new again on its next check, so that check is not a
+/// change; files edited regularly stay cached.
+struct ConfigHashes {
+    capacity: usize,
+    entries: HashMap<PathBuf, (String, u64)>,
+    /// Paths keyed by last check, oldest first.
+    by_use: BTreeMap<u64, PathBuf>,
+    tick: u64,
+}
+
+impl ConfigHashes {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            by_use: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Record `hash` for `path`. Returns true if it differs from the
+    /// remembered hash; a file not remembered is not a change.
+    fn check(&mut self, path: &Path, hash: String) -> bool {
+        let previous = self.entries.remove(path);
+        if let Some((_, last_use)) = &previous {
+            self.by_use.remove(last_use);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.by_use.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        let changed = previous.is_some_and(|(old, _)| old != hash);
+        self.tick += 1;
+        self.entries.insert(path.to_path_buf(), (hash, self.tick));
+        self.by_use.insert(self.tick, path.to_path_buf());
+        changed
+    }
+}
 
 /// Dirty file tracker with overflow protection.
 pub struct DirtyTracker {
-    dirty: Mutex<HashSet<PathBuf>>,
-    overflow: Mutex<bool>,
-    config_changed: Mutex<bool>,
-    config_hashes: Mutex<HashMap<PathBuf, String>>,
+    state: Mutex<TrackerState>,
+    config_hashes: Mutex<ConfigHashes>,
+    /// Dirty set size above which the set overflows into a full run.
+    max_files: usize,
 }
 
 impl DirtyTracker {
-    /// Create a new dirty tracker.
-    pub fn new(_workspace_root: PathBuf) -> Self {
+    /// Create a new dirty tracker overflowing above `max_files` dirty files.
+    pub fn new(_workspace_root: PathBuf, max_files: usize) -> Self {
         Self {
-            dirty: Mutex::new(HashSet::new()),
-            overflow: Mutex::new(false),
-            config_changed: Mutex::new(false),
-            config_hashes: Mutex::new(HashMap::new()),
+            state: Mutex::new(TrackerState {
+                dirty: DrainSnapshot::default(),
+                history: ChangeHistory::new(MAX_CHANGE_HISTORY),
+            }),
+            config_hashes: Mutex::new(ConfigHashes::new(MAX_CONFIG_HASHES)),
+            max_files,
         }
     }
 
     /// Add a dirty file. Returns true if overflow triggered.
     pub fn add_dirty(&self, path: PathBuf) -> bool {
-        let mut dirty = self.dirty.lock().unwrap();
-
-        if dirty.len() >= MAX_DIRTY_FILES {
-            if !*self.overflow.lock().unwrap() {
-                eprintln!(
+        let mut guard = self.state.lock().unwrap();
+        let TrackerState {
+            dirty: state,
+            history,
+        } = &mut *guard;
+        history.record(path.clone());
+
+        if state.files.len() >= self.max_files {
+            if !state.overflow {
+                log_line!(
                     "[affected] WARN: dirty set exceeded {} files, triggering full run",
-                    MAX_DIRTY_FILES
+                    self.max_files
                 );
-                *self.overflow.lock().unwrap() = true;
+                state.overflow = true;
             }
             return true;
         }
 
-        dirty.insert(path);
+        state.files.insert(path);
         false
     }
 
-    /// Drain and return all dirty files. Clears the set.
-    /// Returns (files, overflow, `config_changed`).
-    pub fn drain(&self) -> (HashSet<PathBuf>, bool, bool) {
-        let mut dirty = self.dirty.lock().unwrap();
-        let mut overflow = self.overflow.lock().unwrap();
-        let mut config_changed = self.config_changed.lock().unwrap();
+    /// Drain and return all dirty state, clearing it atomically.
+    pub fn drain(&self) -> DrainSnapshot {
+        let mut state = self.state.lock().unwrap();
+        let cursor = state.history.cursor();
+        DrainSnapshot {
+            cursor,
+            ..std::mem::take(&mut state.dirty)
+        }
+    }
 
-        let files = std::mem::take(&mut *dirty);
-        let was_overflow = *overflow;
-        let was_config_changed = *config_changed;
-        *overflow = false;
-        *config_changed = false;
+    /// Return a copy of the dirty state without clearing it.
+    pub fn snapshot(&self) -> DrainSnapshot {
+        let state = self.state.lock().unwrap();
+        DrainSnapshot {
+            cursor: state.history.cursor(),
+            ..state.dirty.clone()
+        }
+    }
 
-        (files, was_overflow, was_config_changed)
+    /// Return the changes made after `cursor` was issued, leaving the dirty
+    /// set untouched. `None` if the cursor was not issued by this tracker or
+    /// is too old for the bounded change history.
+    pub fn since(&self, cursor: &str) -> Option<DrainSnapshot> {
+        let state = self.state.lock().unwrap();
+        let history = &state.history;
+        let sequence = history.parse_cursor(cursor)?;
+        let files = history.changed_after(sequence);
+        Some(DrainSnapshot {
+            overflow: files.len() > self.max_files,
+            files,
+            config_changed: history.config_changed_at > sequence,
+            cursor: history.cursor(),
+        })
+    }
+
+    /// Cursor for the current point in the change history.
+    pub fn cursor(&self) -> String {
+        self.state.lock().unwrap().history.cursor()
     }
 
     /// Mark that a config file has changed, triggering full run.
     pub fn set_config_changed(&self) {
-        *self.config_changed.lock().unwrap() = true;
+        let mut state = self.state.lock().unwrap();
+        state.dirty.config_changed = true;
+        state.history.record_config_change();
     }
 
     /// Check if a config file changed by comparing hashes.
+    ///
+    /// Only called for recognized config files, whose hashes are bounded by
+    /// `MAX_CONFIG_HASHES`.
     pub fn check_config_change(&self, path: &Path) -> bool {
         let Ok(content) = std::fs::read(path) else {
             return false;
         };
 
         let hash = blake3::hash(&content).to_hex().to_string();
-        let mut hashes = self.config_hashes.lock().unwrap();
-
-        if let Some(old_hash) = hashes.get(path) {
-            if *old_hash != hash {
-                hashes.insert(path.to_path_buf(), hash);
-                return true;
-            }
-            false
-        } else {
-            hashes.insert(path.to_path_buf(), hash);
-            false // First time seeing this file, not a change
-        }
+        // First time seeing this file, not a change
+        self.config_hashes.lock().unwrap().check(path, hash)
     }
 }
 
@@ -102,25 +279,35 @@ impl DirtyTracker {
 pub struct WatcherConfig {
     pub workspace_root: PathBuf,
     pub gitignore: Option<Gitignore>,
+    pub path_mode: PathMode,
+    /// Files larger than this many bytes are ignored (`None` = no limit).
+    /// Config files are always tracked so lockfile changes still force full runs.
+    pub max_file_size: Option<u64>,
+    /// Config files that force full runs, including any configured globs.
+    pub config_files: ConfigFiles,
+    /// Poll interval of the fallback polling watcher.
+    pub debounce: Duration,
 }
 
 impl WatcherConfig {
     /// Create watcher config with gitignore from workspace root.
     pub fn new(workspace_root: PathBuf) -> Self {
         let gitignore = load_gitignore(&workspace_root);
+        let config_files = ConfigFiles::builtin(workspace_root.clone());
         Self {
             workspace_root,
             gitignore,
+            path_mode: PathMode::Canonical,
+            max_file_size: None,
+            config_files,
+            debounce: Duration::from_millis(DEFAULT_DEBOUNCE_MS),
         }
     }
 
     /// Check if a path should be ignored.
     pub fn should_ignore(&self, path: &Path) -> bool {
         // Always ignore node_modules
-        if path
-            .components()
-            .any(|c| c.as_os_str() == "node_modules")
-        {
+        if path.components().any(|c| c.as_os_str() == "node_modules") {
             return true;
         }
 
@@ -132,7 +319,17 @@ impl WatcherConfig {
             }
         }
 
-        false
+        self.exceeds_max_file_size(path)
+    }
+
+    /// Check if an existing, non-config file is above `max_file_size`.
+    /// Deleted files have no metadata and are never ignored here.
+    fn exceeds_max_file_size(&self, path: &Path) -> bool {
+        let Some(max) = self.max_file_size else {
+            return false;
+        };
+        !self.config_files.matches(path)
+            && std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.len() > max)
     }
 }
 
@@ -151,12 +348,11 @@ fn load_gitignore(workspace_root: &Path) -> Option<Gitignore> {
 }
 
 /// Start the file watcher in a background task.
-/// Returns a receiver for file events.
-#[allow(clippy::unnecessary_wraps)]
-pub fn start_watcher(
-    config: WatcherConfig,
-) -> Result<mpsc::Receiver<PathBuf>, notify::Error> {
+/// Returns a receiver for file events once the workspace is being watched,
+/// so changes made right after this returns are observed.
+pub fn start_watcher(config: WatcherConfig) -> Result<mpsc::Receiver<PathBuf>, notify::Error> {
     let (tx, rx) = mpsc::channel(1000);
+    let (ready_tx, ready_rx) = std::sync::mpsc::sync_channel(1);
 
     std::thread::spawn(move || {
         let rt = tokio::runtime::Builder::new_current_thread()
@@ -165,57 +361,133 @@ pub fn start_watcher(
             .unwrap();
 
         rt.block_on(async {
-            if let Err(e) = run_watcher(config, tx).await {
-                eprintln!("[affected] ERROR: watcher error: {e}");
+            let (_watcher, notify_rx) = match watch_workspace(&config) {
+                Ok(watching) => {
+                    let _ = ready_tx.send(Ok(()));
+                    watching
+                }
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            if let Err(e) = forward_events(&config, notify_rx, tx).await {
+                log_line!("[affected] ERROR: watcher error: {e}");
             }
         });
     });
 
-    // Give watcher time to start
-    std::thread::sleep(Duration::from_millis(50));
-
-    Ok(rx)
+    match ready_rx.recv_timeout(WATCHER_READY_TIMEOUT) {
+        Ok(Ok(())) => Ok(rx),
+        Ok(Err(e)) => Err(e),
+        Err(RecvTimeoutError::Timeout) => {
+            log_line!(
+                "[affected] WARN: watcher not ready after {}ms, early changes may be missed",
+                WATCHER_READY_TIMEOUT.as_millis()
+            );
+            Ok(rx)
+        }
+        Err(RecvTimeoutError::Disconnected) => Err(notify::Error::generic(
+            "watcher thread exited before watching",
+        )),
+    }
 }
 
-async fn run_watcher(
-    config: WatcherConfig,
-    tx: mpsc::Sender<PathBuf>,
-) -> Result<(), notify::Error> {
-    let (notify_tx, mut notify_rx) = mpsc::channel(1000);
+type RawEventReceiver = mpsc::Receiver<Result<PathBuf, notify::Error>>;
+
+/// Create a watcher on the workspace root. The watcher must be kept alive
+/// for events to keep arriving.
+fn watch_workspace(
+    config: &WatcherConfig,
+) -> Result<(RecommendedWatcher, RawEventReceiver), notify::Error> {
+    let (notify_tx, notify_rx) = mpsc::channel(1000);
 
     let mut watcher = RecommendedWatcher::new(
-        move |res: Result<notify::Event, notify::Error>| {
-            if let Ok(event) = res {
+        move |res: Result<notify::Event, notify::Error>| match res {
+            Ok(event) => {
                 for path in event.paths {
-                    let _ = notify_tx.blocking_send(path);
+                    let _ = notify_tx.blocking_send(Ok(path));
                 }
             }
+            Err(e) => {
+                let _ = notify_tx.blocking_send(Err(e));
+            }
         },
-        Config::default().with_poll_interval(Duration::from_millis(DEBOUNCE_MS)),
+        Config::default().with_poll_interval(config.debounce),
     )?;
 
     watcher.watch(&config.workspace_root, RecursiveMode::Recursive)?;
+    Ok((watcher, notify_rx))
+}
 
-    // Keep watcher alive and forward events
-    while let Some(path) = notify_rx.recv().await {
-        // Canonicalize to resolve symlinks
-        let canonical = match path.canonicalize() {
-            Ok(p) => p,
-            Err(_) => path,
+/// Forward filtered events. Returning drops `tx`, which the receiver
+/// observes as the watcher becoming unavailable. A dropped receiver
+/// (evicted state) stops the watcher.
+async fn forward_events(
+    config: &WatcherConfig,
+    mut notify_rx: RawEventReceiver,
+    tx: mpsc::Sender<PathBuf>,
+) -> Result<(), notify::Error> {
+    loop {
+        let res = tokio::select! {
+            res = notify_rx.recv() => match res {
+                Some(res) => res,
+                None => break,
+            },
+            () = tx.closed() => break,
         };
+        if let Some(path) = filter_event(config, res)? {
+            let _ = tx.send(path).await;
+        }
+    }
 
-        // Check if should be ignored
-        if config.should_ignore(&canonical) {
-            continue;
+    Ok(())
+}
+
+/// Resolve and filter a raw watcher event. Errors other than the watch limit
+/// are logged and skipped.
+fn filter_event(
+    config: &WatcherConfig,
+    res: Result<PathBuf, notify::Error>,
+) -> Result<Option<PathBuf>, notify::Error> {
+    let path = match res {
+        Ok(path) => path,
+        Err(e) if is_watch_limit_error(&e) => return Err(e),
+        Err(e) => {
+            log_line!("[affected] WARN: watcher event error: {e}");
+            return Ok(None);
         }
+    };
+    // Canonicalize to resolve symlinks
+    let canonical = match config.path_mode.resolve(&path) {
+        Ok(p) => p,
+        Err(_) => path,
+    };
 
-        let _ = tx.send(canonical).await;
+    // Check if should be ignored
+    if config.should_ignore(&canonical) {
+        return Ok(None);
+    }
+    // Directories have no imports and their files report their own events.
+    // A removed directory no longer exists, so it is still forwarded.
+    if canonical.is_dir() {
+        return Ok(None);
     }
+    Ok(Some(canonical))
+}
 
-    Ok(())
+/// Check if an error means the OS watch limit (inotify ENOSPC) was hit, after
+/// which changes in unwatched directories would be silently missed.
+fn is_watch_limit_error(e: &notify::Error) -> bool {
+    match &e.kind {
+        notify::ErrorKind::MaxFilesWatch => true,
+        notify::ErrorKind::Io(io) => io.raw_os_error() == Some(ENOSPC),
+        _ => false,
+    }
 }
 
-/// Check if a path is a config file that should trigger full run.
+/// Check if a path is a built-in config file that should trigger full run.
+/// Use `ConfigFiles` to include a workspace's configured globs.
 pub fn is_config_file(path: &Path) -> bool {
     let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
         return false;
@@ -242,27 +514,70 @@ mod tests {
     #[test]
     fn dirty_tracker_add_and_drain() {
         let dir = tempdir().unwrap();
-        let tracker = DirtyTracker::new(dir.path().to_path_buf());
+        let tracker = DirtyTracker::new(dir.path().to_path_buf(), DEFAULT_MAX_DIRTY_FILES);
 
         tracker.add_dirty(PathBuf::from("/src/a.ts"));
         tracker.add_dirty(PathBuf::from("/src/b.ts"));
 
-        let (files, overflow, config_changed) = tracker.drain();
-        assert_eq!(files.len(), 2);
-        assert!(!overflow);
-        assert!(!config_changed);
+        let drained = tracker.drain();
+        assert_eq!(drained.files.len(), 2);
+        assert!(!drained.overflow);
+        assert!(!drained.config_changed);
 
         // After drain, set should be empty
-        let (files2, _, _) = tracker.drain();
-        assert!(files2.is_empty());
+        assert!(tracker.drain().files.is_empty());
+    }
+
+    #[test]
+    fn since_returns_changes_after_cursor_without_draining() {
+        let dir = tempdir().unwrap();
+        let tracker = DirtyTracker::new(dir.path().to_path_buf(), DEFAULT_MAX_DIRTY_FILES);
+        let start = tracker.cursor();
+        tracker.add_dirty(PathBuf::from("/src/a.ts"));
+
+        let first = tracker.since(&start).unwrap();
+        assert_eq!(first.files, HashSet::from([PathBuf::from("/src/a.ts")]));
+        tracker.add_dirty(PathBuf::from("/src/b.ts"));
+        tracker.add_dirty(PathBuf::from("/src/a.ts"));
+        let second = tracker.since(&first.cursor).unwrap();
+        assert_eq!(second.files.len(), 2);
+        assert!(tracker.since(&second.cursor).unwrap().files.is_empty());
+
+        // The shared dirty set is left for draining clients
+        assert_eq!(tracker.drain().files.len(), 2);
+        let other = DirtyTracker::new(dir.path().to_path_buf(), DEFAULT_MAX_DIRTY_FILES);
+        assert!(other.since(&second.cursor).is_none());
+        assert!(tracker.since("garbage").is_none());
+    }
+
+    #[test]
+    fn change_history_is_bounded_and_expires_old_cursors() {
+        let mut history = ChangeHistory::new(2);
+        let start = history.cursor();
+        history.record(PathBuf::from("/src/a.ts"));
+        let after_a = history.cursor();
+        history.record(PathBuf::from("/src/b.ts"));
+        history.record(PathBuf::from("/src/b.ts"));
+        assert_eq!(history.changed_at.len(), 2);
+
+        // c.ts evicts a.ts, so a cursor from before a.ts would miss it
+        history.record(PathBuf::from("/src/c.ts"));
+        assert_eq!(history.changed_at.len(), 2);
+        assert_eq!(history.by_sequence.len(), 2);
+        assert!(history.parse_cursor(&start).is_none());
+        let sequence = history.parse_cursor(&after_a).unwrap();
+        assert_eq!(
+            history.changed_after(sequence),
+            HashSet::from([PathBuf::from("/src/b.ts"), PathBuf::from("/src/c.ts")])
+        );
     }
 
     #[test]
     fn dirty_tracker_overflow() {
         let dir = tempdir().unwrap();
-        let tracker = DirtyTracker::new(dir.path().to_path_buf());
+        let tracker = DirtyTracker::new(dir.path().to_path_buf(), DEFAULT_MAX_DIRTY_FILES);
 
-        for i in 0..MAX_DIRTY_FILES {
+        for i in 0..DEFAULT_MAX_DIRTY_FILES {
             let overflow = tracker.add_dirty(PathBuf::from(format!("/src/file{i}.ts")));
             assert!(!overflow);
         }
@@ -271,29 +586,69 @@ mod tests {
         let overflow = tracker.add_dirty(PathBuf::from("/src/extra.ts"));
         assert!(overflow);
 
-        let (_, was_overflow, _) = tracker.drain();
-        assert!(was_overflow);
+        assert!(tracker.drain().overflow);
+    }
+
+    #[test]
+    fn dirty_tracker_overflows_at_custom_cap() {
+        let dir = tempdir().unwrap();
+        let tracker = DirtyTracker::new(dir.path().to_path_buf(), 3);
+
+        for i in 0..3 {
+            assert!(!tracker.add_dirty(PathBuf::from(format!("/src/file{i}.ts"))));
+        }
+        assert!(tracker.add_dirty(PathBuf::from("/src/extra.ts")));
+        let drained = tracker.drain();
+        assert!(drained.overflow);
+        assert_eq!(drained.files.len(), 3);
+
+        let cursor = tracker.cursor();
+        for i in 0..4 {
+            tracker.add_dirty(PathBuf::from(format!("/src/later{i}.ts")));
+        }
+        assert!(tracker.since(&cursor).unwrap().overflow);
     }
 
     #[test]
     fn dirty_tracker_config_changed() {
         let dir = tempdir().unwrap();
-        let tracker = DirtyTracker::new(dir.path().to_path_buf());
+        let tracker = DirtyTracker::new(dir.path().to_path_buf(), DEFAULT_MAX_DIRTY_FILES);
 
         tracker.set_config_changed();
 
-        let (_, _, config_changed) = tracker.drain();
-        assert!(config_changed);
+        assert!(tracker.drain().config_changed);
 
         // After drain, config_changed should be cleared
-        let (_, _, config_changed2) = tracker.drain();
-        assert!(!config_changed2);
+        assert!(!tracker.drain().config_changed);
+    }
+
+    #[test]
+    fn concurrent_adds_are_never_lost_across_drains() {
+        let dir = tempdir().unwrap();
+        let tracker = DirtyTracker::new(dir.path().to_path_buf(), DEFAULT_MAX_DIRTY_FILES);
+        let tracker = std::sync::Arc::new(tracker);
+        let writer = {
+            let tracker = std::sync::Arc::clone(&tracker);
+            std::thread::spawn(move || {
+                for i in 0..400 {
+                    tracker.add_dirty(PathBuf::from(format!("/src/file{i}.ts")));
+                }
+            })
+        };
+
+        let mut seen = HashSet::new();
+        while !writer.is_finished() {
+            seen.extend(tracker.drain().files);
+        }
+        writer.join().unwrap();
+        seen.extend(tracker.drain().files);
+        assert_eq!(seen.len(), 400);
     }
 
     #[test]
     fn config_hash_detects_change() {
         let dir = tempdir().unwrap();
-        let tracker = DirtyTracker::new(dir.path().to_path_buf());
+        let tracker = DirtyTracker::new(dir.path().to_path_buf(), DEFAULT_MAX_DIRTY_FILES);
         let config = dir.path().join("package.json");
 
         fs::write(&config, r#"{"name": "test"}"#).unwrap();
@@ -312,6 +667,26 @@ mod tests {
         assert!(!tracker.check_config_change(&config));
     }
 
+    #[test]
+    fn config_hashes_evict_least_recently_checked() {
+        let mut hashes = ConfigHashes::new(2);
+        let active = Path::new("/ws/package.json");
+        let stale = Path::new("/ws/a/package.json");
+        let other = Path::new("/ws/b/tsconfig.json");
+        assert!(!hashes.check(active, "1".into()));
+        assert!(!hashes.check(stale, "1".into()));
+        assert!(hashes.check(active, "2".into()));
+
+        // Over capacity: the stale config goes, the active one still detects changes
+        assert!(!hashes.check(other, "1".into()));
+        assert_eq!(hashes.entries.len(), 2);
+        assert!(!hashes.entries.contains_key(stale));
+        assert!(hashes.check(active, "3".into()));
+        // Seen again after eviction counts as first sight
+        assert!(!hashes.check(stale, "2".into()));
+        assert_eq!(hashes.by_use.len(), 2);
+    }
+
     #[test]
     fn watcher_config_ignores_node_modules() {
         let dir = tempdir().unwrap();
@@ -324,6 +699,18 @@ mod tests {
         assert!(!config.should_ignore(&src_path));
     }
 
+    #[test]
+    fn filter_event_skips_existing_directories() {
+        let dir = tempdir().unwrap();
+        let config = WatcherConfig::new(dir.path().to_path_buf());
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        assert_eq!(filter_event(&config, Ok(src.clone())).unwrap(), None);
+
+        fs::remove_dir(&src).unwrap();
+        assert!(filter_event(&config, Ok(src)).unwrap().is_some());
+    }
+
     #[test]
     fn watcher_config_respects_gitignore() {
         let dir = tempdir().unwrap();
@@ -337,6 +724,38 @@ mod tests {
     }
 
     #[test]
+    fn watcher_config_ignores_files_above_max_size() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("bundle.js"), "x".repeat(2048)).unwrap();
+        fs::write(dir.path().join("small.ts"), "export {};").unwrap();
+        fs::write(dir.path().join("pnpm-lock.yaml"), "x".repeat(2048)).unwrap();
+
+        let mut config = WatcherConfig::new(dir.path().to_path_buf());
+        assert!(!config.should_ignore(&dir.path().join("bundle.js")));
+
+        config.max_file_size = Some(1024);
+        assert!(config.should_ignore(&dir.path().join("bundle.js")));
+        assert!(!config.should_ignore(&dir.path().join("small.ts")));
+        assert!(!config.should_ignore(&dir.path().join("pnpm-lock.yaml")));
+        assert!(!config.should_ignore(&dir.path().join("deleted.js")));
+    }
+
+    #[tokio::test]
+    async fn changes_right_after_start_are_observed() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let mut rx = start_watcher(WatcherConfig::new(root.clone())).unwrap();
+
+        fs::write(root.join("early.ts"), "export {};").unwrap();
+
+        let path = tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap();
+        assert_eq!(path, Some(root.join("early.ts")));
+    }
+
+    #[test]
+    #[allow(clippy::cognitive_complexity)]
     fn is_config_file_matches() {
         assert!(is_config_file(Path::new("package.json")));
         assert!(is_config_file(Path::new("package-lock.json")));
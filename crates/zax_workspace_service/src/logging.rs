@@ -0,0 +1,191 @@
+//! Diagnostic logging.
+//!
+//! Lines go to stderr (redirected to `engine.log`) and into a bounded
+//! in-memory ring buffer served by `GetRecentLogs`, so recent diagnostics are
+//! available without filesystem access.
+//!
+//! Lines logged while handling an RPC are prefixed with `[req:<id>]`, so one
+//! request can be followed across the rpc, affected, and store modules. The id
+//! is scoped to the handler's thread by `in_request`.
+
+#![allow(clippy::print_stderr)]
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Default number of log lines retained in memory.
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+/// Fixed-capacity buffer that evicts the oldest line when full.
+pub struct LogBuffer {
+    capacity: usize,
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, line: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        let Ok(mut lines) = self.lines.lock() else {
+            return;
+        };
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Returns up to `limit` most recent lines, oldest first (0 = all).
+    pub fn recent(&self, limit: usize) -> Vec<String> {
+        let Ok(lines) = self.lines.lock() else {
+            return Vec::new();
+        };
+        let skip = if limit == 0 {
+            0
+        } else {
+            lines.len().saturating_sub(limit)
+        };
+        lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+
+/// Sets the buffer capacity. Must run before the first log line to take effect.
+pub fn init(capacity: usize) {
+    let _ = BUFFER.set(LogBuffer::new(capacity));
+}
+
+/// Returns the process-wide log buffer.
+pub fn buffer() -> &'static LogBuffer {
+    BUFFER.get_or_init(|| LogBuffer::new(DEFAULT_CAPACITY))
+}
+
+thread_local! {
+    static REQUEST_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Distinguishes requests started within the same clock tick.
+static REQUEST_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Generates a short hex id for correlating one request's log lines.
+pub fn generate_request_id() -> String {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let sequence = u128::from(REQUEST_SEQUENCE.fetch_add(1, Ordering::Relaxed));
+    format!("{:x}", (nanos ^ (sequence << 24)) % 0xFFFF_FFFF)
+}
+
+/// Returns the id of the request being handled on this thread, if any.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.with(|id| id.borrow().clone())
+}
+
+/// Runs `f` with a request id attached to every line it logs.
+///
+/// Nested calls reuse the enclosing request's id, so the RPC boundary and
+/// the modules it calls into share one id. `f` must not await: the id is
+/// bound to the current thread.
+pub fn in_request<R>(f: impl FnOnce() -> R) -> R {
+    if current_request_id().is_some() {
+        return f();
+    }
+    REQUEST_ID.with(|id| *id.borrow_mut() = Some(generate_request_id()));
+    let _scope = RequestScope;
+    f()
+}
+
+/// Clears the thread's request id on drop, including on panic.
+struct RequestScope;
+
+impl Drop for RequestScope {
+    fn drop(&mut self) {
+        REQUEST_ID.with(|id| *id.borrow_mut() = None);
+    }
+}
+
+/// Writes a line to stderr and records it in the buffer.
+pub fn emit(line: String) {
+    let line = match current_request_id() {
+        Some(id) => format!("[req:{id}] {line}"),
+        None => line,
+    };
+    eprintln!("{line}");
+    buffer().push(line);
+}
+
+/// Formats and emits a log line, like `eprintln!`.
+macro_rules! log_line {
+    ($($arg:tt)*) => {
+        $crate::logging::emit(format!($($arg)*))
+    };
+}
+pub(crate) use log_line;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emitted_lines_appear_in_buffer() {
+        log_line!("[test] marker {}", 42);
+        assert!(buffer().recent(0).contains(&"[test] marker 42".to_string()));
+    }
+
+    #[test]
+    fn generate_request_id_is_hex() {
+        let id = generate_request_id();
+        assert!(!id.is_empty());
+        assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn lines_within_a_request_share_its_id() {
+        let (outer, inner) = in_request(|| {
+            log_line!("[test] request marker outer");
+            let inner = in_request(|| {
+                log_line!("[test] request marker inner");
+                current_request_id()
+            });
+            (current_request_id().unwrap(), inner.unwrap())
+        });
+        assert_eq!(outer, inner);
+        assert!(current_request_id().is_none());
+
+        let lines = buffer().recent(0);
+        for marker in ["outer", "inner"] {
+            let expected = format!("[req:{outer}] [test] request marker {marker}");
+            assert!(lines.contains(&expected), "missing {expected}");
+        }
+    }
+
+    #[test]
+    fn oldest_lines_evicted_past_capacity() {
+        let buf = LogBuffer::new(2);
+        buf.push("a".into());
+        buf.push("b".into());
+        buf.push("c".into());
+        assert_eq!(buf.recent(0), vec!["b", "c"]);
+        assert_eq!(buf.recent(1), vec!["c"]);
+    }
+
+    #[test]
+    fn zero_capacity_retains_nothing() {
+        let buf = LogBuffer::new(0);
+        buf.push("a".into());
+        assert!(buf.recent(0).is_empty());
+    }
+}
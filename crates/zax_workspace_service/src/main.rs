@@ -1,10 +1,11 @@
-#![allow(clippy::print_stderr)]
 #![allow(clippy::unwrap_used)]
 
 use std::env;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 use tokio::fs;
 use tokio::net::TcpListener;
 use tokio::signal::unix::{signal, SignalKind};
@@ -12,6 +13,7 @@ use tonic::transport::Server;
 use tonic::{Request, Response, Status};
 
 mod affected;
+mod logging;
 mod normalize;
 mod parsers;
 mod rpc;
@@ -25,24 +27,132 @@ pub mod zax {
     }
 }
 
-use affected::AffectedState;
+use affected::config_files::ConfigFiles;
+use affected::{AffectedState, ParseLimits};
+use logging::log_line;
+use normalize::path::PathMode;
 use zax::v1::workspace_service_server::{WorkspaceService, WorkspaceServiceServer};
 use zax::v1::{
-    GetAffectedTestsRequest, GetAffectedTestsResponse, GetDeltaSummaryRequest,
-    GetDeltaSummaryResponse, IngestManifestRequest, IngestManifestResponse, PingRequest,
-    PingResponse,
+    AffectedFromDiffRequest, AffectedFromDiffResponse, AffectedFromRangesRequest,
+    AffectedFromRangesResponse, AppliedMigration, AttributePoint, ChangeKind,
+    ClearWorkspaceDataRequest, ClearWorkspaceDataResponse, DatabaseMaintenanceRequest,
+    DatabaseMaintenanceResponse, EvictWorkspaceRequest, EvictWorkspaceResponse, FileHealth,
+    FindingTrendPoint, FlakyTest, GetAffectedTestsRequest, GetAffectedTestsResponse,
+    GetAttributeTrendRequest, GetAttributeTrendResponse, GetDeltaReportRequest,
+    GetDeltaReportResponse, GetDeltaSummaryRequest, GetDeltaSummaryResponse,
+    GetDependencyPathRequest, GetDependencyPathResponse, GetFileHealthRequest,
+    GetFileHealthResponse, GetFindingTrendRequest, GetFindingTrendResponse, GetFlakyTestsRequest,
+    GetFlakyTestsResponse, GetGraphStatsRequest, GetGraphStatsResponse, GetHubModulesRequest,
+    GetHubModulesResponse, GetOrphanTestsRequest, GetOrphanTestsResponse, GetPackageDeltasRequest,
+    GetPackageDeltasResponse, GetRecentLogsRequest, GetRecentLogsResponse, GetRunHistoryRequest,
+    GetRunHistoryResponse, GetRunStatsRequest, GetRunStatsResponse, GetSchemaVersionRequest,
+    GetSchemaVersionResponse, GetTruncatedImportsRequest, GetTruncatedImportsResponse, HubModule,
+    IngestManifestRequest, IngestManifestResponse, NormalizeWorkspaceIdsRequest,
+    NormalizeWorkspaceIdsResponse, PackageDelta, PingRequest, PingResponse, RuleDelta,
+    RunHistoryEntry, RunStats, TruncatedImports,
 };
 
+/// Protocol revision reported by `Ping`; bumped on incompatible changes.
+const API_VERSION: u32 = 1;
+
+/// Features reported by `Ping` so clients can check before relying on them:
+/// older servers reject an unknown RPC but silently ignore an unknown field.
+/// A capability is
+///
+/// - every RPC the service serves, by method name in snake case
+/// - every `ArtifactKind` ingest parses, by name without its prefix
+/// - every request field added to an RPC after the RPC itself, by field name
+///   (`ArtifactManifest` and `ArtifactRef` fields belong to `IngestManifest`)
+///
+/// Response fields and full run reasons are not listed: clients read a
+/// missing field as empty and any reason as a full run.
+const CAPABILITIES: &[&str] = &[
+    "ping",
+    "ingest_manifest",
+    "get_delta_summary",
+    "get_package_deltas",
+    "get_delta_report",
+    "get_affected_tests",
+    "get_orphan_tests",
+    "get_hub_modules",
+    "get_truncated_imports",
+    "get_graph_stats",
+    "get_dependency_path",
+    "affected_from_diff",
+    "affected_from_ranges",
+    "get_run_stats",
+    "get_run_history",
+    "get_flaky_tests",
+    "get_attribute_trend",
+    "get_file_health",
+    "get_finding_trend",
+    "get_schema_version",
+    "get_recent_logs",
+    "evict_workspace",
+    "clear_workspace_data",
+    "database_maintenance",
+    "normalize_workspace_ids",
+    // Artifact kinds
+    "finding",
+    "test_failure",
+    "rust_test_failure",
+    "stylelint_finding",
+    "tsc_finding",
+    "playwright_test_failure",
+    // Request fields of IngestManifest
+    "extract_embedded_json",
+    "min_severity",
+    "stable_id_salt",
+    "stable_id_version",
+    "stable_id_length",
+    "attributes",
+    // Request fields of GetDeltaSummary
+    "include_incomplete_runs",
+    // Request fields of GetAffectedTests
+    "absolute_paths",
+    "peek",
+    "include_source_files",
+    "since_cursor",
+];
+
 pub struct WorkspaceServiceImpl {
     state: rpc::RpcState,
-    affected: Arc<Mutex<AffectedState>>,
+    /// Id of the workspace whose affected state this service holds: the name
+    /// of its cache directory, which clients derive from the workspace root.
+    workspace_id: String,
+    /// `None` after `EvictWorkspace` until the next affected request.
+    affected: Arc<Mutex<Option<AffectedState>>>,
+    affected_config: AffectedConfig,
+}
+
+impl WorkspaceServiceImpl {
+    /// Runs `f` on the affected state within a request scope, re-initializing
+    /// the state first if evicted.
+    #[allow(clippy::result_large_err)]
+    fn with_affected<T>(&self, f: impl FnOnce(&mut AffectedState) -> T) -> Result<T, Status> {
+        logging::in_request(|| {
+            let mut guard = self
+                .affected
+                .lock()
+                .map_err(|_| Status::internal("affected lock error"))?;
+            let affected = guard.get_or_insert_with(|| {
+                log_line!("[affected] INFO: re-initializing evicted workspace state");
+                start_affected(&self.affected_config)
+            });
+            Ok(f(affected))
+        })
+    }
 }
 
+// Handlers scope storage calls with `logging::in_request`, whose closures return `Status`
+#[allow(clippy::result_large_err)]
 #[tonic::async_trait]
 impl WorkspaceService for WorkspaceServiceImpl {
     async fn ping(&self, _request: Request<PingRequest>) -> Result<Response<PingResponse>, Status> {
         let response = PingResponse {
             version: env!("CARGO_PKG_VERSION").to_string(),
+            api_version: API_VERSION,
+            capabilities: CAPABILITIES.iter().map(ToString::to_string).collect(),
         };
         Ok(Response::new(response))
     }
@@ -55,8 +165,15 @@ impl WorkspaceService for WorkspaceServiceImpl {
         let manifest = req
             .manifest
             .ok_or_else(|| Status::invalid_argument("manifest is required"))?;
-        rpc::ingest_manifest(&self.state, &manifest, &req.package_scope)?;
-        Ok(Response::new(IngestManifestResponse {}))
+        let run_id = logging::in_request(|| {
+            rpc::ingest_manifest(
+                &self.state,
+                &manifest,
+                &req.package_scope,
+                req.extract_embedded_json,
+            )
+        })?;
+        Ok(Response::new(IngestManifestResponse { run_id }))
     }
 
     async fn get_delta_summary(
@@ -64,190 +181,1390 @@ impl WorkspaceService for WorkspaceServiceImpl {
         request: Request<GetDeltaSummaryRequest>,
     ) -> Result<Response<GetDeltaSummaryResponse>, Status> {
         let req = request.into_inner();
-        let result = rpc::get_delta_summary(&self.state, &req.workspace_id, &req.package_scope)?;
+        let result = logging::in_request(|| {
+            rpc::get_delta_summary(
+                &self.state,
+                &req.workspace_id,
+                &req.package_scope,
+                req.include_incomplete_runs,
+            )
+        })?;
         Ok(Response::new(GetDeltaSummaryResponse {
             new_findings: result.new_findings,
             fixed_findings: result.fixed_findings,
             new_test_failures: result.new_test_failures,
             fixed_test_failures: result.fixed_test_failures,
+            new_findings_by_category: result.new_findings_by_category,
+            new_fixable_findings: result.new_fixable_findings,
+            rule_delta: result
+                .rule_deltas
+                .into_iter()
+                .map(|d| RuleDelta {
+                    rule: d.rule,
+                    new_count: d.new_count,
+                    fixed_count: d.fixed_count,
+                })
+                .collect(),
         }))
     }
 
+    async fn get_package_deltas(
+        &self,
+        request: Request<GetPackageDeltasRequest>,
+    ) -> Result<Response<GetPackageDeltasResponse>, Status> {
+        let req = request.into_inner();
+        let deltas =
+            logging::in_request(|| rpc::get_package_deltas(&self.state, &req.workspace_id))?
+                .into_iter()
+                .map(|(package, result)| PackageDelta {
+                    package,
+                    new_findings: result.new_findings,
+                    fixed_findings: result.fixed_findings,
+                    new_test_failures: result.new_test_failures,
+                    fixed_test_failures: result.fixed_test_failures,
+                    new_fixable_findings: result.new_fixable_findings,
+                })
+                .collect();
+        Ok(Response::new(GetPackageDeltasResponse { deltas }))
+    }
+
+    async fn get_delta_report(
+        &self,
+        request: Request<GetDeltaReportRequest>,
+    ) -> Result<Response<GetDeltaReportResponse>, Status> {
+        let req = request.into_inner();
+        let report = logging::in_request(|| {
+            rpc::get_delta_report(&self.state, &req.workspace_id, &req.package_scope)
+        })?;
+        Ok(Response::new(GetDeltaReportResponse { report }))
+    }
+
     async fn get_affected_tests(
         &self,
         request: Request<GetAffectedTestsRequest>,
     ) -> Result<Response<GetAffectedTestsResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
         let req = request.into_inner();
-        let result = {
-            let mut affected = self
-                .affected
-                .lock()
-                .map_err(|_| Status::internal("affected lock error"))?;
-            affected.get_affected_tests(req.force_full, &req.package_scope)
+        let result = self.with_affected(|affected| {
+            let result = select_affected_tests(affected, &req);
+            let dirty = result.partition_dirty(&affected.test_patterns);
+            let result = if req.absolute_paths {
+                affected.absolutize(result)
+            } else {
+                result
+            };
+            (result, dirty)
+        })?;
+        let (result, (dirty_source_files, dirty_test_files)) = result;
+        let result_hash = result.result_hash();
+        let source_files = if req.include_source_files {
+            result.source_files
+        } else {
+            Vec::new()
         };
         Ok(Response::new(GetAffectedTestsResponse {
+            result_hash,
+            source_files,
             test_files: result.test_files,
             dirty_files: result.dirty_files,
+            dirty_source_files,
+            dirty_test_files,
             is_full_run: result.is_full_run,
+            full_run_reason: result
+                .full_run_reason
+                .map(|r| r.as_str().to_string())
+                .unwrap_or_default(),
+            cursor: result.cursor,
+            graph_generation: result.graph_generation,
+        }))
+    }
+
+    async fn get_orphan_tests(
+        &self,
+        request: Request<GetOrphanTestsRequest>,
+    ) -> Result<Response<GetOrphanTestsResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let req = request.into_inner();
+        let orphans = self.with_affected(|affected| {
+            affected.get_orphan_tests(&req.package_scope).map(|files| {
+                if req.absolute_paths {
+                    affected.to_absolute_paths(files)
+                } else {
+                    files
+                }
+            })
+        })?;
+        let test_files =
+            orphans.ok_or_else(|| Status::unavailable("dependency graph still building"))?;
+        Ok(Response::new(GetOrphanTestsResponse { test_files }))
+    }
+
+    async fn get_hub_modules(
+        &self,
+        _request: Request<GetHubModulesRequest>,
+    ) -> Result<Response<GetHubModulesResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let (hubs, threshold) =
+            self.with_affected(|affected| (affected.get_hub_modules(), affected.fan_in_threshold))?;
+        let modules = hubs
+            .ok_or_else(|| Status::unavailable("dependency graph still building"))?
+            .into_iter()
+            .map(|(path, dependents)| HubModule {
+                path,
+                dependents: u32::try_from(dependents).unwrap_or(u32::MAX),
+            })
+            .collect();
+        Ok(Response::new(GetHubModulesResponse {
+            modules,
+            fan_in_threshold: u32::try_from(threshold).unwrap_or(u32::MAX),
+        }))
+    }
+
+    async fn get_truncated_imports(
+        &self,
+        _request: Request<GetTruncatedImportsRequest>,
+    ) -> Result<Response<GetTruncatedImportsResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let (truncated, max_imports) = self.with_affected(|affected| {
+            (
+                affected.get_truncated_imports(),
+                affected.parse_limits.max_imports,
+            )
+        })?;
+        let files = truncated
+            .ok_or_else(|| Status::unavailable("dependency graph still building"))?
+            .into_iter()
+            .map(|(path, count)| TruncatedImports {
+                path,
+                import_count: u32::try_from(count).unwrap_or(u32::MAX),
+            })
+            .collect();
+        Ok(Response::new(GetTruncatedImportsResponse {
+            files,
+            max_imports: u32::try_from(max_imports).unwrap_or(u32::MAX),
+        }))
+    }
+
+    async fn get_graph_stats(
+        &self,
+        _request: Request<GetGraphStatsRequest>,
+    ) -> Result<Response<GetGraphStatsResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let stats = self
+            .with_affected(AffectedState::get_graph_stats)?
+            .ok_or_else(|| Status::unavailable("dependency graph still building or busy"))?;
+        Ok(Response::new(GetGraphStatsResponse {
+            node_count: u32::try_from(stats.nodes).unwrap_or(u32::MAX),
+            edge_count: u32::try_from(stats.edges).unwrap_or(u32::MAX),
+            cycle_count: u32::try_from(stats.cycles).unwrap_or(u32::MAX),
+        }))
+    }
+
+    async fn get_dependency_path(
+        &self,
+        request: Request<GetDependencyPathRequest>,
+    ) -> Result<Response<GetDependencyPathResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let req = request.into_inner();
+        let path = self
+            .with_affected(|affected| affected.get_dependency_path(&req.from_file, &req.to_file))?
+            .ok_or_else(|| Status::unavailable("dependency graph still building"))?;
+        Ok(Response::new(GetDependencyPathResponse { path }))
+    }
+
+    async fn affected_from_diff(
+        &self,
+        request: Request<AffectedFromDiffRequest>,
+    ) -> Result<Response<AffectedFromDiffResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let req = request.into_inner();
+        let changes = req
+            .changes
+            .iter()
+            .map(to_file_change)
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self.with_affected(|affected| {
+            let result = affected.get_affected_from_diff(&changes, &req.package_scope);
+            if req.absolute_paths {
+                affected.absolutize(result)
+            } else {
+                result
+            }
+        })?;
+        Ok(Response::new(AffectedFromDiffResponse {
+            test_files: result.test_files,
+            dirty_files: result.dirty_files,
+            is_full_run: result.is_full_run,
+            full_run_reason: result
+                .full_run_reason
+                .map(|r| r.as_str().to_string())
+                .unwrap_or_default(),
+        }))
+    }
+
+    async fn affected_from_ranges(
+        &self,
+        request: Request<AffectedFromRangesRequest>,
+    ) -> Result<Response<AffectedFromRangesResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let req = request.into_inner();
+        let changes = req
+            .files
+            .iter()
+            .map(to_range_change)
+            .collect::<Result<Vec<_>, _>>()?;
+        let result = self.with_affected(|affected| {
+            let result = affected.get_affected_from_ranges(&changes, &req.package_scope);
+            if req.absolute_paths {
+                affected.absolutize(result)
+            } else {
+                result
+            }
+        })?;
+        Ok(Response::new(AffectedFromRangesResponse {
+            test_files: result.test_files,
+            dirty_files: result.dirty_files,
+            is_full_run: result.is_full_run,
+            full_run_reason: result
+                .full_run_reason
+                .map(|r| r.as_str().to_string())
+                .unwrap_or_default(),
+        }))
+    }
+
+    async fn get_schema_version(
+        &self,
+        _request: Request<GetSchemaVersionRequest>,
+    ) -> Result<Response<GetSchemaVersionResponse>, Status> {
+        let applied = logging::in_request(|| rpc::get_schema_version(&self.state))?;
+        let current_version = applied.last().map_or(0, |m| m.version);
+        let migrations = applied
+            .into_iter()
+            .map(|m| AppliedMigration {
+                version: m.version,
+                name: m.name,
+                applied_on: m.applied_on,
+            })
+            .collect();
+        Ok(Response::new(GetSchemaVersionResponse {
+            current_version,
+            migrations,
         }))
     }
+
+    async fn get_run_stats(
+        &self,
+        request: Request<GetRunStatsRequest>,
+    ) -> Result<Response<GetRunStatsResponse>, Status> {
+        let req = request.into_inner();
+        let runs =
+            logging::in_request(|| rpc::get_run_stats(&self.state, &req.workspace_id, req.limit))?
+                .into_iter()
+                .map(|run| RunStats {
+                    run_id: run.run_id,
+                    started_at: run.started_at,
+                    ingest_duration_ms: run.ingest_duration_ms,
+                    artifact_bytes: run.artifact_bytes,
+                })
+                .collect();
+        Ok(Response::new(GetRunStatsResponse { runs }))
+    }
+
+    async fn get_run_history(
+        &self,
+        request: Request<GetRunHistoryRequest>,
+    ) -> Result<Response<GetRunHistoryResponse>, Status> {
+        let req = request.into_inner();
+        let runs = logging::in_request(|| {
+            rpc::get_run_history(&self.state, &req.workspace_id, req.limit)
+        })?
+        .into_iter()
+        .map(|run| RunHistoryEntry {
+            run_id: run.run_id,
+            started_at: run.started_at,
+            completed_at: run.completed_at,
+            test_failure_count: u32::try_from(run.test_failures).unwrap_or(u32::MAX),
+            finding_count: u32::try_from(run.findings).unwrap_or(u32::MAX),
+        })
+        .collect();
+        Ok(Response::new(GetRunHistoryResponse { runs }))
+    }
+
+    async fn get_attribute_trend(
+        &self,
+        request: Request<GetAttributeTrendRequest>,
+    ) -> Result<Response<GetAttributeTrendResponse>, Status> {
+        let req = request.into_inner();
+        let points = logging::in_request(|| {
+            rpc::get_attribute_trend(&self.state, &req.workspace_id, &req.key, req.limit)
+        })?
+        .into_iter()
+        .map(|point| AttributePoint {
+            run_id: point.run_id,
+            started_at: point.started_at,
+            value: point.value,
+        })
+        .collect();
+        Ok(Response::new(GetAttributeTrendResponse { points }))
+    }
+
+    async fn get_flaky_tests(
+        &self,
+        request: Request<GetFlakyTestsRequest>,
+    ) -> Result<Response<GetFlakyTestsResponse>, Status> {
+        let req = request.into_inner();
+        let tests = logging::in_request(|| {
+            rpc::get_flaky_tests(&self.state, &req.workspace_id, req.window)
+        })?
+        .into_iter()
+        .map(|test| FlakyTest {
+            stable_id: test.stable_id,
+            test_id: test.test_id,
+            file: test.file,
+            flip_count: u32::try_from(test.flip_count).unwrap_or(u32::MAX),
+        })
+        .collect();
+        Ok(Response::new(GetFlakyTestsResponse { tests }))
+    }
+
+    async fn get_file_health(
+        &self,
+        request: Request<GetFileHealthRequest>,
+    ) -> Result<Response<GetFileHealthResponse>, Status> {
+        let workspace_id = request.into_inner().workspace_id;
+        let files = logging::in_request(|| rpc::get_file_health(&self.state, &workspace_id))?
+            .into_iter()
+            .map(|f| FileHealth {
+                file: f.file,
+                error_count: u32::try_from(f.errors).unwrap_or(u32::MAX),
+                warning_count: u32::try_from(f.warnings).unwrap_or(u32::MAX),
+                finding_density: f.finding_density,
+            })
+            .collect();
+        Ok(Response::new(GetFileHealthResponse { files }))
+    }
+
+    async fn get_finding_trend(
+        &self,
+        request: Request<GetFindingTrendRequest>,
+    ) -> Result<Response<GetFindingTrendResponse>, Status> {
+        let req = request.into_inner();
+        let points = logging::in_request(|| {
+            rpc::get_finding_trend(&self.state, &req.workspace_id, req.since, req.bucket_secs)
+        })?
+        .into_iter()
+        .map(|p| FindingTrendPoint {
+            bucket_start: p.bucket_start,
+            run_count: u32::try_from(p.runs).unwrap_or(u32::MAX),
+            error_count: u32::try_from(p.errors).unwrap_or(u32::MAX),
+            warning_count: u32::try_from(p.warnings).unwrap_or(u32::MAX),
+        })
+        .collect();
+        Ok(Response::new(GetFindingTrendResponse { points }))
+    }
+
+    async fn get_recent_logs(
+        &self,
+        request: Request<GetRecentLogsRequest>,
+    ) -> Result<Response<GetRecentLogsResponse>, Status> {
+        let limit = request.into_inner().limit as usize;
+        Ok(Response::new(GetRecentLogsResponse {
+            lines: logging::buffer().recent(limit),
+        }))
+    }
+
+    async fn clear_workspace_data(
+        &self,
+        request: Request<ClearWorkspaceDataRequest>,
+    ) -> Result<Response<ClearWorkspaceDataResponse>, Status> {
+        let workspace_id = request.into_inner().workspace_id;
+        let deleted =
+            logging::in_request(|| rpc::clear_workspace_data(&self.state, &workspace_id))?;
+        Ok(Response::new(ClearWorkspaceDataResponse {
+            runs_deleted: u32::try_from(deleted).unwrap_or(u32::MAX),
+        }))
+    }
+
+    async fn database_maintenance(
+        &self,
+        _request: Request<DatabaseMaintenanceRequest>,
+    ) -> Result<Response<DatabaseMaintenanceResponse>, Status> {
+        let integrity_check = logging::in_request(|| rpc::database_maintenance(&self.state))?;
+        Ok(Response::new(DatabaseMaintenanceResponse {
+            integrity_check,
+        }))
+    }
+
+    async fn normalize_workspace_ids(
+        &self,
+        _request: Request<NormalizeWorkspaceIdsRequest>,
+    ) -> Result<Response<NormalizeWorkspaceIdsResponse>, Status> {
+        let rewritten = logging::in_request(|| rpc::normalize_stored_workspace_ids(&self.state))?;
+        Ok(Response::new(NormalizeWorkspaceIdsResponse {
+            ids_rewritten: u32::try_from(rewritten).unwrap_or(u32::MAX),
+        }))
+    }
+
+    async fn evict_workspace(
+        &self,
+        request: Request<EvictWorkspaceRequest>,
+    ) -> Result<Response<EvictWorkspaceResponse>, Status> {
+        rpc::ensure_affected_available(&self.state)?;
+        let workspace_id = request.into_inner().workspace_id;
+        let normalized = rpc::normalize_workspace_id(&self.state, &workspace_id)?;
+        // Other workspaces only have a writer here; their affected state lives elsewhere
+        let evicted = normalized == self.workspace_id
+            && self
+                .affected
+                .lock()
+                .map_err(|_| Status::internal("affected lock error"))?
+                .take()
+                .is_some();
+        rpc::close_workspace_writer(&self.state, &workspace_id);
+        log_line!("[affected] INFO: EvictWorkspace: workspace={workspace_id}, evicted={evicted}");
+        Ok(Response::new(EvictWorkspaceResponse { evicted }))
+    }
+}
+
+#[allow(clippy::result_large_err)]
+fn to_file_change(change: &zax::v1::FileChange) -> Result<affected::diff::FileChange, Status> {
+    use affected::diff::ChangeKind as Kind;
+    let kind = match ChangeKind::try_from(change.kind) {
+        Ok(ChangeKind::Added) => Kind::Added,
+        Ok(ChangeKind::Modified) => Kind::Modified,
+        Ok(ChangeKind::Deleted) => Kind::Deleted,
+        Ok(ChangeKind::Unspecified) | Err(_) => {
+            return Err(Status::invalid_argument(format!(
+                "change kind is required for {}",
+                change.path
+            )))
+        }
+    };
+    Ok(affected::diff::FileChange {
+        path: PathBuf::from(&change.path),
+        kind,
+    })
 }
 
-async fn write_port_file(cache_dir: &Path, port: u16) -> std::io::Result<()> {
-    let port_file = cache_dir.join("rust.port");
-    let tmp_file = cache_dir.join("rust.port.tmp");
+#[allow(clippy::result_large_err)]
+fn to_range_change(file: &zax::v1::FileRanges) -> Result<affected::diff::RangeChange, Status> {
+    let ranges = file
+        .ranges
+        .iter()
+        .map(|r| {
+            if r.start_line == 0 || r.start_line > r.end_line {
+                return Err(Status::invalid_argument(format!(
+                    "invalid line range {}-{} for {}",
+                    r.start_line, r.end_line, file.path
+                )));
+            }
+            Ok((r.start_line as usize, r.end_line as usize))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(affected::diff::RangeChange {
+        path: PathBuf::from(&file.path),
+        ranges,
+    })
+}
+
+/// Port file read by Engine clients.
+const PORT_FILE: &str = "rust.port";
+/// Port file of a read-only replica. Replicas share the primary's cache dir,
+/// so they must not take over `PORT_FILE` and receive its ingests.
+const REPLICA_PORT_FILE: &str = "rust.replica.port";
+
+async fn write_port_file(cache_dir: &Path, name: &str, port: u16) -> std::io::Result<()> {
+    let port_file = cache_dir.join(name);
+    let tmp_file = cache_dir.join(format!("{name}.tmp"));
     fs::write(&tmp_file, port.to_string()).await?;
     fs::rename(&tmp_file, &port_file).await?;
     Ok(())
 }
 
-#[allow(clippy::too_many_lines)]
-async fn run_server(
+/// Command-line arguments for the service.
+#[derive(Debug, PartialEq)]
+struct ServerArgs {
     cache_dir: PathBuf,
     workspace_root: PathBuf,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let addr: SocketAddr = "127.0.0.1:0".parse()?;
-    let listener = TcpListener::bind(addr).await?;
-    let local_addr = listener.local_addr()?;
-    let port = local_addr.port();
+    /// Open the database read-only and skip the watcher and graph build.
+    /// The port is written to `REPLICA_PORT_FILE` instead of `PORT_FILE`.
+    read_only: bool,
+    /// Verify stored row counts after each ingest.
+    verify_ingest: bool,
+    /// Enable administrative RPCs (e.g. `ClearWorkspaceData`).
+    admin: bool,
+    /// Carry failures of renamed test files over between runs.
+    detect_renames: bool,
+    /// Store `ESLint` warnings alongside errors.
+    eslint_warnings: bool,
+    /// Fold workspace ids to lowercase. Stored ids keep their case unless
+    /// rewritten by the `NormalizeWorkspaceIds` admin RPC.
+    lowercase_workspace_ids: bool,
+    /// How workspace file paths are resolved (`--logical-paths` skips canonicalize).
+    path_mode: PathMode,
+    /// Whether graph paths ignore case (`--path-case`); `None` = detect.
+    case_insensitive_paths: Option<bool>,
+    /// Capacity of the in-memory log buffer served by `GetRecentLogs`.
+    log_buffer_lines: usize,
+    /// Fraction of all tests above which a selection becomes a full run.
+    full_run_ratio: f64,
+    /// Thresholds for skipping generated or minified files during import parsing.
+    parse_limits: ParseLimits,
+    /// Direct dependents above which a module is reported as a hub.
+    fan_in_threshold: usize,
+    /// Graph nodes above which selections become full runs.
+    max_graph_nodes: usize,
+    /// Keep test files out of the graph node count (`--exclude-test-nodes`).
+    exclude_test_nodes: bool,
+    /// `git ls-files` output to build the graph from instead of walking the
+    /// workspace (`--graph-file-list=<PATH>`).
+    graph_file_list: Option<PathBuf>,
+    /// Seconds between background WAL checkpoints (0 = only on shutdown).
+    wal_checkpoint_secs: u64,
+    /// Import prefix resolved from the workspace root (`--root-alias[=<P>]`).
+    root_alias: Option<String>,
+    /// How long an affected query waits for the graph lock before a full run.
+    lock_timeout: Duration,
+    /// Changed files above this size are not tracked (`--watch-max-file-size=<N>`).
+    watch_max_file_size: Option<u64>,
+    /// Poll interval of the fallback polling watcher (`--watch-debounce-ms=<N>`
+    /// or `ZAX_DEBOUNCE_MS`).
+    watch_debounce_ms: u64,
+    /// Dirty files above which a selection becomes a full run
+    /// (`--max-dirty-files=<N>` or `ZAX_MAX_DIRTY_FILES`).
+    max_dirty_files: usize,
+    /// `SQLite` page cache and mmap sizes for the service connection.
+    sqlite_cache: store::CacheSettings,
+    /// Invalid settings replaced by their defaults, logged once the log
+    /// buffer is set up.
+    warnings: Vec<String>,
+    /// Extra directories artifacts may be read from (`--artifact-root=<DIR>`, repeatable).
+    artifact_roots: Vec<PathBuf>,
+}
+
+/// Default interval between background WAL checkpoints.
+const DEFAULT_WAL_CHECKPOINT_SECS: u64 = 300;
+/// Dependency graph saved in the cache dir after each build.
+const GRAPH_CACHE_FILE: &str = "graph.bin";
+
+const USAGE: &str = "Usage: zax_workspace_service <cache_dir> <workspace_root> \
+                     [--read-only] [--verify-ingest] [--admin] [--detect-renames] [--eslint-warnings] \
+                     [--lowercase-workspace-ids] \
+                     [--logical-paths] [--path-case=<auto|sensitive|insensitive>] \
+                     [--log-buffer-lines=<N>] [--full-run-ratio=<R>] \
+                     [--max-line-length=<N>] [--max-file-size=<N>] [--max-graph-nodes=<N>] \
+                     [--max-imports=<N>] [--exclude-test-nodes] [--graph-file-list=<PATH>] \
+                     [--fan-in-threshold=<N>] \
+                     [--wal-checkpoint-secs=<N>] [--root-alias[=<PREFIX>]] \
+                     [--affected-timeout-ms=<N>] [--watch-max-file-size=<N>] \
+                     [--watch-debounce-ms=<N>] [--max-dirty-files=<N>] \
+                     [--sqlite-cache-mib=<N>] [--sqlite-mmap-mib=<N>] [--artifact-root=<DIR>]...";
+
+/// Parses `<cache_dir> <workspace_root>` followed or interleaved with flags.
+fn parse_args(args: &[String]) -> Result<ServerArgs, String> {
+    let (flags, positional): (Vec<&String>, Vec<&String>) =
+        args.iter().skip(1).partition(|arg| arg.starts_with("--"));
+    if positional.len() < 2 {
+        return Err(USAGE.to_string());
+    }
+    let mut parsed = ServerArgs {
+        cache_dir: PathBuf::from(positional[0]),
+        workspace_root: PathBuf::from(positional[1]),
+        read_only: false,
+        verify_ingest: false,
+        admin: false,
+        detect_renames: false,
+        eslint_warnings: false,
+        lowercase_workspace_ids: false,
+        path_mode: PathMode::Canonical,
+        case_insensitive_paths: None,
+        log_buffer_lines: logging::DEFAULT_CAPACITY,
+        full_run_ratio: affected::DEFAULT_FULL_RUN_RATIO,
+        parse_limits: ParseLimits::default(),
+        fan_in_threshold: affected::DEFAULT_FAN_IN_THRESHOLD,
+        max_graph_nodes: affected::DEFAULT_MAX_GRAPH_NODES,
+        exclude_test_nodes: false,
+        graph_file_list: None,
+        wal_checkpoint_secs: DEFAULT_WAL_CHECKPOINT_SECS,
+        root_alias: None,
+        lock_timeout: affected::DEFAULT_LOCK_TIMEOUT,
+        watch_max_file_size: None,
+        watch_debounce_ms: affected::DEFAULT_DEBOUNCE_MS,
+        max_dirty_files: affected::DEFAULT_MAX_DIRTY_FILES,
+        sqlite_cache: store::CacheSettings::default(),
+        artifact_roots: Vec::new(),
+        warnings: Vec::new(),
+    };
+    apply_watch_env(&mut parsed, |name| env::var(name).ok());
+    for flag in flags {
+        apply_flag(&mut parsed, flag)?;
+    }
+    check_workspace_root(&parsed.workspace_root)?;
+    Ok(parsed)
+}
+
+/// Rejects a workspace root inside `node_modules`.
+fn check_workspace_root(root: &Path) -> Result<(), String> {
+    if is_inside_node_modules(root) {
+        return Err(format!(
+            "workspace root {} is inside node_modules, where every file is ignored; \
+             pass the project root instead",
+            root.display()
+        ));
+    }
+    Ok(())
+}
+
+/// True if `root`, or what it resolves to through symlinks, has a
+/// `node_modules` component.
+fn is_inside_node_modules(root: &Path) -> bool {
+    let has_component = |path: &Path| path.components().any(|c| c.as_os_str() == "node_modules");
+    has_component(root)
+        || root
+            .canonicalize()
+            .is_ok_and(|resolved| has_component(&resolved))
+}
+
+/// Prefixes of the flags handled by `apply_affected_flag`.
+const AFFECTED_FLAG_PREFIXES: [&str; 5] = [
+    "--fan-in-",
+    "--full-run-",
+    "--graph-",
+    "--affected-",
+    "--watch-",
+];
+
+/// Applies a single `--flag` or `--flag=value` argument.
+fn apply_flag(args: &mut ServerArgs, flag: &str) -> Result<(), String> {
+    match flag {
+        "--read-only" => args.read_only = true,
+        "--verify-ingest" => args.verify_ingest = true,
+        "--admin" => args.admin = true,
+        "--detect-renames" => args.detect_renames = true,
+        "--eslint-warnings" => args.eslint_warnings = true,
+        "--lowercase-workspace-ids" => args.lowercase_workspace_ids = true,
+        "--exclude-test-nodes" => args.exclude_test_nodes = true,
+        "--logical-paths" => args.path_mode = PathMode::Logical,
+        "--path-case=auto" => args.case_insensitive_paths = None,
+        "--path-case=sensitive" => args.case_insensitive_paths = Some(false),
+        "--path-case=insensitive" => args.case_insensitive_paths = Some(true),
+        "--root-alias" => args.root_alias = Some(affected::DEFAULT_ROOT_ALIAS.to_string()),
+        _ if flag.starts_with("--root-alias=") => {
+            args.root_alias = Some(parse_flag_value(flag)?);
+        }
+        _ if flag.starts_with("--log-buffer-lines=") => {
+            args.log_buffer_lines = parse_flag_value(flag)?;
+        }
+        _ if flag.starts_with("--max-") => apply_limit_flag(args, flag)?,
+        _ if AFFECTED_FLAG_PREFIXES.iter().any(|p| flag.starts_with(p)) => {
+            apply_affected_flag(args, flag)?;
+        }
+        _ if ["--sqlite-", "--wal-", "--artifact-"]
+            .iter()
+            .any(|p| flag.starts_with(p)) =>
+        {
+            apply_storage_flag(args, flag)?;
+        }
+        _ => return Err(format!("unknown flag: {flag}")),
+    }
+    Ok(())
+}
+
+/// Runs the selection `req` asks for: since a cursor, a peek, or a consuming query.
+fn select_affected_tests(
+    affected: &mut AffectedState,
+    req: &GetAffectedTestsRequest,
+) -> affected::state::AffectedResult {
+    if !req.since_cursor.is_empty() {
+        let cursor = &req.since_cursor;
+        affected.get_affected_tests_since(req.force_full, &req.package_scope, cursor)
+    } else if req.peek {
+        affected.peek_affected_tests(req.force_full, &req.package_scope)
+    } else {
+        affected.get_affected_tests(req.force_full, &req.package_scope)
+    }
+}
+
+/// Applies a flag tuning the graph build, watcher, or affected queries.
+fn apply_affected_flag(args: &mut ServerArgs, flag: &str) -> Result<(), String> {
+    match flag {
+        _ if flag.starts_with("--graph-file-list=") => {
+            args.graph_file_list = Some(parse_flag_value(flag)?);
+        }
+        _ if flag.starts_with("--fan-in-threshold=") => {
+            args.fan_in_threshold = parse_flag_value(flag)?;
+        }
+        _ if flag.starts_with("--full-run-ratio=") => {
+            args.full_run_ratio = parse_flag_value(flag)?;
+            if args.full_run_ratio.is_nan() || args.full_run_ratio <= 0.0 {
+                return Err(format!("--full-run-ratio must be positive: {flag}"));
+            }
+        }
+        _ if flag.starts_with("--affected-timeout-ms=") => {
+            args.lock_timeout = Duration::from_millis(parse_flag_value(flag)?);
+        }
+        _ if flag.starts_with("--watch-max-file-size=") => {
+            args.watch_max_file_size = Some(parse_flag_value(flag)?);
+        }
+        _ if flag.starts_with("--watch-debounce-ms=") => {
+            let value = flag.split_once('=').map_or("", |(_, v)| v);
+            let default = affected::DEFAULT_DEBOUNCE_MS;
+            args.watch_debounce_ms =
+                positive_or_default(&mut args.warnings, "--watch-debounce-ms", value, default);
+        }
+        _ => return Err(format!("unknown flag: {flag}")),
+    }
+    Ok(())
+}
+
+/// Applies `ZAX_DEBOUNCE_MS` and `ZAX_MAX_DIRTY_FILES` from `var`; the
+/// matching flags override them.
+fn apply_watch_env(args: &mut ServerArgs, var: impl Fn(&str) -> Option<String>) {
+    let warnings = &mut args.warnings;
+    if let Some(value) = var("ZAX_DEBOUNCE_MS") {
+        let default = affected::DEFAULT_DEBOUNCE_MS;
+        args.watch_debounce_ms = positive_or_default(warnings, "ZAX_DEBOUNCE_MS", &value, default);
+    }
+    if let Some(value) = var("ZAX_MAX_DIRTY_FILES") {
+        let default = affected::DEFAULT_MAX_DIRTY_FILES as u64;
+        args.max_dirty_files =
+            positive_or_default(warnings, "ZAX_MAX_DIRTY_FILES", &value, default) as usize;
+    }
+}
+
+/// Parses a positive integer watcher setting. Invalid input keeps `default`
+/// with a warning rather than refusing to start.
+fn positive_or_default(warnings: &mut Vec<String>, name: &str, value: &str, default: u64) -> u64 {
+    match value.parse::<u64>() {
+        Ok(n) if n > 0 => n,
+        _ => {
+            warnings.push(format!(
+                "{name}={value:?} is not a positive integer, using {default}"
+            ));
+            default
+        }
+    }
+}
+
+/// Applies a `--max-*` limit flag for import parsing or the graph.
+fn apply_limit_flag(args: &mut ServerArgs, flag: &str) -> Result<(), String> {
+    match flag {
+        _ if flag.starts_with("--max-line-length=") => {
+            args.parse_limits.max_line_length = parse_flag_value(flag)?;
+        }
+        _ if flag.starts_with("--max-file-size=") => {
+            args.parse_limits.max_file_size = parse_flag_value(flag)?;
+        }
+        _ if flag.starts_with("--max-dirty-files=") => {
+            let value = flag.split_once('=').map_or("", |(_, v)| v);
+            let default = affected::DEFAULT_MAX_DIRTY_FILES as u64;
+            let max = positive_or_default(&mut args.warnings, "--max-dirty-files", value, default);
+            args.max_dirty_files = max as usize;
+        }
+        _ if flag.starts_with("--max-graph-nodes=") => {
+            args.max_graph_nodes = parse_flag_value(flag)?;
+        }
+        _ if flag.starts_with("--max-imports=") => {
+            args.parse_limits.max_imports = parse_flag_value(flag)?;
+        }
+        _ => return Err(format!("unknown flag: {flag}")),
+    }
+    Ok(())
+}
+
+/// Applies a storage flag: `SQLite` tuning or artifact locations.
+fn apply_storage_flag(args: &mut ServerArgs, flag: &str) -> Result<(), String> {
+    match flag {
+        _ if flag.starts_with("--artifact-root=") => {
+            args.artifact_roots.push(parse_flag_value(flag)?);
+        }
+        _ if flag.starts_with("--sqlite-cache-mib=") => {
+            args.sqlite_cache.cache_mib = parse_flag_value(flag)?;
+        }
+        _ if flag.starts_with("--sqlite-mmap-mib=") => {
+            args.sqlite_cache.mmap_mib = parse_flag_value(flag)?;
+        }
+        _ if flag.starts_with("--wal-checkpoint-secs=") => {
+            args.wal_checkpoint_secs = parse_flag_value(flag)?;
+        }
+        _ => return Err(format!("unknown flag: {flag}")),
+    }
+    Ok(())
+}
 
-    // Initialize storage before anything else
-    store::init_storage(&cache_dir)?;
-    let conn = store::open_connection(&cache_dir)?;
+/// Parses the value of a `--name=value` flag.
+fn parse_flag_value<T: std::str::FromStr>(flag: &str) -> Result<T, String> {
+    let (name, value) = flag.split_once('=').unwrap_or((flag, ""));
+    value
+        .parse()
+        .map_err(|_| format!("invalid {name} value: {value}"))
+}
 
-    // Initialize affected state
-    let mut affected_state = AffectedState::new(workspace_root.clone());
+/// Start the watcher and background graph build for affected selection.
+fn start_affected(config: &AffectedConfig) -> AffectedState {
+    let mut affected_state = AffectedState::new(config.workspace_root.clone());
+    affected_state.path_mode = config.path_mode;
+    affected_state.full_run_ratio = config.full_run_ratio;
+    affected_state.parse_limits = config.parse_limits;
+    affected_state.fan_in_threshold = config.fan_in_threshold;
+    affected_state.set_max_graph_nodes(config.max_graph_nodes);
+    affected_state.set_test_leaves(config.exclude_test_nodes);
+    affected_state.set_case_insensitive_paths(
+        config
+            .case_insensitive_paths
+            .unwrap_or_else(|| normalize::path::is_case_insensitive(&config.workspace_root)),
+    );
+    affected_state.root_alias.clone_from(&config.root_alias);
+    affected_state.lock_timeout = config.lock_timeout;
+    affected_state.watch_max_file_size = config.watch_max_file_size;
+    affected_state.watch_debounce = config.watch_debounce;
+    affected_state.set_max_dirty_files(config.max_dirty_files);
     if let Err(e) = affected_state.start_watcher() {
-        eprintln!("[affected] ERROR: {e}");
+        log_line!("[affected] ERROR: {e}");
     }
-    let affected = Arc::new(Mutex::new(affected_state));
+    let config = config.clone();
+    let graph_arc = Arc::clone(&affected_state.graph);
+    let ready_arc = Arc::clone(&affected_state.graph_ready);
+    let negative_cache = Arc::clone(&affected_state.negative_cache);
+    let config_files = affected_state.config_files.clone();
+    affected_state.build_task = Some(tokio::spawn(async move {
+        build_graph_async(config, graph_arc, ready_arc, negative_cache, config_files).await;
+    }));
+    affected_state
+}
 
-    // Start graph initialization in background
-    let (ws_root, graph_arc, ready_arc) = {
-        let state = affected.lock().unwrap();
-        (
-            state.workspace_root.clone(),
-            Arc::clone(&state.graph),
-            Arc::clone(&state.graph_ready),
-        )
+/// Resolves once the process receives SIGTERM. The handler is installed by
+/// this call, so a signal arriving before the future is polled still counts.
+fn terminate_signal() -> std::io::Result<impl Future<Output = ()> + Send> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    Ok(async move {
+        sigterm.recv().await;
+    })
+}
+
+/// Serves until `shutdown` resolves, then checkpoints the WAL.
+async fn run_server(
+    args: ServerArgs,
+    shutdown: impl Future<Output = ()> + Send,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let addr: SocketAddr = "127.0.0.1:0".parse()?;
+    let listener = TcpListener::bind(addr).await?;
+    let port = listener.local_addr()?.port();
+
+    // Initialize storage before anything else (replicas never migrate)
+    let conn = open_storage(&args)?;
+    let cache_dir = args.cache_dir.clone();
+    let port_file = if args.read_only {
+        REPLICA_PORT_FILE
+    } else {
+        PORT_FILE
     };
-    tokio::spawn(async move {
-        build_graph_async(ws_root, graph_arc, ready_arc).await;
+    let checkpoints = (!args.read_only && args.wal_checkpoint_secs > 0).then(|| {
+        spawn_wal_checkpoints(
+            cache_dir.clone(),
+            Duration::from_secs(args.wal_checkpoint_secs),
+        )
     });
 
-    write_port_file(&cache_dir, port).await?;
+    let service = build_service(args, conn);
+    write_port_file(&cache_dir, port_file, port).await?;
 
-    let service = WorkspaceServiceImpl {
-        state: rpc::RpcState {
-            cache_dir: cache_dir.clone(),
-            conn: Arc::new(Mutex::new(conn)),
-        },
-        affected,
-    };
     let incoming = tokio_stream::wrappers::TcpListenerStream::new(listener);
-    let mut sigterm = signal(SignalKind::terminate())?;
 
     Server::builder()
         .add_service(WorkspaceServiceServer::new(service))
-        .serve_with_incoming_shutdown(incoming, async {
-            sigterm.recv().await;
-        })
+        .serve_with_incoming_shutdown(incoming, shutdown)
         .await?;
 
+    if let Some(task) = checkpoints {
+        task.abort();
+        checkpoint_wal(&cache_dir);
+    }
     Ok(())
 }
 
+/// Assemble the service, starting affected selection unless read-only.
+fn build_service(args: ServerArgs, conn: rusqlite::Connection) -> WorkspaceServiceImpl {
+    // Affected selection is unavailable on read-only replicas
+    let affected_config = AffectedConfig::from_args(&args);
+    let affected = if args.read_only {
+        AffectedState::new(args.workspace_root.clone())
+    } else {
+        start_affected(&affected_config)
+    };
+    let mut artifact_roots = rpc::default_artifact_roots(&args.cache_dir);
+    // Canonical roots, since artifact paths are canonicalized before the check
+    artifact_roots.extend(
+        args.artifact_roots
+            .into_iter()
+            .map(|root| root.canonicalize().unwrap_or(root)),
+    );
+    WorkspaceServiceImpl {
+        state: rpc::RpcState {
+            conn: Arc::new(Mutex::new(conn)),
+            writers: store::WorkspaceConnections::new(&args.cache_dir, args.sqlite_cache),
+            read_only: args.read_only,
+            verify_ingest: args.verify_ingest,
+            admin: args.admin,
+            artifact_roots,
+            detect_renames: args.detect_renames,
+            eslint_warnings: args.eslint_warnings,
+            lowercase_workspace_ids: args.lowercase_workspace_ids,
+            workspace_root: args
+                .path_mode
+                .resolve(&args.workspace_root)
+                .unwrap_or_else(|_| args.workspace_root.clone()),
+        },
+        workspace_id: cache_dir_workspace_id(&args.cache_dir),
+        affected: Arc::new(Mutex::new(Some(affected))),
+        affected_config,
+    }
+}
+
+/// Workspace id a cache directory belongs to, i.e. its name.
+fn cache_dir_workspace_id(cache_dir: &Path) -> String {
+    cache_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Open the database, running migrations unless read-only.
+fn open_storage(args: &ServerArgs) -> Result<rusqlite::Connection, store::StoreError> {
+    if args.read_only {
+        log_line!("[rpc] INFO: starting in read-only mode");
+        return store::open_connection_read_only(&args.cache_dir, args.sqlite_cache);
+    }
+    store::init_storage(&args.cache_dir)?;
+    store::open_connection(&args.cache_dir, args.sqlite_cache)
+}
+
+/// Periodically checkpoint the WAL so the `-wal` file stays bounded under
+/// sustained ingest.
+fn spawn_wal_checkpoints(cache_dir: PathBuf, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick completes immediately
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let dir = cache_dir.clone();
+            let _ = tokio::task::spawn_blocking(move || checkpoint_wal(&dir)).await;
+        }
+    })
+}
+
+/// Checkpoint the WAL on a dedicated connection so RPC handlers holding the
+/// shared connection are not blocked, logging anything unexpected.
+fn checkpoint_wal(cache_dir: &Path) {
+    match store::open_connection(cache_dir, store::CacheSettings::default())
+        .and_then(|conn| store::checkpoint_wal(&conn))
+    {
+        Ok(result) if result.busy => log_line!(
+            "[store] WARN: WAL checkpoint incomplete: {}/{} frames (database busy)",
+            result.checkpointed_frames,
+            result.log_frames
+        ),
+        Ok(_) => {}
+        Err(e) => log_line!("[store] ERROR: WAL checkpoint failed: {e}"),
+    }
+}
+
+/// Settings for affected selection and its background graph build, kept so
+/// evicted state can be rebuilt.
+#[derive(Debug, Clone)]
+struct AffectedConfig {
+    workspace_root: PathBuf,
+    path_mode: PathMode,
+    case_insensitive_paths: Option<bool>,
+    full_run_ratio: f64,
+    parse_limits: ParseLimits,
+    fan_in_threshold: usize,
+    max_graph_nodes: usize,
+    exclude_test_nodes: bool,
+    graph_file_list: Option<PathBuf>,
+    /// Where the graph is saved after a build and loaded from on the next.
+    graph_cache: Option<PathBuf>,
+    root_alias: Option<String>,
+    lock_timeout: Duration,
+    watch_max_file_size: Option<u64>,
+    watch_debounce: Duration,
+    max_dirty_files: usize,
+}
+
+impl AffectedConfig {
+    fn from_args(args: &ServerArgs) -> Self {
+        Self {
+            workspace_root: args.workspace_root.clone(),
+            path_mode: args.path_mode,
+            case_insensitive_paths: args.case_insensitive_paths,
+            full_run_ratio: args.full_run_ratio,
+            parse_limits: args.parse_limits,
+            fan_in_threshold: args.fan_in_threshold,
+            max_graph_nodes: args.max_graph_nodes,
+            exclude_test_nodes: args.exclude_test_nodes,
+            graph_file_list: args.graph_file_list.clone(),
+            graph_cache: Some(args.cache_dir.join(GRAPH_CACHE_FILE)),
+            root_alias: args.root_alias.clone(),
+            lock_timeout: args.lock_timeout,
+            watch_max_file_size: args.watch_max_file_size,
+            watch_debounce: Duration::from_millis(args.watch_debounce_ms),
+            max_dirty_files: args.max_dirty_files,
+        }
+    }
+}
+
+/// Files the graph build visits: the listed files when the engine passed
+/// `git ls-files` output (newline or NUL separated, workspace-relative),
+/// otherwise a gitignore-aware walk of the workspace.
+fn graph_seed_files(
+    workspace_root: &Path,
+    file_list: Option<&Path>,
+) -> Box<dyn Iterator<Item = PathBuf> + Send> {
+    if let Some(list) = file_list {
+        match std::fs::read_to_string(list) {
+            Ok(content) => {
+                let files: Vec<PathBuf> = content
+                    .split(['\n', '\0'])
+                    .map(|line| line.trim_end_matches('\r'))
+                    .filter(|line| !line.is_empty())
+                    .map(|line| workspace_root.join(line))
+                    .filter(|path| path.is_file())
+                    .collect();
+                log_line!(
+                    "[affected] INFO: building graph from {} listed files",
+                    files.len()
+                );
+                return Box::new(files.into_iter());
+            }
+            Err(e) => log_line!(
+                "[affected] WARN: cannot read {}: {e}; walking the workspace",
+                list.display()
+            ),
+        }
+    }
+    let walker = ignore::WalkBuilder::new(workspace_root)
+        .hidden(false)
+        .git_ignore(true)
+        .build();
+    Box::new(walker.flatten().map(ignore::DirEntry::into_path))
+}
+
+/// Settings that change how imports resolve to edges; a saved graph built
+/// with different ones is discarded. Resolver config files are checked
+/// against the build time instead, in `graph_sources`.
+fn graph_cache_key(config: &AffectedConfig) -> String {
+    format!(
+        "{}|{:?}|{:?}|{:?}",
+        config.workspace_root.display(),
+        config.path_mode,
+        config.root_alias,
+        config.parse_limits
+    )
+}
+
+/// Load the saved graph into `graph` and drop files deleted since. Returns
+/// when the saved build started; a missing or unusable cache yields `None`.
+fn load_graph_cache(
+    graph: &affected::SharedDepGraph,
+    cache: &Path,
+    key: &str,
+) -> Option<SystemTime> {
+    let mut g = graph.write().unwrap();
+    match g.load(cache, key) {
+        Ok(built_at) => {
+            let deleted: Vec<PathBuf> = g.files().into_iter().filter(|p| !p.exists()).collect();
+            for path in &deleted {
+                g.remove_file(path);
+            }
+            log_line!(
+                "[affected] INFO: loaded graph cache: {} files, {} deleted since",
+                g.files().len(),
+                deleted.len()
+            );
+            Some(built_at)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            log_line!(
+                "[affected] WARN: discarding graph cache {}: {e}",
+                cache.display()
+            );
+            None
+        }
+    }
+}
+
+/// Splits the files the build visits into TS/JS sources and whether a config
+/// file changed after `cached_at`. tsconfig `paths`, package.json `exports`
+/// and installed packages decide how imports resolve, so such a change makes
+/// the cached edges of unmodified files stale; no watcher event reports it
+/// while the service is stopped.
+fn graph_sources(
+    files: impl Iterator<Item = PathBuf>,
+    cached_at: Option<SystemTime>,
+    config_files: &ConfigFiles,
+    path_mode: PathMode,
+) -> (Vec<PathBuf>, bool) {
+    let mut sources = Vec::new();
+    let mut config_changed = false;
+    for entry in files {
+        if is_ts_js_file(&entry) {
+            sources.push(entry);
+        } else if let Some(at) = cached_at.filter(|_| !config_changed) {
+            // Config globs match canonical paths, so resolve only candidates
+            config_changed = !unmodified_since(&entry, at)
+                && path_mode
+                    .resolve(&entry)
+                    .is_ok_and(|path| config_files.matches(&path));
+        }
+    }
+    (sources, config_changed)
+}
+
+/// True if a TS/JS source is missing from the loaded graph, i.e. was created
+/// since it was saved. An unmodified file may import it through an import the
+/// saved build could not resolve, which its cached edges do not record.
+fn has_uncached_source(
+    graph: &affected::SharedDepGraph,
+    sources: &[PathBuf],
+    path_mode: PathMode,
+) -> bool {
+    let g = graph.read().unwrap();
+    sources.iter().any(|entry| {
+        path_mode
+            .resolve(entry)
+            .is_ok_and(|path| !g.contains(&path))
+    })
+}
+
+/// Why the loaded graph cannot be reused, if it cannot.
+fn stale_cache_reason(
+    graph: &affected::SharedDepGraph,
+    sources: &[PathBuf],
+    config_changed: bool,
+    path_mode: PathMode,
+) -> Option<&'static str> {
+    if config_changed {
+        return Some("config changed");
+    }
+    has_uncached_source(graph, sources, path_mode).then_some("source files were created")
+}
+
+/// Empty a graph loaded from a stale cache so every file is re-parsed.
+fn discard_graph_cache(graph: &affected::SharedDepGraph, reason: &str) -> Option<SystemTime> {
+    log_line!("[affected] INFO: {reason} since the graph cache was saved; rebuilding");
+    let mut g = graph.write().unwrap();
+    for path in g.files() {
+        g.remove_file(&path);
+    }
+    None
+}
+
+/// True if `path` was last modified no later than `time`.
+fn unmodified_since(path: &Path, time: SystemTime) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified <= time)
+}
+
+/// Parse `path` and add it to the graph with the edges of its resolved imports.
+/// Returns false if the graph overflowed.
+fn add_parsed_file(
+    graph: &affected::SharedDepGraph,
+    resolver: &affected::PathResolver,
+    path: &Path,
+    parse_limits: ParseLimits,
+) -> bool {
+    if !graph.write().unwrap().add_file(path.to_path_buf()) {
+        return false;
+    }
+
+    // Parse imports and resolve
+    let parsed = affected::parse_file(path, parse_limits);
+    let mut resolved = Vec::new();
+    for import in parsed.imports {
+        if let Some(resolved_path) = resolver.resolve(path, &import.specifier) {
+            let mut g = graph.write().unwrap();
+            if g.add_file(resolved_path.clone()) {
+                resolved.push(resolved_path);
+            }
+        }
+    }
+
+    // Update edges
+    let mut g = graph.write().unwrap();
+    g.update_edges(path, &resolved);
+    g.set_truncated_imports(path, parsed.truncated_from);
+    true
+}
+
 /// Build the dependency graph asynchronously.
-#[allow(clippy::too_many_lines)]
+///
+/// Starts from the graph saved by the previous build, if any, re-parsing only
+/// files modified since. A config file changed or a source file created since
+/// the saved build discards it, as either can change what unmodified files
+/// resolve their imports to.
+#[allow(clippy::too_many_lines, clippy::too_many_arguments)]
 async fn build_graph_async(
-    workspace_root: PathBuf,
+    config: AffectedConfig,
     graph: affected::SharedDepGraph,
     graph_ready: Arc<std::sync::atomic::AtomicBool>,
+    negative_cache: affected::SharedNegativeCache,
+    config_files: ConfigFiles,
 ) {
-    use affected::{parse_imports, PathResolver};
-    use ignore::WalkBuilder;
+    use affected::PathResolver;
     use std::sync::atomic::Ordering;
     use std::time::Instant;
 
     const GRAPH_INIT_TIMEOUT_SECS: u64 = 30;
 
+    let cache_key = graph_cache_key(&config);
+    let AffectedConfig {
+        workspace_root,
+        path_mode,
+        parse_limits,
+        fan_in_threshold,
+        root_alias,
+        graph_file_list,
+        graph_cache,
+        ..
+    } = config;
+
     let start = Instant::now();
-    eprintln!(
+    let built_at = SystemTime::now();
+    log_line!(
         "[affected] INFO: starting graph build for {}",
         workspace_root.display()
     );
+    let cached_at = graph_cache
+        .as_deref()
+        .and_then(|cache| load_graph_cache(&graph, cache, &cache_key));
+    let seeds = graph_seed_files(&workspace_root, graph_file_list.as_deref());
+    let (sources, config_changed) = graph_sources(seeds, cached_at, &config_files, path_mode);
+    let stale =
+        cached_at.and_then(|_| stale_cache_reason(&graph, &sources, config_changed, path_mode));
+    let cached_at = match stale {
+        Some(reason) => discard_graph_cache(&graph, reason),
+        None => cached_at,
+    };
 
-    let resolver = PathResolver::new(workspace_root.clone());
+    let resolver = PathResolver::new(workspace_root.clone(), path_mode)
+        .with_root_alias(root_alias)
+        .with_negative_cache(Arc::clone(&negative_cache));
     let mut file_count = 0;
+    let mut reused_count = 0;
+    let mut complete = true;
 
-    let walker = WalkBuilder::new(&workspace_root)
-        .hidden(false)
-        .git_ignore(true)
-        .build();
-
-    for entry in walker.flatten() {
-        if !is_ts_js_file(entry.path()) {
-            continue;
-        }
-
-        let Ok(path) = entry.path().canonicalize() else {
+    for entry in sources {
+        let Ok(path) = path_mode.resolve(&entry) else {
             continue;
         };
 
-        // Add file to graph
+        // Keep the cached edges of files unmodified since the cached build
+        if cached_at.is_some_and(|at| unmodified_since(&path, at))
+            && graph.read().unwrap().contains(&path)
         {
-            let mut g = graph.write().unwrap();
-            if g.add_file(path.clone()).is_none() {
-                eprintln!("[affected] WARN: graph overflow during init");
-                break;
-            }
-        }
-
-        // Parse imports and resolve
-        let imports = parse_imports(&path);
-        let mut resolved = Vec::new();
-        for import in imports {
-            if let Some(resolved_path) = resolver.resolve(&path, &import.specifier) {
-                let mut g = graph.write().unwrap();
-                if g.add_file(resolved_path.clone()).is_some() {
-                    resolved.push(resolved_path);
-                }
-            }
+            file_count += 1;
+            reused_count += 1;
+            continue;
         }
 
-        // Update edges
-        {
-            let mut g = graph.write().unwrap();
-            g.update_edges(&path, &resolved);
+        if !add_parsed_file(&graph, &resolver, &path, parse_limits) {
+            log_line!("[affected] WARN: graph overflow during init");
+            complete = false;
+            break;
         }
 
         file_count += 1;
+        // Lets an evicted state's build be aborted between files
+        tokio::task::yield_now().await;
 
         if start.elapsed().as_secs() > GRAPH_INIT_TIMEOUT_SECS {
-            eprintln!(
+            log_line!(
                 "[affected] WARN: graph init timeout after {}s",
                 GRAPH_INIT_TIMEOUT_SECS
             );
+            complete = false;
             break;
         }
     }
 
-    let (node_count, edge_count) = {
+    // A partial graph would pass unvisited files off as parsed
+    if let Some(cache) = graph_cache.as_deref().filter(|_| complete) {
+        if let Err(e) = graph.read().unwrap().save(cache, &cache_key, built_at) {
+            log_line!(
+                "[affected] WARN: failed to save graph cache {}: {e}",
+                cache.display()
+            );
+        }
+    }
+
+    let (node_count, edge_count, leaf_count) = {
         let g = graph.read().unwrap();
-        (g.node_count(), g.edge_count())
+        (g.node_count(), g.edge_count(), g.leaf_count())
     };
 
-    eprintln!(
-        "[affected] INFO: graph build complete: {} files, {} nodes, {} edges in {}ms",
+    let unresolvable = negative_cache
+        .lock()
+        .map(|c| c.recorded())
+        .unwrap_or_default();
+    log_line!(
+        "[affected] INFO: graph build complete: {} files ({} from cache), {} nodes, {} edges, \
+         {} test leaves, {} unresolvable bare imports in {}ms",
         file_count,
+        reused_count,
         node_count,
         edge_count,
+        leaf_count,
+        unresolvable,
         start.elapsed().as_millis()
     );
+    log_hub_modules(&graph.read().unwrap(), fan_in_threshold);
+    log_import_cycles(&graph.read().unwrap());
+    if node_count == 0 && leaf_count == 0 {
+        log_line!(
+            "[affected] WARN: graph is empty under {}; selections will be full runs",
+            workspace_root.display()
+        );
+    }
 
     graph_ready.store(true, Ordering::SeqCst);
 }
 
+/// Warn about modules whose fan-in exceeds the threshold (observability only).
+fn log_hub_modules(graph: &affected::graph::DepGraph, fan_in_threshold: usize) {
+    for hub in graph.hub_modules(fan_in_threshold) {
+        log_line!(
+            "[affected] WARN: {} has {} dependents (threshold {fan_in_threshold})",
+            hub.path.display(),
+            hub.dependents
+        );
+    }
+}
+
+/// Modules of an import cycle named in its warning; the rest are counted.
+const CYCLE_LOG_PATHS: usize = 5;
+
+/// Warn about modules importing each other (observability only).
+fn log_import_cycles(graph: &affected::graph::DepGraph) {
+    for cycle in graph.find_cycles() {
+        let shown: Vec<String> = cycle
+            .iter()
+            .take(CYCLE_LOG_PATHS)
+            .map(|p| p.display().to_string())
+            .collect();
+        let more = match cycle.len().saturating_sub(CYCLE_LOG_PATHS) {
+            0 => String::new(),
+            n => format!(" and {n} more"),
+        };
+        log_line!(
+            "[affected] WARN: import cycle between {}{more}",
+            shown.join(", ")
+        );
+    }
+}
+
 fn is_ts_js_file(path: &Path) -> bool {
     let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
     matches!(
@@ -260,15 +1577,28 @@ fn is_ts_js_file(path: &Path) -> bool {
 #[tokio::main]
 async fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 3 {
-        eprintln!("Usage: zax_workspace_service <cache_dir> <workspace_root>");
-        std::process::exit(1);
-    }
-
-    let cache_dir = PathBuf::from(&args[1]);
-    let workspace_root = PathBuf::from(&args[2]);
+    let args = match parse_args(&args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
 
-    if let Err(e) = run_server(cache_dir, workspace_root).await {
+    logging::init(args.log_buffer_lines);
+    for warning in &args.warnings {
+        log_line!("[affected] WARN: {warning}");
+    }
+    // Handle SIGTERM before the port is announced, so a client that stops
+    // the service right after reading it still gets a clean shutdown
+    let shutdown = match terminate_signal() {
+        Ok(shutdown) => shutdown,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = run_server(args, shutdown).await {
         eprintln!("Error: {e}");
         std::process::exit(1);
     }
@@ -284,18 +1614,195 @@ mod tests {
     fn create_test_service() -> (WorkspaceServiceImpl, tempfile::TempDir) {
         let dir = tempdir().unwrap();
         store::init_storage(dir.path()).unwrap();
-        let conn = store::open_connection(dir.path()).unwrap();
+        let conn = store::open_connection(dir.path(), store::CacheSettings::default()).unwrap();
         let affected = AffectedState::new(dir.path().to_path_buf());
         let service = WorkspaceServiceImpl {
             state: rpc::RpcState {
-                cache_dir: dir.path().to_path_buf(),
                 conn: Arc::new(Mutex::new(conn)),
+                writers: store::WorkspaceConnections::new(
+                    dir.path(),
+                    store::CacheSettings::default(),
+                ),
+                read_only: false,
+                verify_ingest: false,
+                admin: false,
+                artifact_roots: rpc::default_artifact_roots(dir.path()),
+                detect_renames: false,
+                eslint_warnings: false,
+                lowercase_workspace_ids: false,
+                workspace_root: dir.path().to_path_buf(),
             },
-            affected: Arc::new(Mutex::new(affected)),
+            workspace_id: cache_dir_workspace_id(dir.path()),
+            affected: Arc::new(Mutex::new(Some(affected))),
+            affected_config: test_affected_config(dir.path()),
         };
         (service, dir)
     }
 
+    fn test_affected_config(workspace_root: &Path) -> AffectedConfig {
+        AffectedConfig {
+            workspace_root: workspace_root.to_path_buf(),
+            path_mode: PathMode::Canonical,
+            case_insensitive_paths: None,
+            full_run_ratio: affected::DEFAULT_FULL_RUN_RATIO,
+            parse_limits: ParseLimits::default(),
+            fan_in_threshold: affected::DEFAULT_FAN_IN_THRESHOLD,
+            max_graph_nodes: affected::DEFAULT_MAX_GRAPH_NODES,
+            exclude_test_nodes: false,
+            graph_file_list: None,
+            graph_cache: None,
+            root_alias: None,
+            lock_timeout: affected::DEFAULT_LOCK_TIMEOUT,
+            watch_max_file_size: None,
+            watch_debounce: Duration::from_millis(affected::DEFAULT_DEBOUNCE_MS),
+            max_dirty_files: affected::DEFAULT_MAX_DIRTY_FILES,
+        }
+    }
+
+    #[tokio::test]
+    async fn graph_build_seeds_from_file_list() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("a.ts"), "export const a = 1;").unwrap();
+        std::fs::write(root.join("b.ts"), "import { a } from './a';").unwrap();
+        std::fs::write(root.join("untracked.ts"), "export const u = 1;").unwrap();
+        // Listed but deleted since, as `git ls-files` reports until committed
+        let list = root.join("files.txt");
+        std::fs::write(&list, "b.ts\nREADME.md\ngone.ts\n").unwrap();
+
+        let config = AffectedConfig {
+            graph_file_list: Some(list),
+            ..test_affected_config(&root)
+        };
+        let state = AffectedState::new(root.clone());
+        let graph = Arc::clone(&state.graph);
+        let ready = Arc::clone(&state.graph_ready);
+        let negatives = Arc::clone(&state.negative_cache);
+        build_graph_async(
+            config,
+            Arc::clone(&graph),
+            ready,
+            negatives,
+            state.config_files.clone(),
+        )
+        .await;
+
+        // a.ts is reached through b.ts's import; untracked.ts is never visited
+        let graph = graph.read().unwrap();
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(
+            graph.get_dependents(&root.join("a.ts")),
+            vec![root.join("b.ts")]
+        );
+        assert!(!graph.contains(&root.join("untracked.ts")));
+    }
+
+    /// Writes `content` to `name` under `root` and backdates its mtime to `modified`.
+    fn write_at(root: &Path, name: &str, content: &str, modified: SystemTime) {
+        let path = root.join(name);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, content).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(modified).unwrap();
+    }
+
+    /// Builds the graph of `root` with `cache` as its graph cache.
+    async fn build_cached_graph(root: &Path, cache: &Path) -> affected::SharedDepGraph {
+        let config = AffectedConfig {
+            graph_cache: Some(cache.to_path_buf()),
+            ..test_affected_config(root)
+        };
+        let state = AffectedState::new(root.to_path_buf());
+        let graph = Arc::clone(&state.graph);
+        let ready = Arc::clone(&state.graph_ready);
+        let negatives = Arc::clone(&state.negative_cache);
+        build_graph_async(
+            config,
+            Arc::clone(&graph),
+            ready,
+            negatives,
+            state.config_files.clone(),
+        )
+        .await;
+        graph
+    }
+
+    #[tokio::test]
+    async fn graph_build_reparses_only_files_modified_since_cache() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let cache = dir.path().join("graph.bin");
+        let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        let write_old = |name: &str, content: &str| write_at(&root, name, content, hour_ago);
+        write_old("a.ts", "export const a = 1;");
+        write_old("b.ts", "import { a } from './a';");
+        write_old("c.ts", "export const c = 3;");
+        build_cached_graph(&root, &cache).await;
+
+        // b.ts keeps its cached edge as it looks unmodified; c.ts is parsed
+        write_old("b.ts", "export const b = 2;");
+        std::fs::write(root.join("c.ts"), "import { a } from './a';").unwrap();
+        let graph = build_cached_graph(&root, &cache).await;
+        let mut dependents = graph.read().unwrap().get_dependents(&root.join("a.ts"));
+        dependents.sort();
+        assert_eq!(dependents, vec![root.join("b.ts"), root.join("c.ts")]);
+
+        // An incompatible cache is discarded and everything re-parsed
+        let body = std::fs::read_to_string(&cache).unwrap();
+        std::fs::write(&cache, body.replacen("zax-graph 1", "zax-graph 0", 1)).unwrap();
+        let graph = build_cached_graph(&root, &cache).await;
+        let dependents = graph.read().unwrap().get_dependents(&root.join("a.ts"));
+        assert_eq!(dependents, vec![root.join("c.ts")]);
+    }
+
+    #[tokio::test]
+    async fn graph_build_links_unmodified_importer_of_created_file() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let cache = dir.path().join("graph.bin");
+        let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        write_at(&root, "b.ts", "import { a } from './a';", hour_ago);
+        build_cached_graph(&root, &cache).await;
+
+        // a.ts is created while stopped; b.ts still looks unmodified
+        std::fs::write(root.join("a.ts"), "export const a = 1;").unwrap();
+        let graph = build_cached_graph(&root, &cache).await;
+        let dependents = graph.read().unwrap().get_dependents(&root.join("a.ts"));
+        assert_eq!(dependents, vec![root.join("b.ts")]);
+    }
+
+    #[tokio::test]
+    async fn graph_build_discards_cache_after_tsconfig_change() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let cache = dir.path().join("graph.bin");
+        let write_at = |name: &str, content: &str, modified: SystemTime| {
+            write_at(&root, name, content, modified);
+        };
+        let tsconfig = |target: &str| {
+            format!(r#"{{"compilerOptions": {{"paths": {{"@lib/*": ["{target}/*"]}}}}}}"#)
+        };
+        let hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        write_at("lib/a.ts", "export const a = 1;", hour_ago);
+        write_at("other/a.ts", "export const a = 2;", hour_ago);
+        write_at("b.ts", "import { a } from '@lib/a';", hour_ago);
+        write_at("tsconfig.json", &tsconfig("lib"), hour_ago);
+        let graph = build_cached_graph(&root, &cache).await;
+        let dependents = graph.read().unwrap().get_dependents(&root.join("lib/a.ts"));
+        assert_eq!(dependents, vec![root.join("b.ts")]);
+
+        // b.ts is unmodified, but its alias now points elsewhere
+        let later = SystemTime::now() + Duration::from_secs(60);
+        write_at("tsconfig.json", &tsconfig("other"), later);
+        let graph = build_cached_graph(&root, &cache).await;
+        let graph = graph.read().unwrap();
+        assert_eq!(
+            graph.get_dependents(&root.join("other/a.ts")),
+            vec![root.join("b.ts")]
+        );
+        assert!(graph.get_dependents(&root.join("lib/a.ts")).is_empty());
+    }
+
     #[tokio::test]
     async fn ping_returns_cargo_pkg_version() {
         let (service, _dir) = create_test_service();
@@ -304,6 +1811,87 @@ mod tests {
         assert_eq!(response.get_ref().version, env!("CARGO_PKG_VERSION"));
     }
 
+    const PROTOS: [&str; 3] = [
+        include_str!("../../../proto/zax/v1/workspace.proto"),
+        include_str!("../../../proto/zax/v1/affected.proto"),
+        include_str!("../../../proto/zax/v1/artifacts.proto"),
+    ];
+
+    fn proto_lines() -> impl Iterator<Item = &'static str> {
+        PROTOS.iter().flat_map(|proto| proto.lines()).map(str::trim)
+    }
+
+    fn snake_case(name: &str) -> String {
+        let mut out = String::new();
+        for (i, c) in name.char_indices() {
+            if c.is_ascii_uppercase() && i > 0 {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        }
+        out
+    }
+
+    /// Declared RPCs and artifact kinds, named as capabilities.
+    fn proto_rpcs_and_kinds() -> Vec<String> {
+        let rpcs = proto_lines()
+            .filter_map(|line| line.strip_prefix("rpc "))
+            .filter_map(|line| line.split('(').next())
+            .map(snake_case);
+        let kinds = proto_lines()
+            .filter_map(|line| line.strip_prefix("ARTIFACT_KIND_"))
+            .filter_map(|line| line.split(' ').next())
+            .filter(|kind| *kind != "UNSPECIFIED")
+            .map(str::to_lowercase);
+        rpcs.chain(kinds).collect()
+    }
+
+    /// Field names declared in request messages and the ingest manifest.
+    fn proto_request_fields() -> std::collections::HashSet<&'static str> {
+        let mut message = "";
+        let mut fields = std::collections::HashSet::new();
+        for line in proto_lines() {
+            if let Some(rest) = line.strip_prefix("message ") {
+                message = rest.trim_end_matches(" {").trim_end_matches(" {}");
+            } else if line == "}" {
+                message = "";
+            } else if let Some((declaration, _)) = line.split_once(" = ") {
+                let is_request = message.ends_with("Request") || message.starts_with("Artifact");
+                if is_request {
+                    fields.extend(declaration.rsplit(' ').next());
+                }
+            }
+        }
+        fields
+    }
+
+    #[tokio::test]
+    async fn ping_lists_served_rpcs_artifact_kinds_and_request_fields() {
+        let (service, _dir) = create_test_service();
+        let response = service
+            .ping(Request::new(PingRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.api_version, API_VERSION);
+        assert_eq!(response.capabilities, CAPABILITIES);
+
+        let declared = proto_rpcs_and_kinds();
+        for name in &declared {
+            assert!(CAPABILITIES.contains(&name.as_str()), "{name} missing");
+        }
+        let fields = proto_request_fields();
+        for capability in CAPABILITIES
+            .iter()
+            .filter(|c| !declared.iter().any(|d| d == *c))
+        {
+            assert!(
+                fields.contains(capability),
+                "{capability} is not a request field"
+            );
+        }
+    }
+
     #[tokio::test]
     async fn ping_version_is_semver() {
         let (service, _dir) = create_test_service();
@@ -320,7 +1908,7 @@ mod tests {
     #[tokio::test]
     async fn write_port_file_creates_file() {
         let dir = tempdir().unwrap();
-        write_port_file(dir.path(), 12345).await.unwrap();
+        write_port_file(dir.path(), PORT_FILE, 12345).await.unwrap();
         let content = tokio::fs::read_to_string(dir.path().join("rust.port"))
             .await
             .unwrap();
@@ -330,7 +1918,7 @@ mod tests {
     #[tokio::test]
     async fn write_port_file_is_atomic() {
         let dir = tempdir().unwrap();
-        write_port_file(dir.path(), 54321).await.unwrap();
+        write_port_file(dir.path(), PORT_FILE, 54321).await.unwrap();
         // tmp file should not exist after atomic write
         assert!(!dir.path().join("rust.port.tmp").exists());
         // final file should exist
@@ -340,11 +1928,567 @@ mod tests {
     #[tokio::test]
     async fn write_port_file_overwrites_existing() {
         let dir = tempdir().unwrap();
-        write_port_file(dir.path(), 11111).await.unwrap();
-        write_port_file(dir.path(), 22222).await.unwrap();
+        write_port_file(dir.path(), PORT_FILE, 11111).await.unwrap();
+        write_port_file(dir.path(), PORT_FILE, 22222).await.unwrap();
         let content = tokio::fs::read_to_string(dir.path().join("rust.port"))
             .await
             .unwrap();
         assert_eq!(content, "22222");
     }
+
+    /// Wait for a starting server to announce its port in `name`.
+    async fn read_port_file(cache_dir: &Path, name: &str) -> u16 {
+        for _ in 0..200 {
+            if let Ok(port) = tokio::fs::read_to_string(cache_dir.join(name)).await {
+                return port.parse().unwrap();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        std::panic::resume_unwind(Box::new(format!("server did not write {name}")));
+    }
+
+    async fn read_port(cache_dir: &Path) -> u16 {
+        read_port_file(cache_dir, PORT_FILE).await
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn read_only_start_leaves_primary_port_file_untouched() {
+        let cache = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        store::init_storage(cache.path()).unwrap();
+        std::fs::write(cache.path().join(PORT_FILE), "11111").unwrap();
+        let server_args = parse_args(&args(&[
+            "zax",
+            "--read-only",
+            cache.path().to_str().unwrap(),
+            workspace.path().to_str().unwrap(),
+        ]))
+        .unwrap();
+        let server = tokio::spawn(run_server(server_args, std::future::pending()));
+
+        let port = read_port_file(cache.path(), REPLICA_PORT_FILE).await;
+        server.abort();
+        assert_ne!(port, 11111);
+        let primary = std::fs::read_to_string(cache.path().join(PORT_FILE)).unwrap();
+        assert_eq!(primary, "11111");
+    }
+
+    /// Write a failing Vitest artifact under the default artifact root.
+    fn vitest_manifest(cache_dir: &Path) -> zax::v1::ArtifactManifest {
+        use zax::v1::{ArtifactKind, ArtifactManifest, ArtifactRef};
+        let artifacts = cache_dir.join("artifacts");
+        std::fs::create_dir_all(&artifacts).unwrap();
+        let vitest_json = r#"{"testResults":[{"name":"t.ts","status":"failed","assertionResults":[{"title":"t","status":"failed","failureMessages":["e"]}]}]}"#;
+        std::fs::write(artifacts.join("vitest.json"), vitest_json).unwrap();
+        ArtifactManifest {
+            workspace_id: "ws1".into(),
+            artifacts: vec![ArtifactRef {
+                artifact_id: "a1".into(),
+                kind: ArtifactKind::TestFailure as i32,
+                path: artifacts.join("vitest.json").to_string_lossy().into_owned(),
+                hash: String::new(),
+                min_severity: 0,
+            }],
+            ..ArtifactManifest::default()
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn serves_ingest_and_delta_over_grpc() {
+        use zax::v1::workspace_service_client::WorkspaceServiceClient;
+
+        let cache = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let server_args = parse_args(&args(&[
+            "zax",
+            cache.path().to_str().unwrap(),
+            workspace.path().to_str().unwrap(),
+        ]))
+        .unwrap();
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let server = tokio::spawn(run_server(server_args, async {
+            let _ = stopped.await;
+        }));
+        let port = read_port(cache.path()).await;
+        let mut client = WorkspaceServiceClient::connect(format!("http://127.0.0.1:{port}"))
+            .await
+            .unwrap();
+
+        let ping = client.ping(PingRequest {}).await.unwrap().into_inner();
+        assert_eq!(ping.version, env!("CARGO_PKG_VERSION"));
+
+        let ingest = IngestManifestRequest {
+            manifest: Some(vitest_manifest(cache.path())),
+            ..Default::default()
+        };
+        let response = client.ingest_manifest(ingest).await.unwrap();
+        assert_eq!(response.into_inner().run_id.len(), 32);
+
+        let request = GetDeltaSummaryRequest {
+            workspace_id: "ws1".into(),
+            ..Default::default()
+        };
+        let summary = client
+            .get_delta_summary(request)
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(summary.new_test_failures, 1);
+
+        stop.send(()).unwrap();
+        let stopped = tokio::time::timeout(Duration::from_secs(10), server).await;
+        assert!(stopped.unwrap().unwrap().is_ok());
+    }
+
+    /// Set in the child process `sigterm_stops_the_server_cleanly` spawns:
+    /// its cache and workspace directories, separated by a newline.
+    const SIGTERM_CHILD_ENV: &str = "ZAX_SIGTERM_CHILD_DIRS";
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sigterm_stops_the_server_cleanly() {
+        // The signal goes to a child process running only this test, so
+        // servers of other tests in this process are unaffected
+        if let Ok(dirs) = env::var(SIGTERM_CHILD_ENV) {
+            let (cache, workspace) = dirs.split_once('\n').unwrap();
+            let server_args = parse_args(&args(&["zax", cache, workspace])).unwrap();
+            run_server(server_args, terminate_signal().unwrap())
+                .await
+                .unwrap();
+            return;
+        }
+        let cache = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let dirs = format!("{}\n{}", cache.path().display(), workspace.path().display());
+        let mut child = std::process::Command::new(env::current_exe().unwrap())
+            .args(["--exact", "tests::sigterm_stops_the_server_cleanly"])
+            .env(SIGTERM_CHILD_ENV, dirs)
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        read_port(cache.path()).await;
+
+        let pid = child.id().to_string();
+        let kill = std::process::Command::new("kill")
+            .args(["-TERM", &pid])
+            .status();
+        assert!(kill.unwrap().success());
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        let status = loop {
+            if let Some(status) = child.try_wait().unwrap() {
+                break status;
+            }
+            if std::time::Instant::now() > deadline {
+                let _ = child.kill();
+                std::panic::resume_unwind(Box::new("server ignored SIGTERM"));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        };
+        // Killed by the signal itself unless the handler shut down cleanly
+        assert!(status.success(), "{status}");
+    }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn parse_args_positional() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws"])).unwrap();
+        assert_eq!(parsed.cache_dir, PathBuf::from("/cache"));
+        assert_eq!(parsed.workspace_root, PathBuf::from("/ws"));
+        assert!(!parsed.read_only);
+        assert_eq!(parsed.path_mode, PathMode::Canonical);
+        assert_eq!(parsed.log_buffer_lines, logging::DEFAULT_CAPACITY);
+        assert!((parsed.full_run_ratio - affected::DEFAULT_FULL_RUN_RATIO).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn parse_args_logical_paths_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--logical-paths"])).unwrap();
+        assert_eq!(parsed.path_mode, PathMode::Logical);
+    }
+
+    #[test]
+    fn parse_args_path_case_flag() {
+        let parse_case = |flags: &[&str]| {
+            let mut argv = vec!["zax", "/cache", "/ws"];
+            argv.extend_from_slice(flags);
+            parse_args(&args(&argv)).map(|a| a.case_insensitive_paths)
+        };
+        assert_eq!(parse_case(&[]), Ok(None));
+        assert_eq!(parse_case(&["--path-case=insensitive"]), Ok(Some(true)));
+        assert_eq!(
+            parse_case(&["--path-case=insensitive", "--path-case=auto"]),
+            Ok(None)
+        );
+        assert_eq!(parse_case(&["--path-case=sensitive"]), Ok(Some(false)));
+        assert!(parse_case(&["--path-case=upper"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_log_buffer_lines_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--log-buffer-lines=50"])).unwrap();
+        assert_eq!(parsed.log_buffer_lines, 50);
+        assert!(parse_args(&args(&["zax", "/cache", "/ws", "--log-buffer-lines=x"])).is_err());
+    }
+
+    #[test]
+    fn parse_args_parse_limit_flags() {
+        let parsed = parse_args(&args(&[
+            "zax",
+            "/cache",
+            "/ws",
+            "--max-line-length=500",
+            "--max-file-size=2048",
+            "--max-imports=5000",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.parse_limits.max_line_length, 500);
+        assert_eq!(parsed.parse_limits.max_file_size, 2048);
+        assert_eq!(parsed.parse_limits.max_imports, 5000);
+    }
+
+    #[test]
+    fn parse_args_fan_in_threshold_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--fan-in-threshold=5"])).unwrap();
+        assert_eq!(parsed.fan_in_threshold, 5);
+    }
+
+    #[test]
+    fn watcher_settings_fall_back_to_defaults_on_invalid_input() {
+        let flags = [
+            "zax",
+            "/cache",
+            "/ws",
+            "--watch-debounce-ms=250",
+            "--max-dirty-files=0",
+        ];
+        let parsed = parse_args(&args(&flags)).unwrap();
+        assert_eq!(parsed.watch_debounce_ms, 250);
+        assert_eq!(parsed.max_dirty_files, affected::DEFAULT_MAX_DIRTY_FILES);
+        assert_eq!(parsed.warnings.len(), 1);
+
+        let mut parsed = parse_args(&args(&["zax", "/cache", "/ws"])).unwrap();
+        apply_watch_env(&mut parsed, |name| match name {
+            "ZAX_DEBOUNCE_MS" => Some("soon".to_string()),
+            "ZAX_MAX_DIRTY_FILES" => Some("2000".to_string()),
+            _ => None,
+        });
+        assert_eq!(parsed.watch_debounce_ms, affected::DEFAULT_DEBOUNCE_MS);
+        assert_eq!(parsed.max_dirty_files, 2000);
+    }
+
+    #[test]
+    fn parse_args_max_graph_nodes_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws"])).unwrap();
+        assert_eq!(parsed.max_graph_nodes, affected::DEFAULT_MAX_GRAPH_NODES);
+        let parsed =
+            parse_args(&args(&["zax", "/cache", "/ws", "--max-graph-nodes=50000"])).unwrap();
+        assert_eq!(parsed.max_graph_nodes, 50_000);
+    }
+
+    #[test]
+    fn parse_args_full_run_ratio_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--full-run-ratio=0.5"])).unwrap();
+        assert!((parsed.full_run_ratio - 0.5).abs() < f64::EPSILON);
+        assert!(parse_args(&args(&["zax", "/cache", "/ws", "--full-run-ratio=0"])).is_err());
+        assert!(parse_args(&args(&["zax", "/cache", "/ws", "--full-run-ratio=NaN"])).is_err());
+    }
+
+    #[test]
+    fn parse_args_read_only_flag() {
+        let parsed = parse_args(&args(&["zax", "--read-only", "/cache", "/ws"])).unwrap();
+        assert!(parsed.read_only);
+        assert_eq!(parsed.workspace_root, PathBuf::from("/ws"));
+    }
+
+    #[test]
+    fn parse_args_wal_checkpoint_secs_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws"])).unwrap();
+        assert_eq!(parsed.wal_checkpoint_secs, DEFAULT_WAL_CHECKPOINT_SECS);
+        let parsed =
+            parse_args(&args(&["zax", "/cache", "/ws", "--wal-checkpoint-secs=0"])).unwrap();
+        assert_eq!(parsed.wal_checkpoint_secs, 0);
+    }
+
+    #[test]
+    fn parse_args_verify_ingest_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--verify-ingest"])).unwrap();
+        assert!(parsed.verify_ingest);
+    }
+
+    #[test]
+    fn parse_args_affected_timeout_flag() {
+        let parsed = parse_args(&args(&[
+            "zax",
+            "/cache",
+            "/ws",
+            "--affected-timeout-ms=250",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.lock_timeout, Duration::from_millis(250));
+        assert!(parse_args(&args(&["zax", "/cache", "/ws", "--affected-timeout-ms=x"])).is_err());
+    }
+
+    #[test]
+    fn parse_args_watch_max_file_size_flag() {
+        assert_eq!(
+            parse_args(&args(&["zax", "/cache", "/ws"]))
+                .unwrap()
+                .watch_max_file_size,
+            None
+        );
+        let parsed = parse_args(&args(&[
+            "zax",
+            "/cache",
+            "/ws",
+            "--watch-max-file-size=4096",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.watch_max_file_size, Some(4096));
+    }
+
+    #[test]
+    fn parse_args_sqlite_cache_flags() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws"])).unwrap();
+        assert_eq!(parsed.sqlite_cache, store::CacheSettings::default());
+        let parsed = parse_args(&args(&[
+            "zax",
+            "/cache",
+            "/ws",
+            "--sqlite-cache-mib=128",
+            "--sqlite-mmap-mib=0",
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed.sqlite_cache,
+            store::CacheSettings {
+                cache_mib: 128,
+                mmap_mib: 0
+            }
+        );
+    }
+
+    #[test]
+    fn parse_args_artifact_root_flag_repeats() {
+        let parsed = parse_args(&args(&[
+            "zax",
+            "/cache",
+            "/ws",
+            "--artifact-root=/shared",
+            "--artifact-root=/tmp/run",
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed.artifact_roots,
+            vec![PathBuf::from("/shared"), PathBuf::from("/tmp/run")]
+        );
+    }
+
+    #[test]
+    fn parse_args_root_alias_flag() {
+        assert_eq!(
+            parse_args(&args(&["zax", "/cache", "/ws"]))
+                .unwrap()
+                .root_alias,
+            None
+        );
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--root-alias"])).unwrap();
+        assert_eq!(parsed.root_alias.as_deref(), Some("~/"));
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--root-alias=#/"])).unwrap();
+        assert_eq!(parsed.root_alias.as_deref(), Some("#/"));
+    }
+
+    #[test]
+    fn parse_args_lowercase_workspace_ids_flag() {
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws"])).unwrap();
+        assert!(!parsed.lowercase_workspace_ids);
+        let parsed = parse_args(&args(&[
+            "zax",
+            "/cache",
+            "/ws",
+            "--lowercase-workspace-ids",
+        ]));
+        assert!(parsed.unwrap().lowercase_workspace_ids);
+    }
+
+    #[test]
+    fn parse_args_admin_flag() {
+        assert!(!parse_args(&args(&["zax", "/cache", "/ws"])).unwrap().admin);
+        assert!(
+            parse_args(&args(&["zax", "/cache", "/ws", "--admin"]))
+                .unwrap()
+                .admin
+        );
+    }
+
+    #[test]
+    fn parse_args_detect_renames_flag() {
+        assert!(
+            !parse_args(&args(&["zax", "/cache", "/ws"]))
+                .unwrap()
+                .detect_renames
+        );
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--detect-renames"])).unwrap();
+        assert!(parsed.detect_renames);
+    }
+
+    #[test]
+    fn parse_args_graph_file_list_flag() {
+        assert_eq!(
+            parse_args(&args(&["zax", "/cache", "/ws"]))
+                .unwrap()
+                .graph_file_list,
+            None
+        );
+        let parsed = parse_args(&args(&[
+            "zax",
+            "/cache",
+            "/ws",
+            "--graph-file-list=/tmp/files",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.graph_file_list, Some(PathBuf::from("/tmp/files")));
+    }
+
+    #[test]
+    fn parse_args_exclude_test_nodes_flag() {
+        assert!(
+            !parse_args(&args(&["zax", "/cache", "/ws"]))
+                .unwrap()
+                .exclude_test_nodes
+        );
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--exclude-test-nodes"])).unwrap();
+        assert!(parsed.exclude_test_nodes);
+    }
+
+    #[test]
+    fn parse_args_eslint_warnings_flag() {
+        assert!(
+            !parse_args(&args(&["zax", "/cache", "/ws"]))
+                .unwrap()
+                .eslint_warnings
+        );
+        let parsed = parse_args(&args(&["zax", "/cache", "/ws", "--eslint-warnings"])).unwrap();
+        assert!(parsed.eslint_warnings);
+    }
+
+    #[test]
+    fn parse_args_rejects_root_inside_node_modules() {
+        let err = parse_args(&args(&["zax", "/cache", "/ws/node_modules/pkg"])).unwrap_err();
+        assert!(err.contains("node_modules"));
+        assert!(parse_args(&args(&["zax", "/cache", "/ws/node_modules_backup"])).is_ok());
+
+        let dir = tempdir().unwrap();
+        let installed = dir.path().join("node_modules/pkg");
+        std::fs::create_dir_all(&installed).unwrap();
+        let link = dir.path().join("linked");
+        std::os::unix::fs::symlink(&installed, &link).unwrap();
+        assert!(parse_args(&args(&["zax", "/cache", link.to_str().unwrap()])).is_err());
+    }
+
+    #[test]
+    fn parse_args_rejects_missing_and_unknown() {
+        assert!(parse_args(&args(&["zax", "/cache"])).is_err());
+        assert!(parse_args(&args(&["zax", "/cache", "/ws", "--bogus"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn read_only_rejects_affected_selection() {
+        let (mut service, _dir) = create_test_service();
+        service.state.read_only = true;
+        let request = Request::new(GetAffectedTestsRequest::default());
+        let err = service.get_affected_tests(request).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn evicted_state_is_rebuilt_on_next_query() {
+        let (service, dir) = create_test_service();
+        std::fs::write(dir.path().join("a.test.ts"), "test('a', () => {});").unwrap();
+        let request = |workspace_id: &str| {
+            Request::new(EvictWorkspaceRequest {
+                workspace_id: workspace_id.into(),
+            })
+        };
+        let err = service.evict_workspace(request(" ")).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        let other = service.evict_workspace(request("other")).await.unwrap();
+        assert!(!other.get_ref().evicted);
+        assert!(service.affected.lock().unwrap().is_some());
+
+        let served = service.workspace_id.clone();
+        let evict = || request(&served);
+
+        assert!(
+            service
+                .evict_workspace(evict())
+                .await
+                .unwrap()
+                .get_ref()
+                .evicted
+        );
+        assert!(service.affected.lock().unwrap().is_none());
+        assert!(
+            !service
+                .evict_workspace(evict())
+                .await
+                .unwrap()
+                .get_ref()
+                .evicted
+        );
+
+        let request = Request::new(GetAffectedTestsRequest {
+            force_full: true,
+            ..Default::default()
+        });
+        let response = service.get_affected_tests(request).await.unwrap();
+        assert_eq!(response.get_ref().test_files, vec!["a.test.ts"]);
+        assert!(service.affected.lock().unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn dropping_affected_state_aborts_its_graph_build() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.ts"), "export const a = 1;").unwrap();
+        let state = start_affected(&test_affected_config(dir.path()));
+        let graph = Arc::clone(&state.graph);
+        let ready = Arc::clone(&state.graph_ready);
+
+        // The single-threaded test runtime has not polled the build yet
+        drop(state);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!ready.load(std::sync::atomic::Ordering::SeqCst));
+        assert_eq!(Arc::strong_count(&graph), 1);
+    }
+
+    #[test]
+    fn to_file_change_rejects_unspecified_kind() {
+        let change = zax::v1::FileChange {
+            path: "a.ts".into(),
+            kind: ChangeKind::Unspecified as i32,
+        };
+        assert!(to_file_change(&change).is_err());
+
+        let change = zax::v1::FileChange {
+            path: "a.ts".into(),
+            kind: ChangeKind::Deleted as i32,
+        };
+        let converted = to_file_change(&change).unwrap();
+        assert_eq!(converted.kind, affected::diff::ChangeKind::Deleted);
+    }
+
+    #[test]
+    fn to_range_change_rejects_invalid_ranges() {
+        let file = |start_line, end_line| zax::v1::FileRanges {
+            path: "a.ts".into(),
+            ranges: vec![zax::v1::LineRange {
+                start_line,
+                end_line,
+            }],
+        };
+        assert!(to_range_change(&file(0, 3)).is_err());
+        assert!(to_range_change(&file(4, 3)).is_err());
+        assert_eq!(to_range_change(&file(2, 3)).unwrap().ranges, vec![(2, 3)]);
+    }
 }
@@ -2,3 +2,4 @@
 
 pub mod path;
 pub mod stable_id;
+pub mod workspace;
@@ -1,7 +1,11 @@
 //! Path normalization utilities for cross-platform consistency.
 //!
-//! Normalizes paths to forward slashes and validates package scope values.
+//! Normalizes paths to forward slashes (folding Windows drive letters),
+//! validates package scope values, and resolves filesystem paths to a
+//! comparable absolute form.
 
+use std::io;
+use std::path::{Component, Path, PathBuf};
 use thiserror::Error;
 
 /// Errors that can occur during path normalization.
@@ -13,13 +17,14 @@ pub enum PathError {
     InvalidChars,
     #[error("path exceeds maximum length of {0} characters")]
     TooLong(usize),
+    #[error("path must be workspace-relative, not start with drive '{0}:'")]
+    DrivePath(char),
 }
 
 /// Maximum length for package scope paths (1024 characters).
 const MAX_PACKAGE_SCOPE_LEN: usize = 1024;
 
 /// Normalizes a path to use forward slashes only.
-#[allow(dead_code)]
 ///
 /// - Converts backslashes to forward slashes
 /// - Collapses consecutive slashes
@@ -43,11 +48,117 @@ pub fn normalize_slashes(path: &str) -> String {
     result
 }
 
+/// Normalizes a path that may come from Windows to forward slashes, with
+/// an uppercase drive letter: `c:\repo\src` → `C:/repo/src`.
+///
+/// Drive letters are case-insensitive, so both spellings of a path compare
+/// equal after normalization. Other paths are only slash-normalized.
+pub fn normalize_drive_path(path: &str) -> String {
+    let slashed = normalize_slashes(path);
+    match drive_letter(&slashed) {
+        Some(drive) => format!("{}{}", drive.to_ascii_uppercase(), &slashed[1..]),
+        None => slashed,
+    }
+}
+
+/// The drive letter of a Windows path such as `C:/repo` or `c:`.
+fn drive_letter(path: &str) -> Option<char> {
+    let mut chars = path.chars();
+    let drive = chars.next().filter(char::is_ascii_alphabetic)?;
+    let rest = chars.as_str().strip_prefix(':')?;
+    (rest.is_empty() || rest.starts_with(['/', '\\'])).then_some(drive)
+}
+
+/// How filesystem paths are resolved to their comparable absolute form.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathMode {
+    /// `canonicalize`: resolves symlinks, at the cost of a syscall per component.
+    #[default]
+    Canonical,
+    /// Lexical `.`/`..` resolution against the current directory. Identical to
+    /// `Canonical` when the workspace has no symlinks, and much cheaper on
+    /// slow (e.g. network) filesystems.
+    Logical,
+}
+
+impl PathMode {
+    /// Resolves a path to absolute form.
+    ///
+    /// Like `canonicalize`, fails if the path does not exist in either mode.
+    pub fn resolve(self, path: &Path) -> io::Result<PathBuf> {
+        match self {
+            Self::Canonical => path.canonicalize(),
+            Self::Logical => {
+                let resolved = normalize_logical(&std::path::absolute(path)?);
+                if resolved.exists() {
+                    Ok(resolved)
+                } else {
+                    Err(io::Error::from(io::ErrorKind::NotFound))
+                }
+            }
+        }
+    }
+}
+
+/// Detects whether the filesystem holding `dir` is case-insensitive by
+/// looking `dir` up under a case-swapped name. Falls back to the platform
+/// default (insensitive on macOS and Windows) when the name has no letters.
+pub fn is_case_insensitive(dir: &Path) -> bool {
+    let platform_default = cfg!(any(target_os = "macos", target_os = "windows"));
+    let Some(name) = dir.file_name().and_then(|n| n.to_str()) else {
+        return platform_default;
+    };
+    let swapped: String = name
+        .chars()
+        .map(|c| {
+            if c.is_lowercase() {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect();
+    if swapped == name {
+        return platform_default;
+    }
+    match (dir.metadata(), dir.with_file_name(&swapped).metadata()) {
+        (Ok(original), Ok(variant)) => same_file(&original, &variant),
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+fn same_file(a: &std::fs::Metadata, b: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+#[cfg(not(unix))]
+fn same_file(_a: &std::fs::Metadata, _b: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// Lexically resolves `.` and `..` components without touching the filesystem.
+pub fn normalize_logical(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
 /// Validates a package scope string for security and correctness.
 ///
 /// A valid package scope:
 /// - Contains only alphanumeric, hyphens, underscores, forward slashes, periods
 /// - Has no `..` path components
+/// - Is workspace-relative: no Windows drive (`C:/...`)
 /// - Is under 1024 characters
 /// - Empty string is valid (means no scoping)
 pub fn validate_package_scope(scope: &str) -> Result<(), PathError> {
@@ -56,6 +167,11 @@ pub fn validate_package_scope(scope: &str) -> Result<(), PathError> {
         return Ok(());
     }
 
+    // Absolute Windows paths are the likely source of a ':'; say so
+    if let Some(drive) = drive_letter(scope) {
+        return Err(PathError::DrivePath(drive));
+    }
+
     // Check length
     if scope.len() > MAX_PACKAGE_SCOPE_LEN {
         return Err(PathError::TooLong(MAX_PACKAGE_SCOPE_LEN));
@@ -70,13 +186,7 @@ pub fn validate_package_scope(scope: &str) -> Result<(), PathError> {
 
     // Check for invalid characters (allow alphanumeric, hyphen, underscore, slash, dot)
     for c in scope.chars() {
-        if !c.is_ascii_alphanumeric()
-            && c != '-'
-            && c != '_'
-            && c != '/'
-            && c != '.'
-            && c != '@'
-        {
+        if !c.is_ascii_alphanumeric() && c != '-' && c != '_' && c != '/' && c != '.' && c != '@' {
             return Err(PathError::InvalidChars);
         }
     }
@@ -134,10 +244,7 @@ mod tests {
             validate_package_scope("packages/../secrets"),
             Err(PathError::PathTraversal)
         );
-        assert_eq!(
-            validate_package_scope(".."),
-            Err(PathError::PathTraversal)
-        );
+        assert_eq!(validate_package_scope(".."), Err(PathError::PathTraversal));
         assert_eq!(
             validate_package_scope("../foo"),
             Err(PathError::PathTraversal)
@@ -160,6 +267,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn normalize_drive_path_folds_windows_absolute_paths() {
+        assert_eq!(
+            normalize_drive_path("c:\\repo\\packages\\auth"),
+            "C:/repo/packages/auth"
+        );
+        assert_eq!(normalize_drive_path("C:/repo"), "C:/repo");
+        assert_eq!(normalize_drive_path("d:"), "D:");
+        // Not a drive: a relative path with a colon later on
+        assert_eq!(normalize_drive_path("src\\a:b"), "src/a:b");
+        assert_eq!(normalize_drive_path("ab:/x"), "ab:/x");
+    }
+
+    #[test]
+    fn validate_rejects_drive_scope_with_clear_error() {
+        let err = validate_package_scope("C:\\repo\\packages\\auth").unwrap_err();
+        assert_eq!(err, PathError::DrivePath('C'));
+        assert_eq!(
+            err.to_string(),
+            "path must be workspace-relative, not start with drive 'C:'"
+        );
+        assert_eq!(
+            validate_package_scope("c:/repo"),
+            Err(PathError::DrivePath('c'))
+        );
+        assert_eq!(
+            validate_package_scope("packages/a:b"),
+            Err(PathError::InvalidChars)
+        );
+    }
+
     #[test]
     fn validate_rejects_too_long() {
         let long = "a".repeat(MAX_PACKAGE_SCOPE_LEN + 1);
@@ -174,4 +312,57 @@ mod tests {
         let max = "a".repeat(MAX_PACKAGE_SCOPE_LEN);
         assert!(validate_package_scope(&max).is_ok());
     }
+
+    #[test]
+    fn normalize_logical_resolves_dots() {
+        assert_eq!(
+            normalize_logical(Path::new("/ws/src/./lib/../a.ts")),
+            PathBuf::from("/ws/src/a.ts")
+        );
+        assert_eq!(
+            normalize_logical(Path::new("/../a.ts")),
+            PathBuf::from("/a.ts")
+        );
+    }
+
+    #[test]
+    fn logical_matches_canonical_without_symlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("src/lib")).unwrap();
+        std::fs::write(root.join("src/lib/a.ts"), "").unwrap();
+
+        for path in [
+            root.join("src/lib/a.ts"),
+            root.join("src/./lib/a.ts"),
+            root.join("src/lib/../lib/a.ts"),
+            root.join("src"),
+        ] {
+            assert_eq!(
+                PathMode::Logical.resolve(&path).unwrap(),
+                PathMode::Canonical.resolve(&path).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn logical_fails_for_missing_path_like_canonical() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.ts");
+        assert!(PathMode::Logical.resolve(&missing).is_err());
+        assert!(PathMode::Canonical.resolve(&missing).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn distinct_case_variants_are_case_sensitive() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("Workspace");
+        std::fs::create_dir(&root).unwrap();
+        assert!(!is_case_insensitive(&root));
+
+        // A separate directory that only differs in case is not the same file
+        std::fs::create_dir(dir.path().join("wORKSPACE")).unwrap();
+        assert!(!is_case_insensitive(&root));
+    }
 }
@@ -2,23 +2,74 @@
 //!
 //! Generates deterministic identifiers for test failures based on file path
 //! and test ID. The same inputs always produce the same stable ID.
+//! An optional per-workspace salt keeps IDs distinct across workspaces that
+//! share a database.
+//!
+//! Version 1 inputs join `<file>:<test_id>`, so a `:` inside either part can
+//! make two different pairs hash identically. Version 2 length-prefixes the
+//! file instead; it changes every ID, so clients opt in per manifest.
+//!
+//! IDs are truncated to `DEFAULT_ID_LENGTH` hex characters unless a manifest
+//! opts into longer ones (up to the full 64) to lower collision odds on very
+//! large workspaces; like a version switch, that changes every ID.
+
+/// `<file>:<test_id>`, the original scheme. Also used for version 0.
+pub const VERSION_LEGACY: u32 = 1;
+/// `<file byte length>:<file>:<test_id>`, unambiguous for any inputs.
+pub const VERSION_LENGTH_PREFIXED: u32 = 2;
+/// Hex characters kept from the BLAKE3 digest by default.
+pub const DEFAULT_ID_LENGTH: usize = 32;
+/// Hex characters in a full BLAKE3 digest.
+pub const MAX_ID_LENGTH: usize = 64;
 
 /// Computes a stable ID for a test failure.
 ///
-/// The stable ID is computed as the BLAKE3 hash of `<file>:<test_id>`,
-/// truncated to the first 32 hexadecimal characters (lowercase).
+/// The stable ID is the BLAKE3 hash of the `version` scheme's input (for
+/// the legacy scheme `<file>:<test_id>`), truncated to the first `length`
+/// hexadecimal characters (lowercase; see `id_length`).
 ///
 /// # Arguments
 /// * `file` - Workspace-relative file path
 /// * `test_id` - Canonical test identifier
 ///
 /// # Returns
-/// A 32-character lowercase hex string
-pub fn compute(file: &str, test_id: &str) -> String {
-    let input = format!("{file}:{test_id}");
+/// A lowercase hex string, or `None` for an unknown version
+pub fn compute_versioned(file: &str, test_id: &str, version: u32, length: usize) -> Option<String> {
+    let input = match version {
+        0 | VERSION_LEGACY => format!("{file}:{test_id}"),
+        VERSION_LENGTH_PREFIXED => format!("{}:{file}:{test_id}", file.len()),
+        _ => return None,
+    };
+    Some(hash_hex(&input, length))
+}
+
+/// Resolves a requested ID length: 0 means `DEFAULT_ID_LENGTH`. Returns
+/// `None` outside `DEFAULT_ID_LENGTH..=MAX_ID_LENGTH`.
+pub fn id_length(requested: u32) -> Option<usize> {
+    match usize::try_from(requested).ok()? {
+        0 => Some(DEFAULT_ID_LENGTH),
+        length @ DEFAULT_ID_LENGTH..=MAX_ID_LENGTH => Some(length),
+        _ => None,
+    }
+}
+
+/// Lowercase hex BLAKE3 digest of `input`, truncated to `length` characters.
+pub fn hash_hex(input: &str, length: usize) -> String {
     let hash = blake3::hash(input.as_bytes());
     let hex = hash.to_hex();
-    hex[..32].to_lowercase()
+    hex[..length.min(MAX_ID_LENGTH)].to_lowercase()
+}
+
+/// Mixes a per-workspace salt into an already computed stable ID.
+///
+/// An empty salt returns the ID unchanged, so single-workspace databases keep
+/// their existing IDs. Applies to test failure and finding IDs alike. The
+/// salted ID keeps the length of `id`.
+pub fn salted(id: String, salt: &str) -> String {
+    if salt.is_empty() {
+        return id;
+    }
+    hash_hex(&format!("{salt}\0{id}"), id.len())
 }
 
 #[cfg(test)]
@@ -26,6 +77,10 @@ pub fn compute(file: &str, test_id: &str) -> String {
 mod tests {
     use super::*;
 
+    fn compute(file: &str, test_id: &str) -> String {
+        compute_versioned(file, test_id, VERSION_LEGACY, DEFAULT_ID_LENGTH).unwrap()
+    }
+
     #[test]
     fn same_input_produces_same_stable_id() {
         let id1 = compute("src/math.test.ts", "Math > add > handles negatives");
@@ -54,4 +109,59 @@ mod tests {
         let id2 = compute("src/b.test.ts", "test");
         assert_ne!(id1, id2);
     }
+
+    #[test]
+    fn length_prefixed_ids_do_not_collide_on_embedded_separator() {
+        // Both join to "a:b:c" under the legacy scheme
+        assert_eq!(compute("a:b", "c"), compute("a", "b:c"));
+        let v2 = |file, test_id| {
+            compute_versioned(file, test_id, VERSION_LENGTH_PREFIXED, DEFAULT_ID_LENGTH).unwrap()
+        };
+        assert_ne!(v2("a:b", "c"), v2("a", "b:c"));
+        assert_eq!(v2("a", "b:c").len(), 32);
+    }
+
+    #[test]
+    fn legacy_version_keeps_existing_ids() {
+        let id = compute("src/a.test.ts", "test");
+        let legacy = |version| compute_versioned("src/a.test.ts", "test", version, 32);
+        assert_eq!(legacy(0), Some(id.clone()));
+        assert_eq!(legacy(VERSION_LEGACY), Some(id));
+        assert_eq!(legacy(3), None);
+    }
+
+    #[test]
+    fn configured_length_is_applied_deterministically() {
+        let length = id_length(48).unwrap();
+        let id = compute_versioned("src/a.test.ts", "test", VERSION_LEGACY, length).unwrap();
+        assert_eq!(id.len(), 48);
+        assert_eq!(
+            Some(&id),
+            compute_versioned("src/a.test.ts", "test", 1, 48).as_ref()
+        );
+        // A longer ID extends the default one
+        assert!(id.starts_with(&compute("src/a.test.ts", "test")));
+        assert_eq!(salted(id, "workspace-a").len(), 48);
+
+        assert_eq!(id_length(0), Some(DEFAULT_ID_LENGTH));
+        assert_eq!(id_length(64), Some(MAX_ID_LENGTH));
+        assert_eq!((id_length(16), id_length(65)), (None, None));
+    }
+
+    #[test]
+    fn different_salts_produce_different_ids() {
+        let id = compute("src/a.test.ts", "test");
+        let a = salted(id.clone(), "workspace-a");
+        let b = salted(id.clone(), "workspace-b");
+        assert_ne!(a, b);
+        assert_ne!(a, id);
+        assert_eq!(a.len(), 32);
+        assert_eq!(a, salted(id, "workspace-a"));
+    }
+
+    #[test]
+    fn empty_salt_leaves_id_unchanged() {
+        let id = compute("src/a.test.ts", "test");
+        assert_eq!(salted(id.clone(), ""), id);
+    }
 }
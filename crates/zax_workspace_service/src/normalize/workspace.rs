@@ -0,0 +1,170 @@
+//! Workspace identifier normalization.
+//!
+//! Ensures the same logical workspace always maps to the same run history,
+//! regardless of surrounding whitespace, separators, or trailing slashes.
+//!
+//! Relative and absolute ids are canonicalized against a base directory: the
+//! service passes the parent of the workspace root it serves, so the
+//! workspace's directory name (`ws1`) and its absolute path (`/home/u/ws1`)
+//! name the same history. Absolute ids outside the base stay absolute, and
+//! opaque ids that are not paths are unaffected.
+
+use super::path::normalize_drive_path;
+use thiserror::Error;
+
+/// Errors that can occur during workspace id normalization.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum WorkspaceIdError {
+    #[error("workspace_id is empty")]
+    Empty,
+    #[error("workspace_id exceeds maximum length of {0} characters")]
+    TooLong(usize),
+}
+
+/// Maximum length for workspace ids (1024 characters).
+const MAX_WORKSPACE_ID_LEN: usize = 1024;
+
+/// Normalizes a workspace id to its canonical form.
+///
+/// - Trims surrounding whitespace
+/// - Converts backslashes to forward slashes and collapses repeats
+/// - Uppercases a Windows drive letter (`c:\ws` → `C:/ws`)
+/// - Drops `.` segments and trailing slashes (`/` itself is preserved)
+/// - Makes an absolute id strictly below `base` relative to it
+///   (`/home/u/ws1` with base `/home/u` → `ws1`)
+/// - Lowercases the result when `lowercase` is true
+pub fn normalize_id(
+    id: &str,
+    base: Option<&str>,
+    lowercase: bool,
+) -> Result<String, WorkspaceIdError> {
+    let trimmed = id.trim();
+    if trimmed.len() > MAX_WORKSPACE_ID_LEN {
+        return Err(WorkspaceIdError::TooLong(MAX_WORKSPACE_ID_LEN));
+    }
+
+    let fold = |s: String| if lowercase { s.to_lowercase() } else { s };
+    let normalized = fold(canonical_form(trimmed));
+    if normalized.is_empty() {
+        return Err(WorkspaceIdError::Empty);
+    }
+    let base = base.map(|b| fold(canonical_form(b.trim())));
+    Ok(match base.and_then(|b| relative_to(&normalized, &b)) {
+        Some(relative) => relative.to_string(),
+        None => normalized,
+    })
+}
+
+/// Slash-normalized form of `id` without `.` segments or trailing slashes.
+fn canonical_form(id: &str) -> String {
+    let slashed = normalize_drive_path(id);
+    let segments: Vec<&str> = slashed
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect();
+    let joined = segments.join("/");
+    if slashed.starts_with('/') {
+        format!("/{joined}")
+    } else {
+        joined
+    }
+}
+
+/// The non-empty remainder of absolute `id` below absolute `base`.
+fn relative_to<'a>(id: &'a str, base: &str) -> Option<&'a str> {
+    if !base.starts_with('/') && !base.contains(':') {
+        return None;
+    }
+    let rest = id.strip_prefix(base)?;
+    let rest = if base.ends_with('/') {
+        rest
+    } else {
+        rest.strip_prefix('/')?
+    };
+    (!rest.is_empty()).then_some(rest)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_trailing_slash() {
+        assert_eq!(normalize_id("ws1/", None, false).unwrap(), "ws1");
+        assert_eq!(
+            normalize_id("/home/u/ws1//", None, false).unwrap(),
+            "/home/u/ws1"
+        );
+    }
+
+    #[test]
+    fn trims_whitespace_and_dot_segments() {
+        assert_eq!(normalize_id("  ./ws1/./a ", None, false).unwrap(), "ws1/a");
+    }
+
+    #[test]
+    fn converts_backslashes() {
+        assert_eq!(normalize_id("C:\\\\ws1\\", None, false).unwrap(), "C:/ws1");
+    }
+
+    #[test]
+    fn drive_letter_case_is_folded() {
+        assert_eq!(normalize_id("c:\\ws1", None, false).unwrap(), "C:/ws1");
+    }
+
+    #[test]
+    fn lowercase_is_optional() {
+        assert_eq!(normalize_id("WS1", None, true).unwrap(), "ws1");
+        assert_eq!(normalize_id("WS1", None, false).unwrap(), "WS1");
+    }
+
+    #[test]
+    fn absolute_ids_below_base_become_relative() {
+        let base = Some("/home/u/");
+        assert_eq!(normalize_id("/home/u/ws1/", base, false).unwrap(), "ws1");
+        assert_eq!(normalize_id("ws1", base, false).unwrap(), "ws1");
+        assert_eq!(normalize_id("/home/u", base, false).unwrap(), "/home/u");
+        assert_eq!(
+            normalize_id("/home/uv/ws1", base, false).unwrap(),
+            "/home/uv/ws1"
+        );
+        assert_eq!(
+            normalize_id("C:\\u\\WS1", Some("c:/U"), true).unwrap(),
+            "ws1"
+        );
+    }
+
+    #[test]
+    fn relative_and_absolute_ids_stay_distinct_without_base() {
+        assert_eq!(normalize_id("ws1", None, false).unwrap(), "ws1");
+        assert_eq!(normalize_id("/ws1", None, false).unwrap(), "/ws1");
+        assert_eq!(
+            normalize_id("/ws1", Some("relative"), false).unwrap(),
+            "/ws1"
+        );
+    }
+
+    #[test]
+    fn preserves_root() {
+        assert_eq!(normalize_id("/", None, false).unwrap(), "/");
+    }
+
+    #[test]
+    fn rejects_empty() {
+        assert_eq!(normalize_id("", None, false), Err(WorkspaceIdError::Empty));
+        assert_eq!(
+            normalize_id("  ./ ", None, false),
+            Err(WorkspaceIdError::Empty)
+        );
+    }
+
+    #[test]
+    fn rejects_too_long() {
+        let long = "a".repeat(MAX_WORKSPACE_ID_LEN + 1);
+        assert_eq!(
+            normalize_id(&long, None, false),
+            Err(WorkspaceIdError::TooLong(MAX_WORKSPACE_ID_LEN))
+        );
+    }
+}
@@ -1,16 +1,26 @@
 //! `ESLint` JSON output parser.
 //!
-//! Parses `ESLint` JSON reporter output and extracts findings (errors only).
-
-use super::ParseError;
+//! Parses `ESLint` JSON reporter output and extracts findings (errors only,
+//! unless warnings are requested).
+//! Accepts both the `json` and `json-with-metadata` formatters; the latter
+//! supplies each rule's `meta.type` for categorization.
+
+use super::eslint_categories::categorize;
+use super::{is_empty_output, ParseError};
+use crate::normalize::stable_id;
 use serde::Deserialize;
+use std::collections::HashMap;
 
+/// `ESLint` severity of a warning.
+pub const SEVERITY_WARNING: i32 = 1;
+/// `ESLint` severity of an error.
+pub const SEVERITY_ERROR: i32 = 2;
 /// Maximum rule name length before truncation.
-const MAX_RULE_LENGTH: usize = 256;
+pub(super) const MAX_RULE_LENGTH: usize = 256;
 /// Maximum file path length before truncation.
-const MAX_FILE_LENGTH: usize = 4096;
+pub(super) const MAX_FILE_LENGTH: usize = 4096;
 /// Maximum message length before truncation.
-const MAX_MESSAGE_LENGTH: usize = 1000;
+pub(super) const MAX_MESSAGE_LENGTH: usize = 1000;
 
 /// A parsed finding from `ESLint` output.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -24,6 +34,39 @@ pub struct Finding {
     pub end_line: i32,
     pub end_column: i32,
     pub message: String,
+    /// Rule category: `problem`, `suggestion`, `layout`, or `unknown`.
+    pub category: String,
+    /// Whether `ESLint` offered an automatic fix.
+    pub fixable: bool,
+    /// `SEVERITY_WARNING` or `SEVERITY_ERROR`.
+    pub severity: i32,
+}
+
+/// Top-level output: a bare array (`json`) or an object (`json-with-metadata`).
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum EslintOutput {
+    Plain(Vec<EslintFileResult>),
+    WithMetadata {
+        results: Vec<EslintFileResult>,
+        #[serde(default)]
+        metadata: EslintMetadata,
+    },
+}
+
+/// Metadata emitted by the `json-with-metadata` formatter.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EslintMetadata {
+    #[serde(default)]
+    rules_meta: HashMap<String, RuleMeta>,
+}
+
+/// Subset of a rule's `meta` object.
+#[derive(Debug, Deserialize)]
+struct RuleMeta {
+    #[serde(rename = "type")]
+    rule_type: Option<String>,
 }
 
 /// `ESLint` JSON output is an array of file results.
@@ -50,18 +93,34 @@ struct EslintMessage {
     end_column: Option<i32>,
     #[serde(default)]
     message: String,
+    /// Present when an automatic fix is available; contents are not needed.
+    #[serde(default)]
+    fix: Option<serde::de::IgnoredAny>,
 }
 
-/// Parses `ESLint` JSON output and extracts all error-level findings.
+/// Parses `ESLint` JSON output and extracts findings at or above `min_severity`.
 ///
 /// # Arguments
 /// * `json_content` - Raw JSON content from `ESLint` reporter output
 /// * `workspace_root` - Workspace root path for normalizing file paths
+/// * `min_severity` - `SEVERITY_ERROR` for errors only, `SEVERITY_WARNING` to include warnings
+/// * `id_length` - Hex characters kept of each stable ID (see `stable_id::id_length`)
 ///
 /// # Returns
-/// List of findings (errors only, severity=2), or a `ParseError` if JSON is malformed
-pub fn parse(json_content: &str, workspace_root: &str) -> Result<Vec<Finding>, ParseError> {
-    let results: Vec<EslintFileResult> = serde_json::from_str(json_content)?;
+/// List of findings (none for empty content), or a `ParseError` if JSON is malformed
+pub fn parse(
+    json_content: &str,
+    workspace_root: &str,
+    min_severity: i32,
+    id_length: usize,
+) -> Result<Vec<Finding>, ParseError> {
+    if is_empty_output(json_content) {
+        return Ok(Vec::new());
+    }
+    let (results, rules_meta) = match serde_json::from_str(json_content)? {
+        EslintOutput::Plain(results) => (results, HashMap::new()),
+        EslintOutput::WithMetadata { results, metadata } => (results, metadata.rules_meta),
+    };
     let mut findings = Vec::new();
 
     for result in results {
@@ -70,10 +129,16 @@ pub fn parse(json_content: &str, workspace_root: &str) -> Result<Vec<Finding>, P
         };
         let file = normalize_path(file_path, workspace_root);
         for msg in &result.messages {
-            if msg.severity != 2 {
-                continue; // Only errors (severity=2), skip warnings
+            if msg.severity < min_severity.max(SEVERITY_WARNING) {
+                continue; // Off (severity=0) or below the requested floor
             }
-            let finding = build_finding(&file, msg);
+            let mut finding = build_finding(&file, msg, id_length);
+            let meta_type = msg
+                .rule_id
+                .as_deref()
+                .and_then(|r| rules_meta.get(r))
+                .and_then(|m| m.rule_type.as_deref());
+            finding.category = categorize(&finding.rule, meta_type);
             findings.push(finding);
         }
     }
@@ -81,7 +146,7 @@ pub fn parse(json_content: &str, workspace_root: &str) -> Result<Vec<Finding>, P
     Ok(findings)
 }
 
-fn normalize_path(absolute_path: &str, workspace_root: &str) -> String {
+pub(super) fn normalize_path(absolute_path: &str, workspace_root: &str) -> String {
     let stripped = if let Some(s) = absolute_path.strip_prefix(workspace_root) {
         s.strip_prefix('/').unwrap_or(s)
     } else {
@@ -90,14 +155,15 @@ fn normalize_path(absolute_path: &str, workspace_root: &str) -> String {
     truncate(stripped, MAX_FILE_LENGTH)
 }
 
-fn build_finding(file: &str, msg: &EslintMessage) -> Finding {
+fn build_finding(file: &str, msg: &EslintMessage, id_length: usize) -> Finding {
     let rule = truncate(msg.rule_id.as_deref().unwrap_or("unknown"), MAX_RULE_LENGTH);
     let message = truncate(&msg.message, MAX_MESSAGE_LENGTH);
     let line = normalize_line_col(msg.line);
     let column = normalize_line_col(msg.column);
     let end_line = msg.end_line.map(normalize_line_col).unwrap_or(line);
     let end_column = msg.end_column.map(normalize_line_col).unwrap_or(column);
-    let stable_id = compute_stable_id(&rule, file, line, column);
+    let stable_id = compute_stable_id(&rule, file, line, column, id_length);
+    let category = categorize(&rule, None);
 
     Finding {
         stable_id,
@@ -109,10 +175,13 @@ fn build_finding(file: &str, msg: &EslintMessage) -> Finding {
         end_line,
         end_column,
         message,
+        category,
+        fixable: msg.fix.is_some(),
+        severity: msg.severity.min(SEVERITY_ERROR),
     }
 }
 
-fn normalize_line_col(value: i32) -> i32 {
+pub(super) fn normalize_line_col(value: i32) -> i32 {
     if value < 1 {
         1
     } else {
@@ -120,7 +189,7 @@ fn normalize_line_col(value: i32) -> i32 {
     }
 }
 
-fn truncate(s: &str, max_chars: usize) -> String {
+pub(super) fn truncate(s: &str, max_chars: usize) -> String {
     if s.chars().count() > max_chars {
         format!("{}...", s.chars().take(max_chars - 3).collect::<String>())
     } else {
@@ -129,17 +198,16 @@ fn truncate(s: &str, max_chars: usize) -> String {
 }
 
 /// Computes stable ID for a finding: BLAKE3 of `eslint:{rule}:{file}:{line}:{column}`.
-fn compute_stable_id(rule: &str, file: &str, line: i32, column: i32) -> String {
-    let input = format!("eslint:{rule}:{file}:{line}:{column}");
-    let hash = blake3::hash(input.as_bytes());
-    let hex = hash.to_hex();
-    hex[..32].to_lowercase()
+#[allow(clippy::too_many_arguments)]
+fn compute_stable_id(rule: &str, file: &str, line: i32, column: i32, length: usize) -> String {
+    stable_id::hash_hex(&format!("eslint:{rule}:{file}:{line}:{column}"), length)
 }
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::too_many_arguments)]
 mod tests {
     use super::*;
+    use crate::normalize::stable_id::DEFAULT_ID_LENGTH;
 
     fn make_eslint_json(file_path: Option<&str>, messages: &str) -> String {
         match file_path {
@@ -162,16 +230,33 @@ mod tests {
         let err = make_message(Some("error"), 2, 1, 1, "e");
         let warn = make_message(Some("warning"), 1, 1, 1, "w");
         let json = make_eslint_json(Some("/ws/f.js"), &format!("{err},{warn}"));
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings.len(), 1);
         assert_eq!(findings[0].rule, "error");
     }
 
+    #[test]
+    fn parse_keeps_warnings_above_min_severity() {
+        let err = make_message(Some("error"), 2, 1, 1, "e");
+        let warn = make_message(Some("warning"), 1, 1, 1, "w");
+        let off = make_message(Some("off"), 0, 1, 1, "o");
+        let json = make_eslint_json(Some("/ws/f.js"), &format!("{err},{warn},{off}"));
+        let findings = parse(&json, "/ws", SEVERITY_WARNING, DEFAULT_ID_LENGTH).unwrap();
+        let severities: Vec<(&str, i32)> = findings
+            .iter()
+            .map(|f| (f.rule.as_str(), f.severity))
+            .collect();
+        assert_eq!(
+            severities,
+            vec![("error", SEVERITY_ERROR), ("warning", SEVERITY_WARNING)]
+        );
+    }
+
     #[test]
     fn parse_maps_basic_fields() {
         let err = make_message(Some("no-unused-vars"), 2, 10, 5, "x is unused");
         let json = make_eslint_json(Some("/ws/src/a.js"), &err);
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].rule, "no-unused-vars");
         assert_eq!(findings[0].file, "src/a.js");
         assert_eq!(findings[0].start_line, 10);
@@ -182,20 +267,33 @@ mod tests {
 
     #[test]
     fn parse_empty_array() {
-        assert!(parse("[]", "/ws").unwrap().is_empty());
+        assert!(parse("[]", "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn parse_empty_content_has_no_findings() {
+        for content in ["", " \n\t"] {
+            assert!(parse(content, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH)
+                .unwrap()
+                .is_empty());
+        }
     }
 
     #[test]
     fn parse_missing_file_path_skipped() {
         let msg = make_message(Some("rule"), 2, 1, 1, "err");
         let json = make_eslint_json(None, &msg);
-        assert!(parse(&json, "/ws").unwrap().is_empty());
+        assert!(parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH)
+            .unwrap()
+            .is_empty());
     }
 
     #[test]
     fn parse_null_rule_id_defaults_to_unknown() {
         let json = r#"[{"filePath":"/ws/f.js","messages":[{"severity":2,"line":1,"column":1,"message":"err"}]}]"#;
-        let findings = parse(json, "/ws").unwrap();
+        let findings = parse(json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].rule, "unknown");
     }
 
@@ -203,7 +301,7 @@ mod tests {
     fn parse_invalid_line_column_defaults_to_1() {
         let msg = r#"{"ruleId":"r","severity":2,"line":-5,"column":0,"message":"err"}"#;
         let json = format!(r#"[{{"filePath":"/ws/f.js","messages":[{msg}]}}]"#);
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].start_line, 1);
         assert_eq!(findings[0].start_column, 1);
     }
@@ -213,7 +311,7 @@ mod tests {
         let long_rule = "x".repeat(MAX_RULE_LENGTH + 10);
         let msg = make_message(Some(&long_rule), 2, 1, 1, "m");
         let json = make_eslint_json(Some("/ws/f.js"), &msg);
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].rule.len(), MAX_RULE_LENGTH);
         assert!(findings[0].rule.ends_with("..."));
     }
@@ -223,7 +321,7 @@ mod tests {
         let long_file = format!("/ws/{}", "y".repeat(MAX_FILE_LENGTH + 10));
         let msg = make_message(Some("r"), 2, 1, 1, "m");
         let json = make_eslint_json(Some(&long_file), &msg);
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].file.len(), MAX_FILE_LENGTH);
         assert!(findings[0].file.ends_with("..."));
     }
@@ -233,7 +331,7 @@ mod tests {
         let long_msg = "z".repeat(MAX_MESSAGE_LENGTH + 10);
         let msg = make_message(Some("r"), 2, 1, 1, &long_msg);
         let json = make_eslint_json(Some("/ws/f.js"), &msg);
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].message.len(), MAX_MESSAGE_LENGTH);
         assert!(findings[0].message.ends_with("..."));
     }
@@ -245,7 +343,7 @@ mod tests {
             Some("/ws/f.js"),
             &make_message(Some("r"), 2, 1, 1, short_msg),
         );
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].message, short_msg);
         assert!(!findings[0].message.ends_with("..."));
     }
@@ -259,7 +357,7 @@ mod tests {
             Some("/ws/f.js"),
             &make_message(Some("r"), 2, 1, 1, &emoji_msg),
         );
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         // Result should be truncated to MAX_MESSAGE_LENGTH chars
         assert_eq!(findings[0].message.chars().count(), MAX_MESSAGE_LENGTH);
         assert!(findings[0].message.ends_with("..."));
@@ -271,7 +369,7 @@ mod tests {
     #[test]
     fn parse_malformed_json_returns_error() {
         assert!(matches!(
-            parse("bad json", "/ws"),
+            parse("bad json", "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH),
             Err(ParseError::InvalidJson(_))
         ));
     }
@@ -279,15 +377,15 @@ mod tests {
     #[test]
     fn stable_id_is_deterministic() {
         let json = make_eslint_json(Some("/ws/f.js"), &make_message(Some("r"), 2, 1, 1, "m"));
-        let f1 = parse(&json, "/ws").unwrap();
-        let f2 = parse(&json, "/ws").unwrap();
+        let f1 = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        let f2 = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(f1[0].stable_id, f2[0].stable_id);
     }
 
     #[test]
     fn stable_id_has_expected_format() {
         let json = make_eslint_json(Some("/ws/f.js"), &make_message(Some("r"), 2, 1, 1, "m"));
-        let f = parse(&json, "/ws").unwrap();
+        let f = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(f[0].stable_id.len(), 32);
         assert!(f[0].stable_id.chars().all(|c| c.is_ascii_hexdigit()));
     }
@@ -296,16 +394,46 @@ mod tests {
     fn stable_id_different_for_different_input() {
         let j1 = make_eslint_json(Some("/ws/f.js"), &make_message(Some("r"), 2, 1, 1, "m"));
         let j2 = make_eslint_json(Some("/ws/f.js"), &make_message(Some("r"), 2, 2, 1, "m"));
-        let f1 = parse(&j1, "/ws").unwrap();
-        let f2 = parse(&j2, "/ws").unwrap();
+        let f1 = parse(&j1, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        let f2 = parse(&j2, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_ne!(f1[0].stable_id, f2[0].stable_id);
     }
 
+    #[test]
+    fn parse_assigns_builtin_category() {
+        let json = make_eslint_json(
+            Some("/ws/f.js"),
+            &make_message(Some("no-undef"), 2, 1, 1, "m"),
+        );
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        assert_eq!(findings[0].category, "problem");
+    }
+
+    #[test]
+    fn parse_reads_meta_type_from_metadata_format() {
+        let msg = make_message(Some("import/no-cycle"), 2, 1, 1, "m");
+        let json = format!(
+            r#"{{"results":[{{"filePath":"/ws/f.js","messages":[{msg}]}}],"metadata":{{"rulesMeta":{{"import/no-cycle":{{"type":"suggestion"}}}}}}}}"#
+        );
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        assert_eq!(findings[0].category, "suggestion");
+    }
+
+    #[test]
+    fn parse_sets_fixable_from_fix_presence() {
+        let fixable = r#"{"ruleId":"semi","severity":2,"line":1,"column":5,"message":"m","fix":{"range":[4,4],"text":";"}}"#;
+        let plain = make_message(Some("no-undef"), 2, 2, 1, "m");
+        let json = make_eslint_json(Some("/ws/f.js"), &format!("{fixable},{plain}"));
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        assert!(findings[0].fixable);
+        assert!(!findings[1].fixable);
+    }
+
     #[test]
     fn end_line_column_defaults_to_start() {
         let msg = r#"{"ruleId":"r","severity":2,"line":10,"column":5,"message":"err"}"#;
         let json = format!(r#"[{{"filePath":"/ws/f.js","messages":[{msg}]}}]"#);
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].end_line, 10);
         assert_eq!(findings[0].end_column, 5);
     }
@@ -314,7 +442,7 @@ mod tests {
     fn end_line_column_uses_provided_values() {
         let msg = r#"{"ruleId":"r","severity":2,"line":10,"column":5,"endLine":15,"endColumn":20,"message":"err"}"#;
         let json = format!(r#"[{{"filePath":"/ws/f.js","messages":[{msg}]}}]"#);
-        let findings = parse(&json, "/ws").unwrap();
+        let findings = parse(&json, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
         assert_eq!(findings[0].end_line, 15);
         assert_eq!(findings[0].end_column, 20);
     }
@@ -0,0 +1,218 @@
+//! Built-in `ESLint` rule category mapping.
+//!
+//! Mirrors `meta.type` of common core rules so findings can be grouped for
+//! triage even when the reporter does not include rule metadata.
+
+/// Category for rules with no known `meta.type`.
+pub const UNKNOWN: &str = "unknown";
+
+/// Valid `meta.type` values.
+const CATEGORIES: &[&str] = &["problem", "suggestion", "layout"];
+
+/// Core rules that report likely bugs.
+const PROBLEM_RULES: &[&str] = &[
+    "array-callback-return",
+    "constructor-super",
+    "for-direction",
+    "getter-return",
+    "no-async-promise-executor",
+    "no-await-in-loop",
+    "no-class-assign",
+    "no-compare-neg-zero",
+    "no-cond-assign",
+    "no-const-assign",
+    "no-constant-condition",
+    "no-control-regex",
+    "no-debugger",
+    "no-dupe-args",
+    "no-dupe-class-members",
+    "no-dupe-else-if",
+    "no-dupe-keys",
+    "no-duplicate-case",
+    "no-empty-character-class",
+    "no-empty-pattern",
+    "no-ex-assign",
+    "no-fallthrough",
+    "no-func-assign",
+    "no-import-assign",
+    "no-inner-declarations",
+    "no-invalid-regexp",
+    "no-irregular-whitespace",
+    "no-loss-of-precision",
+    "no-misleading-character-class",
+    "no-new-native-nonconstructor",
+    "no-obj-calls",
+    "no-prototype-builtins",
+    "no-self-assign",
+    "no-self-compare",
+    "no-setter-return",
+    "no-sparse-arrays",
+    "no-template-curly-in-string",
+    "no-this-before-super",
+    "no-undef",
+    "no-unexpected-multiline",
+    "no-unmodified-loop-condition",
+    "no-unreachable",
+    "no-unsafe-finally",
+    "no-unsafe-negation",
+    "no-unsafe-optional-chaining",
+    "no-unused-private-class-members",
+    "no-unused-vars",
+    "no-use-before-define",
+    "require-atomic-updates",
+    "use-isnan",
+    "valid-typeof",
+];
+
+/// Core rules that suggest a better way of doing something.
+const SUGGESTION_RULES: &[&str] = &[
+    "camelcase",
+    "complexity",
+    "curly",
+    "default-case",
+    "dot-notation",
+    "eqeqeq",
+    "guard-for-in",
+    "max-depth",
+    "max-lines",
+    "max-lines-per-function",
+    "max-params",
+    "max-statements",
+    "no-alert",
+    "no-case-declarations",
+    "no-console",
+    "no-delete-var",
+    "no-else-return",
+    "no-empty",
+    "no-empty-function",
+    "no-eval",
+    "no-extra-boolean-cast",
+    "no-global-assign",
+    "no-implied-eval",
+    "no-lonely-if",
+    "no-magic-numbers",
+    "no-nested-ternary",
+    "no-new",
+    "no-octal",
+    "no-param-reassign",
+    "no-plusplus",
+    "no-regex-spaces",
+    "no-return-await",
+    "no-shadow",
+    "no-throw-literal",
+    "no-undef-init",
+    "no-unused-expressions",
+    "no-useless-catch",
+    "no-useless-constructor",
+    "no-useless-escape",
+    "no-useless-return",
+    "no-var",
+    "no-with",
+    "object-shorthand",
+    "prefer-arrow-callback",
+    "prefer-const",
+    "prefer-promise-reject-errors",
+    "prefer-template",
+    "require-await",
+    "sort-imports",
+    "sort-keys",
+    "yoda",
+];
+
+/// Core rules that only concern formatting.
+const LAYOUT_RULES: &[&str] = &[
+    "array-bracket-spacing",
+    "arrow-spacing",
+    "brace-style",
+    "comma-dangle",
+    "comma-spacing",
+    "comma-style",
+    "eol-last",
+    "func-call-spacing",
+    "indent",
+    "key-spacing",
+    "keyword-spacing",
+    "linebreak-style",
+    "max-len",
+    "no-mixed-spaces-and-tabs",
+    "no-multiple-empty-lines",
+    "no-trailing-spaces",
+    "object-curly-spacing",
+    "operator-linebreak",
+    "padded-blocks",
+    "quote-props",
+    "quotes",
+    "semi",
+    "semi-spacing",
+    "semi-style",
+    "space-before-blocks",
+    "space-before-function-paren",
+    "space-infix-ops",
+    "unicode-bom",
+];
+
+/// Returns the category for a rule from reporter metadata, falling back to
+/// the built-in mapping, then `unknown`.
+pub fn categorize(rule: &str, meta_type: Option<&str>) -> String {
+    if let Some(category) = meta_type.filter(|t| CATEGORIES.contains(t)) {
+        return category.to_string();
+    }
+    builtin_category(rule).unwrap_or(UNKNOWN).to_string()
+}
+
+/// Looks up the built-in category for a core rule.
+///
+/// `@typescript-eslint/` extension rules share their core rule's category and
+/// every `@stylistic/` rule is layout.
+fn builtin_category(rule: &str) -> Option<&'static str> {
+    if rule.starts_with("@stylistic/") {
+        return Some("layout");
+    }
+    let core = rule.strip_prefix("@typescript-eslint/").unwrap_or(rule);
+    if PROBLEM_RULES.contains(&core) {
+        Some("problem")
+    } else if SUGGESTION_RULES.contains(&core) {
+        Some("suggestion")
+    } else if LAYOUT_RULES.contains(&core) {
+        Some("layout")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_rules_map_to_category() {
+        assert_eq!(categorize("no-undef", None), "problem");
+        assert_eq!(categorize("prefer-const", None), "suggestion");
+        assert_eq!(categorize("semi", None), "layout");
+    }
+
+    #[test]
+    fn plugin_prefixes_are_understood() {
+        assert_eq!(
+            categorize("@typescript-eslint/no-unused-vars", None),
+            "problem"
+        );
+        assert_eq!(categorize("@stylistic/indent", None), "layout");
+    }
+
+    #[test]
+    fn unknown_rule_is_unknown() {
+        assert_eq!(categorize("import/no-unresolved", None), UNKNOWN);
+    }
+
+    #[test]
+    fn meta_type_takes_precedence() {
+        assert_eq!(
+            categorize("import/no-unresolved", Some("problem")),
+            "problem"
+        );
+        assert_eq!(categorize("semi", Some("suggestion")), "suggestion");
+        // Unrecognized meta.type falls back to built-in mapping
+        assert_eq!(categorize("semi", Some("bogus")), "layout");
+    }
+}
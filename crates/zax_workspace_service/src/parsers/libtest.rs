@@ -0,0 +1,157 @@
+//! Rust libtest JSON output parser.
+//!
+//! Parses the line-delimited events of `cargo test -- -Z unstable-options
+//! --format json` and `cargo nextest run --message-format libtest-json`,
+//! extracting failed tests. Lines that are not JSON objects (cargo's
+//! progress output) are skipped.
+//!
+//! Failures share Vitest's `TestFailure` shape so they are stored the same
+//! way; the artifact kind records that they came from Rust tests.
+
+use super::vitest::{TestFailure, KIND_ASSERTION};
+use super::ParseError;
+use serde::Deserialize;
+
+/// Maximum message length before truncation.
+const MAX_MESSAGE_LENGTH: usize = 1000;
+
+/// A single libtest event line.
+#[derive(Debug, Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    event: String,
+    #[serde(default)]
+    name: String,
+    /// Captured output, including the panic message
+    #[serde(default)]
+    stdout: Option<String>,
+    /// Set instead of `stdout` for e.g. `should_panic` mismatches
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Parses libtest JSON events and extracts all test failures.
+///
+/// `test_id` is the test path (e.g. `parser::tests::rejects_empty`) and
+/// `file` its module path, qualified by the nextest binary id when present.
+pub fn parse(content: &str) -> Result<Vec<TestFailure>, ParseError> {
+    let mut failures = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if !line.starts_with('{') {
+            continue;
+        }
+        let event: Event = serde_json::from_str(line)?;
+        if event.kind == "test" && event.event == "failed" {
+            failures.push(build_failure(&event));
+        }
+    }
+    Ok(failures)
+}
+
+fn build_failure(event: &Event) -> TestFailure {
+    // nextest names are `<binary-id>$<test path>`
+    let (binary, test_path) = match event.name.split_once('$') {
+        Some((binary, path)) => (Some(binary), path),
+        None => (None, event.name.as_str()),
+    };
+    let output = event
+        .stdout
+        .as_deref()
+        .or(event.message.as_deref())
+        .unwrap_or("");
+    TestFailure {
+        test_id: test_path.to_string(),
+        file: test_file(binary, test_path),
+        message: truncate_message(output.trim()),
+        kind: KIND_ASSERTION,
+        expected: None,
+        received: None,
+    }
+}
+
+/// Maps a test path to the closest thing libtest reports to a file.
+///
+/// Doctests are named `src/lib.rs - item (line 10)` and carry their file;
+/// other tests map to their module path, or the binary for top-level tests.
+fn test_file(binary: Option<&str>, test_path: &str) -> String {
+    if let Some((file, _)) = test_path.split_once(" - ") {
+        return file.to_string();
+    }
+    let module = test_path.rsplit_once("::").map(|(module, _)| module);
+    match (binary, module) {
+        (Some(binary), Some(module)) => format!("{binary}::{module}"),
+        (Some(binary), None) => binary.to_string(),
+        (None, Some(module)) => module.to_string(),
+        (None, None) => test_path.to_string(),
+    }
+}
+
+fn truncate_message(message: &str) -> String {
+    if message.chars().count() > MAX_MESSAGE_LENGTH {
+        format!(
+            "{}...",
+            message
+                .chars()
+                .take(MAX_MESSAGE_LENGTH - 3)
+                .collect::<String>()
+        )
+    } else {
+        message.to_string()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const CARGO_FIXTURE: &str = r#"
+   Compiling demo v0.1.0 (/ws/demo)
+{ "type": "suite", "event": "started", "test_count": 4 }
+{ "type": "test", "event": "started", "name": "parser::tests::parses" }
+{ "type": "test", "event": "started", "name": "parser::tests::rejects_empty" }
+{ "type": "test", "name": "parser::tests::parses", "event": "ok" }
+{ "type": "test", "name": "parser::tests::rejects_empty", "event": "failed", "stdout": "\nthread 'parser::tests::rejects_empty' panicked at src/parser.rs:42:9:\nassertion `left == right` failed\n" }
+{ "type": "test", "name": "top_level", "event": "failed", "message": "test did not panic as expected" }
+{ "type": "test", "name": "src/lib.rs - add (line 3)", "event": "failed", "stdout": "doctest failed" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 3, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 0.01 }
+"#;
+
+    #[test]
+    fn parse_extracts_failed_tests() {
+        let f = parse(CARGO_FIXTURE).unwrap();
+        let ids: Vec<(&str, &str)> = f
+            .iter()
+            .map(|f| (f.test_id.as_str(), f.file.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("parser::tests::rejects_empty", "parser::tests"),
+                ("top_level", "top_level"),
+                ("src/lib.rs - add (line 3)", "src/lib.rs"),
+            ]
+        );
+        assert!(f[0]
+            .message
+            .starts_with("thread 'parser::tests::rejects_empty' panicked"));
+        assert_eq!(f[1].message, "test did not panic as expected");
+    }
+
+    #[test]
+    fn parse_qualifies_nextest_names_with_binary_id() {
+        let line = r#"{"type":"test","event":"failed","name":"demo::integration$api::tests::get","stdout":"boom"}"#;
+        let f = parse(line).unwrap();
+        assert_eq!(f[0].test_id, "api::tests::get");
+        assert_eq!(f[0].file, "demo::integration::api::tests");
+        assert_eq!(test_file(Some("demo"), "smoke"), "demo");
+    }
+
+    #[test]
+    fn parse_rejects_malformed_event() {
+        assert!(parse("{ \"type\": \"test\", ").is_err());
+        assert!(parse("running 0 tests\n").unwrap().is_empty());
+    }
+}
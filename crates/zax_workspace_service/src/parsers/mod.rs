@@ -1,6 +1,11 @@
 //! Artifact parsers for extracting test failures and findings.
 
 pub mod eslint;
+pub mod eslint_categories;
+pub mod libtest;
+pub mod playwright;
+pub mod stylelint;
+pub mod tsc;
 pub mod vitest;
 
 use thiserror::Error;
@@ -10,6 +15,8 @@ use thiserror::Error;
 pub enum ParseError {
     #[error("invalid JSON: {0}")]
     InvalidJson(String),
+    #[error("no JSON value found in content")]
+    NoJsonValue,
 }
 
 impl From<serde_json::Error> for ParseError {
@@ -17,3 +24,85 @@ impl From<serde_json::Error> for ParseError {
         ParseError::InvalidJson(e.to_string())
     }
 }
+
+/// True for empty or whitespace-only reporter output, which parsers treat as
+/// no results: reporters that ran on nothing often leave an empty file.
+pub fn is_empty_output(content: &str) -> bool {
+    content.trim().is_empty()
+}
+
+/// Extracts the first balanced top-level JSON array or object from `content`.
+///
+/// CI wrappers may interleave log lines with reporter output on one stream.
+/// Candidates that balance but fail to parse (e.g. `[INFO]`) are skipped.
+/// Only use when noise is expected, since it can mask genuine corruption.
+pub fn extract_json(content: &str) -> Result<&str, ParseError> {
+    let bytes = content.as_bytes();
+    let mut start = 0;
+    while let Some(offset) = bytes[start..].iter().position(|&b| b == b'[' || b == b'{') {
+        let begin = start + offset;
+        if let Some(end) = balanced_end(&bytes[begin..]) {
+            let candidate = &content[begin..begin + end];
+            if serde_json::from_str::<serde::de::IgnoredAny>(candidate).is_ok() {
+                return Ok(candidate);
+            }
+        }
+        start = begin + 1;
+    }
+    Err(ParseError::NoJsonValue)
+}
+
+/// Returns the length of the bracketed value at the start of `bytes`, if it closes.
+fn balanced_end(bytes: &[u8]) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, &b) in bytes.iter().enumerate() {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match b {
+            b'"' => in_string = true,
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_json_skips_surrounding_log_lines() {
+        let content = "[INFO] starting run\nwarn: {retry}\n[{\"a\":\"]}\"}]\n[INFO] done\n";
+        assert_eq!(extract_json(content).unwrap(), r#"[{"a":"]}"}]"#);
+    }
+
+    #[test]
+    fn extract_json_returns_clean_content_unchanged() {
+        assert_eq!(extract_json(r#"{"x":1}"#).unwrap(), r#"{"x":1}"#);
+    }
+
+    #[test]
+    fn extract_json_fails_without_json_value() {
+        assert!(matches!(
+            extract_json("just logs [INFO]"),
+            Err(ParseError::NoJsonValue)
+        ));
+    }
+}
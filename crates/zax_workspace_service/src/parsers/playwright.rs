@@ -0,0 +1,285 @@
+//! Playwright JSON report parser.
+//!
+//! Parses the output of Playwright's `json` reporter and extracts failed
+//! tests. Suites nest (file, then `describe` blocks), so the tree is walked
+//! recursively and a test's ID joins its suite titles and spec title with
+//! ` > `. Unlike Vitest's IDs, these start with the file suite's title, the
+//! spec file's path relative to the report's `config.rootDir`.
+//!
+//! Spec files are also reported relative to `config.rootDir` (the Playwright
+//! config's `testDir`), so they are resolved against it before being made
+//! workspace-relative.
+//!
+//! Retried tests carry one result per attempt; only the final attempt
+//! decides, so a test that passed on retry is not reported as a failure.
+
+use super::vitest::{normalize_path, truncate_message, TestFailure, KIND_ASSERTION};
+use super::{is_empty_output, ParseError};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Final-attempt statuses treated as failures.
+const FAILED_STATUSES: [&str; 2] = ["failed", "timedOut"];
+
+#[derive(Debug, Deserialize)]
+struct Report {
+    #[serde(default)]
+    config: Config,
+    #[serde(default)]
+    suites: Vec<Suite>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Config {
+    /// Absolute directory spec files are relative to
+    #[serde(default, rename = "rootDir")]
+    root_dir: String,
+}
+
+/// Maps spec files to workspace-relative paths.
+struct SpecPaths<'a> {
+    root_dir: &'a str,
+    workspace_root: &'a str,
+}
+
+impl SpecPaths<'_> {
+    fn file(&self, spec_file: &str) -> String {
+        if self.root_dir.is_empty() {
+            return normalize_path(spec_file, self.workspace_root);
+        }
+        let absolute = Path::new(self.root_dir).join(spec_file);
+        normalize_path(&absolute.to_string_lossy(), self.workspace_root)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Suite {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    suites: Vec<Suite>,
+    #[serde(default)]
+    specs: Vec<Spec>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Spec {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    file: String,
+    /// One entry per project the spec ran in
+    #[serde(default)]
+    tests: Vec<Test>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Test {
+    /// One entry per attempt, in order
+    #[serde(default)]
+    results: Vec<TestResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestResult {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    error: Option<TestError>,
+    #[serde(default)]
+    errors: Vec<TestError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestError {
+    #[serde(default)]
+    message: String,
+}
+
+impl TestResult {
+    fn failed(&self) -> bool {
+        FAILED_STATUSES.contains(&self.status.as_str())
+    }
+
+    fn message(&self) -> &str {
+        self.error
+            .as_ref()
+            .or(self.errors.first())
+            .map_or("", |e| e.message.as_str())
+    }
+}
+
+/// Parses a Playwright JSON report and extracts all test failures.
+///
+/// A spec that ran in several projects is reported once, with the message
+/// of the first project whose final attempt failed.
+pub fn parse(json: &str, workspace_root: &str) -> Result<Vec<TestFailure>, ParseError> {
+    if is_empty_output(json) {
+        return Ok(Vec::new());
+    }
+    let report: Report = serde_json::from_str(json)?;
+    let paths = SpecPaths {
+        root_dir: &report.config.root_dir,
+        workspace_root,
+    };
+    let mut failures = Vec::new();
+    collect_failures(&report.suites, &mut Vec::new(), &paths, &mut failures);
+    Ok(failures)
+}
+
+fn collect_failures<'a>(
+    suites: &'a [Suite],
+    titles: &mut Vec<&'a str>,
+    paths: &SpecPaths,
+    failures: &mut Vec<TestFailure>,
+) {
+    for suite in suites {
+        let titled = !suite.title.is_empty();
+        if titled {
+            titles.push(&suite.title);
+        }
+        for spec in &suite.specs {
+            if let Some(result) = final_failure(spec) {
+                failures.push(build_failure(titles, spec, result, paths));
+            }
+        }
+        collect_failures(&suite.suites, titles, paths, failures);
+        if titled {
+            titles.pop();
+        }
+    }
+}
+
+/// The first final attempt that failed across the spec's projects.
+fn final_failure(spec: &Spec) -> Option<&TestResult> {
+    spec.tests
+        .iter()
+        .filter_map(|test| test.results.last())
+        .find(|result| result.failed())
+}
+
+fn build_failure(
+    titles: &[&str],
+    spec: &Spec,
+    result: &TestResult,
+    paths: &SpecPaths,
+) -> TestFailure {
+    let mut parts = titles.to_vec();
+    parts.push(&spec.title);
+    TestFailure {
+        test_id: parts.join(" > "),
+        file: paths.file(&spec.file),
+        message: truncate_message(result.message()),
+        kind: KIND_ASSERTION,
+        expected: None,
+        received: None,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"{
+      "config": { "rootDir": "/ws/e2e" },
+      "suites": [{
+        "title": "login.spec.ts",
+        "file": "login.spec.ts",
+        "specs": [{
+          "title": "shows form",
+          "file": "login.spec.ts",
+          "tests": [{ "results": [{ "status": "passed" }] }]
+        }],
+        "suites": [{
+          "title": "login",
+          "file": "login.spec.ts",
+          "specs": [
+            {
+              "title": "rejects bad password",
+              "file": "login.spec.ts",
+              "tests": [{ "results": [{
+                "status": "failed",
+                "error": {
+                  "message": "\u001b[2mexpect(\u001b[22mreceived\u001b[2m).toBe\u001b[22m"
+                }
+              }] }]
+            },
+            {
+              "title": "redirects",
+              "file": "login.spec.ts",
+              "tests": [{ "results": [
+                { "status": "passed" },
+                {
+                  "status": "timedOut",
+                  "errors": [{ "message": "Test timeout of 30000ms exceeded." }]
+                }
+              ] }]
+            }
+          ]
+        }]
+      }],
+      "errors": []
+    }"#;
+
+    #[test]
+    fn parse_collects_failed_and_timed_out_specs() {
+        let failures = parse(FIXTURE, "/ws").unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0].test_id,
+            "login.spec.ts > login > rejects bad password"
+        );
+        assert_eq!(failures[0].file, "e2e/login.spec.ts");
+        assert_eq!(failures[0].message, "expect(received).toBe");
+        assert_eq!(failures[1].test_id, "login.spec.ts > login > redirects");
+        assert_eq!(failures[1].message, "Test timeout of 30000ms exceeded.");
+    }
+
+    #[test]
+    fn parse_resolves_spec_files_against_root_dir() {
+        let admin = FIXTURE.replace("/ws/e2e", "/ws/apps/admin/e2e");
+        let failures = parse(&admin, "/ws").unwrap();
+        assert_eq!(failures[0].file, "apps/admin/e2e/login.spec.ts");
+        assert_eq!(
+            parse(&admin, "/ws/apps/admin").unwrap()[0].file,
+            "e2e/login.spec.ts"
+        );
+
+        // Without a rootDir, files are taken as reported
+        let bare = FIXTURE.replace(r#""rootDir": "/ws/e2e""#, "");
+        assert_eq!(parse(&bare, "/ws").unwrap()[0].file, "login.spec.ts");
+    }
+
+    #[test]
+    fn parse_counts_only_the_final_retry() {
+        let json = r#"{"suites": [{
+          "title": "a.spec.ts",
+          "specs": [{
+            "title": "flaky",
+            "file": "/ws/e2e/a.spec.ts",
+            "tests": [
+              { "results": [{ "status": "failed" }, { "status": "passed" }] },
+              { "results": [{ "status": "timedOut" }, { "status": "failed" }] }
+            ]
+          }]
+        }]}"#;
+        let failures = parse(json, "/ws").unwrap();
+        assert_eq!(
+            failures.len(),
+            1,
+            "the second project still fails after retrying"
+        );
+        assert_eq!(failures[0].file, "e2e/a.spec.ts");
+
+        let passed_on_retry =
+            json.replace(r#"{ "status": "failed" }]"#, r#"{ "status": "passed" }]"#);
+        assert!(parse(&passed_on_retry, "/ws").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_accepts_empty_output_and_rejects_invalid_json() {
+        assert!(parse("  \n", "/ws").unwrap().is_empty());
+        assert!(parse("{ not json", "/ws").is_err());
+    }
+}
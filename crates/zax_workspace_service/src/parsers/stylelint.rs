@@ -0,0 +1,157 @@
+//! Stylelint JSON output parser.
+//!
+//! Parses `stylelint --formatter json` output into the same `Finding`s as
+//! `ESLint`, so CSS/SCSS lint results are stored and diffed alongside them.
+
+use super::eslint::{
+    normalize_line_col, normalize_path, truncate, Finding, MAX_MESSAGE_LENGTH, MAX_RULE_LENGTH,
+    SEVERITY_ERROR, SEVERITY_WARNING,
+};
+use super::eslint_categories::UNKNOWN;
+use super::{is_empty_output, ParseError};
+use crate::normalize::stable_id;
+use serde::Deserialize;
+
+/// One linted file.
+#[derive(Debug, Deserialize)]
+struct StylelintResult {
+    source: Option<String>,
+    #[serde(default)]
+    warnings: Vec<StylelintWarning>,
+}
+
+/// A single problem within a file result.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StylelintWarning {
+    #[serde(default)]
+    rule: String,
+    #[serde(default)]
+    severity: String,
+    #[serde(default)]
+    line: i32,
+    #[serde(default)]
+    column: i32,
+    end_line: Option<i32>,
+    end_column: Option<i32>,
+    #[serde(default)]
+    text: String,
+}
+
+/// Parses Stylelint JSON output and extracts findings at or above `min_severity`.
+///
+/// `"error"` maps to `SEVERITY_ERROR` and `"warning"` to `SEVERITY_WARNING`;
+/// stable IDs follow the `ESLint` scheme under the `stylelint` tool name.
+pub fn parse(
+    json_content: &str,
+    workspace_root: &str,
+    min_severity: i32,
+    id_length: usize,
+) -> Result<Vec<Finding>, ParseError> {
+    if is_empty_output(json_content) {
+        return Ok(Vec::new());
+    }
+    let results: Vec<StylelintResult> = serde_json::from_str(json_content)?;
+    let mut findings = Vec::new();
+    for result in results {
+        let Some(source) = &result.source else {
+            continue; // Stdin input has no source file
+        };
+        let file = normalize_path(source, workspace_root);
+        for warning in &result.warnings {
+            let severity = match warning.severity.as_str() {
+                "error" => SEVERITY_ERROR,
+                "warning" => SEVERITY_WARNING,
+                _ => continue,
+            };
+            if severity >= min_severity {
+                findings.push(build_finding(&file, warning, severity, id_length));
+            }
+        }
+    }
+    Ok(findings)
+}
+
+fn build_finding(
+    file: &str,
+    warning: &StylelintWarning,
+    severity: i32,
+    id_length: usize,
+) -> Finding {
+    let rule = if warning.rule.is_empty() {
+        "unknown"
+    } else {
+        warning.rule.as_str()
+    };
+    let line = normalize_line_col(warning.line);
+    let column = normalize_line_col(warning.column);
+    let input = format!("stylelint:{rule}:{file}:{line}:{column}");
+    Finding {
+        stable_id: stable_id::hash_hex(&input, id_length),
+        tool: "stylelint".to_string(),
+        rule: truncate(rule, MAX_RULE_LENGTH),
+        file: file.to_string(),
+        start_line: line,
+        start_column: column,
+        end_line: warning.end_line.map(normalize_line_col).unwrap_or(line),
+        end_column: warning.end_column.map(normalize_line_col).unwrap_or(column),
+        message: truncate(strip_rule_suffix(&warning.text, rule), MAX_MESSAGE_LENGTH),
+        category: UNKNOWN.to_string(),
+        fixable: false,
+        severity,
+    }
+}
+
+/// Stylelint appends ` (<rule>)` to each message; the rule is stored separately.
+fn strip_rule_suffix<'a>(text: &'a str, rule: &str) -> &'a str {
+    text.strip_suffix(')')
+        .and_then(|t| t.strip_suffix(rule))
+        .and_then(|t| t.strip_suffix(" ("))
+        .unwrap_or(text)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::normalize::stable_id::DEFAULT_ID_LENGTH;
+
+    const FIXTURE: &str = r##"[
+        {"source":"/ws/src/app.scss","errored":true,"warnings":[
+            {"line":3,"column":10,"endLine":3,"endColumn":14,"rule":"color-no-invalid-hex","severity":"error","text":"Unexpected invalid hex color \"#ffg\" (color-no-invalid-hex)"},
+            {"line":7,"column":1,"rule":"block-no-empty","severity":"warning","text":"Unexpected empty block (block-no-empty)"}
+        ]},
+        {"source":"/ws/src/clean.css","errored":false,"warnings":[]}
+    ]"##;
+
+    #[test]
+    fn parse_extracts_error_findings() {
+        let findings = parse(FIXTURE, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        assert_eq!(findings.len(), 1);
+        let finding = &findings[0];
+        assert_eq!(finding.tool, "stylelint");
+        assert_eq!(finding.rule, "color-no-invalid-hex");
+        assert_eq!(finding.file, "src/app.scss");
+        assert_eq!((finding.start_line, finding.start_column), (3, 10));
+        assert_eq!((finding.end_line, finding.end_column), (3, 14));
+        assert_eq!(finding.message, "Unexpected invalid hex color \"#ffg\"");
+        assert_eq!(finding.severity, SEVERITY_ERROR);
+        let input = "stylelint:color-no-invalid-hex:src/app.scss:3:10";
+        assert_eq!(
+            finding.stable_id,
+            stable_id::hash_hex(input, DEFAULT_ID_LENGTH)
+        );
+    }
+
+    #[test]
+    fn parse_keeps_warnings_above_min_severity() {
+        let findings = parse(FIXTURE, "/ws", SEVERITY_WARNING, DEFAULT_ID_LENGTH).unwrap();
+        let severities: Vec<i32> = findings.iter().map(|f| f.severity).collect();
+        assert_eq!(severities, vec![SEVERITY_ERROR, SEVERITY_WARNING]);
+    }
+
+    #[test]
+    fn parse_rejects_malformed_json() {
+        assert!(parse("{", "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! TypeScript compiler diagnostics parser.
+//!
+//! Parses the plain-text output of `tsc --pretty false`, one diagnostic per
+//! `file(line,col): error TSxxxx: message` line. Indented lines that follow
+//! (elaborations such as "Type 'x' is not assignable to ...") belong to the
+//! diagnostic above them. Diagnostics without a file location and summary
+//! lines ("Found 3 errors.") are skipped.
+//!
+//! `tsc --build` output is handled too: timestamped status lines ("Projects in
+//! this build:", "Building project ...") and their indented lists are
+//! skipped, and a diagnostic may be prefixed with its project
+//! (`packages/app/tsconfig.json: src/a.ts(1,1): error ...`), in which case a
+//! relative file is taken to be under that project's directory.
+
+use super::eslint::{
+    normalize_line_col, normalize_path, truncate, Finding, MAX_MESSAGE_LENGTH, MAX_RULE_LENGTH,
+    SEVERITY_ERROR, SEVERITY_WARNING,
+};
+use super::ParseError;
+use crate::normalize::stable_id;
+
+/// Category of every compiler diagnostic.
+const CATEGORY: &str = "problem";
+
+/// A diagnostic line's fields, before continuation lines are folded in.
+struct Diagnostic<'a> {
+    /// Project prefix of `tsc --build` or task-runner output, if any.
+    project: Option<&'a str>,
+    file: &'a str,
+    line: i32,
+    column: i32,
+    severity: i32,
+    code: &'a str,
+    message: String,
+}
+
+/// Parses `tsc --pretty false` output into findings with `tool = "tsc"` and
+/// the `TSxxxx` code as the rule, keeping those at or above `min_severity`.
+///
+/// Plain text cannot be malformed as a whole, so unrecognized lines are
+/// skipped; the `Result` matches the other parsers.
+#[allow(clippy::unnecessary_wraps)]
+pub fn parse(
+    text: &str,
+    workspace_root: &str,
+    min_severity: i32,
+    id_length: usize,
+) -> Result<Vec<Finding>, ParseError> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    // Whether indented lines continue the last diagnostic
+    let mut continuing = false;
+    for line in text.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let (true, Some(last)) = (continuing, diagnostics.last_mut()) {
+                last.message.push('\n');
+                last.message.push_str(line.trim_end());
+            }
+            continue;
+        }
+        let parsed = Some(line.trim_end())
+            .filter(|l| !is_build_status(l))
+            .and_then(parse_line);
+        continuing = parsed.is_some();
+        diagnostics.extend(parsed);
+    }
+    Ok(diagnostics
+        .into_iter()
+        .filter(|d| d.severity >= min_severity)
+        .map(|d| build_finding(&d, workspace_root, id_length))
+        .collect())
+}
+
+/// True for `tsc --build` status lines, which start with a timestamp such as
+/// `[12:00:00 PM]`.
+fn is_build_status(line: &str) -> bool {
+    let Some((stamp, _)) = line
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("] "))
+    else {
+        return false;
+    };
+    stamp.contains(':') && stamp.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Splits `[project: ]file(line,col): error TSxxxx: message`.
+fn parse_line(line: &str) -> Option<Diagnostic<'_>> {
+    let (location, rest) = line.split_once("): ")?;
+    let (file, position) = location.rsplit_once('(')?;
+    let (project, file) = match file.rsplit_once(": ") {
+        Some((project, file)) => (Some(project), file),
+        None => (None, file),
+    };
+    let (row, column) = position.split_once(',')?;
+    let (severity, rest) = if let Some(rest) = rest.strip_prefix("error ") {
+        (SEVERITY_ERROR, rest)
+    } else {
+        (SEVERITY_WARNING, rest.strip_prefix("warning ")?)
+    };
+    let (code, message) = rest.split_once(": ")?;
+    if file.is_empty() || !code.starts_with("TS") {
+        return None;
+    }
+    Some(Diagnostic {
+        project,
+        file,
+        line: row.trim().parse().ok()?,
+        column: column.trim().parse().ok()?,
+        severity,
+        code,
+        message: message.to_string(),
+    })
+}
+
+fn build_finding(diagnostic: &Diagnostic, workspace_root: &str, id_length: usize) -> Finding {
+    let file = project_file(diagnostic.project, &diagnostic.file.replace('\\', "/"));
+    let file = normalize_path(&file, workspace_root);
+    let rule = truncate(diagnostic.code, MAX_RULE_LENGTH);
+    let line = normalize_line_col(diagnostic.line);
+    let column = normalize_line_col(diagnostic.column);
+    let input = format!("tsc:{rule}:{file}:{line}:{column}");
+    Finding {
+        stable_id: stable_id::hash_hex(&input, id_length),
+        tool: "tsc".to_string(),
+        rule,
+        file,
+        start_line: line,
+        start_column: column,
+        end_line: line,
+        end_column: column,
+        message: truncate(&diagnostic.message, MAX_MESSAGE_LENGTH),
+        category: CATEGORY.to_string(),
+        fixable: false,
+        severity: diagnostic.severity,
+    }
+}
+
+/// Places a relative `file` under the directory of a path-like project
+/// prefix (`packages/app` or `packages/app/tsconfig.json`). Other prefixes,
+/// such as task-runner labels (`app:typecheck`), are dropped.
+fn project_file(project: Option<&str>, file: &str) -> String {
+    let Some(project) = project.map(|p| p.replace('\\', "/")) else {
+        return file.to_string();
+    };
+    let is_absolute = file.starts_with('/') || file.get(1..3) == Some(":/");
+    let dir = match project.strip_suffix(".json") {
+        Some(_) => project.rsplit_once('/').map_or("", |(dir, _)| dir),
+        None if project.contains('/') => project.trim_end_matches('/'),
+        None => "",
+    };
+    if is_absolute || dir.is_empty() || file.starts_with(&format!("{dir}/")) {
+        return file.to_string();
+    }
+    format!("{dir}/{file}")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::normalize::stable_id::DEFAULT_ID_LENGTH;
+
+    const OUTPUT: &str = "\
+src/a.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.
+/ws/src/b(1).ts(10,1): error TS2345: Argument of type '{ a: string; }' is not assignable.
+  Types of property 'a' are incompatible.
+    Type 'string' is not assignable to type 'number'.
+error TS5083: Cannot read file '/ws/tsconfig.base.json'.
+  ignored continuation of a skipped line
+
+Found 3 errors in 2 files.
+";
+
+    #[test]
+    fn parse_extracts_located_diagnostics() {
+        let findings = parse(OUTPUT, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        assert_eq!(findings.len(), 2);
+        let first = &findings[0];
+        assert_eq!(
+            (first.tool.as_str(), first.rule.as_str()),
+            ("tsc", "TS2322")
+        );
+        assert_eq!(
+            (first.file.as_str(), first.start_line, first.start_column),
+            ("src/a.ts", 3, 7)
+        );
+        assert_eq!(
+            first.message,
+            "Type 'string' is not assignable to type 'number'."
+        );
+        assert_eq!(first.severity, SEVERITY_ERROR);
+        let input = "tsc:TS2322:src/a.ts:3:7";
+        assert_eq!(
+            first.stable_id,
+            stable_id::hash_hex(input, DEFAULT_ID_LENGTH)
+        );
+    }
+
+    #[test]
+    fn parse_folds_continuation_lines() {
+        let findings = parse(OUTPUT, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        assert_eq!(findings[1].file, "src/b(1).ts");
+        assert_eq!(
+            findings[1].message,
+            "Argument of type '{ a: string; }' is not assignable.\n  \
+             Types of property 'a' are incompatible.\n    \
+             Type 'string' is not assignable to type 'number'."
+        );
+    }
+
+    #[test]
+    fn parse_locates_build_mode_diagnostics() {
+        let output = "\
+[12:00:00 PM] Projects in this build: \n    * packages/core/tsconfig.json
+    * packages/app/tsconfig.json
+
+[12:00:01 PM] Building project '/ws/packages/core/tsconfig.json'...
+
+packages/core/src/a.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.
+packages/app/tsconfig.json: src/main.ts(1,20): error TS2307: Cannot find module './missing'.
+app:typecheck: packages/app/src/b.ts(2,1): error TS1005: ';' expected.
+
+Found 3 errors.
+";
+        let findings = parse(output, "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH).unwrap();
+        let located: Vec<(&str, i32, &str)> = findings
+            .iter()
+            .map(|f| (f.file.as_str(), f.start_line, f.rule.as_str()))
+            .collect();
+        assert_eq!(
+            located,
+            vec![
+                ("packages/core/src/a.ts", 3, "TS2322"),
+                ("packages/app/src/main.ts", 1, "TS2307"),
+                ("packages/app/src/b.ts", 2, "TS1005"),
+            ]
+        );
+        assert_eq!(findings[1].message, "Cannot find module './missing'.");
+    }
+
+    #[test]
+    fn parse_keeps_warnings_above_min_severity() {
+        let output = "\
+src/a.ts(1,1): error TS2304: Cannot find name 'x'.
+src/b.ts(2,1): warning TS6133: 'y' is declared but never used.
+";
+        let severities = |min_severity| {
+            let findings = parse(output, "/ws", min_severity, DEFAULT_ID_LENGTH).unwrap();
+            findings.iter().map(|f| f.severity).collect::<Vec<_>>()
+        };
+        assert_eq!(severities(SEVERITY_ERROR), vec![SEVERITY_ERROR]);
+        assert_eq!(
+            severities(SEVERITY_WARNING),
+            vec![SEVERITY_ERROR, SEVERITY_WARNING]
+        );
+    }
+
+    #[test]
+    fn parse_empty_output_has_no_findings() {
+        assert!(parse("", "/ws", SEVERITY_ERROR, DEFAULT_ID_LENGTH)
+            .unwrap()
+            .is_empty());
+    }
+}
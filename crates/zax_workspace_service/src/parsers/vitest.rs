@@ -1,19 +1,38 @@
 //! Vitest JSON output parser.
 //!
 //! Parses Vitest JSON reporter output and extracts test failures.
+//!
+//! Two shapes are accepted: the flat `testResults` reporter format and the
+//! nested `suites` task tree emitted by newer Vitest versions. The shape is
+//! chosen by probing the top-level keys.
+//!
+//! When Vitest captures an assertion's `expected`/`actual` values separately
+//! from the message, they are kept as structured `expected`/`received` strings.
 
-use super::ParseError;
+use super::{is_empty_output, ParseError};
 use serde::Deserialize;
 
 /// Maximum message length before truncation.
 const MAX_MESSAGE_LENGTH: usize = 1000;
 
+/// Failure kind for a test whose assertions failed.
+pub const KIND_ASSERTION: &str = "assertion";
+/// Failure kind for a test file that failed before its tests ran (import
+/// errors, top-level throws).
+pub const KIND_FILE_ERROR: &str = "file_error";
+
 /// A parsed test failure from Vitest output.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TestFailure {
     pub test_id: String,
     pub file: String,
     pub message: String,
+    /// `KIND_ASSERTION` or `KIND_FILE_ERROR`.
+    pub kind: &'static str,
+    /// Expected value of a failed assertion, if Vitest captured one.
+    pub expected: Option<String>,
+    /// Actual value of a failed assertion, if Vitest captured one.
+    pub received: Option<String>,
 }
 
 /// Vitest JSON output root structure.
@@ -50,6 +69,92 @@ struct AssertionResult {
     status: String,
     #[serde(default)]
     failure_messages: Vec<String>,
+    #[serde(default)]
+    failure_details: Vec<AssertionDiff>,
+}
+
+/// Expected/actual values serialized alongside an assertion error.
+#[derive(Debug, Default, Deserialize)]
+struct AssertionDiff {
+    #[serde(default)]
+    expected: Option<serde_json::Value>,
+    #[serde(default)]
+    actual: Option<serde_json::Value>,
+}
+
+impl AssertionDiff {
+    /// Returns `(expected, received)` as display strings.
+    fn strings(diff: Option<&Self>) -> (Option<String>, Option<String>) {
+        let Some(diff) = diff else {
+            return (None, None);
+        };
+        (
+            value_string(diff.expected.as_ref()),
+            value_string(diff.actual.as_ref()),
+        )
+    }
+}
+
+/// Nested task tree output root structure.
+#[derive(Debug, Deserialize)]
+struct NestedOutput {
+    #[serde(default)]
+    suites: Vec<Task>,
+}
+
+/// A file, suite, or test node in the nested task tree.
+#[derive(Debug, Deserialize)]
+struct Task {
+    #[serde(default)]
+    name: String,
+    /// Test file path; only set on file-level suites
+    #[serde(default)]
+    filepath: Option<String>,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    result: Option<TaskResult>,
+    #[serde(default)]
+    tasks: Vec<Task>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskResult {
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    errors: Vec<TaskError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TaskError {
+    #[serde(default)]
+    message: String,
+    #[serde(flatten)]
+    diff: AssertionDiff,
+}
+
+impl Task {
+    fn failed(&self) -> bool {
+        self.result.as_ref().is_some_and(|r| r.state == "fail")
+    }
+
+    fn first_error(&self) -> Option<&TaskError> {
+        self.result.as_ref().and_then(|r| r.errors.first())
+    }
+
+    fn failure(&self, test_id: String, file: &str, kind: &'static str) -> TestFailure {
+        let error = self.first_error();
+        let (expected, received) = AssertionDiff::strings(error.map(|e| &e.diff));
+        TestFailure {
+            test_id,
+            file: file.to_string(),
+            message: truncate_message(error.map_or("", |e| e.message.as_str())),
+            kind,
+            expected,
+            received,
+        }
+    }
 }
 
 /// Parses Vitest JSON output and extracts all test failures.
@@ -59,9 +164,16 @@ struct AssertionResult {
 /// * `workspace_root` - Workspace root path for normalizing file paths
 ///
 /// # Returns
-/// List of test failures, or a `ParseError` if JSON is malformed
+/// List of test failures (none for empty content), or a `ParseError` if JSON is malformed
 pub fn parse(json_content: &str, workspace_root: &str) -> Result<Vec<TestFailure>, ParseError> {
-    let output: VitestOutput = serde_json::from_str(json_content)?;
+    if is_empty_output(json_content) {
+        return Ok(Vec::new());
+    }
+    let value: serde_json::Value = serde_json::from_str(json_content)?;
+    if is_nested_format(&value) {
+        return parse_nested(value, workspace_root);
+    }
+    let output: VitestOutput = serde_json::from_value(value)?;
     let mut failures = Vec::new();
 
     for test_result in output.test_results {
@@ -72,6 +184,50 @@ pub fn parse(json_content: &str, workspace_root: &str) -> Result<Vec<TestFailure
     Ok(failures)
 }
 
+/// Detects the nested task tree by its top-level `suites` key.
+fn is_nested_format(value: &serde_json::Value) -> bool {
+    value.get("testResults").is_none() && value.get("suites").is_some()
+}
+
+/// Flattens the nested task tree into failures. Test IDs join suite names
+/// with ` > `, matching the flat format so stable IDs agree across formats.
+fn parse_nested(
+    value: serde_json::Value,
+    workspace_root: &str,
+) -> Result<Vec<TestFailure>, ParseError> {
+    let output: NestedOutput = serde_json::from_value(value)?;
+    let mut failures = Vec::new();
+
+    for file_task in &output.suites {
+        let path = file_task.filepath.as_deref().unwrap_or(&file_task.name);
+        let file = normalize_path(path, workspace_root);
+        if file_task.failed() && file_task.tasks.is_empty() {
+            failures.push(file_task.failure(format!("{file}::file-error"), &file, KIND_FILE_ERROR));
+            continue;
+        }
+        collect_task_failures(&file_task.tasks, &mut Vec::new(), &file, &mut failures);
+    }
+
+    Ok(failures)
+}
+
+fn collect_task_failures(
+    tasks: &[Task],
+    ancestors: &mut Vec<String>,
+    file: &str,
+    failures: &mut Vec<TestFailure>,
+) {
+    for task in tasks {
+        if task.kind == "suite" {
+            ancestors.push(task.name.clone());
+            collect_task_failures(&task.tasks, ancestors, file, failures);
+            ancestors.pop();
+        } else if task.failed() {
+            failures.push(task.failure(build_test_id(ancestors, &task.name), file, KIND_ASSERTION));
+        }
+    }
+}
+
 fn process_test_result(test_result: &TestResult, file: &str, failures: &mut Vec<TestFailure>) {
     // Handle file-level errors (status: failed, empty assertionResults, non-null message)
     if test_result.status == "failed"
@@ -83,6 +239,9 @@ fn process_test_result(test_result: &TestResult, file: &str, failures: &mut Vec<
             test_id: format!("{file}::file-error"),
             file: file.to_string(),
             message,
+            kind: KIND_FILE_ERROR,
+            expected: None,
+            received: None,
         });
         return;
     }
@@ -92,21 +251,32 @@ fn process_test_result(test_result: &TestResult, file: &str, failures: &mut Vec<
         if assertion.status == "failed" {
             let test_id = build_test_id(&assertion.ancestor_titles, &assertion.title);
             let message = extract_message(&assertion.failure_messages);
+            let (expected, received) = AssertionDiff::strings(assertion.failure_details.first());
             failures.push(TestFailure {
                 test_id,
                 file: file.to_string(),
                 message,
+                kind: KIND_ASSERTION,
+                expected,
+                received,
             });
         }
     }
 }
 
-fn normalize_path(absolute_path: &str, workspace_root: &str) -> String {
-    // Strip workspace prefix if present
-    if let Some(stripped) = absolute_path.strip_prefix(workspace_root) {
-        stripped.strip_prefix('/').unwrap_or(stripped).to_string()
-    } else {
-        absolute_path.to_string()
+/// Normalizes a test file path to be workspace-relative.
+///
+/// Older Vitest versions emit `name` already relative to `rootDir`, newer ones
+/// emit absolute paths. The root is only stripped when the path starts with it
+/// at a path boundary, so `/ws-other/a.ts` is never mangled by root `/ws`.
+pub(super) fn normalize_path(path: &str, workspace_root: &str) -> String {
+    let root = workspace_root.trim_end_matches('/');
+    if root.is_empty() {
+        return path.to_string();
+    }
+    match path.strip_prefix(root) {
+        Some(rest) if rest.starts_with('/') => rest.trim_start_matches('/').to_string(),
+        _ => path.to_string(),
     }
 }
 
@@ -123,7 +293,19 @@ fn extract_message(failure_messages: &[String]) -> String {
     truncate_message(raw)
 }
 
-fn truncate_message(message: &str) -> String {
+/// Renders a captured value: strings as-is, anything else as JSON.
+fn value_string(value: Option<&serde_json::Value>) -> Option<String> {
+    let text = match value? {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    Some(truncate_message(&text))
+}
+
+/// Strips ANSI escape sequences left by Vitest runs with colors forced on,
+/// then truncates so stored messages are plain text.
+pub(super) fn truncate_message(message: &str) -> String {
+    let message = strip_ansi(message);
     if message.chars().count() > MAX_MESSAGE_LENGTH {
         format!(
             "{}...",
@@ -133,10 +315,31 @@ fn truncate_message(message: &str) -> String {
                 .collect::<String>()
         )
     } else {
-        message.to_string()
+        message
     }
 }
 
+/// Removes CSI (`ESC [ ... final`) and other two-byte `ESC x` sequences.
+fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        if chars.next() == Some('[') {
+            // Parameter and intermediate bytes run until a final byte in @..~
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    out
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -176,11 +379,36 @@ mod tests {
         assert_eq!(f[0].file, "src/t.ts");
     }
 
+    #[test]
+    fn parse_captures_assertion_expected_and_actual() {
+        let json = r#"{"testResults":[{"name":"/ws/t.ts","status":"failed","assertionResults":[
+            {"title":"eq","status":"failed","failureMessages":["AssertionError: expected { a: 1 } to deeply equal { a: 2 }"],
+             "failureDetails":[{"expected":{"a":2},"actual":{"a":1}}]},
+            {"title":"plain","status":"failed","failureMessages":["boom"]}
+        ]}]}"#;
+        let f = parse(json, "/ws").unwrap();
+        assert_eq!(f[0].expected.as_deref(), Some(r#"{"a":2}"#));
+        assert_eq!(f[0].received.as_deref(), Some(r#"{"a":1}"#));
+        assert!(f[0].message.starts_with("AssertionError"));
+        // Without captured values, consumers fall back to the raw message
+        assert_eq!(
+            (f[1].expected.as_deref(), f[1].received.as_deref()),
+            (None, None)
+        );
+        assert_eq!(f[1].message, "boom");
+    }
+
     #[test]
     fn parse_returns_empty_for_no_results() {
         assert!(parse(r#"{"testResults":[]}"#, "/ws").unwrap().is_empty());
     }
 
+    #[test]
+    fn parse_empty_content_has_no_failures() {
+        assert!(parse("", "/ws").unwrap().is_empty());
+        assert!(parse("\n  \n", "/ws").unwrap().is_empty());
+    }
+
     #[test]
     fn parse_returns_error_for_malformed_json() {
         assert!(matches!(
@@ -214,6 +442,23 @@ mod tests {
         assert!(result[0].message.ends_with("..."));
     }
 
+    #[test]
+    fn parse_strips_ansi_color_codes() {
+        // JSON-escaped, as the reporter writes them
+        let colored = concat!(
+            r"\u001b[31mAssertionError\u001b[39m: ",
+            r"expected \u001b[32m1\u001b[39m\u001b[2m;\u001b[22m",
+        );
+        let json = make_json(
+            "/ws/t.ts",
+            "failed",
+            None,
+            &assertion(&[], "t", "failed", colored),
+        );
+        let result = parse(&json, "/ws").unwrap();
+        assert_eq!(result[0].message, "AssertionError: expected 1;");
+    }
+
     #[test]
     fn parse_preserves_short_messages() {
         let short = "short error";
@@ -241,6 +486,20 @@ mod tests {
         assert_eq!(f[0].test_id, "src/b.ts::file-error");
     }
 
+    #[test]
+    fn file_level_error_is_tagged_file_error() {
+        let broken = make_json("/ws/src/b.ts", "failed", Some("SyntaxError"), "");
+        assert_eq!(parse(&broken, "/ws").unwrap()[0].kind, KIND_FILE_ERROR);
+
+        let failing = make_json(
+            "/ws/t.ts",
+            "failed",
+            None,
+            &assertion(&[], "t", "failed", "e"),
+        );
+        assert_eq!(parse(&failing, "/ws").unwrap()[0].kind, KIND_ASSERTION);
+    }
+
     #[test]
     fn parse_constructs_test_id_from_nested_ancestors() {
         let nested = make_json(
@@ -251,4 +510,107 @@ mod tests {
         );
         assert_eq!(parse(&nested, "/ws").unwrap()[0].test_id, "A > B > C > d");
     }
+
+    /// Nested task tree: one failing test two suites deep, one passing test,
+    /// and a file that failed to load.
+    const NESTED_FIXTURE: &str = r#"{
+      "suites": [
+        {
+          "name": "src/math.test.ts",
+          "filepath": "/ws/src/math.test.ts",
+          "type": "suite",
+          "result": {"state": "fail"},
+          "tasks": [
+            {
+              "name": "Math",
+              "type": "suite",
+              "tasks": [
+                {
+                  "name": "add",
+                  "type": "suite",
+                  "tasks": [
+                    {"name": "handles negatives", "type": "test",
+                     "result": {"state": "fail", "errors": [{"message": "expected -1 to be 1", "expected": "1", "actual": "-1"}]}},
+                    {"name": "handles zero", "type": "test", "result": {"state": "pass"}}
+                  ]
+                }
+              ]
+            },
+            {"name": "top level", "type": "test",
+             "result": {"state": "fail", "errors": [{"message": "boom"}]}}
+          ]
+        },
+        {
+          "name": "src/broken.test.ts",
+          "filepath": "/ws/src/broken.test.ts",
+          "type": "suite",
+          "result": {"state": "fail", "errors": [{"message": "SyntaxError"}]},
+          "tasks": []
+        }
+      ]
+    }"#;
+
+    #[test]
+    fn parse_flattens_nested_task_tree() {
+        let f = parse(NESTED_FIXTURE, "/ws").unwrap();
+        assert_eq!(
+            f,
+            vec![
+                TestFailure {
+                    test_id: "Math > add > handles negatives".into(),
+                    file: "src/math.test.ts".into(),
+                    message: "expected -1 to be 1".into(),
+                    kind: KIND_ASSERTION,
+                    expected: Some("1".into()),
+                    received: Some("-1".into()),
+                },
+                TestFailure {
+                    test_id: "top level".into(),
+                    file: "src/math.test.ts".into(),
+                    message: "boom".into(),
+                    kind: KIND_ASSERTION,
+                    expected: None,
+                    received: None,
+                },
+                TestFailure {
+                    test_id: "src/broken.test.ts::file-error".into(),
+                    file: "src/broken.test.ts".into(),
+                    message: "SyntaxError".into(),
+                    kind: KIND_FILE_ERROR,
+                    expected: None,
+                    received: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_and_flat_formats_share_test_ids() {
+        let flat = make_json(
+            "/ws/src/math.test.ts",
+            "failed",
+            None,
+            &assertion(&["Math", "add"], "handles negatives", "failed", "e"),
+        );
+        let flat_id = &parse(&flat, "/ws").unwrap()[0].test_id;
+        assert_eq!(flat_id, &parse(NESTED_FIXTURE, "/ws").unwrap()[0].test_id);
+    }
+
+    #[test]
+    fn normalize_path_strips_absolute_root() {
+        assert_eq!(normalize_path("/ws/src/t.ts", "/ws"), "src/t.ts");
+        assert_eq!(normalize_path("/ws/src/t.ts", "/ws/"), "src/t.ts");
+    }
+
+    #[test]
+    fn normalize_path_keeps_already_relative() {
+        assert_eq!(normalize_path("src/t.ts", "/ws"), "src/t.ts");
+        assert_eq!(normalize_path("src/t.ts", ""), "src/t.ts");
+    }
+
+    #[test]
+    fn normalize_path_ignores_deceptive_prefix() {
+        assert_eq!(normalize_path("/ws-other/t.ts", "/ws"), "/ws-other/t.ts");
+        assert_eq!(normalize_path("wsx/t.ts", "ws"), "wsx/t.ts");
+    }
 }
@@ -3,36 +3,107 @@
 // tonic::Status is 3 words (24 bytes) which exceeds clippy's default threshold.
 // This is intentional - Status provides rich error info for gRPC responses.
 #![allow(clippy::result_large_err)]
-// Allow eprintln! for logging - output goes to engine.log via stderr redirect.
-#![allow(clippy::print_stderr)]
 
-use crate::normalize::{path::validate_package_scope, stable_id};
-use crate::parsers::{eslint, vitest};
+use crate::logging::log_line;
+use crate::normalize::{path::validate_package_scope, stable_id, workspace};
+use crate::parsers::{self, eslint, libtest, playwright, stylelint, tsc, vitest};
 use crate::store::{self, FindingRow, TestFailureRow};
-use crate::zax::v1::{ArtifactKind, ArtifactManifest};
-use rusqlite::Connection;
-use std::collections::HashSet;
+use crate::zax::v1::{ArtifactKind, ArtifactManifest, ArtifactRef};
+use flate2::read::GzDecoder;
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 use tonic::Status;
 
-/// Maximum artifact file size in bytes (100MB).
+/// Maximum artifact file size in bytes (100MB), also applied after decompression.
 const MAX_ARTIFACT_SIZE: u64 = 100 * 1024 * 1024;
+/// Leading bytes of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Runs returned by `GetRunStats` and `GetRunHistory` when the request sets
+/// no limit.
+const DEFAULT_RUN_STATS_LIMIT: usize = 20;
+/// Upper bound on runs returned by `GetRunStats` and `GetRunHistory`.
+const MAX_RUN_STATS_LIMIT: usize = 1000;
+/// `GetFindingTrend` bucket width when the request sets none: one day.
+const DEFAULT_TREND_BUCKET_SECS: u32 = 86_400;
+/// Distinguishes run ids generated within the same clock tick.
+static RUN_ID_SEQUENCE: AtomicU64 = AtomicU64::new(0);
 
 /// Shared state for RPC handlers.
 pub struct RpcState {
-    pub cache_dir: std::path::PathBuf,
+    /// Shared connection for queries.
     pub conn: Arc<Mutex<Connection>>,
+    /// Per-workspace connections for ingests and other writes.
+    pub writers: store::WorkspaceConnections,
+    /// Query-only replica: mutating RPCs are rejected.
+    pub read_only: bool,
+    /// Re-read row counts after each ingest commit and fail on mismatch.
+    pub verify_ingest: bool,
+    /// Allow administrative RPCs such as `ClearWorkspaceData`.
+    pub admin: bool,
+    /// Directories artifact files must resolve under (see `default_artifact_roots`).
+    pub artifact_roots: Vec<std::path::PathBuf>,
+    /// Carry failures of renamed test files over instead of reporting them
+    /// as fixed + new (see `detect_renames`).
+    pub detect_renames: bool,
+    /// Store `ESLint` warnings alongside errors. Warnings then also count
+    /// towards finding deltas.
+    pub eslint_warnings: bool,
+    /// Fold workspace ids to lowercase, for clients whose ids are paths on
+    /// case-insensitive filesystems.
+    pub lowercase_workspace_ids: bool,
+    /// Workspace root that artifacts the Engine does not normalize (Playwright
+    /// reports) are made relative to.
+    pub workspace_root: std::path::PathBuf,
+}
+
+/// Rejects mutating RPCs on a read-only instance.
+pub fn ensure_writable(state: &RpcState) -> Result<(), Status> {
+    if state.read_only {
+        return Err(Status::failed_precondition(
+            "workspace service is running in read-only mode",
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects administrative RPCs unless started with `--admin`.
+pub fn ensure_admin(state: &RpcState) -> Result<(), Status> {
+    if !state.admin {
+        return Err(Status::permission_denied(
+            "administrative RPCs require --admin",
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects affected selection on a read-only instance.
+///
+/// Replicas skip the watcher and graph build, so there is nothing to select from.
+pub fn ensure_affected_available(state: &RpcState) -> Result<(), Status> {
+    if state.read_only {
+        return Err(Status::failed_precondition(
+            "affected selection is unavailable in read-only mode",
+        ));
+    }
+    Ok(())
 }
 
 /// Handles `IngestManifest` RPC.
+///
+/// With `extract_json`, reporter JSON is extracted from surrounding log noise
+/// before parsing. Returns the run id, generated if the manifest omitted it.
 pub fn ingest_manifest(
     state: &RpcState,
     manifest: &ArtifactManifest,
     package_scope: &str,
-) -> Result<(), Status> {
-    eprintln!(
+    extract_json: bool,
+) -> Result<String, Status> {
+    log_line!(
         "[rpc] IngestManifest: workspace={}, run={}, artifacts={}, package={}",
         manifest.workspace_id,
         manifest.run_id,
@@ -43,11 +114,14 @@ pub fn ingest_manifest(
             package_scope
         }
     );
+    ensure_writable(state)?;
+    let started_at_ms = unix_millis()?;
     validate_manifest(manifest)?;
+    let manifest = &resolve_manifest(state, manifest)?;
     validate_package_scope(package_scope)
         .map_err(|e| Status::invalid_argument(format!("invalid package_scope: {e}")))?;
-    let (failures, findings) = parse_artifacts(state, manifest)?;
-    eprintln!(
+    let (failures, findings, artifact_bytes) = parse_artifacts(state, manifest, extract_json)?;
+    log_line!(
         "[rpc] Parsed: {} test failures, {} findings",
         failures.len(),
         findings.len()
@@ -56,50 +130,169 @@ pub fn ingest_manifest(
         failures: &failures,
         findings: &findings,
         package_scope,
+        started_at_ms,
+        artifact_bytes,
     };
-    store_all(state, manifest, &artifacts)
+    store_all(state, manifest, &artifacts)?;
+    if state.verify_ingest {
+        verify_ingest(state, &manifest.run_id, &artifacts)?;
+    }
+    Ok(manifest.run_id.clone())
 }
 
 fn validate_manifest(manifest: &ArtifactManifest) -> Result<(), Status> {
     if manifest.workspace_id.is_empty() {
         return Err(Status::invalid_argument("workspace_id is required"));
     }
-    if manifest.run_id.is_empty() {
-        return Err(Status::invalid_argument("run_id is required"));
+    if manifest.attributes.contains_key("") {
+        return Err(Status::invalid_argument("attribute keys must not be empty"));
     }
     Ok(())
 }
 
+/// Normalizes the workspace id and fills in a generated run id if absent.
+fn resolve_manifest(
+    state: &RpcState,
+    manifest: &ArtifactManifest,
+) -> Result<ArtifactManifest, Status> {
+    let mut resolved = ArtifactManifest {
+        workspace_id: normalize_workspace_id(state, &manifest.workspace_id)?,
+        ..manifest.clone()
+    };
+    if resolved.run_id.is_empty() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| Status::internal(format!("time error: {e}")))?
+            .as_nanos();
+        let sequence = RUN_ID_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        resolved.run_id = generate_run_id(&resolved, nanos, sequence);
+        log_line!("[rpc] Generated run_id={}", resolved.run_id);
+    }
+    Ok(resolved)
+}
+
+/// Derives a run id from the workspace, artifact hashes, ingest time and a
+/// process-wide sequence number. The sequence keeps ingests of identical
+/// artifacts within one clock tick distinct.
+fn generate_run_id(manifest: &ArtifactManifest, timestamp_nanos: u128, sequence: u64) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(manifest.workspace_id.as_bytes());
+    for artifact in &manifest.artifacts {
+        hasher.update(b"\0");
+        hasher.update(artifact.hash.as_bytes());
+    }
+    hasher.update(&timestamp_nanos.to_le_bytes());
+    hasher.update(&sequence.to_le_bytes());
+    hasher.finalize().to_hex()[..32].to_string()
+}
+
+/// Normalizes a client's workspace id, rejecting empty and oversized ones.
+pub fn normalize_workspace_id(state: &RpcState, workspace_id: &str) -> Result<String, Status> {
+    canonical_workspace_id(state, workspace_id)
+        .map_err(|e| Status::invalid_argument(format!("invalid workspace_id: {e}")))
+}
+
+/// `workspace::normalize_id` with this service's settings: absolute ids are
+/// made relative to the directory containing the served workspace root.
+fn canonical_workspace_id(
+    state: &RpcState,
+    workspace_id: &str,
+) -> Result<String, workspace::WorkspaceIdError> {
+    let base = state
+        .workspace_root
+        .parent()
+        .map(|parent| parent.to_string_lossy());
+    workspace::normalize_id(workspace_id, base.as_deref(), state.lowercase_workspace_ids)
+}
+
 fn parse_artifacts(
     state: &RpcState,
     manifest: &ArtifactManifest,
-) -> Result<(Vec<TestFailureRow>, Vec<FindingRow>), Status> {
+    extract_json: bool,
+) -> Result<(Vec<TestFailureRow>, Vec<FindingRow>, i64), Status> {
     let mut failures = Vec::new();
     let mut findings = Vec::new();
+    let mut bytes = 0;
 
     for artifact in &manifest.artifacts {
-        let path = validate_artifact_path(&state.cache_dir, &artifact.path)?;
-        let content = read_artifact_file(&path)?;
+        let path = validate_artifact_path(&state.artifact_roots, &artifact.path)?;
+        let raw = read_artifact_file(&path)?;
+        bytes += i64::try_from(raw.len()).unwrap_or(i64::MAX);
+        // Line-delimited libtest events and tsc's text output are not a
+        // single embedded JSON value
+        let rust_tests = artifact.kind == ArtifactKind::RustTestFailure as i32;
+        let tsc = artifact.kind == ArtifactKind::TscFinding as i32;
+        let content = if extract_json && !rust_tests && !tsc && !parsers::is_empty_output(&raw) {
+            parsers::extract_json(&raw).map_err(|e| {
+                log_line!("[rpc] JSON extraction error: {e}");
+                Status::invalid_argument(format!("parse error: {e}"))
+            })?
+        } else {
+            raw.as_str()
+        };
 
         if artifact.kind == ArtifactKind::TestFailure as i32 {
-            failures = parse_test_failures(&content)?;
+            failures.extend(parse_test_failures(content, manifest)?);
+        } else if rust_tests {
+            failures.extend(parse_rust_test_failures(content, manifest)?);
+        } else if artifact.kind == ArtifactKind::PlaywrightTestFailure as i32 {
+            failures.extend(parse_playwright_failures(state, content, manifest)?);
         } else if artifact.kind == ArtifactKind::Finding as i32 {
-            findings = parse_findings(&content)?;
+            findings.extend(parse_findings(
+                content,
+                manifest,
+                min_severity(state, artifact)?,
+            )?);
+        } else if artifact.kind == ArtifactKind::StylelintFinding as i32 {
+            let floor = min_severity(state, artifact)?;
+            findings.extend(parse_stylelint_findings(content, manifest, floor)?);
+        } else if tsc {
+            let floor = min_severity(state, artifact)?;
+            findings.extend(parse_tsc_findings(content, manifest, floor)?);
         }
     }
-    Ok((failures, findings))
+    Ok((failures, dedupe_findings(findings), bytes))
+}
+
+/// Lowest finding severity stored from an artifact: its own `min_severity`
+/// if set, otherwise warnings only with `eslint_warnings`.
+fn min_severity(state: &RpcState, artifact: &ArtifactRef) -> Result<i32, Status> {
+    match artifact.min_severity {
+        0 if state.eslint_warnings => Ok(eslint::SEVERITY_WARNING),
+        0 | 2 => Ok(eslint::SEVERITY_ERROR),
+        1 => Ok(eslint::SEVERITY_WARNING),
+        n => Err(Status::invalid_argument(format!(
+            "unsupported min_severity {n} (expected 1 for warnings or 2 for errors)"
+        ))),
+    }
+}
+
+/// Drops repeated findings across Finding artifacts, keeping the first row
+/// per `(tool, stable_id)` so each tool keeps its own attribution.
+fn dedupe_findings(findings: Vec<FindingRow>) -> Vec<FindingRow> {
+    let mut seen = HashSet::new();
+    findings
+        .into_iter()
+        .filter(|f| seen.insert((f.tool.clone(), f.stable_id.clone())))
+        .collect()
+}
+
+/// The artifact roots every service accepts: `<cache_dir>/artifacts`.
+pub fn default_artifact_roots(cache_dir: &Path) -> Vec<std::path::PathBuf> {
+    vec![cache_dir.join("artifacts")]
 }
 
+/// Resolves an artifact path, requiring it to lie under one of `roots`.
+/// Canonicalizing first means `..` segments and symlinks cannot escape.
 fn validate_artifact_path(
-    cache_dir: &Path,
+    roots: &[std::path::PathBuf],
     artifact_path: &str,
 ) -> Result<std::path::PathBuf, Status> {
     let path = std::path::PathBuf::from(artifact_path);
     let canonical = path
         .canonicalize()
         .map_err(|_| Status::not_found(format!("artifact file not found: {artifact_path}")))?;
-    let artifacts_dir = cache_dir.join("artifacts");
-    if !canonical.starts_with(&artifacts_dir) {
+    if !roots.iter().any(|root| canonical.starts_with(root)) {
         return Err(Status::not_found(
             "artifact path outside artifacts directory",
         ));
@@ -107,6 +300,8 @@ fn validate_artifact_path(
     Ok(canonical)
 }
 
+/// Reads an artifact as UTF-8 text, transparently decompressing gzip files
+/// (detected by magic bytes or a `.gz` extension).
 fn read_artifact_file(path: &Path) -> Result<String, Status> {
     let metadata =
         std::fs::metadata(path).map_err(|_| Status::not_found("artifact file not found"))?;
@@ -116,8 +311,32 @@ fn read_artifact_file(path: &Path) -> Result<String, Status> {
             metadata.len()
         )));
     }
-    std::fs::read_to_string(path)
-        .map_err(|e| Status::internal(format!("failed to read artifact: {e}")))
+    let bytes = std::fs::read(path)
+        .map_err(|e| Status::internal(format!("failed to read artifact: {e}")))?;
+    let is_gzip = bytes.starts_with(&GZIP_MAGIC) || path.extension().is_some_and(|ext| ext == "gz");
+    let bytes = if is_gzip {
+        decompress_gzip(&bytes, MAX_ARTIFACT_SIZE)?
+    } else {
+        bytes
+    };
+    String::from_utf8(bytes).map_err(|e| Status::internal(format!("failed to read artifact: {e}")))
+}
+
+/// Decompresses gzip data, failing once output exceeds `limit` bytes.
+///
+/// Reading through `take` bounds memory regardless of the compression ratio.
+fn decompress_gzip(bytes: &[u8], limit: u64) -> Result<Vec<u8>, Status> {
+    let mut decompressed = Vec::new();
+    GzDecoder::new(bytes)
+        .take(limit + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| Status::invalid_argument(format!("invalid gzip artifact: {e}")))?;
+    if decompressed.len() as u64 > limit {
+        return Err(Status::invalid_argument(
+            "decompressed artifact exceeds 100MB limit",
+        ));
+    }
+    Ok(decompressed)
 }
 
 /// Parses test failures from pre-normalized Vitest JSON output.
@@ -125,20 +344,83 @@ fn read_artifact_file(path: &Path) -> Result<String, Status> {
 /// NOTE: The Engine layer (TypeScript) normalizes file paths before writing
 /// artifact files, stripping the `workspace_root` prefix. Therefore we pass
 /// empty `workspace_root` here - paths are already relative.
-fn parse_test_failures(content: &str) -> Result<Vec<TestFailureRow>, Status> {
+fn parse_test_failures(
+    content: &str,
+    manifest: &ArtifactManifest,
+) -> Result<Vec<TestFailureRow>, Status> {
     let parsed = vitest::parse(content, "").map_err(|e| {
-        eprintln!("[rpc] Vitest parse error: {e}");
+        log_line!("[rpc] Vitest parse error: {e}");
+        Status::invalid_argument(format!("parse error: {e}"))
+    })?;
+    to_failure_rows(parsed, manifest)
+}
+
+/// Parses test failures from Rust libtest JSON events.
+fn parse_rust_test_failures(
+    content: &str,
+    manifest: &ArtifactManifest,
+) -> Result<Vec<TestFailureRow>, Status> {
+    let parsed = libtest::parse(content).map_err(|e| {
+        log_line!("[rpc] libtest parse error: {e}");
         Status::invalid_argument(format!("parse error: {e}"))
     })?;
-    Ok(parsed
+    to_failure_rows(parsed, manifest)
+}
+
+/// Parses test failures from a Playwright JSON report. Unlike Vitest output,
+/// reports reach the service unnormalized, so spec paths are made relative
+/// to the workspace root here.
+fn parse_playwright_failures(
+    state: &RpcState,
+    content: &str,
+    manifest: &ArtifactManifest,
+) -> Result<Vec<TestFailureRow>, Status> {
+    let root = state.workspace_root.to_string_lossy();
+    let parsed = playwright::parse(content, &root).map_err(|e| {
+        log_line!("[rpc] Playwright parse error: {e}");
+        Status::invalid_argument(format!("parse error: {e}"))
+    })?;
+    to_failure_rows(parsed, manifest)
+}
+
+/// Converts parsed failures to rows, computing stable ids with the
+/// manifest's id scheme version and salt.
+fn to_failure_rows(
+    parsed: Vec<vitest::TestFailure>,
+    manifest: &ArtifactManifest,
+) -> Result<Vec<TestFailureRow>, Status> {
+    let version = manifest.stable_id_version;
+    let length = stable_id_length(manifest)?;
+    parsed
         .into_iter()
-        .map(|f| TestFailureRow {
-            stable_id: stable_id::compute(&f.file, &f.test_id),
-            test_id: f.test_id,
-            file: f.file,
-            message: f.message,
+        .map(|f| {
+            let id = stable_id::compute_versioned(&f.file, &f.test_id, version, length)
+                .ok_or_else(|| {
+                    Status::invalid_argument(format!("unsupported stable_id_version {version}"))
+                })?;
+            Ok(TestFailureRow {
+                stable_id: stable_id::salted(id, &manifest.stable_id_salt),
+                test_id: f.test_id,
+                file: f.file,
+                message: f.message,
+                kind: f.kind.to_string(),
+                expected: f.expected,
+                received: f.received,
+            })
         })
-        .collect())
+        .collect()
+}
+
+/// Resolves the manifest's stable id length, rejecting unsupported ones.
+fn stable_id_length(manifest: &ArtifactManifest) -> Result<usize, Status> {
+    let requested = manifest.stable_id_length;
+    stable_id::id_length(requested).ok_or_else(|| {
+        Status::invalid_argument(format!(
+            "unsupported stable_id_length {requested} (expected {}-{})",
+            stable_id::DEFAULT_ID_LENGTH,
+            stable_id::MAX_ID_LENGTH
+        ))
+    })
 }
 
 /// Parses findings from pre-normalized `ESLint` JSON output.
@@ -146,15 +428,53 @@ fn parse_test_failures(content: &str) -> Result<Vec<TestFailureRow>, Status> {
 /// NOTE: The Engine layer (TypeScript) normalizes file paths before writing
 /// artifact files, stripping the `workspace_root` prefix. Therefore we pass
 /// empty `workspace_root` here - paths are already relative.
-fn parse_findings(content: &str) -> Result<Vec<FindingRow>, Status> {
-    let parsed = eslint::parse(content, "").map_err(|e| {
-        eprintln!("[rpc] ESLint parse error: {e}");
+fn parse_findings(
+    content: &str,
+    manifest: &ArtifactManifest,
+    min_severity: i32,
+) -> Result<Vec<FindingRow>, Status> {
+    let length = stable_id_length(manifest)?;
+    let parsed = eslint::parse(content, "", min_severity, length).map_err(|e| {
+        log_line!("[rpc] ESLint parse error: {e}");
+        Status::invalid_argument(format!("parse error: {e}"))
+    })?;
+    Ok(to_finding_rows(parsed, &manifest.stable_id_salt))
+}
+
+/// Parses findings from Stylelint JSON output, with paths relative like
+/// `ESLint` artifacts.
+fn parse_stylelint_findings(
+    content: &str,
+    manifest: &ArtifactManifest,
+    min_severity: i32,
+) -> Result<Vec<FindingRow>, Status> {
+    let length = stable_id_length(manifest)?;
+    let parsed = stylelint::parse(content, "", min_severity, length).map_err(|e| {
+        log_line!("[rpc] Stylelint parse error: {e}");
+        Status::invalid_argument(format!("parse error: {e}"))
+    })?;
+    Ok(to_finding_rows(parsed, &manifest.stable_id_salt))
+}
+
+/// Parses findings from `tsc --pretty false` diagnostics.
+fn parse_tsc_findings(
+    content: &str,
+    manifest: &ArtifactManifest,
+    min_severity: i32,
+) -> Result<Vec<FindingRow>, Status> {
+    let length = stable_id_length(manifest)?;
+    let parsed = tsc::parse(content, "", min_severity, length).map_err(|e| {
+        log_line!("[rpc] tsc parse error: {e}");
         Status::invalid_argument(format!("parse error: {e}"))
     })?;
-    Ok(parsed
+    Ok(to_finding_rows(parsed, &manifest.stable_id_salt))
+}
+
+fn to_finding_rows(parsed: Vec<eslint::Finding>, salt: &str) -> Vec<FindingRow> {
+    parsed
         .into_iter()
         .map(|f| FindingRow {
-            stable_id: f.stable_id,
+            stable_id: stable_id::salted(f.stable_id, salt),
             tool: f.tool,
             rule: f.rule,
             file: f.file,
@@ -163,8 +483,11 @@ fn parse_findings(content: &str) -> Result<Vec<FindingRow>, Status> {
             end_line: f.end_line,
             end_column: f.end_column,
             message: f.message,
+            category: f.category,
+            fixable: f.fixable,
+            severity: f.severity,
         })
-        .collect())
+        .collect()
 }
 
 /// Parsed artifacts to store.
@@ -172,6 +495,10 @@ struct ParsedArtifacts<'a> {
     failures: &'a [TestFailureRow],
     findings: &'a [FindingRow],
     package_scope: &'a str,
+    /// Unix milliseconds when the ingest request arrived.
+    started_at_ms: i64,
+    /// Total size of the artifacts as read (decompressed).
+    artifact_bytes: i64,
 }
 
 fn store_all(
@@ -183,42 +510,481 @@ fn store_all(
         .duration_since(UNIX_EPOCH)
         .map_err(|e| Status::internal(format!("time error: {e}")))?
         .as_secs() as i64;
-    let mut conn = state
-        .conn
-        .lock()
-        .map_err(|_| Status::internal("lock error"))?;
+    let writer = workspace_writer(state, &manifest.workspace_id)?;
+    let mut conn = writer.lock().map_err(|_| Status::internal("lock error"))?;
+    // Immediate: a deferred read-then-write transaction cannot wait for a
+    // concurrent writer and would fail with SQLITE_BUSY instead
     let tx = conn
-        .transaction()
+        .transaction_with_behavior(TransactionBehavior::Immediate)
         .map_err(|e| Status::internal(format!("transaction error: {e}")))?;
+    let renames = if state.detect_renames {
+        renames_since_previous_run(&tx, &manifest.workspace_id, artifacts)?
+    } else {
+        Vec::new()
+    };
     store::insert_run(&tx, &manifest.workspace_id, &manifest.run_id, now)
         .map_err(|e| Status::internal(format!("insert run: {e}")))?;
-    store::insert_test_failures(&tx, &manifest.run_id, artifacts.package_scope, artifacts.failures)
-        .map_err(|e| Status::internal(format!("insert failures: {e}")))?;
-    store::insert_findings(&tx, &manifest.run_id, artifacts.package_scope, artifacts.findings)
-        .map_err(|e| Status::internal(format!("insert findings: {e}")))?;
+    store::record_run_package_scope(&tx, &manifest.run_id, artifacts.package_scope)
+        .map_err(|e| Status::internal(format!("record package scope: {e}")))?;
+    insert_run_rows(&tx, manifest, artifacts, &renames)?;
     store::complete_run(&tx, &manifest.run_id, now)
         .map_err(|e| Status::internal(format!("complete run: {e}")))?;
+    let stats = store::IngestStats {
+        started_at_ms: artifacts.started_at_ms,
+        completed_at_ms: unix_millis()?,
+        artifact_bytes: artifacts.artifact_bytes,
+    };
+    store::record_ingest_stats(&tx, &manifest.run_id, &stats)
+        .map_err(|e| Status::internal(format!("record ingest stats: {e}")))?;
     tx.commit()
         .map_err(|e| Status::internal(format!("commit: {e}")))?;
     Ok(())
 }
 
+/// Inserts the parsed rows that belong to a run.
+fn insert_run_rows(
+    tx: &Transaction,
+    manifest: &ArtifactManifest,
+    artifacts: &ParsedArtifacts,
+    renames: &[(String, String)],
+) -> Result<(), Status> {
+    store::insert_test_failures(
+        tx,
+        &manifest.run_id,
+        artifacts.package_scope,
+        artifacts.failures,
+    )
+    .map_err(|e| Status::internal(format!("insert failures: {e}")))?;
+    store::insert_test_renames(tx, &manifest.run_id, renames)
+        .map_err(|e| Status::internal(format!("insert renames: {e}")))?;
+    store::insert_findings(
+        tx,
+        &manifest.run_id,
+        artifacts.package_scope,
+        artifacts.findings,
+    )
+    .map_err(|e| Status::internal(format!("insert findings: {e}")))?;
+    store::insert_run_attributes(tx, &manifest.run_id, &manifest.attributes)
+        .map_err(|e| Status::internal(format!("insert attributes: {e}")))?;
+    Ok(())
+}
+
+fn workspace_writer(
+    state: &RpcState,
+    workspace_id: &str,
+) -> Result<Arc<Mutex<Connection>>, Status> {
+    state
+        .writers
+        .get(workspace_id)
+        .map_err(|e| Status::internal(format!("open connection: {e}")))
+}
+
+/// Detects test files renamed since the workspace's latest completed run.
+fn renames_since_previous_run(
+    conn: &Connection,
+    workspace_id: &str,
+    artifacts: &ParsedArtifacts,
+) -> Result<Vec<(String, String)>, Status> {
+    let runs = store::get_recent_runs(conn, workspace_id, 1, false)
+        .map_err(|e| Status::internal(format!("query runs: {e}")))?;
+    let Some(previous_run) = runs.first() else {
+        return Ok(Vec::new());
+    };
+    let previous =
+        store::get_failure_identities_scoped(conn, &previous_run.run_id, artifacts.package_scope)
+            .map_err(|e| Status::internal(format!("query previous failures: {e}")))?;
+    let renames = detect_renames(&previous, artifacts.failures);
+    if !renames.is_empty() {
+        log_line!(
+            "[rpc] Carried over {} failures from renamed test files",
+            renames.len()
+        );
+    }
+    Ok(renames)
+}
+
+/// Pairs failures of files that vanished since the previous run with those
+/// of a newly appearing file reporting exactly the same tests, returning
+/// `(old_stable_id, new_stable_id)` per carried-over failure.
+///
+/// Test ids are compared without a leading file path, so synthesized ids
+/// such as `<file>::file-error` match across the rename. A vanished file
+/// matching several new files is ambiguous and left as fixed + new.
+fn detect_renames(
+    previous: &[store::FailureIdentity],
+    current: &[TestFailureRow],
+) -> Vec<(String, String)> {
+    let previous_by_file =
+        failures_by_file(previous.iter().map(|f| (&f.file, &f.test_id, &f.stable_id)));
+    let current_by_file =
+        failures_by_file(current.iter().map(|f| (&f.file, &f.test_id, &f.stable_id)));
+    let mut renames = Vec::new();
+    for (old_file, old_tests) in &previous_by_file {
+        if current_by_file.contains_key(old_file) {
+            continue;
+        }
+        let mut candidates = current_by_file.iter().filter(|(new_file, new_tests)| {
+            !previous_by_file.contains_key(*new_file) && new_tests.keys().eq(old_tests.keys())
+        });
+        if let (Some((_, new_tests)), None) = (candidates.next(), candidates.next()) {
+            renames.extend(
+                old_tests
+                    .values()
+                    .zip(new_tests.values())
+                    .map(|(old_id, new_id)| ((*old_id).clone(), (*new_id).clone())),
+            );
+        }
+    }
+    renames
+}
+
+/// Groups `(file, test_id, stable_id)` by file, keying each file's failures
+/// by test id relative to the file.
+fn failures_by_file<'a>(
+    failures: impl Iterator<Item = (&'a String, &'a String, &'a String)>,
+) -> BTreeMap<&'a str, BTreeMap<&'a str, &'a String>> {
+    let mut by_file: BTreeMap<&str, BTreeMap<&str, &String>> = BTreeMap::new();
+    for (file, test_id, stable_id) in failures {
+        let relative = test_id.strip_prefix(file.as_str()).unwrap_or(test_id);
+        by_file.entry(file).or_default().insert(relative, stable_id);
+    }
+    by_file
+}
+
+fn unix_millis() -> Result<i64, Status> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| Status::internal(format!("time error: {e}")))?
+        .as_millis();
+    Ok(i64::try_from(millis).unwrap_or(i64::MAX))
+}
+
+/// Re-reads a committed run and compares row counts to what was parsed.
+///
+/// Catches rows silently dropped by schema or constraint bugs.
+fn verify_ingest(
+    state: &RpcState,
+    run_id: &str,
+    artifacts: &ParsedArtifacts,
+) -> Result<(), Status> {
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    let stored_failures = store::get_stable_ids_for_run(&conn, run_id)
+        .map_err(|e| Status::internal(format!("verify failures: {e}")))?
+        .len();
+    let stored_findings = store::get_finding_stable_ids_for_run(&conn, run_id)
+        .map_err(|e| Status::internal(format!("verify findings: {e}")))?
+        .len();
+    if stored_failures != artifacts.failures.len() || stored_findings != artifacts.findings.len() {
+        let detail = format!(
+            "run={run_id}: stored {stored_failures}/{} test failures, {stored_findings}/{} findings",
+            artifacts.failures.len(),
+            artifacts.findings.len()
+        );
+        log_line!("[rpc] ERROR: ingest verification failed: {detail}");
+        return Err(Status::data_loss(format!(
+            "ingest verification failed: {detail}"
+        )));
+    }
+    Ok(())
+}
+
+/// Handles `ClearWorkspaceData` RPC.
+///
+/// Deletes all stored runs, test failures and findings of one workspace in a
+/// single transaction; other workspaces sharing the database are untouched.
+/// Returns the number of runs deleted.
+pub fn clear_workspace_data(state: &RpcState, workspace_id: &str) -> Result<usize, Status> {
+    log_line!("[rpc] ClearWorkspaceData: workspace={workspace_id}");
+    ensure_admin(state)?;
+    ensure_writable(state)?;
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let writer = workspace_writer(state, &workspace_id)?;
+    let mut conn = writer.lock().map_err(|_| Status::internal("lock error"))?;
+    let tx = conn
+        .transaction_with_behavior(TransactionBehavior::Immediate)
+        .map_err(|e| Status::internal(format!("transaction error: {e}")))?;
+    let deleted = store::delete_workspace_data(&tx, &workspace_id)
+        .map_err(|e| Status::internal(format!("delete workspace data: {e}")))?;
+    tx.commit()
+        .map_err(|e| Status::internal(format!("commit: {e}")))?;
+    state.writers.remove(&workspace_id);
+    Ok(deleted)
+}
+
+/// Closes the cached write connection of `workspace_id`, for
+/// `EvictWorkspace`. Invalid ids have no connection to close.
+pub fn close_workspace_writer(state: &RpcState, workspace_id: &str) {
+    if let Ok(workspace_id) = normalize_workspace_id(state, workspace_id) {
+        state.writers.remove(&workspace_id);
+    }
+}
+
+/// Handles `DatabaseMaintenance` RPC.
+///
+/// Runs `store::run_maintenance` on a dedicated connection so queries on the
+/// shared connection and per-workspace ingests only wait for the individual
+/// `REINDEX` and `VACUUM` steps. Returns the integrity check report.
+pub fn database_maintenance(state: &RpcState) -> Result<String, Status> {
+    log_line!("[rpc] DatabaseMaintenance");
+    ensure_admin(state)?;
+    ensure_writable(state)?;
+    let conn = state
+        .writers
+        .open_unshared()
+        .map_err(|e| Status::internal(format!("open connection: {e}")))?;
+    let integrity = store::run_maintenance(&conn)
+        .map_err(|e| Status::internal(format!("database maintenance: {e}")))?;
+    if integrity != "ok" {
+        log_line!("[store] ERROR: integrity check failed: {integrity}");
+    }
+    Ok(integrity)
+}
+
+/// Handles `NormalizeWorkspaceIds` RPC.
+///
+/// Rewrites the workspace ids of stored runs to the form queries normalize
+/// to, so history ingested before normalization, or under other settings,
+/// stays reachable. Merging ids is irreversible, so this is an explicit
+/// admin step rather than part of startup. Returns the number of distinct
+/// ids rewritten.
+pub fn normalize_stored_workspace_ids(state: &RpcState) -> Result<usize, Status> {
+    log_line!("[rpc] NormalizeWorkspaceIds");
+    ensure_admin(state)?;
+    ensure_writable(state)?;
+    let mut conn = state
+        .writers
+        .open_unshared()
+        .map_err(|e| Status::internal(format!("open connection: {e}")))?;
+    let rewritten =
+        store::normalize_workspace_ids(&mut conn, |id| canonical_workspace_id(state, id).ok())
+            .map_err(|e| Status::internal(format!("normalize workspace ids: {e}")))?;
+    log_line!("[store] INFO: normalized {rewritten} stored workspace ids");
+    Ok(rewritten)
+}
+
+/// Handles `GetRunStats` RPC.
+///
+/// Returns ingest duration and artifact size for the most recent completed
+/// runs, newest first. A `limit` of 0 uses `DEFAULT_RUN_STATS_LIMIT`.
+pub fn get_run_stats(
+    state: &RpcState,
+    workspace_id: &str,
+    limit: u32,
+) -> Result<Vec<store::RunStats>, Status> {
+    log_line!("[rpc] GetRunStats: workspace={workspace_id}, limit={limit}");
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    store::get_run_stats(&conn, &workspace_id, run_limit(limit))
+        .map_err(|e| Status::internal(format!("query run stats: {e}")))
+}
+
+/// Handles `GetRunHistory` RPC.
+///
+/// Lists the most recent runs, including ones still being ingested, with
+/// their test failure and finding counts, newest first. A `limit` of 0 uses
+/// `DEFAULT_RUN_STATS_LIMIT`.
+pub fn get_run_history(
+    state: &RpcState,
+    workspace_id: &str,
+    limit: u32,
+) -> Result<Vec<store::RunHistoryEntry>, Status> {
+    log_line!("[rpc] GetRunHistory: workspace={workspace_id}, limit={limit}");
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    store::get_run_history(&conn, &workspace_id, run_limit(limit))
+        .map_err(|e| Status::internal(format!("query run history: {e}")))
+}
+
+/// Handles `GetAttributeTrend` RPC.
+///
+/// Returns `key`'s values in up to `limit` recent completed runs that set it,
+/// oldest first; 0 means `DEFAULT_RUN_STATS_LIMIT`.
+pub fn get_attribute_trend(
+    state: &RpcState,
+    workspace_id: &str,
+    key: &str,
+    limit: u32,
+) -> Result<Vec<store::AttributePoint>, Status> {
+    log_line!("[rpc] GetAttributeTrend: workspace={workspace_id}, key={key}, limit={limit}");
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    if key.is_empty() {
+        return Err(Status::invalid_argument("key is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    store::get_attribute_trend(&conn, &workspace_id, key, run_limit(limit))
+        .map_err(|e| Status::internal(format!("query attribute trend: {e}")))
+}
+
+/// Handles `GetFlakyTests` RPC.
+///
+/// Looks at the last `window` completed runs; 0 means `DEFAULT_RUN_STATS_LIMIT`.
+pub fn get_flaky_tests(
+    state: &RpcState,
+    workspace_id: &str,
+    window: i32,
+) -> Result<Vec<store::FlakyTest>, Status> {
+    log_line!("[rpc] GetFlakyTests: workspace={workspace_id}, window={window}");
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let window = u32::try_from(window)
+        .map_err(|_| Status::invalid_argument("window must not be negative"))?;
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    store::get_flaky_tests(&conn, &workspace_id, run_limit(window))
+        .map_err(|e| Status::internal(format!("query flaky tests: {e}")))
+}
+
+/// Clamps a requested run count, with 0 meaning `DEFAULT_RUN_STATS_LIMIT`.
+fn run_limit(limit: u32) -> usize {
+    match limit as usize {
+        0 => DEFAULT_RUN_STATS_LIMIT,
+        n => n.min(MAX_RUN_STATS_LIMIT),
+    }
+}
+
+/// Finding counts and density of one file in the latest run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHealth {
+    pub file: String,
+    pub errors: i64,
+    pub warnings: i64,
+    /// Fraction of the run's findings located in this file.
+    pub finding_density: f64,
+}
+
+/// Handles `GetFileHealth` RPC.
+///
+/// Aggregates the latest completed run's findings per file. Warnings are
+/// only present when the service stores them (`eslint_warnings`).
+pub fn get_file_health(state: &RpcState, workspace_id: &str) -> Result<Vec<FileHealth>, Status> {
+    log_line!("[rpc] GetFileHealth: workspace={workspace_id}");
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    let runs = store::get_recent_runs(&conn, &workspace_id, 1, false)
+        .map_err(|e| Status::internal(format!("query runs: {e}")))?;
+    let Some(latest) = runs.first() else {
+        return Ok(Vec::new());
+    };
+    let counts = store::get_file_finding_counts(&conn, &latest.run_id)
+        .map_err(|e| Status::internal(format!("query file findings: {e}")))?;
+    let total: i64 = counts.iter().map(|c| c.errors + c.warnings).sum();
+    Ok(counts
+        .into_iter()
+        .map(|c| FileHealth {
+            finding_density: (c.errors + c.warnings) as f64 / total as f64,
+            file: c.file,
+            errors: c.errors,
+            warnings: c.warnings,
+        })
+        .collect())
+}
+
+/// Handles `GetFindingTrend` RPC.
+///
+/// Returns finding counts per time bucket for completed runs started at or
+/// after `since`. A `bucket_secs` of 0 uses `DEFAULT_TREND_BUCKET_SECS`.
+pub fn get_finding_trend(
+    state: &RpcState,
+    workspace_id: &str,
+    since: i64,
+    bucket_secs: u32,
+) -> Result<Vec<store::FindingTrendPoint>, Status> {
+    log_line!(
+        "[rpc] GetFindingTrend: workspace={workspace_id}, since={since}, bucket={bucket_secs}s"
+    );
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let bucket = match bucket_secs {
+        0 => DEFAULT_TREND_BUCKET_SECS,
+        n => n,
+    };
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    store::get_finding_counts_over_time(&conn, &workspace_id, since, i64::from(bucket))
+        .map_err(|e| Status::internal(format!("query finding trend: {e}")))
+}
+
+/// Handles `GetSchemaVersion` RPC.
+pub fn get_schema_version(state: &RpcState) -> Result<Vec<store::AppliedMigration>, Status> {
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    store::applied_migrations(&conn).map_err(|e| Status::internal(format!("query migrations: {e}")))
+}
+
 /// Delta result with test failures and findings counts.
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct DeltaResult {
     pub new_test_failures: i32,
     pub fixed_test_failures: i32,
     pub new_findings: i32,
     pub fixed_findings: i32,
+    /// New findings grouped by rule category.
+    pub new_findings_by_category: HashMap<String, i32>,
+    /// New findings with an automatic fix available.
+    pub new_fixable_findings: i32,
+    /// New and fixed findings per rule, sorted by rule.
+    pub rule_deltas: Vec<RuleDelta>,
+}
+
+/// New and fixed finding counts for one rule.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RuleDelta {
+    pub rule: String,
+    pub new_count: i32,
+    pub fixed_count: i32,
 }
 
 /// Handles `GetDeltaSummary` RPC.
+///
+/// Compares the two most recent completed runs, or the two most recent runs
+/// of any state when `include_incomplete` is set.
 pub fn get_delta_summary(
     state: &RpcState,
     workspace_id: &str,
     package_scope: &str,
+    include_incomplete: bool,
 ) -> Result<DeltaResult, Status> {
-    eprintln!(
+    log_line!(
         "[rpc] GetDeltaSummary: workspace={}, package={}",
         workspace_id,
         if package_scope.is_empty() {
@@ -230,16 +996,17 @@ pub fn get_delta_summary(
     if workspace_id.is_empty() {
         return Err(Status::invalid_argument("workspace_id is required"));
     }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
     validate_package_scope(package_scope)
         .map_err(|e| Status::invalid_argument(format!("invalid package_scope: {e}")))?;
     let conn = state
         .conn
         .lock()
         .map_err(|_| Status::internal("lock error"))?;
-    let runs = store::get_recent_runs(&conn, workspace_id, 2)
+    let runs = store::get_recent_runs(&conn, &workspace_id, 2, include_incomplete)
         .map_err(|e| Status::internal(format!("query runs: {e}")))?;
     let result = compute_delta(&conn, &runs, package_scope)?;
-    eprintln!(
+    log_line!(
         "[rpc] Delta: new_tf={}, fixed_tf={}, new_f={}, fixed_f={}",
         result.new_test_failures,
         result.fixed_test_failures,
@@ -249,68 +1016,318 @@ pub fn get_delta_summary(
     Ok(result)
 }
 
-fn compute_delta(
-    conn: &Connection,
-    runs: &[store::RunInfo],
+/// Handles `GetPackageDeltas` RPC.
+///
+/// Computes the delta for every package present in the two most recent runs.
+/// Rows stored without a package scope are not grouped; use `GetDeltaSummary`
+/// with an empty scope for workspace-wide totals.
+pub fn get_package_deltas(
+    state: &RpcState,
+    workspace_id: &str,
+) -> Result<Vec<(String, DeltaResult)>, Status> {
+    log_line!("[rpc] GetPackageDeltas: workspace={workspace_id}");
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    let runs = store::get_recent_runs(&conn, &workspace_id, 2, false)
+        .map_err(|e| Status::internal(format!("query runs: {e}")))?;
+    let run_ids: Vec<&str> = runs.iter().map(|r| r.run_id.as_str()).collect();
+    let packages = store::get_packages_for_runs(&conn, &run_ids)
+        .map_err(|e| Status::internal(format!("query packages: {e}")))?;
+    packages
+        .into_iter()
+        .map(|package| {
+            let delta = compute_delta(&conn, &runs, &package)?;
+            Ok((package, delta))
+        })
+        .collect()
+}
+
+fn compute_delta(
+    conn: &Connection,
+    runs: &[store::RunInfo],
     package_scope: &str,
 ) -> Result<DeltaResult, Status> {
     if runs.is_empty() {
-        return Ok(DeltaResult {
-            new_test_failures: 0,
-            fixed_test_failures: 0,
-            new_findings: 0,
-            fixed_findings: 0,
-        });
+        return Ok(DeltaResult::default());
     }
-    let (new_tf, fixed_tf) =
-        compute_entity_delta(conn, runs, package_scope, store::get_test_failure_stable_ids_scoped)?;
-    let (new_f, fixed_f) =
-        compute_entity_delta(conn, runs, package_scope, store::get_finding_stable_ids_scoped)?;
+    let renames = store::get_test_renames(conn, &runs[0].run_id)
+        .map_err(|e| Status::internal(format!("query renames: {e}")))?;
+    let failure_ids = |conn: &Connection, run_id: &str, scope: &str| {
+        store::get_test_failure_stable_ids_scoped(conn, run_id, scope)
+            .map(|ids| ids.into_iter().map(|id| (id, String::new())).collect())
+    };
+    let (new_tf, fixed_tf) = delta_totals(&compute_entity_delta(
+        conn,
+        runs,
+        package_scope,
+        failure_ids,
+        &renames,
+    )?);
+    let by_rule = compute_entity_delta(
+        conn,
+        runs,
+        package_scope,
+        store::get_finding_rules_scoped,
+        &HashMap::new(),
+    )?;
+    let (new_f, fixed_f) = delta_totals(&by_rule);
+    let (by_category, fixable) = count_new_finding_breakdown(conn, runs, package_scope)?;
     Ok(DeltaResult {
         new_test_failures: new_tf,
         fixed_test_failures: fixed_tf,
         new_findings: new_f,
         fixed_findings: fixed_f,
+        new_findings_by_category: by_category,
+        new_fixable_findings: fixable,
+        rule_deltas: rule_deltas(by_rule),
     })
 }
 
-fn compute_entity_delta<F>(
+fn rule_deltas(by_rule: BTreeMap<String, (i32, i32)>) -> Vec<RuleDelta> {
+    by_rule
+        .into_iter()
+        .map(|(rule, (new_count, fixed_count))| RuleDelta {
+            rule,
+            new_count,
+            fixed_count,
+        })
+        .collect()
+}
+
+/// Sums `(new, fixed)` counts across groups.
+fn delta_totals(groups: &BTreeMap<String, (i32, i32)>) -> (i32, i32) {
+    groups
+        .values()
+        .fold((0, 0), |(new, fixed), (n, f)| (new + n, fixed + f))
+}
+
+/// Counts new findings per category and how many of them are fixable.
+fn count_new_finding_breakdown(
     conn: &Connection,
     runs: &[store::RunInfo],
     package_scope: &str,
+) -> Result<(HashMap<String, i32>, i32), Status> {
+    let current = store::get_finding_attributes_scoped(conn, &runs[0].run_id, package_scope)
+        .map_err(|e| Status::internal(format!("query finding attributes: {e}")))?;
+    let previous_ids: HashSet<String> = match runs.get(1) {
+        Some(run) => store::get_finding_stable_ids_scoped(conn, &run.run_id, package_scope)
+            .map_err(|e| Status::internal(format!("query previous: {e}")))?
+            .into_iter()
+            .collect(),
+        None => HashSet::new(),
+    };
+    let mut counts = HashMap::new();
+    let mut fixable = 0;
+    let mut seen = HashSet::new();
+    for finding in current {
+        if !previous_ids.contains(&finding.stable_id) && seen.insert(finding.stable_id) {
+            *counts.entry(finding.category).or_insert(0) += 1;
+            fixable += i32::from(finding.fixable);
+        }
+    }
+    Ok((counts, fixable))
+}
+
+/// One finding or test failure line in a delta report.
+struct ReportEntry {
+    stable_id: String,
+    /// `(file, line)`, so entries sort by location rather than as text.
+    location: (String, i32),
+    text: String,
+}
+
+impl ReportEntry {
+    fn finding(f: store::FindingLocation) -> Self {
+        let text = format!("{}:{}:{} {}", f.file, f.start_line, f.start_column, f.rule);
+        Self {
+            stable_id: f.stable_id,
+            location: (f.file, f.start_line),
+            text,
+        }
+    }
+
+    fn failure(f: store::FailureIdentity) -> Self {
+        let name = f
+            .test_id
+            .strip_prefix(f.file.as_str())
+            .unwrap_or(&f.test_id);
+        let text = format!("{} {}", f.file, name.trim_start_matches([':', ' ', '>']));
+        Self {
+            stable_id: f.stable_id,
+            location: (f.file, 0),
+            text,
+        }
+    }
+}
+
+/// Handles `GetDeltaReport` RPC.
+///
+/// Renders the delta between the two most recent runs as Markdown, with new
+/// (`+`) and fixed (`-`) findings and test failures in a `diff` block per
+/// section, for posting as a CI comment.
+pub fn get_delta_report(
+    state: &RpcState,
+    workspace_id: &str,
+    package_scope: &str,
+) -> Result<String, Status> {
+    log_line!("[rpc] GetDeltaReport: workspace={workspace_id}");
+    if workspace_id.is_empty() {
+        return Err(Status::invalid_argument("workspace_id is required"));
+    }
+    let workspace_id = normalize_workspace_id(state, workspace_id)?;
+    validate_package_scope(package_scope)
+        .map_err(|e| Status::invalid_argument(format!("invalid package_scope: {e}")))?;
+    let conn = state
+        .conn
+        .lock()
+        .map_err(|_| Status::internal("lock error"))?;
+    let runs = store::get_recent_runs(&conn, &workspace_id, 2, false)
+        .map_err(|e| Status::internal(format!("query runs: {e}")))?;
+    let findings = report_section(&runs, &HashMap::new(), |run_id| {
+        store::get_finding_locations_scoped(&conn, run_id, package_scope)
+            .map(|rows| rows.into_iter().map(ReportEntry::finding).collect())
+    })?;
+    let renames = match runs.first() {
+        Some(run) => store::get_test_renames(&conn, &run.run_id)
+            .map_err(|e| Status::internal(format!("query renames: {e}")))?,
+        None => HashMap::new(),
+    };
+    let failures = report_section(&runs, &renames, |run_id| {
+        store::get_failure_identities_scoped(&conn, run_id, package_scope)
+            .map(|rows| rows.into_iter().map(ReportEntry::failure).collect())
+    })?;
+    Ok(format!(
+        "{}\n{}",
+        format_report_section("Findings", &findings),
+        format_report_section("Test failures", &failures)
+    ))
+}
+
+/// New and fixed entries between the latest run and the one before it,
+/// each sorted by location. Previous ids in `renames` compare under the id
+/// they were carried over to.
+fn report_section<F>(
+    runs: &[store::RunInfo],
+    renames: &HashMap<String, String>,
     query_fn: F,
-) -> Result<(i32, i32), Status>
+) -> Result<(Vec<ReportEntry>, Vec<ReportEntry>), Status>
 where
-    F: Fn(&Connection, &str, &str) -> Result<Vec<String>, store::StoreError>,
+    F: Fn(&str) -> Result<Vec<ReportEntry>, store::StoreError>,
 {
-    let current_ids: HashSet<String> = query_fn(conn, &runs[0].run_id, package_scope)
-        .map_err(|e| Status::internal(format!("query current: {e}")))?
+    let query = |run: Option<&store::RunInfo>| match run {
+        Some(run) => query_fn(&run.run_id).map_err(|e| Status::internal(format!("query: {e}"))),
+        None => Ok(Vec::new()),
+    };
+    let current = query(runs.first())?;
+    let previous = query(runs.get(1))?;
+    let current_ids: HashSet<String> = current.iter().map(|e| e.stable_id.clone()).collect();
+    let previous_ids: HashSet<String> = previous
+        .iter()
+        .map(|e| renames.get(&e.stable_id).unwrap_or(&e.stable_id).clone())
+        .collect();
+    let mut seen_new = HashSet::new();
+    let mut new: Vec<ReportEntry> = current
+        .into_iter()
+        .filter(|e| !previous_ids.contains(&e.stable_id) && seen_new.insert(e.stable_id.clone()))
+        .collect();
+    let mut seen_fixed = HashSet::new();
+    let mut fixed: Vec<ReportEntry> = previous
         .into_iter()
+        .filter(|e| {
+            let id = renames.get(&e.stable_id).unwrap_or(&e.stable_id);
+            !current_ids.contains(id) && seen_fixed.insert(e.stable_id.clone())
+        })
         .collect();
-    if runs.len() < 2 {
-        return Ok((current_ids.len() as i32, 0));
+    new.sort_by(|a, b| {
+        a.location
+            .cmp(&b.location)
+            .then_with(|| a.text.cmp(&b.text))
+    });
+    fixed.sort_by(|a, b| {
+        a.location
+            .cmp(&b.location)
+            .then_with(|| a.text.cmp(&b.text))
+    });
+    Ok((new, fixed))
+}
+
+fn format_report_section(
+    title: &str,
+    (new, fixed): &(Vec<ReportEntry>, Vec<ReportEntry>),
+) -> String {
+    let mut out = format!("### {title}: {} new, {} fixed\n", new.len(), fixed.len());
+    if new.is_empty() && fixed.is_empty() {
+        return out;
+    }
+    out.push_str("\n```diff\n");
+    for entry in new {
+        out.push_str(&format!("+ {}\n", entry.text));
+    }
+    for entry in fixed {
+        out.push_str(&format!("- {}\n", entry.text));
     }
-    let previous_ids: HashSet<String> = query_fn(conn, &runs[1].run_id, package_scope)
-        .map_err(|e| Status::internal(format!("query previous: {e}")))?
+    out.push_str("```\n");
+    out
+}
+
+/// Counts ids new in and gone from the latest run as `(new, fixed)`, grouped
+/// by the key `query_fn` pairs each id with. A group with only fixed ids is
+/// still present. Previous ids found in `renames` are compared under the id
+/// they were carried over to.
+#[allow(clippy::too_many_arguments)]
+fn compute_entity_delta<F>(
+    conn: &Connection,
+    runs: &[store::RunInfo],
+    package_scope: &str,
+    query_fn: F,
+    renames: &HashMap<String, String>,
+) -> Result<BTreeMap<String, (i32, i32)>, Status>
+where
+    F: Fn(&Connection, &str, &str) -> Result<Vec<(String, String)>, store::StoreError>,
+{
+    let current: HashMap<String, String> = query_fn(conn, &runs[0].run_id, package_scope)
+        .map_err(|e| Status::internal(format!("query current: {e}")))?
         .into_iter()
         .collect();
-    Ok((
-        current_ids.difference(&previous_ids).count() as i32,
-        previous_ids.difference(&current_ids).count() as i32,
-    ))
+    let previous: HashMap<String, String> = match runs.get(1) {
+        Some(run) => query_fn(conn, &run.run_id, package_scope)
+            .map_err(|e| Status::internal(format!("query previous: {e}")))?
+            .into_iter()
+            .map(|(id, key)| (renames.get(&id).cloned().unwrap_or(id), key))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let mut groups: BTreeMap<String, (i32, i32)> = BTreeMap::new();
+    for (id, key) in &current {
+        if !previous.contains_key(id) {
+            groups.entry(key.clone()).or_default().0 += 1;
+        }
+    }
+    for (id, key) in &previous {
+        if !current.contains_key(id) {
+            groups.entry(key.clone()).or_default().1 += 1;
+        }
+    }
+    Ok(groups)
 }
 
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
     use super::*;
-    use crate::store::{init_storage, open_connection};
-    use crate::zax::v1::ArtifactRef;
+    use crate::store::{init_storage, open_connection, CacheSettings, WorkspaceConnections};
     use std::fs;
     use tempfile::TempDir;
 
     struct TestHelper {
         _dir: TempDir,
+        cache_dir: std::path::PathBuf,
         state: RpcState,
     }
 
@@ -318,14 +1335,23 @@ mod tests {
         fn new() -> Self {
             let temp_dir = tempfile::tempdir().unwrap();
             init_storage(temp_dir.path()).unwrap();
-            let conn = open_connection(temp_dir.path()).unwrap();
+            let conn = open_connection(temp_dir.path(), CacheSettings::default()).unwrap();
             let cache_dir = temp_dir.path().to_path_buf();
             Self {
                 _dir: temp_dir,
                 state: RpcState {
-                    cache_dir,
                     conn: Arc::new(Mutex::new(conn)),
+                    writers: WorkspaceConnections::new(&cache_dir, CacheSettings::default()),
+                    read_only: false,
+                    verify_ingest: false,
+                    admin: false,
+                    artifact_roots: default_artifact_roots(&cache_dir),
+                    detect_renames: false,
+                    eslint_warnings: false,
+                    lowercase_workspace_ids: false,
+                    workspace_root: cache_dir.join("workspace"),
                 },
+                cache_dir,
             }
         }
 
@@ -337,6 +1363,7 @@ mod tests {
             tx.commit().unwrap();
         }
 
+        #[allow(clippy::too_many_arguments)]
         fn insert_run_with_data(
             &self,
             workspace: &str,
@@ -348,6 +1375,7 @@ mod tests {
             self.insert_run_with_data_and_package(workspace, run, time, "", failures, findings);
         }
 
+        #[allow(clippy::too_many_arguments)]
         fn insert_run_with_data_and_package(
             &self,
             workspace: &str,
@@ -360,6 +1388,7 @@ mod tests {
             let mut conn = self.state.conn.lock().unwrap();
             let tx = conn.transaction().unwrap();
             store::insert_run(&tx, workspace, run, time).unwrap();
+            store::record_run_package_scope(&tx, run, package).unwrap();
             if !failures.is_empty() {
                 store::insert_test_failures(&tx, run, package, failures).unwrap();
             }
@@ -385,7 +1414,12 @@ mod tests {
                 kind: kind as i32,
                 path: path.into(),
                 hash: String::new(),
+                min_severity: 0,
             }],
+            stable_id_salt: String::new(),
+            stable_id_version: 0,
+            stable_id_length: 0,
+            attributes: HashMap::new(),
         }
     }
 
@@ -393,26 +1427,46 @@ mod tests {
     fn manifest_validation_rejects_empty_workspace_id() {
         let helper = TestHelper::new();
         let m = create_manifest("", "run1", ArtifactKind::TestFailure, "/p");
-        assert!(ingest_manifest(&helper.state, &m, "")
+        assert!(ingest_manifest(&helper.state, &m, "", false)
             .unwrap_err()
             .message()
             .contains("workspace_id"));
     }
 
     #[test]
-    fn manifest_validation_rejects_empty_run_id() {
+    fn empty_run_id_is_generated_and_returned() {
         let helper = TestHelper::new();
-        let m = create_manifest("ws1", "", ArtifactKind::TestFailure, "/p");
-        assert!(ingest_manifest(&helper.state, &m, "")
-            .unwrap_err()
-            .message()
-            .contains("run_id"));
+        let eslint_json = r#"[{"filePath":"a.ts","messages":[{"ruleId":"r","severity":2,"line":1,"column":1,"message":"x"}]}]"#;
+        let path = write_artifact(&helper, "eslint.json", eslint_json);
+        let m = create_manifest("ws1", "", ArtifactKind::Finding, &path);
+
+        let first = ingest_manifest(&helper.state, &m, "", false).unwrap();
+        let second = ingest_manifest(&helper.state, &m, "", false).unwrap();
+        assert_eq!(first.len(), 32);
+        assert_ne!(first, second);
+        let conn = helper.state.conn.lock().unwrap();
+        for run_id in [&first, &second] {
+            assert_eq!(
+                store::get_finding_stable_ids_for_run(&conn, run_id)
+                    .unwrap()
+                    .len(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn generated_run_id_is_deterministic_in_inputs() {
+        let m = create_manifest("ws1", "", ArtifactKind::Finding, "/p");
+        assert_eq!(generate_run_id(&m, 1, 0), generate_run_id(&m, 1, 0));
+        assert_ne!(generate_run_id(&m, 1, 0), generate_run_id(&m, 1, 1));
+        assert_ne!(generate_run_id(&m, 1, 0), generate_run_id(&m, 2, 0));
     }
 
     #[test]
     fn delta_validation_rejects_empty_workspace() {
         let helper = TestHelper::new();
-        assert!(get_delta_summary(&helper.state, "", "")
+        assert!(get_delta_summary(&helper.state, "", "", false)
             .unwrap_err()
             .message()
             .contains("workspace_id"));
@@ -421,13 +1475,35 @@ mod tests {
     #[test]
     fn path_traversal_rejected() {
         let helper = TestHelper::new();
-        let artifacts_dir = helper.state.cache_dir.join("artifacts");
+        let artifacts_dir = helper.cache_dir.join("artifacts");
         fs::create_dir_all(&artifacts_dir).unwrap();
-        fs::write(helper.state.cache_dir.join("secret.txt"), "secret").unwrap();
+        fs::write(helper.cache_dir.join("secret.txt"), "secret").unwrap();
         let path = artifacts_dir.join("..").join("secret.txt");
-        let err =
-            validate_artifact_path(&helper.state.cache_dir, path.to_str().unwrap()).unwrap_err();
+        let err = validate_artifact_path(&helper.state.artifact_roots, path.to_str().unwrap())
+            .unwrap_err();
+        assert!(err.message().contains("outside"));
+    }
+
+    #[test]
+    fn artifact_accepted_under_any_allowed_root() {
+        let mut helper = TestHelper::new();
+        let staging = helper.cache_dir.join("staging");
+        fs::create_dir_all(&staging).unwrap();
+        helper.state.artifact_roots.push(staging.clone());
+        let staged = staging.join("vitest.json");
+        fs::write(&staged, "{}").unwrap();
+        let stray = helper.cache_dir.join("stray.json");
+        fs::write(&stray, "{}").unwrap();
+
+        let roots = &helper.state.artifact_roots;
+        assert_eq!(
+            validate_artifact_path(roots, staged.to_str().unwrap()).unwrap(),
+            staged
+        );
+        let err = validate_artifact_path(roots, stray.to_str().unwrap()).unwrap_err();
         assert!(err.message().contains("outside"));
+        let escape = staging.join("..").join("stray.json");
+        assert!(validate_artifact_path(roots, escape.to_str().unwrap()).is_err());
     }
 
     #[test]
@@ -447,10 +1523,13 @@ mod tests {
                 test_id: "t1".into(),
                 file: "f".into(),
                 message: "m".into(),
+                kind: "assertion".into(),
+                expected: None,
+                received: None,
             }],
             &[],
         );
-        let result = get_delta_summary(&helper.state, "ws1", "").unwrap();
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
         assert_eq!(result.new_test_failures, 1);
         assert_eq!(result.fixed_test_failures, 0);
     }
@@ -473,9 +1552,12 @@ mod tests {
                 end_line: 1,
                 end_column: 1,
                 message: "m".into(),
+                category: "unknown".into(),
+                fixable: false,
+                severity: 2,
             }],
         );
-        let result = get_delta_summary(&helper.state, "ws1", "").unwrap();
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
         assert_eq!(result.new_findings, 1);
         assert_eq!(result.fixed_findings, 0);
     }
@@ -485,7 +1567,7 @@ mod tests {
     fn delta_with_no_findings_returns_zero() {
         let helper = TestHelper::new();
         helper.insert_run("ws1", "run1", 1000);
-        let result = get_delta_summary(&helper.state, "ws1", "").unwrap();
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
         assert_eq!(result.new_findings, 0);
         assert_eq!(result.fixed_findings, 0);
     }
@@ -510,6 +1592,9 @@ mod tests {
                     end_line: 1,
                     end_column: 1,
                     message: "m".into(),
+                    category: "unknown".into(),
+                    fixable: false,
+                    severity: 2,
                 },
                 FindingRow {
                     stable_id: "f2".into(),
@@ -521,16 +1606,20 @@ mod tests {
                     end_line: 2,
                     end_column: 1,
                     message: "m".into(),
+                    category: "unknown".into(),
+                    fixable: false,
+                    severity: 2,
                 },
             ],
         );
-        let result = get_delta_summary(&helper.state, "ws1", "").unwrap();
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
         assert_eq!(result.new_findings, 2);
         assert_eq!(result.fixed_findings, 0);
     }
 
     // P16: Delta computation - finds new and fixed findings
     #[test]
+    #[allow(clippy::too_many_lines)]
     fn delta_detects_new_and_fixed_findings() {
         let helper = TestHelper::new();
         // Run 1: has f1, f2
@@ -550,6 +1639,9 @@ mod tests {
                     end_line: 1,
                     end_column: 1,
                     message: "m".into(),
+                    category: "unknown".into(),
+                    fixable: false,
+                    severity: 2,
                 },
                 FindingRow {
                     stable_id: "f2".into(),
@@ -561,6 +1653,9 @@ mod tests {
                     end_line: 2,
                     end_column: 1,
                     message: "m".into(),
+                    category: "unknown".into(),
+                    fixable: false,
+                    severity: 2,
                 },
             ],
         );
@@ -581,6 +1676,9 @@ mod tests {
                     end_line: 1,
                     end_column: 1,
                     message: "m".into(),
+                    category: "unknown".into(),
+                    fixable: false,
+                    severity: 2,
                 },
                 FindingRow {
                     stable_id: "f3".into(),
@@ -592,15 +1690,92 @@ mod tests {
                     end_line: 3,
                     end_column: 1,
                     message: "m".into(),
+                    category: "problem".into(),
+                    fixable: true,
+                    severity: 2,
                 },
             ],
         );
-        let result = get_delta_summary(&helper.state, "ws1", "").unwrap();
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
         assert_eq!(result.new_findings, 1); // f3 is new
         assert_eq!(result.fixed_findings, 1); // f2 is fixed
+        assert_eq!(result.new_findings_by_category.len(), 1);
+        assert_eq!(result.new_findings_by_category.get("problem"), Some(&1));
+        assert_eq!(result.new_fixable_findings, 1);
     }
 
     #[test]
+    fn delta_breaks_down_findings_by_rule() {
+        let helper = TestHelper::new();
+        let previous = [
+            located_finding("f1", "eqeqeq", "src/a.ts", 1),
+            located_finding("f2", "no-unused-vars", "src/a.ts", 2),
+            located_finding("f3", "eqeqeq", "src/b.ts", 3),
+        ];
+        let current = [
+            located_finding("f1", "eqeqeq", "src/a.ts", 1),
+            located_finding("f4", "eqeqeq", "src/c.ts", 4),
+            located_finding("f5", "no-console", "src/c.ts", 5),
+        ];
+        helper.insert_run_with_data("ws1", "run1", 1000, &[], &previous);
+        helper.insert_run_with_data("ws1", "run2", 2000, &[], &current);
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
+        let delta = |rule: &str, new_count, fixed_count| RuleDelta {
+            rule: rule.into(),
+            new_count,
+            fixed_count,
+        };
+        assert_eq!(
+            result.rule_deltas,
+            vec![
+                delta("eqeqeq", 1, 1),
+                delta("no-console", 1, 0),
+                delta("no-unused-vars", 0, 1)
+            ]
+        );
+        assert_eq!((result.new_findings, result.fixed_findings), (2, 2));
+    }
+
+    fn located_finding(stable_id: &str, rule: &str, file: &str, line: i32) -> FindingRow {
+        FindingRow {
+            stable_id: stable_id.into(),
+            tool: "eslint".into(),
+            rule: rule.into(),
+            file: file.into(),
+            start_line: line,
+            start_column: 1,
+            end_line: line,
+            end_column: 1,
+            message: "m".into(),
+            category: "unknown".into(),
+            fixable: false,
+            severity: 2,
+        }
+    }
+
+    #[test]
+    fn delta_report_lists_new_and_fixed_locations() {
+        let helper = TestHelper::new();
+        let kept = located_finding("f1", "eqeqeq", "src/b.ts", 3);
+        let gone = located_finding("f2", "no-console", "src/b.ts", 7);
+        let added = located_finding("f3", "no-unused-vars", "src/a.ts", 10);
+        helper.insert_run_with_data("ws1", "run1", 1000, &[], &[kept, gone]);
+        let kept = located_finding("f1", "eqeqeq", "src/b.ts", 3);
+        helper.insert_run_with_data("ws1", "run2", 2000, &[], &[kept, added]);
+
+        let report = get_delta_report(&helper.state, "ws1", "").unwrap();
+        assert_eq!(
+            report,
+            "### Findings: 1 new, 1 fixed\n\n```diff\n\
+             + src/a.ts:10:1 no-unused-vars\n\
+             - src/b.ts:7:1 no-console\n```\n\n\
+             ### Test failures: 0 new, 0 fixed\n"
+        );
+        assert!(get_delta_report(&helper.state, "", "").is_err());
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)]
     fn delta_filters_by_package_scope() {
         let helper = TestHelper::new();
         // Insert data with different packages
@@ -614,6 +1789,9 @@ mod tests {
                 test_id: "t1".into(),
                 file: "f".into(),
                 message: "m".into(),
+                kind: "assertion".into(),
+                expected: None,
+                received: None,
             }],
             &[],
         );
@@ -630,6 +1808,9 @@ mod tests {
                     test_id: "t2".into(),
                     file: "f2".into(),
                     message: "m".into(),
+                    kind: "assertion".into(),
+                    expected: None,
+                    received: None,
                 }],
             )
             .unwrap();
@@ -637,14 +1818,14 @@ mod tests {
         }
 
         // Scoped delta returns only matching package
-        let auth_result = get_delta_summary(&helper.state, "ws1", "packages/auth").unwrap();
+        let auth_result = get_delta_summary(&helper.state, "ws1", "packages/auth", false).unwrap();
         assert_eq!(auth_result.new_test_failures, 1);
 
-        let web_result = get_delta_summary(&helper.state, "ws1", "packages/web").unwrap();
+        let web_result = get_delta_summary(&helper.state, "ws1", "packages/web", false).unwrap();
         assert_eq!(web_result.new_test_failures, 1);
 
         // Empty scope returns all
-        let all_result = get_delta_summary(&helper.state, "ws1", "").unwrap();
+        let all_result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
         assert_eq!(all_result.new_test_failures, 2);
     }
 
@@ -652,14 +1833,933 @@ mod tests {
     fn package_scope_validation_rejects_invalid() {
         let helper = TestHelper::new();
         // Path traversal
-        assert!(get_delta_summary(&helper.state, "ws1", "../secret")
+        assert!(get_delta_summary(&helper.state, "ws1", "../secret", false)
             .unwrap_err()
             .message()
             .contains("package_scope"));
         // Invalid chars
-        assert!(get_delta_summary(&helper.state, "ws1", "foo bar")
+        assert!(get_delta_summary(&helper.state, "ws1", "foo bar", false)
             .unwrap_err()
             .message()
             .contains("package_scope"));
     }
+
+    #[test]
+    fn package_deltas_are_independent_per_package() {
+        let helper = TestHelper::new();
+        let tf = |id: &str| TestFailureRow {
+            stable_id: id.into(),
+            test_id: id.into(),
+            file: "f".into(),
+            message: "m".into(),
+            kind: "assertion".into(),
+            expected: None,
+            received: None,
+        };
+        // auth: a2 fixed between runs; web: w1 new in run2
+        helper.insert_run_with_data_and_package(
+            "ws1",
+            "run1",
+            1000,
+            "packages/auth",
+            &[tf("a1"), tf("a2")],
+            &[],
+        );
+        helper.insert_run_with_data_and_package(
+            "ws1",
+            "run2",
+            2000,
+            "packages/auth",
+            &[tf("a1")],
+            &[],
+        );
+        {
+            let mut conn = helper.state.conn.lock().unwrap();
+            let tx = conn.transaction().unwrap();
+            store::insert_test_failures(&tx, "run2", "packages/web", &[tf("w1")]).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let deltas = get_package_deltas(&helper.state, "ws1").unwrap();
+        let names: Vec<&str> = deltas.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(names, vec!["packages/auth", "packages/web"]);
+        assert_eq!(deltas[0].1.new_test_failures, 0);
+        assert_eq!(deltas[0].1.fixed_test_failures, 1);
+        assert_eq!(deltas[1].1.new_test_failures, 1);
+        assert_eq!(deltas[1].1.fixed_test_failures, 0);
+    }
+
+    fn write_artifact(helper: &TestHelper, name: &str, content: &str) -> String {
+        let artifacts_dir = helper.cache_dir.join("artifacts");
+        fs::create_dir_all(&artifacts_dir).unwrap();
+        let path = artifacts_dir.join(name);
+        fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn extract_json_flag_ingests_noisy_artifact() {
+        let helper = TestHelper::new();
+        let vitest_json = r#"{"testResults":[{"name":"t.ts","status":"failed","assertionResults":[{"title":"t","status":"failed","failureMessages":["e"]}]}]}"#;
+        let noisy = format!("[wrapper] running vitest\n{vitest_json}\n[wrapper] exit 1\n");
+        let path = write_artifact(&helper, "vitest.json", &noisy);
+        let m = create_manifest("ws1", "run1", ArtifactKind::TestFailure, &path);
+
+        let err = ingest_manifest(&helper.state, &m, "", false).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        ingest_manifest(&helper.state, &m, "", true).unwrap();
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
+        assert_eq!(result.new_test_failures, 1);
+    }
+
+    fn gzip(content: &str) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn gzipped_artifact_matches_plaintext() {
+        let helper = TestHelper::new();
+        let eslint_json = r#"[{"filePath":"src/a.ts","messages":[{"ruleId":"no-undef","severity":2,"line":1,"column":1,"message":"x"}]}]"#;
+        let plain = write_artifact(&helper, "eslint.json", eslint_json);
+        let gz_path = helper.cache_dir.join("artifacts/eslint.json.gz");
+        fs::write(&gz_path, gzip(eslint_json)).unwrap();
+
+        let m = create_manifest("ws1", "run1", ArtifactKind::Finding, &plain);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+        let m = create_manifest(
+            "ws2",
+            "run2",
+            ArtifactKind::Finding,
+            gz_path.to_str().unwrap(),
+        );
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+
+        let conn = helper.state.conn.lock().unwrap();
+        let plain_ids = store::get_finding_stable_ids_for_run(&conn, "run1").unwrap();
+        let gz_ids = store::get_finding_stable_ids_for_run(&conn, "run2").unwrap();
+        assert_eq!(plain_ids.len(), 1);
+        assert_eq!(plain_ids, gz_ids);
+    }
+
+    #[test]
+    fn gzip_decompression_is_bounded() {
+        let compressed = gzip(&"a".repeat(1000));
+        assert_eq!(decompress_gzip(&compressed, 1000).unwrap().len(), 1000);
+        let err = decompress_gzip(&compressed, 999).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn verified_ingest_passes_for_normal_run() {
+        let mut helper = TestHelper::new();
+        helper.state.verify_ingest = true;
+        let eslint_json = r#"[{"filePath":"a.ts","messages":[{"ruleId":"r","severity":2,"line":1,"column":1,"message":"x"},{"ruleId":"r","severity":2,"line":2,"column":1,"message":"y"}]}]"#;
+        let path = write_artifact(&helper, "eslint.json", eslint_json);
+        let m = create_manifest("ws1", "run1", ArtifactKind::Finding, &path);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+    }
+
+    #[test]
+    fn manifest_stable_id_version_selects_id_scheme() {
+        let helper = TestHelper::new();
+        let vitest_json = r#"{"testResults":[{"name":"t.ts","status":"failed","assertionResults":[{"title":"t","status":"failed","failureMessages":["e"]}]}]}"#;
+        let path = write_artifact(&helper, "vitest.json", vitest_json);
+        let legacy = create_manifest("ws1", "run1", ArtifactKind::TestFailure, &path);
+        let prefixed = ArtifactManifest {
+            run_id: "run2".into(),
+            stable_id_version: stable_id::VERSION_LENGTH_PREFIXED,
+            ..legacy.clone()
+        };
+        ingest_manifest(&helper.state, &legacy, "", false).unwrap();
+        ingest_manifest(&helper.state, &prefixed, "", false).unwrap();
+        {
+            let conn = helper.state.conn.lock().unwrap();
+            let legacy_ids = store::get_stable_ids_for_run(&conn, "run1").unwrap();
+            let prefixed_ids = store::get_stable_ids_for_run(&conn, "run2").unwrap();
+            assert_eq!(legacy_ids.len(), 1);
+            assert_ne!(legacy_ids, prefixed_ids);
+        }
+
+        let unknown = ArtifactManifest {
+            run_id: "run3".into(),
+            stable_id_version: 99,
+            ..legacy
+        };
+        let err = ingest_manifest(&helper.state, &unknown, "", false).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn manifest_stable_id_length_applies_to_findings() {
+        let helper = TestHelper::new();
+        let eslint_json = r#"[{"filePath":"a.ts","messages":[{"ruleId":"r","severity":2,"line":1,"column":1,"message":"x"}]}]"#;
+        let path = write_artifact(&helper, "eslint.json", eslint_json);
+        let manifest = ArtifactManifest {
+            stable_id_salt: "salt".into(),
+            stable_id_length: 64,
+            ..create_manifest("ws1", "run1", ArtifactKind::Finding, &path)
+        };
+        ingest_manifest(&helper.state, &manifest, "", false).unwrap();
+        {
+            let conn = helper.state.conn.lock().unwrap();
+            let ids = store::get_finding_stable_ids_for_run(&conn, "run1").unwrap();
+            assert_eq!(ids[0].len(), 64);
+        }
+
+        let short = ArtifactManifest {
+            run_id: "run2".into(),
+            stable_id_length: 16,
+            ..manifest
+        };
+        let err = ingest_manifest(&helper.state, &short, "", false).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn manifest_salt_changes_stored_stable_ids() {
+        let helper = TestHelper::new();
+        let eslint_json = r#"[{"filePath":"a.ts","messages":[{"ruleId":"r","severity":2,"line":1,"column":1,"message":"x"}]}]"#;
+        let path = write_artifact(&helper, "eslint.json", eslint_json);
+        let plain = create_manifest("ws1", "run1", ArtifactKind::Finding, &path);
+        let salted = ArtifactManifest {
+            workspace_id: "ws2".into(),
+            run_id: "run2".into(),
+            stable_id_salt: "ws2".into(),
+            ..plain.clone()
+        };
+        ingest_manifest(&helper.state, &plain, "", false).unwrap();
+        ingest_manifest(&helper.state, &salted, "", false).unwrap();
+        let conn = helper.state.conn.lock().unwrap();
+        let plain_ids = store::get_finding_stable_ids_for_run(&conn, "run1").unwrap();
+        let salted_ids = store::get_finding_stable_ids_for_run(&conn, "run2").unwrap();
+        assert_eq!(plain_ids.len(), 1);
+        assert_ne!(plain_ids, salted_ids);
+    }
+
+    #[test]
+    fn findings_from_every_finding_artifact_are_stored() {
+        let helper = TestHelper::new();
+        let first = write_artifact(
+            &helper,
+            "eslint-core.json",
+            r#"[{"filePath":"a.ts","messages":[{"ruleId":"no-var","severity":2,"line":1,"column":1,"message":"x"},{"ruleId":"eqeqeq","severity":2,"line":2,"column":1,"message":"y"}]}]"#,
+        );
+        let second = write_artifact(
+            &helper,
+            "eslint-plugin.json",
+            r#"[{"filePath":"b.ts","messages":[{"ruleId":"import/order","severity":2,"line":3,"column":1,"message":"z"}]},{"filePath":"a.ts","messages":[{"ruleId":"no-var","severity":2,"line":1,"column":1,"message":"x"}]}]"#,
+        );
+        let mut manifest = create_manifest("ws1", "run1", ArtifactKind::Finding, &first);
+        manifest
+            .artifacts
+            .extend(create_manifest("ws1", "run1", ArtifactKind::Finding, &second).artifacts);
+
+        ingest_manifest(&helper.state, &manifest, "", false).unwrap();
+        let conn = helper.state.conn.lock().unwrap();
+        let ids = store::get_finding_stable_ids_for_run(&conn, "run1").unwrap();
+        // The repeated no-var finding is stored once
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[test]
+    fn stylelint_findings_stored_alongside_eslint_findings() {
+        let helper = TestHelper::new();
+        let eslint_json = r#"[{"filePath":"a.ts","messages":[{"ruleId":"r","severity":2,"line":1,"column":1,"message":"x"}]}]"#;
+        let eslint_path = write_artifact(&helper, "eslint.json", eslint_json);
+        let stylelint_json = r#"[{"source":"a.css","warnings":[{"line":1,"column":1,"rule":"r","severity":"error","text":"x (r)"}]}]"#;
+        let css_path = write_artifact(&helper, "stylelint.json", stylelint_json);
+        let mut manifest = create_manifest("ws1", "run1", ArtifactKind::Finding, &eslint_path);
+        let stylelint = create_manifest("ws1", "run1", ArtifactKind::StylelintFinding, &css_path);
+        manifest.artifacts.extend(stylelint.artifacts);
+
+        ingest_manifest(&helper.state, &manifest, "", true).unwrap();
+        let conn = helper.state.conn.lock().unwrap();
+        assert_eq!(
+            store::get_finding_stable_ids_for_run(&conn, "run1")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn rust_test_failures_stored_alongside_vitest_failures() {
+        let helper = TestHelper::new();
+        let vitest_path = write_artifact(
+            &helper,
+            "vitest.json",
+            r#"{"testResults":[{"name":"a.test.ts","status":"failed","assertionResults":[{"title":"t","status":"failed","failureMessages":["x"]}]}]}"#,
+        );
+        let cargo_path = write_artifact(
+            &helper,
+            "cargo-test.json",
+            "{\"type\":\"test\",\"name\":\"tests::fails\",\"event\":\"failed\",\"stdout\":\"boom\"}\n",
+        );
+        let mut manifest = create_manifest("ws1", "run1", ArtifactKind::TestFailure, &vitest_path);
+        manifest.artifacts.extend(
+            create_manifest("ws1", "run1", ArtifactKind::RustTestFailure, &cargo_path).artifacts,
+        );
+
+        // The libtest artifact is not reduced to its first JSON value
+        ingest_manifest(&helper.state, &manifest, "", true).unwrap();
+        let conn = helper.state.conn.lock().unwrap();
+        assert_eq!(
+            store::get_stable_ids_for_run(&conn, "run1").unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn playwright_report_ingested_as_test_failures() {
+        let helper = TestHelper::new();
+        let path = write_artifact(
+            &helper,
+            "playwright.json",
+            &format!(
+                r#"{{"config":{{"rootDir":"{}/e2e"}},"suites":[{{"title":"a.spec.ts","specs":[{{"title":"t","file":"a.spec.ts","tests":[{{"results":[{{"status":"timedOut"}}]}}]}}]}}]}}"#,
+                helper.state.workspace_root.display()
+            ),
+        );
+        let manifest = create_manifest("ws1", "run1", ArtifactKind::PlaywrightTestFailure, &path);
+
+        ingest_manifest(&helper.state, &manifest, "", true).unwrap();
+        let conn = helper.state.conn.lock().unwrap();
+        let failures = store::get_failure_identities_scoped(&conn, "run1", "").unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].file, "e2e/a.spec.ts");
+    }
+
+    #[test]
+    fn clear_workspace_data_leaves_other_workspaces_intact() {
+        let mut helper = TestHelper::new();
+        helper.state.admin = true;
+        let failure = |id: &str| TestFailureRow {
+            stable_id: id.into(),
+            test_id: "t".into(),
+            file: "f".into(),
+            message: "m".into(),
+            kind: "assertion".into(),
+            expected: None,
+            received: None,
+        };
+        helper.insert_run_with_data("ws1", "run1", 1000, &[failure("a")], &[]);
+        helper.insert_run_with_data("ws1", "run2", 2000, &[failure("b")], &[]);
+        helper.insert_run_with_data("ws2", "run3", 1000, &[failure("c")], &[]);
+
+        assert_eq!(clear_workspace_data(&helper.state, "ws1").unwrap(), 2);
+        let conn = helper.state.conn.lock().unwrap();
+        assert!(store::get_recent_runs(&conn, "ws1", 10, false)
+            .unwrap()
+            .is_empty());
+        assert!(store::get_stable_ids_for_run(&conn, "run1")
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store::get_recent_runs(&conn, "ws2", 10, false)
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            store::get_stable_ids_for_run(&conn, "run3").unwrap(),
+            vec!["c"]
+        );
+    }
+
+    #[test]
+    fn ingest_does_not_wait_on_another_workspace() {
+        let helper = TestHelper::new();
+        let eslint_json = r#"[{"filePath":"a.ts","messages":[{"ruleId":"r","severity":2,"line":1,"column":1,"message":"x"}]}]"#;
+        let path = write_artifact(&helper, "eslint.json", eslint_json);
+        let m = create_manifest("ws2", "run1", ArtifactKind::Finding, &path);
+
+        let busy = helper.state.writers.get("ws1").unwrap();
+        std::thread::scope(|scope| {
+            // An ingest for ws1 in progress
+            let _guard = busy.lock().unwrap();
+            let (tx, rx) = std::sync::mpsc::channel();
+            let (state, m) = (&helper.state, &m);
+            scope.spawn(move || tx.send(ingest_manifest(state, m, "", false)).unwrap());
+            let result = rx.recv_timeout(std::time::Duration::from_secs(10));
+            assert_eq!(result.unwrap().unwrap(), "run1");
+        });
+        let summary = get_delta_summary(&helper.state, "ws2", "", false).unwrap();
+        assert_eq!(summary.new_findings, 1);
+    }
+
+    #[test]
+    fn clear_workspace_data_requires_admin() {
+        let helper = TestHelper::new();
+        let err = clear_workspace_data(&helper.state, "ws1").unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+    }
+
+    #[test]
+    fn database_maintenance_reports_ok_on_populated_db() {
+        let mut helper = TestHelper::new();
+        let finding = located_finding("f1", "r", "f", 1);
+        helper.insert_run_with_data("ws1", "run1", 1000, &[], &[finding]);
+        helper.insert_run("ws1", "run2", 2000);
+
+        let err = database_maintenance(&helper.state).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+        helper.state.admin = true;
+        assert_eq!(database_maintenance(&helper.state).unwrap(), "ok");
+        let conn = helper.state.conn.lock().unwrap();
+        assert_eq!(store::get_run_history(&conn, "ws1", 10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn verify_ingest_detects_missing_rows() {
+        let helper = TestHelper::new();
+        helper.insert_run("ws1", "run1", 1000);
+        let failures = [TestFailureRow {
+            stable_id: "tf1".into(),
+            test_id: "t1".into(),
+            file: "f".into(),
+            message: "m".into(),
+            kind: "assertion".into(),
+            expected: None,
+            received: None,
+        }];
+        let artifacts = ParsedArtifacts {
+            failures: &failures,
+            findings: &[],
+            package_scope: "",
+            started_at_ms: 0,
+            artifact_bytes: 0,
+        };
+        let err = verify_ingest(&helper.state, "run1", &artifacts).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::DataLoss);
+    }
+
+    #[test]
+    fn ingest_records_duration_and_artifact_bytes() {
+        let helper = TestHelper::new();
+        let vitest_json = r#"{"testResults":[]}"#;
+        let path = write_artifact(&helper, "vitest.json", vitest_json);
+        let m = create_manifest("ws1", "run1", ArtifactKind::TestFailure, &path);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+        helper.insert_run("ws1", "legacy", 0);
+
+        let stats = get_run_stats(&helper.state, "ws1", 0).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].run_id, "run1");
+        assert!(stats[0].ingest_duration_ms.unwrap() >= 0);
+        assert_eq!(stats[0].artifact_bytes, vitest_json.len() as i64);
+        assert_eq!(stats[1].ingest_duration_ms, None);
+    }
+
+    #[test]
+    fn renamed_test_file_does_not_churn_delta() {
+        let mut helper = TestHelper::new();
+        let vitest_json = |file: &str| {
+            format!(
+                r#"{{"testResults":[{{"name":"{file}","status":"failed","assertionResults":[{{"ancestorTitles":["math"],"title":"adds","status":"failed","failureMessages":["e"]}}]}}]}}"#
+            )
+        };
+        for (detect, workspace, expected) in [(false, "ws1", (1, 1)), (true, "ws2", (0, 0))] {
+            helper.state.detect_renames = detect;
+            for (run, file) in [("run1", "old.test.ts"), ("run2", "new.test.ts")] {
+                let run = format!("{workspace}-{run}");
+                let path = write_artifact(&helper, &format!("{run}.json"), &vitest_json(file));
+                let m = create_manifest(workspace, &run, ArtifactKind::TestFailure, &path);
+                ingest_manifest(&helper.state, &m, "", false).unwrap();
+            }
+            let result = get_delta_summary(&helper.state, workspace, "", false).unwrap();
+            assert_eq!(
+                (result.new_test_failures, result.fixed_test_failures),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn detect_renames_skips_ambiguous_and_partial_matches() {
+        let id = |file: &str, test_id: &str| {
+            stable_id::compute_versioned(file, test_id, 0, stable_id::DEFAULT_ID_LENGTH).unwrap()
+        };
+        let identity = |file: &str, test_id: &str| store::FailureIdentity {
+            stable_id: id(file, test_id),
+            test_id: test_id.into(),
+            file: file.into(),
+        };
+        let row = |file: &str, test_id: &str| TestFailureRow {
+            stable_id: id(file, test_id),
+            test_id: test_id.into(),
+            file: file.into(),
+            message: "m".into(),
+            kind: "assertion".into(),
+            expected: None,
+            received: None,
+        };
+        let previous = [identity("a.ts", "t1"), identity("b.ts", "b.ts::file-error")];
+        // a.ts matches two new files; b.ts's renamed file also fails another test
+        let current = [
+            row("c.ts", "t1"),
+            row("d.ts", "t1"),
+            row("e.ts", "e.ts::file-error"),
+            row("e.ts", "t2"),
+        ];
+        assert!(detect_renames(&previous, &current).is_empty());
+
+        let current = [row("c.ts", "t1"), row("e.ts", "e.ts::file-error")];
+        let renames = detect_renames(&previous, &current);
+        assert_eq!(
+            renames,
+            vec![
+                (previous[0].stable_id.clone(), current[0].stable_id.clone()),
+                (previous[1].stable_id.clone(), current[1].stable_id.clone()),
+            ]
+        );
+    }
+
+    #[test]
+    fn file_health_counts_errors_and_warnings_per_file() {
+        let mut helper = TestHelper::new();
+        helper.state.eslint_warnings = true;
+        let message = |rule: &str, severity: i32| {
+            format!(
+                r#"{{"ruleId":"{rule}","severity":{severity},"line":1,"column":1,"message":"m"}}"#
+            )
+        };
+        let messages: Vec<String> = [("e1", 2), ("e2", 2), ("w1", 1), ("w2", 1), ("w3", 1)]
+            .iter()
+            .map(|(rule, severity)| message(rule, *severity))
+            .collect();
+        let eslint_json = format!(
+            r#"[{{"filePath":"src/a.ts","messages":[{}]}},{{"filePath":"src/b.ts","messages":[{}]}}]"#,
+            messages.join(","),
+            message("e3", 2)
+        );
+        let path = write_artifact(&helper, "eslint.json", &eslint_json);
+        let m = create_manifest("ws1", "run1", ArtifactKind::Finding, &path);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+
+        let health = get_file_health(&helper.state, "ws1").unwrap();
+        assert_eq!(health.len(), 2);
+        let counts: Vec<(&str, i64, i64)> = health
+            .iter()
+            .map(|h| (h.file.as_str(), h.errors, h.warnings))
+            .collect();
+        assert_eq!(counts, vec![("src/a.ts", 2, 3), ("src/b.ts", 1, 0)]);
+        assert!((health[0].finding_density - 5.0 / 6.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn run_history_counts_results_including_empty_runs() {
+        let helper = TestHelper::new();
+        let failure = |id: &str| TestFailureRow {
+            stable_id: id.into(),
+            test_id: id.into(),
+            file: "a.test.ts".into(),
+            message: "m".into(),
+            expected: None,
+            received: None,
+            kind: "assertion".into(),
+        };
+        let finding = located_finding("f1", "r", "f", 1);
+        let failures = [failure("t1"), failure("t2")];
+        helper.insert_run_with_data("ws1", "run1", 1000, &failures, &[finding]);
+        helper.insert_run("ws1", "clean", 2000);
+        {
+            let mut conn = helper.state.conn.lock().unwrap();
+            let tx = conn.transaction().unwrap();
+            store::insert_run(&tx, "ws1", "in-progress", 3000).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let history = get_run_history(&helper.state, "ws1", 0).unwrap();
+        let rows: Vec<(&str, Option<i64>, i64, i64)> = history
+            .iter()
+            .map(|r| {
+                (
+                    r.run_id.as_str(),
+                    r.completed_at,
+                    r.test_failures,
+                    r.findings,
+                )
+            })
+            .collect();
+        let expected = [
+            ("in-progress", None, 0, 0),
+            ("clean", Some(2001), 0, 0),
+            ("run1", Some(1001), 2, 1),
+        ];
+        assert_eq!(rows, expected);
+        assert_eq!(get_run_history(&helper.state, "ws1", 1).unwrap().len(), 1);
+        assert!(get_run_history(&helper.state, "ws2", 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn run_attributes_form_a_trend_across_runs() {
+        let helper = TestHelper::new();
+        for (run_id, coverage) in [("run1", "81.5"), ("run2", "83.0")] {
+            let manifest = ArtifactManifest {
+                artifacts: Vec::new(),
+                attributes: HashMap::from([
+                    ("coverage".to_string(), coverage.to_string()),
+                    ("bundle_kb".to_string(), "120".to_string()),
+                ]),
+                ..create_manifest("ws1", run_id, ArtifactKind::Finding, "")
+            };
+            ingest_manifest(&helper.state, &manifest, "", false).unwrap();
+        }
+
+        let trend = get_attribute_trend(&helper.state, "ws1", "coverage", 0).unwrap();
+        let values: Vec<(&str, &str)> = trend
+            .iter()
+            .map(|p| (p.run_id.as_str(), p.value.as_str()))
+            .collect();
+        assert_eq!(values, vec![("run1", "81.5"), ("run2", "83.0")]);
+        assert!(get_attribute_trend(&helper.state, "ws1", "missing", 0)
+            .unwrap()
+            .is_empty());
+        assert!(get_attribute_trend(&helper.state, "ws1", "", 0).is_err());
+    }
+
+    #[test]
+    fn flaky_tests_need_more_than_one_flip() {
+        let helper = TestHelper::new();
+        let failure = |id: &str| TestFailureRow {
+            stable_id: id.into(),
+            test_id: format!("a.test.ts > {id}"),
+            file: "a.test.ts".into(),
+            message: "m".into(),
+            expected: None,
+            received: None,
+            kind: "assertion".into(),
+        };
+        // "flaky" fails, passes, fails; "broken" always fails; "regressed" starts failing.
+        let runs = [
+            vec![failure("flaky"), failure("broken")],
+            vec![failure("broken")],
+            vec![failure("flaky"), failure("broken"), failure("regressed")],
+            vec![failure("broken"), failure("regressed")],
+        ];
+        for (i, failures) in (0..).zip(&runs) {
+            helper.insert_run_with_data("ws1", &format!("run{i}"), 1000 * (i + 1), failures, &[]);
+        }
+
+        let flaky = get_flaky_tests(&helper.state, "ws1", 0).unwrap();
+        let found: Vec<(&str, &str, usize)> = flaky
+            .iter()
+            .map(|t| (t.stable_id.as_str(), t.test_id.as_str(), t.flip_count))
+            .collect();
+        assert_eq!(found, vec![("flaky", "a.test.ts > flaky", 3)]);
+        // Within the last two runs "flaky" switches only once.
+        assert!(get_flaky_tests(&helper.state, "ws1", 2).unwrap().is_empty());
+        assert!(get_flaky_tests(&helper.state, "ws1", -1).is_err());
+    }
+
+    #[test]
+    fn flaky_tests_compare_runs_of_the_same_package_scope() {
+        let helper = TestHelper::new();
+        let failure = |id: &str| TestFailureRow {
+            stable_id: id.into(),
+            test_id: id.into(),
+            file: "a.test.ts".into(),
+            message: "m".into(),
+            expected: None,
+            received: None,
+            kind: "assertion".into(),
+        };
+        // Runs alternate between packages: "broken" fails in every auth run,
+        // "flaky" fails in the first and third of them
+        let runs = [
+            ("packages/auth", vec![failure("broken"), failure("flaky")]),
+            ("packages/billing", vec![]),
+            ("packages/auth", vec![failure("broken")]),
+            ("packages/billing", vec![]),
+            ("packages/auth", vec![failure("broken"), failure("flaky")]),
+        ];
+        for (i, (package, failures)) in (0..).zip(&runs) {
+            let run = format!("run{i}");
+            let time = 1000 * (i + 1);
+            helper.insert_run_with_data_and_package("ws1", &run, time, package, failures, &[]);
+        }
+
+        let flaky = get_flaky_tests(&helper.state, "ws1", 0).unwrap();
+        let found: Vec<(&str, usize)> = flaky
+            .iter()
+            .map(|t| (t.stable_id.as_str(), t.flip_count))
+            .collect();
+        assert_eq!(found, vec![("flaky", 2)]);
+    }
+
+    #[test]
+    fn tsc_findings_flow_into_delta() {
+        let helper = TestHelper::new();
+        let first = "src/a.ts(1,1): error TS2304: Cannot find name 'x'.\n";
+        let path = write_artifact(&helper, "tsc.txt", first);
+        let mut m = create_manifest("ws1", "run1", ArtifactKind::TscFinding, &path);
+        ingest_manifest(&helper.state, &m, "", true).unwrap();
+        let text = format!("{first}src/b.ts(2,3): error TS2322: Type 'a' is not 'b'.\n");
+        m.artifacts[0].path = write_artifact(&helper, "tsc.txt", &text);
+        m.run_id = "run2".into();
+        ingest_manifest(&helper.state, &m, "", true).unwrap();
+
+        let summary = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
+        assert_eq!((summary.new_findings, summary.fixed_findings), (1, 0));
+    }
+
+    #[test]
+    fn tsc_warnings_follow_the_artifact_min_severity() {
+        let helper = TestHelper::new();
+        let text = "src/a.ts(1,1): error TS2304: Cannot find name 'x'.\n\
+                    src/b.ts(2,1): warning TS6133: 'y' is declared but never used.\n";
+        let path = write_artifact(&helper, "tsc.txt", text);
+        let mut m = create_manifest("ws1", "run1", ArtifactKind::TscFinding, &path);
+        m.artifacts[0].min_severity = 7;
+        let err = ingest_manifest(&helper.state, &m, "", false).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        m.artifacts[0].min_severity = 0;
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+        m.artifacts[0].min_severity = 1;
+        m.run_id = "run2".into();
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+        let summary = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
+        assert_eq!((summary.new_findings, summary.fixed_findings), (1, 0));
+    }
+
+    #[test]
+    fn empty_artifacts_ingest_as_no_results() {
+        let helper = TestHelper::new();
+        let vitest_path = write_artifact(&helper, "vitest.json", "");
+        let eslint_path = write_artifact(&helper, "eslint.json", "\n");
+        let mut m = create_manifest("ws1", "run1", ArtifactKind::TestFailure, &vitest_path);
+        let eslint = create_manifest("ws1", "run1", ArtifactKind::Finding, &eslint_path);
+        m.artifacts.extend(eslint.artifacts);
+
+        // Also with embedded JSON extraction, which finds no value to extract
+        for (run, extract_json) in [("run1", false), ("run2", true)] {
+            m.run_id = run.into();
+            ingest_manifest(&helper.state, &m, "", extract_json).unwrap();
+        }
+        let summary = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
+        assert_eq!((summary.new_findings, summary.new_test_failures), (0, 0));
+    }
+
+    #[test]
+    fn artifact_min_severity_overrides_service_default() {
+        let helper = TestHelper::new();
+        let eslint_json = r#"[{"filePath":"a.ts","messages":[{"ruleId":"e","severity":2,"line":1,"column":1,"message":"x"},{"ruleId":"w","severity":1,"line":2,"column":1,"message":"y"}]}]"#;
+        let path = write_artifact(&helper, "eslint.json", eslint_json);
+        let mut m = create_manifest("ws1", "run1", ArtifactKind::Finding, &path);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+
+        // The service stores errors only, but this artifact asks for warnings
+        m.run_id = "run2".into();
+        m.artifacts[0].min_severity = 1;
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+        let conn = helper.state.conn.lock().unwrap();
+        assert_eq!(
+            store::get_finding_stable_ids_for_run(&conn, "run1")
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            store::get_finding_stable_ids_for_run(&conn, "run2")
+                .unwrap()
+                .len(),
+            2
+        );
+        drop(conn);
+
+        m.run_id = "run3".into();
+        m.artifacts[0].min_severity = 3;
+        let err = ingest_manifest(&helper.state, &m, "", false).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[test]
+    fn delta_can_include_incomplete_run() {
+        let helper = TestHelper::new();
+        let finding = |id: &str| FindingRow {
+            stable_id: id.into(),
+            tool: "eslint".into(),
+            rule: "r".into(),
+            file: "f".into(),
+            start_line: 1,
+            start_column: 1,
+            end_line: 1,
+            end_column: 1,
+            message: "m".into(),
+            category: "unknown".into(),
+            fixable: false,
+            severity: 2,
+        };
+        helper.insert_run_with_data("ws1", "run1", 1000, &[], &[finding("f1")]);
+        helper.insert_run_with_data("ws1", "run2", 2000, &[], &[finding("f2")]);
+        {
+            let mut conn = helper.state.conn.lock().unwrap();
+            let tx = conn.transaction().unwrap();
+            store::insert_run(&tx, "ws1", "in-progress", 3000).unwrap();
+            let findings = [finding("f2"), finding("f3")];
+            store::insert_findings(&tx, "in-progress", "", &findings).unwrap();
+            tx.commit().unwrap();
+        }
+
+        // Completed only: run2 against run1
+        let completed = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
+        assert_eq!((completed.new_findings, completed.fixed_findings), (1, 1));
+        // The in-progress run against run2
+        let latest = get_delta_summary(&helper.state, "ws1", "", true).unwrap();
+        assert_eq!((latest.new_findings, latest.fixed_findings), (1, 0));
+    }
+
+    #[test]
+    fn finding_trend_buckets_runs_by_day() {
+        let helper = TestHelper::new();
+        let finding = |id: &str, severity: i32| FindingRow {
+            stable_id: id.into(),
+            tool: "eslint".into(),
+            rule: "r".into(),
+            file: "f".into(),
+            start_line: 1,
+            start_column: 1,
+            end_line: 1,
+            end_column: 1,
+            message: "m".into(),
+            category: "unknown".into(),
+            fixable: false,
+            severity,
+        };
+        let day = i64::from(DEFAULT_TREND_BUCKET_SECS);
+        let three = [finding("a", 2), finding("b", 2), finding("c", 1)];
+        helper.insert_run_with_data("ws1", "old", 10, &[], &three);
+        helper.insert_run_with_data("ws1", "d1-early", day + 100, &[], &three);
+        helper.insert_run_with_data("ws1", "d1-late", day + 200, &[], &three[..1]);
+        helper.insert_run_with_data("ws1", "d3", 3 * day + 5, &[], &[]);
+        helper.insert_run_with_data("ws2", "other", day + 300, &[], &three);
+
+        let trend = get_finding_trend(&helper.state, "ws1", day, 0).unwrap();
+        let points: Vec<(i64, i64, i64, i64)> = trend
+            .iter()
+            .map(|p| (p.bucket_start, p.runs, p.errors, p.warnings))
+            .collect();
+        // Day 1 reports its latest run; day 2 had no runs
+        assert_eq!(points, vec![(day, 2, 1, 0), (3 * day, 1, 0, 0)]);
+
+        let hourly = get_finding_trend(&helper.state, "ws1", 0, 3600).unwrap();
+        assert_eq!(hourly.len(), 3);
+        assert_eq!(
+            (hourly[0].bucket_start, hourly[0].errors, hourly[0].warnings),
+            (0, 2, 1)
+        );
+    }
+
+    #[test]
+    fn workspace_id_variants_share_history() {
+        let mut helper = TestHelper::new();
+        helper.state.lowercase_workspace_ids = true;
+        let vitest_json = r#"{"testResults":[{"name":"t.ts","status":"failed","assertionResults":[{"title":"t","status":"failed","failureMessages":["e"]}]}]}"#;
+        let path = write_artifact(&helper, "vitest.json", vitest_json);
+        let m = create_manifest("WS1/", "run1", ArtifactKind::TestFailure, &path);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+
+        let result = get_delta_summary(&helper.state, "ws1", "", false).unwrap();
+        assert_eq!(result.new_test_failures, 1);
+        let result = get_delta_summary(&helper.state, " ws1/ ", "", false).unwrap();
+        assert_eq!(result.new_test_failures, 1);
+
+        // Case is only folded when configured
+        helper.state.lowercase_workspace_ids = false;
+        let m = create_manifest("WS2", "run2", ArtifactKind::TestFailure, &path);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+        let new_failures = |id| get_delta_summary(&helper.state, id, "", false).unwrap();
+        assert_eq!(new_failures("ws2").new_test_failures, 0);
+        assert_eq!(new_failures("WS2/").new_test_failures, 1);
+    }
+
+    #[test]
+    fn absolute_workspace_id_shares_history_with_directory_name() {
+        let helper = TestHelper::new();
+        let vitest_json = r#"{"testResults":[{"name":"t.ts","status":"failed","assertionResults":[{"title":"t","status":"failed","failureMessages":["e"]}]}]}"#;
+        let path = write_artifact(&helper, "vitest.json", vitest_json);
+        let absolute = format!("{}/", helper.state.workspace_root.display());
+        let m = create_manifest(&absolute, "run1", ArtifactKind::TestFailure, &path);
+        ingest_manifest(&helper.state, &m, "", false).unwrap();
+
+        let result = get_delta_summary(&helper.state, "workspace", "", false).unwrap();
+        assert_eq!(result.new_test_failures, 1);
+        let result = get_delta_summary(&helper.state, "/workspace", "", false).unwrap();
+        assert_eq!(result.new_test_failures, 0);
+    }
+
+    #[test]
+    fn normalize_stored_workspace_ids_is_an_admin_step() {
+        let mut helper = TestHelper::new();
+        helper.insert_run("WS1/", "run1", 1000);
+        helper.insert_run("ws1", "run2", 2000);
+        helper.state.lowercase_workspace_ids = true;
+        let history = |state: &RpcState| {
+            let conn = state.conn.lock().unwrap();
+            store::get_run_history(&conn, "ws1", 10).unwrap().len()
+        };
+        assert_eq!(history(&helper.state), 1);
+
+        let err = normalize_stored_workspace_ids(&helper.state).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::PermissionDenied);
+        helper.state.admin = true;
+        assert_eq!(normalize_stored_workspace_ids(&helper.state).unwrap(), 1);
+        assert_eq!(history(&helper.state), 2);
+    }
+
+    #[test]
+    fn read_only_serves_deltas_but_rejects_ingest() {
+        let helper = TestHelper::new();
+        helper.insert_run_with_data(
+            "ws1",
+            "run1",
+            1000,
+            &[TestFailureRow {
+                stable_id: "tf1".into(),
+                test_id: "t1".into(),
+                file: "f".into(),
+                message: "m".into(),
+                kind: "assertion".into(),
+                expected: None,
+                received: None,
+            }],
+            &[],
+        );
+        let replica = RpcState {
+            conn: Arc::new(Mutex::new(
+                store::open_connection_read_only(&helper.cache_dir, CacheSettings::default())
+                    .unwrap(),
+            )),
+            writers: WorkspaceConnections::new(&helper.cache_dir, CacheSettings::default()),
+            read_only: true,
+            verify_ingest: false,
+            admin: false,
+            artifact_roots: helper.state.artifact_roots.clone(),
+            detect_renames: false,
+            eslint_warnings: false,
+            lowercase_workspace_ids: false,
+            workspace_root: helper.state.workspace_root.clone(),
+        };
+
+        let result = get_delta_summary(&replica, "ws1", "", false).unwrap();
+        assert_eq!(result.new_test_failures, 1);
+
+        let m = create_manifest("ws1", "run2", ArtifactKind::TestFailure, "/p");
+        let err = ingest_manifest(&replica, &m, "", false).unwrap_err();
+        assert_eq!(err.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[test]
+    fn schema_version_lists_applied_migrations() {
+        let helper = TestHelper::new();
+        let migrations = get_schema_version(&helper.state).unwrap();
+        assert!(!migrations.is_empty());
+        assert!(migrations.windows(2).all(|w| w[0].version < w[1].version));
+    }
 }
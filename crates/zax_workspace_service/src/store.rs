@@ -1,8 +1,11 @@
 //! `SQLite` storage initialization and query functions.
 
 use refinery::embed_migrations;
-use rusqlite::{params, Connection, Transaction};
-use std::path::Path;
+use rusqlite::{params, Connection, OpenFlags, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 use thiserror::Error;
 
 embed_migrations!("migrations");
@@ -14,6 +17,8 @@ pub enum StoreError {
     Sqlite(#[from] rusqlite::Error),
     #[error("migration error: {0}")]
     Migration(#[from] refinery::Error),
+    #[error("journal mode is {0}, expected wal")]
+    JournalMode(String),
 }
 
 /// A test failure to insert into the database.
@@ -22,6 +27,37 @@ pub struct TestFailureRow {
     pub test_id: String,
     pub file: String,
     pub message: String,
+    /// `assertion` or `file_error` (the file failed before its tests ran).
+    pub kind: String,
+    /// Structured assertion values; `None` when only `message` is available.
+    pub expected: Option<String>,
+    pub received: Option<String>,
+}
+
+/// Error and warning counts of one file's findings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFindingCounts {
+    pub file: String,
+    pub errors: i64,
+    pub warnings: i64,
+}
+
+/// Finding counts of the completed runs started within one time bucket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindingTrendPoint {
+    /// Unix seconds.
+    pub bucket_start: i64,
+    pub runs: i64,
+    /// Counts of the bucket's latest run.
+    pub errors: i64,
+    pub warnings: i64,
+}
+
+/// Identity of a stored test failure, used to detect renamed test files.
+pub struct FailureIdentity {
+    pub stable_id: String,
+    pub test_id: String,
+    pub file: String,
 }
 
 /// A finding to insert into the database.
@@ -35,6 +71,26 @@ pub struct FindingRow {
     pub end_line: i32,
     pub end_column: i32,
     pub message: String,
+    pub category: String,
+    pub fixable: bool,
+    /// `1` = warning, `2` = error (`ESLint` numbering).
+    pub severity: i32,
+}
+
+/// Finding attributes used to break down new findings.
+pub struct FindingAttributes {
+    pub stable_id: String,
+    pub category: String,
+    pub fixable: bool,
+}
+
+/// Location of a stored finding, used for delta reports.
+pub struct FindingLocation {
+    pub stable_id: String,
+    pub rule: String,
+    pub file: String,
+    pub start_line: i32,
+    pub start_column: i32,
 }
 
 /// A completed run for delta computation.
@@ -42,18 +98,340 @@ pub struct RunInfo {
     pub run_id: String,
 }
 
+/// Wall-clock timing and size of one ingest.
+pub struct IngestStats {
+    /// Unix milliseconds.
+    pub started_at_ms: i64,
+    /// Unix milliseconds.
+    pub completed_at_ms: i64,
+    pub artifact_bytes: i64,
+}
+
+/// Ingest statistics of a completed run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunStats {
+    pub run_id: String,
+    /// Unix seconds.
+    pub started_at: i64,
+    /// `None` for runs ingested before durations were recorded.
+    pub ingest_duration_ms: Option<i64>,
+    pub artifact_bytes: i64,
+}
+
+/// A recorded run with its stored result counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunHistoryEntry {
+    pub run_id: String,
+    /// Unix seconds.
+    pub started_at: i64,
+    /// `None` while the run is still being ingested.
+    pub completed_at: Option<i64>,
+    pub test_failures: i64,
+    pub findings: i64,
+}
+
+/// The value of a run attribute in one run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributePoint {
+    pub run_id: String,
+    /// Unix seconds.
+    pub started_at: i64,
+    pub value: String,
+}
+
+/// A test whose failure came and went across recent runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlakyTest {
+    pub stable_id: String,
+    pub test_id: String,
+    pub file: String,
+    /// Times the test switched between failing and passing, oldest run first.
+    pub flip_count: usize,
+}
+
+/// A schema migration recorded by refinery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppliedMigration {
+    pub version: i32,
+    pub name: String,
+    /// RFC 3339 timestamp of when the migration ran.
+    pub applied_on: String,
+}
+
 /// Initializes the `SQLite` database at `<cache_dir>/db.sqlite`.
 pub fn init_storage(cache_dir: &Path) -> Result<(), StoreError> {
     let db_path = cache_dir.join("db.sqlite");
     let mut conn = Connection::open(&db_path)?;
     migrations::runner().run(&mut conn)?;
+    enable_wal(&conn)
+}
+
+/// How long a connection retries a locked database before failing with
+/// `database is locked`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Switches the database to WAL, so readers no longer block on (or block)
+/// writers. The mode is persistent; reapplying it is a no-op.
+fn enable_wal(conn: &Connection) -> Result<(), StoreError> {
+    let mode: String = conn.query_row("PRAGMA journal_mode=WAL", [], |row| row.get(0))?;
+    if !mode.eq_ignore_ascii_case("wal") {
+        return Err(StoreError::JournalMode(mode));
+    }
     Ok(())
 }
 
+/// Page cache and memory-map sizes applied to each connection.
+///
+/// The defaults keep the delta queries' working set of a large workspace in
+/// memory; values are clamped to safe ranges when applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSettings {
+    /// Page cache size in MiB (`PRAGMA cache_size`).
+    pub cache_mib: u32,
+    /// Memory-mapped I/O size in MiB (`PRAGMA mmap_size`, 0 disables).
+    pub mmap_mib: u32,
+}
+
+impl CacheSettings {
+    /// Smallest page cache, matching `SQLite`'s own default of about 2 MiB.
+    pub const MIN_CACHE_MIB: u32 = 2;
+    pub const MAX_CACHE_MIB: u32 = 1024;
+    pub const MAX_MMAP_MIB: u32 = 1024;
+
+    /// Returns the settings clamped to the supported ranges.
+    #[must_use]
+    pub fn clamped(self) -> Self {
+        Self {
+            cache_mib: self
+                .cache_mib
+                .clamp(Self::MIN_CACHE_MIB, Self::MAX_CACHE_MIB),
+            mmap_mib: self.mmap_mib.min(Self::MAX_MMAP_MIB),
+        }
+    }
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        Self {
+            cache_mib: 64,
+            mmap_mib: 256,
+        }
+    }
+}
+
 /// Opens a connection to the database.
-pub fn open_connection(cache_dir: &Path) -> Result<Connection, StoreError> {
+pub fn open_connection(cache_dir: &Path, cache: CacheSettings) -> Result<Connection, StoreError> {
     let db_path = cache_dir.join("db.sqlite");
-    Ok(Connection::open(db_path)?)
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    enable_wal(&conn)?;
+    apply_cache_settings(&conn, cache)?;
+    Ok(conn)
+}
+
+/// Opens a read-only connection to an existing database.
+///
+/// Used by query-only replicas; migrations are never run on this connection.
+pub fn open_connection_read_only(
+    cache_dir: &Path,
+    cache: CacheSettings,
+) -> Result<Connection, StoreError> {
+    let db_path = cache_dir.join("db.sqlite");
+    let flags = OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX;
+    let conn = Connection::open_with_flags(db_path, flags)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    apply_cache_settings(&conn, cache)?;
+    Ok(conn)
+}
+
+/// How long a workspace writer waits for another workspace's commit.
+const WRITER_BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most write connections kept open at once. Each carries its own page cache
+/// and mmap, so connections of the least recently used workspaces are closed.
+pub const MAX_WORKSPACE_WRITERS: usize = 8;
+
+/// Write connections keyed by workspace id.
+///
+/// Each workspace gets its own connection and lock, so an ingest does not
+/// queue behind ingests of unrelated workspaces. `SQLite` still admits one
+/// writer at a time: writers take the write lock when their transaction
+/// begins and wait out another workspace's commit via the busy timeout.
+///
+/// At most `MAX_WORKSPACE_WRITERS` connections are cached; the least recently
+/// used one is dropped to make room, and closes once in-flight work on it
+/// releases its handle.
+pub struct WorkspaceConnections {
+    cache_dir: PathBuf,
+    cache: CacheSettings,
+    /// Least recently used first
+    conns: Mutex<Vec<(String, Arc<Mutex<Connection>>)>>,
+}
+
+impl WorkspaceConnections {
+    pub fn new(cache_dir: &Path, cache: CacheSettings) -> Self {
+        Self {
+            cache_dir: cache_dir.to_path_buf(),
+            cache,
+            conns: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The write connection of `workspace_id`, opened on first use.
+    pub fn get(&self, workspace_id: &str) -> Result<Arc<Mutex<Connection>>, StoreError> {
+        // The list only caches connections, so a panicked holder left it intact
+        let mut conns = self.conns.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(pos) = conns.iter().position(|(id, _)| id == workspace_id) {
+            let entry = conns.remove(pos);
+            let conn = Arc::clone(&entry.1);
+            conns.push(entry);
+            return Ok(conn);
+        }
+        let conn = Arc::new(Mutex::new(self.open_unshared()?));
+        if conns.len() >= MAX_WORKSPACE_WRITERS {
+            conns.remove(0);
+        }
+        conns.push((workspace_id.to_string(), Arc::clone(&conn)));
+        Ok(conn)
+    }
+
+    /// Drops the cached connection of `workspace_id`, if any.
+    pub fn remove(&self, workspace_id: &str) {
+        let mut conns = self.conns.lock().unwrap_or_else(PoisonError::into_inner);
+        conns.retain(|(id, _)| id != workspace_id);
+    }
+
+    /// A fresh write connection owned by the caller, for long-running work
+    /// that should not tie up any cached connection.
+    pub fn open_unshared(&self) -> Result<Connection, StoreError> {
+        let conn = open_connection(&self.cache_dir, self.cache)?;
+        conn.busy_timeout(WRITER_BUSY_TIMEOUT)?;
+        Ok(conn)
+    }
+}
+
+fn apply_cache_settings(conn: &Connection, cache: CacheSettings) -> Result<(), StoreError> {
+    let cache = cache.clamped();
+    // A negative cache_size is a size in KiB rather than a page count
+    conn.pragma_update(None, "cache_size", -i64::from(cache.cache_mib) * 1024)?;
+    // mmap_size reports the applied value, so it cannot go through pragma_update
+    let mmap_bytes = i64::from(cache.mmap_mib) * 1024 * 1024;
+    conn.query_row(&format!("PRAGMA mmap_size = {mmap_bytes}"), [], |_| Ok(()))?;
+    Ok(())
+}
+
+/// Outcome of a WAL checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalCheckpoint {
+    /// A reader or writer prevented the checkpoint from completing.
+    pub busy: bool,
+    /// Frames in the WAL before the checkpoint (-1 when not in WAL mode).
+    pub log_frames: i64,
+    /// Frames copied back into the database (-1 when not in WAL mode).
+    pub checkpointed_frames: i64,
+}
+
+/// Copies the WAL into the database and truncates the `-wal` file.
+///
+/// A no-op outside WAL mode. Run it on a dedicated connection so RPC
+/// handlers sharing the main connection are not locked out meanwhile.
+pub fn checkpoint_wal(conn: &Connection) -> Result<WalCheckpoint, StoreError> {
+    Ok(
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+            Ok(WalCheckpoint {
+                busy: row.get::<_, i64>(0)? != 0,
+                log_frames: row.get(1)?,
+                checkpointed_frames: row.get(2)?,
+            })
+        })?,
+    )
+}
+
+/// Integrity-checks the database, then rebuilds its indices and compacts it.
+///
+/// Returns the `PRAGMA integrity_check` report taken before the rebuild:
+/// `"ok"`, or one problem per line. Each step runs in its own implicit
+/// transaction, so the write lock is only held for `REINDEX` and `VACUUM`.
+pub fn run_maintenance(conn: &Connection) -> Result<String, StoreError> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    drop(stmt);
+    conn.execute_batch("REINDEX")?;
+    conn.execute_batch("VACUUM")?;
+    Ok(problems.join("\n"))
+}
+
+/// Lists the migrations applied to this database, oldest first.
+///
+/// Reads refinery's history table directly so it also works on read-only
+/// connections.
+pub fn applied_migrations(conn: &Connection) -> Result<Vec<AppliedMigration>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT version, name, applied_on FROM refinery_schema_history ORDER BY version ASC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(AppliedMigration {
+            version: row.get(0)?,
+            name: row.get(1)?,
+            applied_on: row.get(2)?,
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
+/// Deletes every run of a workspace with its test failures and findings.
+/// Returns the number of runs deleted.
+pub fn delete_workspace_data(tx: &Transaction, workspace_id: &str) -> Result<usize, StoreError> {
+    for table in [
+        "test_failures",
+        "findings",
+        "test_renames",
+        "run_attributes",
+    ] {
+        tx.execute(
+            &format!(
+                "DELETE FROM {table} WHERE run_id IN \
+                 (SELECT run_id FROM runs WHERE workspace_id = ?1)"
+            ),
+            params![workspace_id],
+        )?;
+    }
+    Ok(tx.execute(
+        "DELETE FROM runs WHERE workspace_id = ?1",
+        params![workspace_id],
+    )?)
+}
+
+/// Rewrites the workspace ids of runs stored before ids were normalized, so
+/// their history stays reachable under the canonical id. `normalize` maps an
+/// id to its canonical form, or `None` to leave it as is. Returns the number
+/// of distinct ids rewritten.
+pub fn normalize_workspace_ids(
+    conn: &mut Connection,
+    normalize: impl Fn(&str) -> Option<String>,
+) -> Result<usize, StoreError> {
+    let tx = conn.transaction()?;
+    let ids: Vec<String> = {
+        let mut stmt = tx.prepare("SELECT DISTINCT workspace_id FROM runs")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<Result<_, _>>()?
+    };
+    let mut rewritten = 0;
+    for id in ids {
+        let Some(canonical) = normalize(&id).filter(|canonical| *canonical != id) else {
+            continue;
+        };
+        tx.execute(
+            "UPDATE runs SET workspace_id = ?1 WHERE workspace_id = ?2",
+            params![canonical, id],
+        )?;
+        rewritten += 1;
+    }
+    tx.commit()?;
+    Ok(rewritten)
 }
 
 /// Inserts a new run record.
@@ -79,6 +457,184 @@ pub fn complete_run(tx: &Transaction, run_id: &str, completed_at: i64) -> Result
     Ok(())
 }
 
+/// Records the package scope a run was ingested with ('' = whole workspace).
+pub fn record_run_package_scope(
+    tx: &Transaction,
+    run_id: &str,
+    package_scope: &str,
+) -> Result<(), StoreError> {
+    tx.execute(
+        "UPDATE runs SET package_scope = ?1 WHERE run_id = ?2",
+        params![package_scope, run_id],
+    )?;
+    Ok(())
+}
+
+/// Records how long a run's ingest took and how many artifact bytes it read.
+pub fn record_ingest_stats(
+    tx: &Transaction,
+    run_id: &str,
+    stats: &IngestStats,
+) -> Result<(), StoreError> {
+    tx.execute(
+        "UPDATE runs SET ingest_started_at = ?1, ingest_completed_at = ?2, artifact_bytes = ?3 \
+         WHERE run_id = ?4",
+        params![
+            stats.started_at_ms,
+            stats.completed_at_ms,
+            stats.artifact_bytes,
+            run_id
+        ],
+    )?;
+    Ok(())
+}
+
+/// Gets ingest statistics for the most recent completed runs, newest first.
+pub fn get_run_stats(
+    conn: &Connection,
+    workspace_id: &str,
+    limit: usize,
+) -> Result<Vec<RunStats>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT run_id, started_at, ingest_completed_at - ingest_started_at, artifact_bytes \
+         FROM runs \
+         WHERE workspace_id = ?1 AND completed_at IS NOT NULL \
+         ORDER BY started_at DESC, id DESC LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![workspace_id, limit], |row| {
+        Ok(RunStats {
+            run_id: row.get(0)?,
+            started_at: row.get(1)?,
+            ingest_duration_ms: row.get(2)?,
+            artifact_bytes: row.get(3)?,
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
+/// Gets the most recent runs with their test failure and finding counts,
+/// newest first. Runs without results count 0; incomplete runs are included.
+pub fn get_run_history(
+    conn: &Connection,
+    workspace_id: &str,
+    limit: usize,
+) -> Result<Vec<RunHistoryEntry>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT r.run_id, r.started_at, r.completed_at, \
+         (SELECT COUNT(*) FROM test_failures t WHERE t.run_id = r.run_id), \
+         (SELECT COUNT(*) FROM findings f WHERE f.run_id = r.run_id) \
+         FROM runs r WHERE r.workspace_id = ?1 \
+         ORDER BY r.started_at DESC, r.id DESC LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![workspace_id, limit], |row| {
+        Ok(RunHistoryEntry {
+            run_id: row.get(0)?,
+            started_at: row.get(1)?,
+            completed_at: row.get(2)?,
+            test_failures: row.get(3)?,
+            findings: row.get(4)?,
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
+/// Gets tests that switched between failing and passing more than once over
+/// the last `window` completed runs, most flips first. Tests failing in every
+/// run or in none never flip; a single switch is a regression or a fix.
+///
+/// Flips are counted within the runs of one package scope, since a run
+/// scoped to another package did not run the test; a test failing under
+/// several scopes reports the scope with the most flips.
+pub fn get_flaky_tests(
+    conn: &Connection,
+    workspace_id: &str,
+    window: usize,
+) -> Result<Vec<FlakyTest>, StoreError> {
+    // Per scope and test: the scope's run count and the 0-based positions,
+    // oldest first, of its runs the test failed in
+    let mut stmt = conn.prepare(
+        "WITH recent AS (              SELECT run_id, package_scope, started_at, id FROM runs              WHERE workspace_id = ?1 AND completed_at IS NOT NULL              ORDER BY started_at DESC, id DESC LIMIT ?2          ), positioned AS (              SELECT run_id, package_scope,              ROW_NUMBER() OVER (PARTITION BY package_scope ORDER BY started_at, id) - 1                  AS position,              COUNT(*) OVER (PARTITION BY package_scope) AS runs              FROM recent          )          SELECT f.stable_id, MIN(f.test_id), MIN(f.file), MAX(p.runs), group_concat(p.position)          FROM positioned p JOIN test_failures f ON f.run_id = p.run_id          GROUP BY p.package_scope, f.stable_id",
+    )?;
+    let rows = stmt.query_map(params![workspace_id, window], |row| {
+        let positions: String = row.get(4)?;
+        let failed_in: HashSet<usize> = positions
+            .split(',')
+            .filter_map(|p| p.parse().ok())
+            .collect();
+        let runs: usize = row.get(3)?;
+        Ok(FlakyTest {
+            stable_id: row.get(0)?,
+            test_id: row.get(1)?,
+            file: row.get(2)?,
+            flip_count: (1..runs)
+                .filter(|&i| failed_in.contains(&i) != failed_in.contains(&(i - 1)))
+                .count(),
+        })
+    })?;
+    let mut most_flips: HashMap<String, FlakyTest> = HashMap::new();
+    for test in rows {
+        let test = test?;
+        let best = most_flips
+            .entry(test.stable_id.clone())
+            .or_insert_with(|| test.clone());
+        if test.flip_count > best.flip_count {
+            *best = test;
+        }
+    }
+    let mut flaky: Vec<FlakyTest> = most_flips
+        .into_values()
+        .filter(|test| test.flip_count > 1)
+        .collect();
+    flaky.sort_by(|a, b| {
+        b.flip_count
+            .cmp(&a.flip_count)
+            .then_with(|| a.stable_id.cmp(&b.stable_id))
+    });
+    Ok(flaky)
+}
+
+/// Inserts the custom attributes of a run.
+pub fn insert_run_attributes(
+    tx: &Transaction,
+    run_id: &str,
+    attributes: &HashMap<String, String>,
+) -> Result<(), StoreError> {
+    let mut stmt =
+        tx.prepare("INSERT INTO run_attributes (run_id, key, value) VALUES (?1, ?2, ?3)")?;
+    for (key, value) in attributes {
+        stmt.execute(params![run_id, key, value])?;
+    }
+    Ok(())
+}
+
+/// Gets a key's values in the most recent completed runs that set it, oldest
+/// first.
+pub fn get_attribute_trend(
+    conn: &Connection,
+    workspace_id: &str,
+    key: &str,
+    limit: usize,
+) -> Result<Vec<AttributePoint>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT r.run_id, r.started_at, a.value \
+         FROM runs r JOIN run_attributes a ON a.run_id = r.run_id \
+         WHERE r.workspace_id = ?1 AND r.completed_at IS NOT NULL AND a.key = ?2 \
+         ORDER BY r.started_at DESC, r.id DESC LIMIT ?3",
+    )?;
+    let rows = stmt.query_map(params![workspace_id, key, limit], |row| {
+        Ok(AttributePoint {
+            run_id: row.get(0)?,
+            started_at: row.get(1)?,
+            value: row.get(2)?,
+        })
+    })?;
+    let mut points = rows.collect::<Result<Vec<_>, _>>()?;
+    points.reverse();
+    Ok(points)
+}
+
 /// Inserts test failures in batch with package scope.
 pub fn insert_test_failures(
     tx: &Transaction,
@@ -87,27 +643,40 @@ pub fn insert_test_failures(
     failures: &[TestFailureRow],
 ) -> Result<(), StoreError> {
     let mut stmt = tx.prepare(
-        "INSERT INTO test_failures (run_id, stable_id, test_id, file, message, package) \
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO test_failures \
+         (run_id, stable_id, test_id, file, message, package, expected, received, kind) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
     )?;
     for f in failures {
-        stmt.execute(params![run_id, f.stable_id, f.test_id, f.file, f.message, package])?;
+        stmt.execute(params![
+            run_id,
+            f.stable_id,
+            f.test_id,
+            f.file,
+            f.message,
+            package,
+            f.expected,
+            f.received,
+            f.kind
+        ])?;
     }
     Ok(())
 }
 
-/// Gets the most recent completed runs for a workspace.
+/// Gets the most recent runs for a workspace: completed runs only, unless
+/// `include_incomplete` also admits runs not yet marked complete.
 pub fn get_recent_runs(
     conn: &Connection,
     workspace_id: &str,
     limit: usize,
+    include_incomplete: bool,
 ) -> Result<Vec<RunInfo>, StoreError> {
     let mut stmt = conn.prepare(
         "SELECT run_id FROM runs \
-         WHERE workspace_id = ?1 AND completed_at IS NOT NULL \
-         ORDER BY started_at DESC LIMIT ?2",
+         WHERE workspace_id = ?1 AND (?3 OR completed_at IS NOT NULL) \
+         ORDER BY started_at DESC, id DESC LIMIT ?2",
     )?;
-    let rows = stmt.query_map(params![workspace_id, limit], |row| {
+    let rows = stmt.query_map(params![workspace_id, limit, include_incomplete], |row| {
         Ok(RunInfo {
             run_id: row.get(0)?,
         })
@@ -124,6 +693,122 @@ pub fn get_stable_ids_for_run(conn: &Connection, run_id: &str) -> Result<Vec<Str
         .map_err(StoreError::from)
 }
 
+/// Counts a run's findings per file and severity, files with the most
+/// errors (then warnings) first.
+pub fn get_file_finding_counts(
+    conn: &Connection,
+    run_id: &str,
+) -> Result<Vec<FileFindingCounts>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT file, SUM(severity >= 2), SUM(severity = 1) FROM findings \
+         WHERE run_id = ?1 GROUP BY file \
+         ORDER BY 2 DESC, 3 DESC, file",
+    )?;
+    let rows = stmt.query_map(params![run_id], |row| {
+        Ok(FileFindingCounts {
+            file: row.get(0)?,
+            errors: row.get(1)?,
+            warnings: row.get(2)?,
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
+/// Gets finding counts over time for completed runs started at or after
+/// `since`, in `bucket`-second buckets (`bucket` > 0), oldest first.
+///
+/// Each bucket reports its latest run's counts, so several runs in one bucket
+/// do not add up. Buckets without runs are left out.
+pub fn get_finding_counts_over_time(
+    conn: &Connection,
+    workspace_id: &str,
+    since: i64,
+    bucket: i64,
+) -> Result<Vec<FindingTrendPoint>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT r.started_at, COALESCE(SUM(f.severity >= 2), 0), COALESCE(SUM(f.severity = 1), 0) \
+         FROM runs r LEFT JOIN findings f ON f.run_id = r.run_id \
+         WHERE r.workspace_id = ?1 AND r.completed_at IS NOT NULL AND r.started_at >= ?2 \
+         GROUP BY r.id ORDER BY r.started_at, r.id",
+    )?;
+    let rows = stmt.query_map(params![workspace_id, since], |row| {
+        Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?))
+    })?;
+    let mut points: Vec<FindingTrendPoint> = Vec::new();
+    for row in rows {
+        let (started_at, errors, warnings) = row?;
+        let bucket_start = started_at - started_at.rem_euclid(bucket);
+        match points.last_mut() {
+            Some(point) if point.bucket_start == bucket_start => {
+                *point = FindingTrendPoint {
+                    runs: point.runs + 1,
+                    errors,
+                    warnings,
+                    ..*point
+                };
+            }
+            _ => points.push(FindingTrendPoint {
+                bucket_start,
+                runs: 1,
+                errors,
+                warnings,
+            }),
+        }
+    }
+    Ok(points)
+}
+
+/// Gets the identity of every test failure in a run, scoped to a package.
+/// If `package_scope` is empty, returns all failures (no filtering).
+pub fn get_failure_identities_scoped(
+    conn: &Connection,
+    run_id: &str,
+    package_scope: &str,
+) -> Result<Vec<FailureIdentity>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT stable_id, test_id, file FROM test_failures \
+         WHERE run_id = ?1 AND (?2 = '' OR package = ?2)",
+    )?;
+    let rows = stmt.query_map(params![run_id, package_scope], |row| {
+        Ok(FailureIdentity {
+            stable_id: row.get(0)?,
+            test_id: row.get(1)?,
+            file: row.get(2)?,
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
+/// Records `(old_stable_id, new_stable_id)` pairs of failures whose test file
+/// was renamed since the previous run.
+pub fn insert_test_renames(
+    tx: &Transaction,
+    run_id: &str,
+    renames: &[(String, String)],
+) -> Result<(), StoreError> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO test_renames (run_id, old_stable_id, new_stable_id) VALUES (?1, ?2, ?3)",
+    )?;
+    for (old_id, new_id) in renames {
+        stmt.execute(params![run_id, old_id, new_id])?;
+    }
+    Ok(())
+}
+
+/// Gets the renames recorded for a run, keyed by the previous stable id.
+pub fn get_test_renames(
+    conn: &Connection,
+    run_id: &str,
+) -> Result<HashMap<String, String>, StoreError> {
+    let mut stmt =
+        conn.prepare("SELECT old_stable_id, new_stable_id FROM test_renames WHERE run_id = ?1")?;
+    let rows = stmt.query_map(params![run_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect::<Result<HashMap<_, _>, _>>()
+        .map_err(StoreError::from)
+}
+
 /// Inserts findings in batch with package scope.
 pub fn insert_findings(
     tx: &Transaction,
@@ -133,8 +818,9 @@ pub fn insert_findings(
 ) -> Result<(), StoreError> {
     let mut stmt = tx.prepare(
         "INSERT INTO findings (run_id, stable_id, tool, rule, file, \
-         start_line, start_column, end_line, end_column, message, package) \
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+         start_line, start_column, end_line, end_column, message, package, category, fixable, \
+         severity) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
     )?;
     for f in findings {
         stmt.execute(params![
@@ -148,7 +834,10 @@ pub fn insert_findings(
             f.end_line,
             f.end_column,
             f.message,
-            package
+            package,
+            f.category,
+            f.fixable,
+            f.severity
         ])?;
     }
     Ok(())
@@ -199,6 +888,88 @@ pub fn get_finding_stable_ids_scoped(
         .map_err(StoreError::from)
 }
 
+/// Gets `(stable_id, rule)` pairs of the findings in a given run, scoped to a
+/// package. If `package_scope` is empty, returns all findings (no filtering).
+pub fn get_finding_rules_scoped(
+    conn: &Connection,
+    run_id: &str,
+    package_scope: &str,
+) -> Result<Vec<(String, String)>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT stable_id, rule FROM findings WHERE run_id = ?1 AND (?2 = '' OR package = ?2)",
+    )?;
+    let rows = stmt.query_map(params![run_id, package_scope], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
+/// Gets the distinct non-empty packages with test failures or findings in the given runs.
+pub fn get_packages_for_runs(
+    conn: &Connection,
+    run_ids: &[&str],
+) -> Result<Vec<String>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT package FROM test_failures WHERE run_id = ?1 AND package != '' \
+         UNION SELECT package FROM findings WHERE run_id = ?1 AND package != ''",
+    )?;
+    let mut packages = std::collections::BTreeSet::new();
+    for run_id in run_ids {
+        let rows = stmt.query_map(params![run_id], |row| row.get::<_, String>(0))?;
+        for package in rows {
+            packages.insert(package?);
+        }
+    }
+    Ok(packages.into_iter().collect())
+}
+
+/// Gets finding attributes for a given run, scoped to a package.
+/// If `package_scope` is empty, returns all findings (no filtering).
+pub fn get_finding_attributes_scoped(
+    conn: &Connection,
+    run_id: &str,
+    package_scope: &str,
+) -> Result<Vec<FindingAttributes>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT stable_id, category, fixable FROM findings \
+         WHERE run_id = ?1 AND (?2 = '' OR package = ?2)",
+    )?;
+    let rows = stmt.query_map(params![run_id, package_scope], |row| {
+        Ok(FindingAttributes {
+            stable_id: row.get(0)?,
+            category: row.get(1)?,
+            fixable: row.get(2)?,
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
+/// Gets the rule and location of every finding in a run, scoped to a package.
+/// If `package_scope` is empty, returns all findings (no filtering).
+pub fn get_finding_locations_scoped(
+    conn: &Connection,
+    run_id: &str,
+    package_scope: &str,
+) -> Result<Vec<FindingLocation>, StoreError> {
+    let mut stmt = conn.prepare(
+        "SELECT stable_id, rule, file, start_line, start_column FROM findings \
+         WHERE run_id = ?1 AND (?2 = '' OR package = ?2)",
+    )?;
+    let rows = stmt.query_map(params![run_id, package_scope], |row| {
+        Ok(FindingLocation {
+            stable_id: row.get(0)?,
+            rule: row.get(1)?,
+            file: row.get(2)?,
+            start_line: row.get(3)?,
+            start_column: row.get(4)?,
+        })
+    })?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(StoreError::from)
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -209,10 +980,76 @@ mod tests {
     fn setup() -> (tempfile::TempDir, Connection) {
         let dir = tempdir().unwrap();
         init_storage(dir.path()).unwrap();
-        let conn = open_connection(dir.path()).unwrap();
+        let conn = open_connection(dir.path(), CacheSettings::default()).unwrap();
         (dir, conn)
     }
 
+    #[test]
+    fn normalize_workspace_ids_merges_stored_variants() {
+        let (_dir, mut conn) = setup();
+        let tx = conn.transaction().unwrap();
+        let runs = [
+            ("WS1/", "run1", 1000),
+            ("ws1", "run2", 2000),
+            ("", "run3", 3000),
+        ];
+        for (workspace, run, time) in runs {
+            insert_run(&tx, workspace, run, time).unwrap();
+            complete_run(&tx, run, time + 1).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let lowercase = |id: &str| crate::normalize::workspace::normalize_id(id, None, true).ok();
+        assert_eq!(normalize_workspace_ids(&mut conn, lowercase).unwrap(), 1);
+        assert_eq!(get_recent_runs(&conn, "ws1", 10, false).unwrap().len(), 2);
+        assert_eq!(get_recent_runs(&conn, "", 10, false).unwrap().len(), 1);
+        assert_eq!(normalize_workspace_ids(&mut conn, lowercase).unwrap(), 0);
+    }
+
+    #[test]
+    fn workspace_connections_evict_least_recently_used() {
+        let (dir, _conn) = setup();
+        let writers = WorkspaceConnections::new(dir.path(), CacheSettings::default());
+        let oldest = writers.get("ws0").unwrap();
+        let recent = writers.get("ws1").unwrap();
+        for i in 2..MAX_WORKSPACE_WRITERS {
+            writers.get(&format!("ws{i}")).unwrap();
+        }
+        // Touching ws1 makes ws0 the least recently used
+        assert!(Arc::ptr_eq(&recent, &writers.get("ws1").unwrap()));
+        writers.get("ws-new").unwrap();
+
+        assert_eq!(Arc::strong_count(&oldest), 1, "ws0 was evicted");
+        assert_eq!(Arc::strong_count(&recent), 2, "ws1 is still cached");
+        assert!(!Arc::ptr_eq(&oldest, &writers.get("ws0").unwrap()));
+
+        writers.remove("ws1");
+        assert_eq!(Arc::strong_count(&recent), 1);
+    }
+
+    #[test]
+    fn reads_proceed_during_another_connections_write() {
+        let (dir, mut writer) = setup();
+        let reader = open_connection(dir.path(), CacheSettings::default()).unwrap();
+        let timeout: i64 = reader
+            .query_row("PRAGMA busy_timeout", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(timeout, 5000);
+
+        let tx = writer
+            .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+            .unwrap();
+        insert_run(&tx, "ws1", "run1", 1000).unwrap();
+        let started = std::time::Instant::now();
+        let runs: i64 = reader
+            .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+            .unwrap();
+        // The uncommitted run is invisible and the read did not wait on the lock
+        assert_eq!(runs, 0);
+        assert!(started.elapsed() < BUSY_TIMEOUT);
+        tx.commit().unwrap();
+    }
+
     #[test]
     fn init_creates_db_and_is_idempotent() {
         let dir = tempdir().unwrap();
@@ -221,6 +1058,26 @@ mod tests {
         init_storage(dir.path()).unwrap(); // Second call succeeds
     }
 
+    #[test]
+    fn applied_migrations_reports_embedded_set() {
+        let (_dir, conn) = setup();
+        let applied = applied_migrations(&conn).unwrap();
+        let runner = migrations::runner();
+        let mut expected: Vec<(i32, &str)> = runner
+            .get_migrations()
+            .iter()
+            .map(|m| (m.version() as i32, m.name()))
+            .collect();
+        expected.sort_unstable();
+        let actual: Vec<(i32, &str)> = applied
+            .iter()
+            .map(|m| (m.version, m.name.as_str()))
+            .collect();
+        assert_eq!(actual, expected);
+        assert_eq!(actual[0], (1, "create_runs"));
+        assert!(applied.iter().all(|m| !m.applied_on.is_empty()));
+    }
+
     #[test]
     fn schema_has_runs_table() {
         let (_dir, conn) = setup();
@@ -260,7 +1117,7 @@ mod tests {
         complete_run(&tx, "run1", 2000).unwrap();
         tx.commit().unwrap();
 
-        let runs = get_recent_runs(&conn, "ws1", 10).unwrap();
+        let runs = get_recent_runs(&conn, "ws1", 10, false).unwrap();
         assert_eq!(runs.len(), 1);
         assert_eq!(runs[0].run_id, "run1");
     }
@@ -275,6 +1132,9 @@ mod tests {
             test_id: "test1".into(),
             file: "test.ts".into(),
             message: "failed".into(),
+            kind: "assertion".into(),
+            expected: None,
+            received: None,
         }];
         insert_test_failures(&tx, "run1", "", &failures).unwrap();
         tx.commit().unwrap();
@@ -283,6 +1143,85 @@ mod tests {
         assert_eq!(ids, vec!["abc123"]);
     }
 
+    #[test]
+    fn failure_assertion_diff_is_stored() {
+        let (_dir, mut conn) = setup();
+        let tx = conn.transaction().unwrap();
+        insert_run(&tx, "ws1", "run1", 1000).unwrap();
+        let failures = vec![TestFailureRow {
+            stable_id: "abc123".into(),
+            test_id: "test1".into(),
+            file: "test.ts".into(),
+            message: "expected 2 to be 1".into(),
+            kind: "assertion".into(),
+            expected: Some("1".into()),
+            received: Some("2".into()),
+        }];
+        insert_test_failures(&tx, "run1", "", &failures).unwrap();
+        tx.commit().unwrap();
+
+        let (expected, received): (Option<String>, Option<String>) = conn
+            .query_row(
+                "SELECT expected, received FROM test_failures WHERE stable_id = 'abc123'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (expected.as_deref(), received.as_deref()),
+            (Some("1"), Some("2"))
+        );
+    }
+
+    #[test]
+    fn cache_settings_are_applied_and_clamped() {
+        let (dir, _conn) = setup();
+        let custom = CacheSettings {
+            cache_mib: 32,
+            mmap_mib: 16,
+        };
+        let mut conn = open_connection(dir.path(), custom).unwrap();
+        let pragma = |conn: &Connection, name: &str| -> i64 {
+            conn.query_row(&format!("PRAGMA {name}"), [], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(pragma(&conn, "cache_size"), -32 * 1024);
+        assert_eq!(pragma(&conn, "mmap_size"), 16 * 1024 * 1024);
+
+        let tx = conn.transaction().unwrap();
+        insert_run(&tx, "ws1", "run1", 1000).unwrap();
+        complete_run(&tx, "run1", 2000).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(get_recent_runs(&conn, "ws1", 10, false).unwrap().len(), 1);
+
+        let extreme = CacheSettings {
+            cache_mib: 0,
+            mmap_mib: u32::MAX,
+        };
+        let ro = open_connection_read_only(dir.path(), extreme).unwrap();
+        assert_eq!(
+            pragma(&ro, "cache_size"),
+            -i64::from(CacheSettings::MIN_CACHE_MIB) * 1024
+        );
+        assert_eq!(get_recent_runs(&ro, "ws1", 10, false).unwrap().len(), 1);
+        assert_eq!(extreme.clamped().mmap_mib, CacheSettings::MAX_MMAP_MIB);
+    }
+
+    #[test]
+    fn read_only_connection_rejects_writes() {
+        let (dir, _conn) = setup();
+        let mut ro = open_connection_read_only(dir.path(), CacheSettings::default()).unwrap();
+        assert!(get_recent_runs(&ro, "ws1", 10, false).unwrap().is_empty());
+        let tx = ro.transaction().unwrap();
+        assert!(insert_run(&tx, "ws1", "run1", 1000).is_err());
+    }
+
+    #[test]
+    fn read_only_connection_requires_existing_db() {
+        let dir = tempdir().unwrap();
+        assert!(open_connection_read_only(dir.path(), CacheSettings::default()).is_err());
+    }
+
     #[test]
     fn init_fails_on_invalid_path() {
         let dir = tempdir().unwrap();
@@ -335,12 +1274,47 @@ mod tests {
             end_line: 10,
             end_column: 15,
             message: "x is unused".into(),
+            category: "problem".into(),
+            fixable: true,
+            severity: 2,
         }];
         insert_findings(&tx, "run1", "", &findings).unwrap();
         tx.commit().unwrap();
 
         let ids = get_finding_stable_ids_for_run(&conn, "run1").unwrap();
         assert_eq!(ids, vec!["finding123"]);
+        let attributes = get_finding_attributes_scoped(&conn, "run1", "").unwrap();
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(attributes[0].stable_id, "finding123");
+        assert_eq!(attributes[0].category, "problem");
+        assert!(attributes[0].fixable);
+    }
+
+    #[test]
+    fn wal_checkpoint_truncates_pending_writes() {
+        let (dir, mut conn) = setup();
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode=WAL", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        let tx = conn.transaction().unwrap();
+        insert_run(&tx, "ws1", "run1", 1000).unwrap();
+        complete_run(&tx, "run1", 1001).unwrap();
+        tx.commit().unwrap();
+        let wal = dir.path().join("db.sqlite-wal");
+        assert!(fs::metadata(&wal).unwrap().len() > 0);
+
+        let checkpointer = open_connection(dir.path(), CacheSettings::default()).unwrap();
+        let result = checkpoint_wal(&checkpointer).unwrap();
+        assert!(!result.busy);
+        assert_eq!(result.log_frames, result.checkpointed_frames);
+        assert_eq!(fs::metadata(&wal).unwrap().len(), 0);
+        assert_eq!(
+            get_recent_runs(&checkpointer, "ws1", 1, false)
+                .unwrap()
+                .len(),
+            1
+        );
     }
 
     // P12: Migration Safety - data preserved on re-run
@@ -352,7 +1326,7 @@ mod tests {
 
         // Insert test data
         {
-            let mut conn = open_connection(dir.path()).unwrap();
+            let mut conn = open_connection(dir.path(), CacheSettings::default()).unwrap();
             let tx = conn.transaction().unwrap();
             insert_run(&tx, "ws1", "run1", 1000).unwrap();
             insert_test_failures(
@@ -364,6 +1338,9 @@ mod tests {
                     test_id: "t1".into(),
                     file: "f.ts".into(),
                     message: "m".into(),
+                    kind: "assertion".into(),
+                    expected: None,
+                    received: None,
                 }],
             )
             .unwrap();
@@ -381,6 +1358,9 @@ mod tests {
                     end_line: 1,
                     end_column: 1,
                     message: "m".into(),
+                    category: "unknown".into(),
+                    fixable: false,
+                    severity: 2,
                 }],
             )
             .unwrap();
@@ -392,8 +1372,8 @@ mod tests {
         init_storage(dir.path()).unwrap();
 
         // Verify data preserved
-        let conn = open_connection(dir.path()).unwrap();
-        let runs = get_recent_runs(&conn, "ws1", 10).unwrap();
+        let conn = open_connection(dir.path(), CacheSettings::default()).unwrap();
+        let runs = get_recent_runs(&conn, "ws1", 10, false).unwrap();
         assert_eq!(runs.len(), 1);
         let tf_ids = get_stable_ids_for_run(&conn, "run1").unwrap();
         assert_eq!(tf_ids, vec!["tf1"]);
@@ -422,6 +1402,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::too_many_lines)]
     fn scoped_queries_filter_by_package() {
         let (_dir, mut conn) = setup();
         let tx = conn.transaction().unwrap();
@@ -436,6 +1417,9 @@ mod tests {
                 test_id: "t1".into(),
                 file: "f.ts".into(),
                 message: "m".into(),
+                kind: "assertion".into(),
+                expected: None,
+                received: None,
             }],
         )
         .unwrap();
@@ -448,6 +1432,9 @@ mod tests {
                 test_id: "t2".into(),
                 file: "f2.ts".into(),
                 message: "m".into(),
+                kind: "assertion".into(),
+                expected: None,
+                received: None,
             }],
         )
         .unwrap();
@@ -466,6 +1453,9 @@ mod tests {
                 end_line: 1,
                 end_column: 1,
                 message: "m".into(),
+                category: "unknown".into(),
+                fixable: false,
+                severity: 2,
             }],
         )
         .unwrap();
@@ -503,6 +1493,9 @@ mod tests {
                 test_id: "t1".into(),
                 file: "f.ts".into(),
                 message: "m".into(),
+                kind: "assertion".into(),
+                expected: None,
+                received: None,
             }],
         )
         .unwrap();
@@ -524,6 +1517,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::too_many_lines)]
     fn nonexistent_package_scope_returns_empty() {
         let (_dir, mut conn) = setup();
         let tx = conn.transaction().unwrap();
@@ -537,6 +1531,9 @@ mod tests {
                 test_id: "t1".into(),
                 file: "f.ts".into(),
                 message: "m".into(),
+                kind: "assertion".into(),
+                expected: None,
+                received: None,
             }],
         )
         .unwrap();
@@ -554,6 +1551,9 @@ mod tests {
                 end_line: 1,
                 end_column: 1,
                 message: "m".into(),
+                category: "unknown".into(),
+                fixable: false,
+                severity: 2,
             }],
         )
         .unwrap();